@@ -0,0 +1,81 @@
+//! Sends & receives chat messages
+//!
+//! Messages starting with a `/` are sent to the server as commands.
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        event::{Event, EventReader},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Res, ResMut},
+    },
+    log::warn,
+    render::color::Color,
+    time::Time,
+};
+use bevy_renet::renet::RenetClient;
+use cosmos_core::{
+    chat::{sanitize_chat_message, ClientChatMessages, ServerChatMessages},
+    netty::{cosmos_encoder, system_sets::NetworkingSystemsSet, NettyChannelClient, NettyChannelServer},
+};
+
+use crate::{
+    state::game_state::GameState,
+    ui::message::{ChatHistory, HudMessage, RichText},
+};
+
+#[derive(Event, Debug)]
+/// Send this event to send a message in chat
+pub struct SendChatMessageEvent(pub String);
+
+fn receive_chat_messages(mut client: ResMut<RenetClient>, mut chat_history: ResMut<ChatHistory>, time: Res<Time>) {
+    while let Some(message) = client.receive_message(NettyChannelServer::Chat) {
+        let Ok(msg) = cosmos_encoder::deserialize::<ServerChatMessages>(&message) else {
+            warn!("Bad chat message from server");
+            continue;
+        };
+
+        match msg {
+            ServerChatMessages::Message { sender, message } => {
+                let message = match sender {
+                    Some(sender) => HudMessage::new(vec![
+                        RichText::new(format!("<{sender}> "), Color::YELLOW),
+                        RichText::new(message, Color::WHITE),
+                    ]),
+                    None => HudMessage::with_colored_string(format!("[Server] {message}"), Color::GOLD),
+                };
+
+                chat_history.add_message(message, &time);
+            }
+        }
+    }
+}
+
+fn send_chat_messages(mut client: ResMut<RenetClient>, mut ev_reader: EventReader<SendChatMessageEvent>) {
+    for ev in ev_reader.read() {
+        let Some(message) = sanitize_chat_message(&ev.0) else {
+            continue;
+        };
+
+        let msg = match message.strip_prefix('/') {
+            Some(command) => ClientChatMessages::SendCommand {
+                command: command.to_owned(),
+            },
+            None => ClientChatMessages::SendMessage { message },
+        };
+
+        client.send_message(NettyChannelClient::Chat, cosmos_encoder::serialize(&msg));
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            receive_chat_messages.in_set(NetworkingSystemsSet::ReceiveMessages),
+            send_chat_messages,
+        )
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_event::<SendChatMessageEvent>();
+}
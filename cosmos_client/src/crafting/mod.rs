@@ -0,0 +1,16 @@
+//! Client logic for crafting
+
+use bevy::app::App;
+use cosmos_core::crafting::Recipe;
+
+use crate::registry::sync_registry;
+
+mod netty;
+mod ui;
+
+pub(super) fn register(app: &mut App) {
+    sync_registry::<Recipe>(app);
+
+    ui::register(app);
+    netty::register(app);
+}
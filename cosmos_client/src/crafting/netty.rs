@@ -0,0 +1,69 @@
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        event::EventWriter,
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::ResMut,
+    },
+    render::color::Color,
+};
+use bevy_renet::renet::RenetClient;
+use cosmos_core::{
+    crafting::netty::{CraftingError, ServerCraftingMessages},
+    netty::{cosmos_encoder, system_sets::NetworkingSystemsSet, NettyChannelServer},
+    structure::structure_block::StructureBlock,
+};
+
+use crate::{
+    state::game_state::GameState,
+    ui::message::{HudMessage, HudMessages},
+};
+
+use super::ui::OpenFabricatorUiEvent;
+
+fn crafting_listen_netty(
+    mut client: ResMut<RenetClient>,
+    mut ev_writer_open_fabricator_ui: EventWriter<OpenFabricatorUiEvent>,
+    mut hud_messages: ResMut<HudMessages>,
+) {
+    while let Some(message) = client.receive_message(NettyChannelServer::Crafting) {
+        let msg: ServerCraftingMessages = cosmos_encoder::deserialize(&message).expect("Bad crafting message");
+
+        match msg {
+            ServerCraftingMessages::OpenFabricator {
+                fabricator_block,
+                structure_entity,
+            } => {
+                ev_writer_open_fabricator_ui.send(OpenFabricatorUiEvent {
+                    structure_block: StructureBlock::new(fabricator_block),
+                    structure_entity,
+                });
+            }
+            ServerCraftingMessages::CraftResult { details, .. } => {
+                let Err(error) = details else {
+                    continue;
+                };
+
+                let text = match error {
+                    CraftingError::UnknownRecipe => "That recipe doesn't exist.",
+                    CraftingError::NotAFabricator => "That fabricator is gone.",
+                    CraftingError::NotEnoughItems => "You don't have enough items to craft that.",
+                    CraftingError::NotEnoughInventorySpace => "You don't have enough inventory space to craft that.",
+                    CraftingError::TooFarAway => "You are too far away from that fabricator.",
+                    CraftingError::NotAllowed => "You aren't allowed to use the fabricators on this structure.",
+                };
+
+                hud_messages.display_message(HudMessage::with_colored_string(text.into(), Color::ORANGE_RED));
+            }
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        crafting_listen_netty
+            .run_if(in_state(GameState::Playing))
+            .in_set(NetworkingSystemsSet::ReceiveMessages),
+    );
+}
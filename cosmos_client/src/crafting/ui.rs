@@ -0,0 +1,287 @@
+use bevy::{
+    app::{App, Update},
+    asset::AssetServer,
+    core::Name,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader},
+        query::{Added, With},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::BuildChildren,
+    log::error,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{
+        node_bundles::{NodeBundle, TextBundle},
+        AlignItems, FlexDirection, JustifyContent, Style, UiRect, Val,
+    },
+};
+use bevy_renet::renet::RenetClient;
+use cosmos_core::{
+    crafting::{netty::ClientCraftingMessages, Recipe, RecipeItem},
+    ecs::NeedsDespawned,
+    item::Item,
+    netty::{cosmos_encoder, system_sets::NetworkingSystemsSet, NettyChannelClient},
+    registry::{identifiable::Identifiable, Registry},
+    structure::structure_block::StructureBlock,
+};
+
+use crate::{
+    lang::Lang,
+    state::game_state::GameState,
+    ui::{
+        components::{
+            button::{register_button, Button, ButtonBundle, ButtonEvent, ButtonStyles},
+            scollable_container::{ScrollBox, ScrollBundle},
+            window::{GuiWindow, WindowBundle},
+        },
+        UiSystemSet,
+    },
+};
+
+#[derive(Event)]
+pub(super) struct OpenFabricatorUiEvent {
+    pub structure_block: StructureBlock,
+    pub structure_entity: Entity,
+}
+
+#[derive(Component, Debug)]
+struct FabricatorUi {
+    structure_block: StructureBlock,
+    structure_entity: Entity,
+}
+
+#[derive(Component, Debug)]
+struct CraftButton {
+    fabricator_ui: Entity,
+    recipe_id: u16,
+}
+
+#[derive(Event, Debug)]
+struct CraftBtnEvent(Entity);
+
+impl ButtonEvent for CraftBtnEvent {
+    fn create_event(entity: Entity) -> Self {
+        Self(entity)
+    }
+}
+
+fn open_fabricator_ui(
+    mut commands: Commands,
+    mut ev_reader: EventReader<OpenFabricatorUiEvent>,
+    q_open_fabricators: Query<Entity, With<FabricatorUi>>,
+) {
+    for ev in ev_reader.read() {
+        for ent in q_open_fabricators.iter() {
+            commands.entity(ent).insert(NeedsDespawned);
+        }
+
+        commands.spawn(FabricatorUi {
+            structure_block: ev.structure_block,
+            structure_entity: ev.structure_entity,
+        });
+    }
+}
+
+fn item_name<'a>(item_id: u16, items: &'a Registry<Item>, lang: &'a Lang<Item>) -> &'a str {
+    lang.get_name_from_numeric_id(item_id)
+        .or_else(|| items.try_from_numeric_id(item_id).map(|item| item.unlocalized_name()))
+        .unwrap_or("Unknown Item")
+}
+
+fn describe_items(recipe_items: &[RecipeItem], items: &Registry<Item>, lang: &Lang<Item>) -> String {
+    recipe_items
+        .iter()
+        .map(|x| format!("{}x {}", x.quantity, item_name(x.item_id, items, lang)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn render_fabricator_ui(
+    mut commands: Commands,
+    q_fabricator_ui: Query<Entity, Added<FabricatorUi>>,
+    asset_server: Res<AssetServer>,
+    recipes: Res<Registry<Recipe>>,
+    items: Res<Registry<Item>>,
+    lang: Res<Lang<Item>>,
+) {
+    let Ok(ui_ent) = q_fabricator_ui.get_single() else {
+        return;
+    };
+
+    let text_style = TextStyle {
+        color: Color::WHITE,
+        font_size: 24.0,
+        font: asset_server.load("fonts/PixeloidSans.ttf"),
+    };
+
+    let text_style_small = TextStyle {
+        color: Color::GRAY,
+        font_size: 18.0,
+        font: asset_server.load("fonts/PixeloidSans.ttf"),
+    };
+
+    commands
+        .entity(ui_ent)
+        .insert((
+            Name::new("Fabricator UI"),
+            WindowBundle {
+                node_bundle: NodeBundle {
+                    background_color: Color::hex("2D2D2D").unwrap().into(),
+                    style: Style {
+                        width: Val::Px(700.0),
+                        height: Val::Px(600.0),
+                        margin: UiRect {
+                            // Centers it vertically
+                            top: Val::Auto,
+                            bottom: Val::Auto,
+                            left: Val::Auto,
+                            right: Val::Auto,
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                window: GuiWindow {
+                    title: "Fabricator".into(),
+                    body_styles: Style {
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Name::new("Recipes"),
+                ScrollBundle {
+                    node_bundle: NodeBundle {
+                        style: Style {
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    slider: ScrollBox { ..Default::default() },
+                },
+            ))
+            .with_children(|p| {
+                p.spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|p| {
+                    for recipe in recipes.iter() {
+                        p.spawn((
+                            Name::new(recipe.unlocalized_name().to_owned()),
+                            NodeBundle {
+                                style: Style {
+                                    justify_content: JustifyContent::SpaceBetween,
+                                    align_items: AlignItems::Center,
+                                    margin: UiRect::bottom(Val::Px(10.0)),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                        ))
+                        .with_children(|p| {
+                            p.spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            })
+                            .with_children(|p| {
+                                p.spawn(TextBundle {
+                                    text: Text::from_section(describe_items(&[recipe.output], &items, &lang), text_style.clone()),
+                                    ..Default::default()
+                                });
+
+                                p.spawn(TextBundle {
+                                    text: Text::from_section(describe_items(&recipe.inputs, &items, &lang), text_style_small.clone()),
+                                    ..Default::default()
+                                });
+                            });
+
+                            p.spawn((
+                                CraftButton {
+                                    fabricator_ui: ui_ent,
+                                    recipe_id: recipe.id(),
+                                },
+                                ButtonBundle::<CraftBtnEvent> {
+                                    node_bundle: NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(120.0),
+                                            height: Val::Px(40.0),
+                                            ..Default::default()
+                                        },
+                                        ..Default::default()
+                                    },
+                                    button: Button {
+                                        button_styles: Some(ButtonStyles {
+                                            background_color: Color::hex("008000").unwrap(),
+                                            hover_background_color: Color::hex("006000").unwrap(),
+                                            press_background_color: Color::hex("004000").unwrap(),
+                                            ..Default::default()
+                                        }),
+                                        text: Some(("Craft".into(), text_style.clone())),
+                                        ..Default::default()
+                                    },
+                                },
+                            ));
+                        });
+                    }
+                });
+            });
+        });
+}
+
+fn on_craft(
+    mut client: ResMut<RenetClient>,
+    q_fabricator_ui: Query<&FabricatorUi>,
+    q_craft_button: Query<&CraftButton>,
+    mut ev_reader: EventReader<CraftBtnEvent>,
+) {
+    for ev in ev_reader.read() {
+        let Ok(craft_button) = q_craft_button.get(ev.0) else {
+            error!("Craft button event missing craft button entity");
+            continue;
+        };
+
+        let Ok(fabricator_ui) = q_fabricator_ui.get(craft_button.fabricator_ui) else {
+            continue;
+        };
+
+        client.send_message(
+            NettyChannelClient::Crafting,
+            cosmos_encoder::serialize(&ClientCraftingMessages::Craft {
+                fabricator_block: fabricator_ui.structure_block.coords(),
+                structure_entity: fabricator_ui.structure_entity,
+                recipe_id: craft_button.recipe_id,
+                quantity: 1,
+            }),
+        );
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    register_button::<CraftBtnEvent>(app);
+
+    app.add_event::<OpenFabricatorUiEvent>().add_systems(
+        Update,
+        (open_fabricator_ui, render_fabricator_ui, on_craft)
+            .chain()
+            .after(NetworkingSystemsSet::ProcessReceivedMessages)
+            .before(UiSystemSet::DoUi)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
@@ -103,6 +103,9 @@ pub enum CosmosInputs {
     SwapCameraLeft,
     /// Changes which camera is selected in a ship
     SwapCameraRight,
+
+    /// Opens the chat so the player can type a message
+    OpenChat,
 }
 
 fn init_input(mut input_handler: ResMut<CosmosInputHandler>) {
@@ -159,6 +162,8 @@ fn init_input(mut input_handler: ResMut<CosmosInputHandler>) {
 
     input_handler.set_keycode(CosmosInputs::SwapCameraLeft, KeyCode::ArrowLeft);
     input_handler.set_keycode(CosmosInputs::SwapCameraRight, KeyCode::ArrowRight);
+
+    input_handler.set_keycode(CosmosInputs::OpenChat, KeyCode::Enter);
 }
 
 #[derive(Resource, Default, Debug)]
@@ -8,6 +8,7 @@ use bevy_rapier3d::{
 use cosmos_core::{
     block::{block_events::BlockInteractEvent, Block, BlockFace, BlockRotation, BlockSubRotation},
     blockitems::BlockItems,
+    entities::player::game_mode::GameMode,
     inventory::Inventory,
     item::Item,
     netty::client::LocalPlayer,
@@ -37,7 +38,7 @@ pub struct LookingAt {
 pub(crate) fn process_player_interaction(
     input_handler: InputChecker,
     camera: Query<&GlobalTransform, With<MainCamera>>,
-    mut player_body: Query<(Entity, &mut Inventory, Option<&mut LookingAt>), (With<LocalPlayer>, Without<Pilot>)>,
+    mut player_body: Query<(Entity, &mut Inventory, Option<&mut LookingAt>, Option<&GameMode>), (With<LocalPlayer>, Without<Pilot>)>,
     rapier_context: Res<RapierContext>,
     q_chunk_physics_part: Query<&ChunkPhysicsPart>,
     q_structure: Query<(&Structure, &GlobalTransform, Option<&Planet>)>,
@@ -51,7 +52,7 @@ pub(crate) fn process_player_interaction(
     mut commands: Commands,
 ) {
     // this fails if the player is a pilot
-    let Ok((player_entity, mut inventory, looking_at)) = player_body.get_single_mut() else {
+    let Ok((player_entity, mut inventory, looking_at, game_mode)) = player_body.get_single_mut() else {
         return;
     };
 
@@ -146,7 +147,9 @@ pub(crate) fn process_player_interaction(
                 return;
             }
 
-            inventory.decrease_quantity_at(inventory_slot, 1);
+            if game_mode.copied().unwrap_or_default().uses_items() {
+                inventory.decrease_quantity_at(inventory_slot, 1);
+            }
 
             let (block_up, block_sub_rotation) = if block.is_fully_rotatable() || block.should_face_front() {
                 let delta = UnboundBlockCoordinate::from(place_at_coords) - UnboundBlockCoordinate::from(coords);
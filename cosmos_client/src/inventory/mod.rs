@@ -19,7 +19,10 @@ use crate::{
     input::inputs::{CosmosInputs, InputChecker, InputHandler},
     state::game_state::GameState,
     ui::{
-        components::window::{GuiWindow, WindowBundle},
+        components::{
+            text_input::not_typing,
+            window::{GuiWindow, WindowBundle},
+        },
         item_renderer::RenderItem,
         UiSystemSet,
     },
@@ -715,7 +718,7 @@ pub(super) fn register(app: &mut App) {
     .add_systems(
         Update,
         (
-            (toggle_inventory, close_button_system).in_set(InventorySet::ToggleInventory),
+            (toggle_inventory.run_if(not_typing), close_button_system).in_set(InventorySet::ToggleInventory),
             on_update_inventory.in_set(InventorySet::UpdateInventory),
             handle_interactions.in_set(InventorySet::HandleInteractions),
             follow_cursor.in_set(InventorySet::FollowCursor),
@@ -7,6 +7,8 @@ pub mod asset;
 pub mod audio;
 pub mod block;
 pub mod camera;
+pub mod chat;
+pub mod crafting;
 pub mod economy;
 pub mod ecs;
 pub mod entities;
@@ -42,7 +44,7 @@ use bevy_rapier3d::prelude::{RapierConfiguration, TimestepMode};
 use bevy_renet::transport::NetcodeClientPlugin;
 use bevy_renet::RenetClientPlugin;
 use clap::{arg, Parser};
-use cosmos_core::netty::get_local_ipaddress;
+use cosmos_core::netty::{connection::DEFAULT_PORT, get_local_ipaddress};
 use cosmos_core::plugin::cosmos_core_plugin::CosmosCorePluginGroup;
 use netty::connect::{self, HostConfig};
 use state::game_state::GameState;
@@ -58,6 +60,22 @@ struct Args {
     #[arg(long)]
     ip: Option<String>,
 
+    /// Port of the server to connect to
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// The name to join the server with
+    #[arg(long, default_value = "CoolPlayer")]
+    name: String,
+
+    /// The server's password, if it has one
+    #[arg(long)]
+    password: Option<String>,
+
+    /// The TCP port of the server's token endpoint. Defaults to the server's port.
+    #[arg(long)]
+    token_port: Option<u16>,
+
     /// If this is fullscreen, the app will start in fullscreen
     #[arg(short, long, default_value_t = false)]
     fullscreen: bool,
@@ -76,6 +94,14 @@ fn main() {
 
     info!("Host: {host_name}");
 
+    let host_config = HostConfig {
+        host_name,
+        port: args.port,
+        token_port: args.token_port.unwrap_or(args.port),
+        name: args.name,
+        password: args.password,
+    };
+
     let mut app = App::new();
 
     let default_plugins = DefaultPlugins
@@ -105,7 +131,7 @@ fn main() {
     #[cfg(feature = "print-schedule")]
     let default_plugins = default_plugins.disable::<LogPlugin>();
 
-    app.insert_resource(HostConfig { host_name })
+    app.insert_resource(host_config)
         .insert_resource(RapierConfiguration {
             gravity: Vec3::ZERO,
             timestep_mode: TimestepMode::Interpolated {
@@ -129,7 +155,12 @@ fn main() {
         .add_plugins((RenetClientPlugin, NetcodeClientPlugin, ObjPlugin, HanabiPlugin))
         // .add_plugins(RapierDebugRenderPlugin::default())
         .add_systems(OnEnter(GameState::Connecting), connect::establish_connection)
-        .add_systems(Update, connect::wait_for_connection.run_if(in_state(GameState::Connecting)))
+        .add_systems(
+            Update,
+            (connect::finish_establishing_connection, connect::wait_for_connection)
+                .chain()
+                .run_if(in_state(GameState::Connecting)),
+        )
         .add_systems(Update, connect::wait_for_done_loading.run_if(in_state(GameState::LoadingWorld)));
 
     input::register(&mut app);
@@ -157,6 +188,8 @@ fn main() {
     physics::register(&mut app);
     ecs::register(&mut app);
     shop::register(&mut app);
+    crafting::register(&mut app);
+    chat::register(&mut app);
     economy::register(&mut app);
 
     if cfg!(feature = "print-schedule") {
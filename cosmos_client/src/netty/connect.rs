@@ -3,82 +3,266 @@
 //! This does not add them to the bevy systems by default, and they must be manually added when needed.
 
 use std::{
-    net::UdpSocket,
-    time::{SystemTime, UNIX_EPOCH},
+    net::{SocketAddr, TcpStream, UdpSocket},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
 use bevy_renet::renet::{
-    transport::{ClientAuthentication, NetcodeClientTransport},
+    transport::{ClientAuthentication, ConnectToken, NetcodeClientTransport},
     RenetClient,
 };
 use cosmos_core::{
+    block::Block,
     entities::player::Player,
-    netty::{client::LocalPlayer, connection_config, sync::mapping::NetworkMapping, PROTOCOL_ID},
+    item::Item,
+    netty::{
+        client::LocalPlayer,
+        connection::{
+            read_token_message, write_token_message, ConnectionUserData, GameVersion, HandshakeResponse, IdentityKey, TokenChannel,
+            TokenEndpointKey, TokenRequest, TokenResponse,
+        },
+        connection_config, cosmos_encoder,
+        sync::mapping::NetworkMapping,
+        NettyChannelServer, PROTOCOL_ID,
+    },
+    registry::Registry,
 };
+use futures_lite::future;
 
 use crate::{
-    netty::lobby::{ClientLobby, MostRecentTick},
+    netty::{
+        identity::{check_server_key, identity_key},
+        lobby::{ClientLobby, MostRecentTick},
+    },
     state::game_state::GameState,
 };
 
-fn new_netcode_transport(host: &str) -> NetcodeClientTransport {
-    let port: u16 = 1337;
+/// How long to wait for the server's token endpoint before assuming the server doesn't use one
+const TOKEN_ENDPOINT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Asks the server's token endpoint for a connect token.
+///
+/// Returns `None` if the server doesn't have a token endpoint, meaning it doesn't use secure authentication.
+fn request_connect_token(
+    token_addr: SocketAddr,
+    host_config: &HostConfig,
+    identity_key: IdentityKey,
+    version: &GameVersion,
+) -> Option<Result<ConnectToken, String>> {
+    let mut stream = TcpStream::connect_timeout(&token_addr, TOKEN_ENDPOINT_TIMEOUT).ok()?;
+
+    info!("Requesting connect token from {token_addr}");
+
+    let response = (|| {
+        let io_error = |e: bincode::Error| format!("Unable to get connect token - {e}");
+
+        stream
+            .set_read_timeout(Some(TOKEN_ENDPOINT_TIMEOUT))
+            .map_err(|e| io_error(e.into()))?;
+        stream
+            .set_write_timeout(Some(TOKEN_ENDPOINT_TIMEOUT))
+            .map_err(|e| io_error(e.into()))?;
+
+        let server_key = read_token_message::<TokenEndpointKey>(&mut stream).map_err(io_error)?;
+        // Make sure this is the same server as last time before sending it the password
+        check_server_key(&host_config.host_name, host_config.port, &server_key)?;
+
+        let (channel, client_key) = TokenChannel::client(&server_key);
+        write_token_message(&mut stream, &client_key).map_err(io_error)?;
+
+        channel
+            .write(
+                &mut stream,
+                &TokenRequest {
+                    name: host_config.name.clone(),
+                    password: host_config.password.clone(),
+                    identity_key,
+                    version: version.clone(),
+                },
+            )
+            .map_err(io_error)?;
+
+        channel.read::<TokenResponse>(&mut stream).map_err(io_error)
+    })();
+
+    Some(match response {
+        Ok(TokenResponse::Token(token_bytes)) => {
+            ConnectToken::read(&mut token_bytes.as_slice()).map_err(|e| format!("Received an invalid connect token - {e:?}"))
+        }
+        Ok(TokenResponse::Denied(reason)) => Err(reason),
+        Err(reason) => Err(reason),
+    })
+}
+
+fn new_netcode_transport(host_config: &HostConfig, version: GameVersion) -> Result<NetcodeClientTransport, String> {
+    let host = &host_config.host_name;
+    let port = host_config.port;
+
+    let server_addr: SocketAddr = format!("{host}:{port}")
+        .parse()
+        .map_err(|_| format!("Invalid server address {host}:{port}"))?;
+    let token_addr = SocketAddr::new(server_addr.ip(), host_config.token_port);
 
-    let server_addr = format!("{host}:{port}").parse().unwrap();
     let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
 
     socket.set_nonblocking(true).expect("Unable to make UDP non-blocking!");
 
+    let identity_key = identity_key(host, port);
+
+    let auth = match request_connect_token(token_addr, host_config, identity_key, &version) {
+        Some(Ok(connect_token)) => ClientAuthentication::Secure { connect_token },
+        Some(Err(reason)) => {
+            // The server will ignore an unsecure connection, so there's no point in trying one
+            error!("Server refused to give a connect token: {reason}");
+
+            return Err(reason);
+        }
+        None => unsecure_authentication(server_addr, host_config, identity_key, version)?,
+    };
+
+    info!("Connecting to {server_addr}");
+
+    // Getting the connect token can take a while, so this is only checked once it's done
     let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    let client_id = current_time.as_millis() as u64;
 
-    let name = "CoolPlayer";
+    NetcodeClientTransport::new(current_time, auth, socket).map_err(|e| format!("Unable to connect - {e}"))
+}
 
-    let mut token = [0; 256];
+fn unsecure_authentication(
+    server_addr: SocketAddr,
+    host_config: &HostConfig,
+    identity_key: IdentityKey,
+    version: GameVersion,
+) -> Result<ClientAuthentication, String> {
+    let client_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
 
-    // Bincode because this is stored un a u8, with a fixed length of 256
-    let serialized_name = bincode::serialize(&name).expect("Unable to serialize name");
-    for (i, byte) in serialized_name.iter().enumerate() {
-        token[i] = *byte;
+    let user_data = ConnectionUserData {
+        name: host_config.name.clone(),
+        password: host_config.password.clone(),
+        identity_key,
+        version,
     }
+    .to_user_data()
+    .ok_or_else(|| "Your name and password are too long.".to_owned())?;
 
-    let auth = ClientAuthentication::Unsecure {
+    Ok(ClientAuthentication::Unsecure {
         client_id,
         protocol_id: PROTOCOL_ID,
         server_addr,
-        user_data: Some(token),
-    };
-
-    info!("Connecting to {server_addr}");
-
-    NetcodeClientTransport::new(current_time, auth, socket).unwrap()
+        user_data: Some(user_data),
+    })
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 /// Used to setup the connection with the server
 pub struct HostConfig {
     /// The server's host
     pub host_name: String,
+    /// The server's port
+    pub port: u16,
+    /// The TCP port of the server's token endpoint, which is only used by servers with secure authentication
+    pub token_port: u16,
+    /// The name of the player
+    pub name: String,
+    /// The server's password, if it has one
+    pub password: Option<String>,
 }
 
+#[derive(Resource, Debug, Clone)]
+/// Inserted when the client couldn't join the server, with the reason why
+pub struct ConnectionFailed(pub String);
+
+#[derive(Resource)]
+/// Sets up the connection on another thread, since getting a connect token can take a few seconds
+pub struct ConnectingTask(Task<Result<NetcodeClientTransport, String>>);
+
 /// Establishes a connection with the server.
 ///
 /// Make sure the `ConnectionConfig` resource was added first.
-pub fn establish_connection(mut commands: Commands, host_config: Res<HostConfig>) {
+pub fn establish_connection(
+    mut commands: Commands,
+    host_config: Res<HostConfig>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+) {
     info!("Establishing connection w/ server...");
     commands.insert_resource(ClientLobby::default());
     commands.insert_resource(MostRecentTick(None));
     commands.insert_resource(RenetClient::new(connection_config()));
-    commands.insert_resource(new_netcode_transport(host_config.host_name.as_str()));
     commands.init_resource::<NetworkMapping>();
+
+    let host_config = host_config.clone();
+    let version = GameVersion::current(&blocks, &items);
+
+    let task = IoTaskPool::get().spawn(async move { new_netcode_transport(&host_config, version) });
+
+    commands.insert_resource(ConnectingTask(task));
 }
 
-/// Waits for a connection to be made, then changes the game state to `GameState::LoadingWorld`.
-pub fn wait_for_connection(mut state_changer: ResMut<NextState<GameState>>, client: Res<RenetClient>) {
-    if client.is_connected() {
-        info!("Loading server data...");
-        state_changer.set(GameState::LoadingData);
+/// Starts using the connection once [`establish_connection`] has set it up
+pub fn finish_establishing_connection(mut commands: Commands, connecting_task: Option<ResMut<ConnectingTask>>) {
+    let Some(mut connecting_task) = connecting_task else {
+        return;
+    };
+
+    let Some(result) = future::block_on(future::poll_once(&mut connecting_task.0)) else {
+        return;
+    };
+
+    commands.remove_resource::<ConnectingTask>();
+
+    match result {
+        Ok(transport) => commands.insert_resource(transport),
+        Err(reason) => commands.insert_resource(ConnectionFailed(reason)),
+    }
+}
+
+/// Waits for the server to accept the connection, then changes the game state to `GameState::LoadingData`.
+pub fn wait_for_connection(
+    mut commands: Commands,
+    mut state_changer: ResMut<NextState<GameState>>,
+    mut client: ResMut<RenetClient>,
+    connection_failed: Option<Res<ConnectionFailed>>,
+) {
+    if connection_failed.is_some() {
+        return;
+    }
+
+    if client.is_disconnected() {
+        let reason = client
+            .disconnect_reason()
+            .map(|reason| format!("Disconnected from the server - {reason}"))
+            .unwrap_or_else(|| "Disconnected from the server.".into());
+
+        error!("{reason}");
+        commands.insert_resource(ConnectionFailed(reason));
+        return;
+    }
+
+    if !client.is_connected() {
+        return;
+    }
+
+    while let Some(message) = client.receive_message(NettyChannelServer::Handshake) {
+        match cosmos_encoder::deserialize::<HandshakeResponse>(&message) {
+            Ok(HandshakeResponse::Accepted) => {
+                info!("Loading server data...");
+                state_changer.set(GameState::LoadingData);
+            }
+            Ok(HandshakeResponse::Rejected(reason)) => {
+                error!("Server refused connection: {reason}");
+                commands.insert_resource(ConnectionFailed(reason));
+            }
+            Err(_) => {
+                commands.insert_resource(ConnectionFailed(
+                    "Unable to understand the server. It is likely running a different version of Cosmos.".into(),
+                ));
+            }
+        }
     }
 }
 
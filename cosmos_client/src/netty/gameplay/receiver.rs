@@ -21,7 +21,8 @@ use cosmos_core::{
         client::LocalPlayer,
         client_reliable_messages::ClientReliableMessages,
         cosmos_encoder,
-        netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation},
+        interpolation::{SnapshotBuffer, TickClock, INTERPOLATION_DELAY},
+        netty_rigidbody::NettyRigidBodyLocation,
         server_reliable_messages::ServerReliableMessages,
         server_unreliable_messages::ServerUnreliableMessages,
         sync::mapping::{Mappable, NetworkMapping},
@@ -54,7 +55,7 @@ use crate::{
     state::game_state::GameState,
     structure::{
         planet::{client_planet_builder::ClientPlanetBuilder, generation::SetTerrainGenData},
-        ship::client_ship_builder::ClientShipBuilder,
+        ship::{client_ship_builder::ClientShipBuilder, prediction::ServerBody},
         station::client_station_builder::ClientStationBuilder,
     },
     ui::{
@@ -126,27 +127,25 @@ pub(crate) struct RequestedEntities {
 /// Unused
 pub struct NetworkTick(pub u64);
 
-#[derive(Debug, Component, Deref)]
-pub(crate) struct LerpTowards(NettyRigidBody);
-
-fn lerp_towards(
+/// Moves remote entities to where they were slightly in the past, between the snapshots received around that time
+fn interpolate_remote_bodies(
+    time: Res<Time>,
     mut location_query: Query<&mut Location>,
     global_transform_query: Query<&GlobalTransform>,
-    mut query: Query<(Entity, &LerpTowards, &mut Transform, &mut Velocity), With<Location>>,
+    mut query: Query<(Entity, &mut SnapshotBuffer, &mut Transform, &mut Velocity), With<Location>>,
 ) {
-    for (entity, lerp_towards, mut transform, mut velocity) in query.iter_mut() {
-        match lerp_towards.location {
-            NettyRigidBodyLocation::Absolute(location) => {
-                let to_location = location;
-                let mut location = location_query.get_mut(entity).expect("The above With statement guarentees this");
+    let render_time = time.elapsed_seconds_f64() - INTERPOLATION_DELAY;
 
-                if to_location.distance_sqrd(&location) > 100.0 {
-                    location.set_from(&to_location);
-                } else {
-                    let lerpped_loc = *location + (location.relative_coords_to(&to_location)) * 0.1;
+    for (entity, mut snapshots, mut transform, mut velocity) in query.iter_mut() {
+        let Some(body) = snapshots.sample(render_time) else {
+            continue;
+        };
 
-                    location.set_from(&lerpped_loc);
-                }
+        match body.location {
+            NettyRigidBodyLocation::Absolute(to_location) => {
+                let mut location = location_query.get_mut(entity).expect("The above With statement guarentees this");
+
+                location.set_from(&to_location);
             }
             NettyRigidBodyLocation::Relative(rel_trans, entity) => {
                 if let Ok(g_trans) = global_transform_query.get(entity) {
@@ -158,19 +157,9 @@ fn lerp_towards(
             }
         };
 
-        transform.rotation = //lerp_towards.rotation;
-            transform.rotation.lerp(lerp_towards.rotation, 0.1);
-
-        let vel = lerp_towards.body_vel.unwrap_or_default();
+        transform.rotation = body.rotation;
 
-        velocity.linvel = vel.linvel;
-        // velocity
-        //     .linvel
-        //     .lerp(lerp_towards.body_vel.linvel.into(), 0.1);
-        velocity.angvel = vel.angvel;
-        // velocity
-        //     .angvel
-        //     .lerp(lerp_towards.body_vel.angvel.into(), 0.1);
+        *velocity = body.create_velocity();
     }
 }
 
@@ -197,7 +186,7 @@ fn client_sync_players(
             Option<&mut Transform>,
             Option<&Velocity>,
             Option<&mut NetworkTick>,
-            Option<&mut LerpTowards>,
+            Option<&mut SnapshotBuffer>,
         ),
         Without<LocalPlayer>,
     >,
@@ -212,6 +201,7 @@ fn client_sync_players(
     mut hud_messages: ResMut<HudMessages>,
 
     (mut build_mode_enter, mut build_mode_exit): (EventWriter<EnterBuildModeEvent>, EventWriter<ExitBuildModeEvent>),
+    (mut tick_clock, q_pilot): (ResMut<TickClock>, Query<&Pilot, Without<Docked>>),
 ) {
     let client_id = transport.client_id();
 
@@ -232,13 +222,16 @@ fn client_sync_players(
 
         match msg {
             ServerUnreliableMessages::BulkBodies { bodies, time_stamp } => {
+                tick_clock.receive_tick(time_stamp, time.elapsed_seconds_f64());
+                let snapshot_time = tick_clock.tick_time(time_stamp);
+
                 for (server_entity, body) in bodies.iter() {
-                    let Ok(body) = body.map(&network_mapping) else {
+                    let Ok(body) = body.dequantize().map(&network_mapping) else {
                         continue;
                     };
 
                     if let Some(entity) = network_mapping.client_from_server(server_entity) {
-                        if let Ok((location, transform, velocity, net_tick, lerp_towards)) = query_body.get_mut(entity) {
+                        if let Ok((location, transform, velocity, net_tick, snapshots)) = query_body.get_mut(entity) {
                             if let Some(mut net_tick) = net_tick {
                                 if net_tick.0 >= time_stamp {
                                     // Received position packet for previous time, disregard.
@@ -251,10 +244,22 @@ fn client_sync_players(
                             }
 
                             if location.is_some() && transform.is_some() && velocity.is_some() {
-                                if let Some(mut lerp_towards) = lerp_towards {
-                                    lerp_towards.0 = body;
+                                // The ship the local player is flying is predicted instead of interpolated
+                                let predicted = q_pilot.get(entity).is_ok_and(|pilot| local_player.contains(pilot.entity));
+
+                                if predicted {
+                                    if let Some(mut snapshots) = snapshots {
+                                        snapshots.clear();
+                                    }
+
+                                    commands.entity(entity).insert(ServerBody(body));
+                                } else if let Some(mut snapshots) = snapshots {
+                                    snapshots.push(snapshot_time, body);
                                 } else {
-                                    commands.entity(entity).insert(LerpTowards(body));
+                                    let mut snapshots = SnapshotBuffer::default();
+                                    snapshots.push(snapshot_time, body);
+
+                                    commands.entity(entity).insert(snapshots);
                                 }
                             } else {
                                 let loc = match body.location {
@@ -275,11 +280,14 @@ fn client_sync_players(
                                     }
                                 };
 
+                                let mut snapshots = SnapshotBuffer::default();
+                                snapshots.push(snapshot_time, body);
+
                                 commands.entity(entity).insert((
                                     loc,
                                     BundleStartingRotation(body.rotation),
                                     body.create_velocity(),
-                                    LerpTowards(body),
+                                    snapshots,
                                 ));
                             }
                         }
@@ -302,6 +310,11 @@ fn client_sync_players(
             }
             ServerUnreliableMessages::SetMovement { movement, ship_entity } => {
                 if let Some(entity) = network_mapping.client_from_server(&ship_entity) {
+                    // The local player's ship already has its latest movement set
+                    if q_pilot.get(entity).is_ok_and(|pilot| local_player.contains(pilot.entity)) {
+                        continue;
+                    }
+
                     commands.entity(entity).insert(movement);
                 }
             }
@@ -838,6 +851,7 @@ fn player_changed_parent(
 
 pub(super) fn register(app: &mut App) {
     app.insert_resource(RequestedEntities::default())
+        .init_resource::<TickClock>()
         .configure_sets(Update, LocationPhysicsSet::DoPhysics)
         .add_systems(Update, (update_crosshair, insert_last_rotation))
         .add_systems(
@@ -850,7 +864,7 @@ pub(super) fn register(app: &mut App) {
             Update,
             (
                 fix_location.before(client_sync_players),
-                lerp_towards.after(client_sync_players),
+                interpolate_remote_bodies.after(client_sync_players),
                 (
                     player_changed_parent,
                     sync_transforms_and_locations,
@@ -14,7 +14,7 @@ use cosmos_core::{
 };
 
 use crate::input::inputs::{CosmosInputs, InputHandler};
-use crate::{input::inputs::InputChecker, rendering::MainCamera, state::game_state::GameState};
+use crate::{input::inputs::InputChecker, rendering::MainCamera, state::game_state::GameState, ui::components::text_input::not_typing};
 
 fn send_position(
     mut client: ResMut<RenetClient>,
@@ -66,5 +66,8 @@ fn send_disconnect(input_handler: InputChecker, transport: Option<ResMut<Netcode
 }
 
 pub(super) fn register(app: &mut App) {
-    app.add_systems(Update, (send_position, send_disconnect).run_if(in_state(GameState::Playing)));
+    app.add_systems(
+        Update,
+        (send_position, send_disconnect.run_if(not_typing)).run_if(in_state(GameState::Playing)),
+    );
 }
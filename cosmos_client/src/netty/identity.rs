@@ -0,0 +1,75 @@
+//! Remembers who the player is on each server, and who each server is.
+//!
+//! Each server gets its own [`IdentityKey`], so the player keeps their name on servers they've joined before.
+//! The [`TokenEndpointKey`] of every server with secure authentication is also remembered, so the player's password is
+//! never sent to something pretending to be that server.
+
+use std::{collections::HashMap, fs};
+
+use bevy::log::error;
+use cosmos_core::netty::connection::{IdentityKey, TokenEndpointKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+const IDENTITIES_FILE: &str = "settings/identities.json";
+const KNOWN_SERVERS_FILE: &str = "settings/known_servers.json";
+
+/// Reads a file mapping each server's address to something
+fn read_server_map<T: DeserializeOwned>(path: &str) -> HashMap<String, T> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+fn write_server_map<T: Serialize>(path: &str, map: &HashMap<String, T>) {
+    _ = fs::create_dir_all("settings");
+
+    let json = serde_json::to_string_pretty(map).expect("Unable to serialize server settings");
+    if let Err(e) = fs::write(path, json) {
+        error!("Unable to save {path} - {e}");
+    }
+}
+
+/// Gets the key used to prove who this player is to the server at this address.
+///
+/// A new random key is generated & saved the first time a server is joined.
+pub(super) fn identity_key(host: &str, port: u16) -> IdentityKey {
+    let mut identities = read_server_map::<IdentityKey>(IDENTITIES_FILE);
+
+    let server = format!("{host}:{port}");
+
+    if let Some(key) = identities.get(&server) {
+        return *key;
+    }
+
+    let key = rand::random::<IdentityKey>();
+    identities.insert(server, key);
+
+    // Without saving this key, the player won't be able to use this name on this server again
+    write_server_map(IDENTITIES_FILE, &identities);
+
+    key
+}
+
+/// Makes sure the server at this address is the same one the player joined before.
+///
+/// The first time a server is joined, its key is remembered.
+pub(super) fn check_server_key(host: &str, port: u16, server_key: &TokenEndpointKey) -> Result<(), String> {
+    let mut known_servers = read_server_map::<TokenEndpointKey>(KNOWN_SERVERS_FILE);
+
+    let server = format!("{host}:{port}");
+
+    match known_servers.get(&server) {
+        Some(known_key) if known_key == server_key => Ok(()),
+        Some(_) => Err(format!(
+            "The server at {server} is not the same server you joined before, so your password was not sent. \
+             If the server was reinstalled, remove it from {KNOWN_SERVERS_FILE} to trust it again."
+        )),
+        None => {
+            known_servers.insert(server, *server_key);
+            write_server_map(KNOWN_SERVERS_FILE, &known_servers);
+
+            Ok(())
+        }
+    }
+}
@@ -7,6 +7,7 @@ use crate::registry::sync_registry;
 
 pub mod connect;
 mod gameplay;
+mod identity;
 pub mod lobby;
 
 pub(super) fn register(app: &mut App) {
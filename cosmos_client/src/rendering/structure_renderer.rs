@@ -7,9 +7,9 @@ use crate::state::game_state::GameState;
 use crate::structure::planet::unload_chunks_far_from_players;
 use bevy::log::warn;
 use bevy::prelude::{
-    in_state, App, Assets, BuildChildren, Commands, Component, Deref, DerefMut, DespawnRecursiveExt, Entity, EventReader, EventWriter,
-    GlobalTransform, Handle, IntoSystemConfigs, Mesh, PointLight, PointLightBundle, Query, Rect, Res, ResMut, Resource, Transform, Update,
-    Vec3, VisibilityBundle, With,
+    in_state, App, Assets, BuildChildren, Commands, Component, Deref, DerefMut, DespawnRecursiveExt, DetectChangesMut, Entity, EventReader,
+    EventWriter, GlobalTransform, Handle, IntoSystemConfigs, Mesh, PointLight, PointLightBundle, Query, Rect, Ref, RemovedComponents, Res,
+    ResMut, Resource, Transform, Update, Vec3, Visibility, VisibilityBundle, With,
 };
 use bevy::reflect::Reflect;
 use bevy::render::mesh::{MeshVertexAttribute, VertexAttributeValues};
@@ -19,6 +19,7 @@ use bevy::transform::TransformBundle;
 use bevy::utils::hashbrown::HashMap;
 use cosmos_core::block::{Block, BlockFace};
 use cosmos_core::events::block_events::BlockChangedEvent;
+use cosmos_core::logic::LogicSignals;
 use cosmos_core::netty::client::LocalPlayer;
 use cosmos_core::physics::location::SECTOR_DIMENSIONS;
 use cosmos_core::registry::identifiable::Identifiable;
@@ -362,6 +363,36 @@ fn poll_rendering_chunks(
     }
 }
 
+/// Lights connected to a logic circuit only shine while they're receiving a signal
+fn toggle_logic_lights(
+    q_lights: Query<(Ref<LightsHolder>, &ChunkEntity)>,
+    q_logic_signals: Query<Ref<LogicSignals>>,
+    mut removed_logic_signals: RemovedComponents<LogicSignals>,
+    mut q_visibility: Query<&mut Visibility, With<PointLight>>,
+) {
+    let removed = removed_logic_signals.read().collect::<HashSet<Entity>>();
+
+    for (lights, chunk_entity) in q_lights.iter() {
+        let logic_signals = q_logic_signals.get(chunk_entity.structure_entity).ok();
+
+        if !lights.is_changed()
+            && !removed.contains(&chunk_entity.structure_entity)
+            && !logic_signals.as_ref().is_some_and(|x| x.is_changed())
+        {
+            continue;
+        }
+
+        for light in lights.lights.iter() {
+            let coords = light.position.to_block_coordinate(chunk_entity.chunk_location);
+            let on = logic_signals.as_ref().and_then(|x| x.signal(coords)).unwrap_or(true);
+
+            if let Ok(mut visibility) = q_visibility.get_mut(light.entity) {
+                visibility.set_if_neq(if on { Visibility::Inherited } else { Visibility::Hidden });
+            }
+        }
+    }
+}
+
 /// Performance hot spot
 fn monitor_needs_rendered_system(
     mut commands: Commands,
@@ -864,7 +895,12 @@ impl ChunkRenderer {
 pub(super) fn register(app: &mut App) {
     app.add_systems(
         Update,
-        (monitor_block_updates_system, monitor_needs_rendered_system, poll_rendering_chunks)
+        (
+            monitor_block_updates_system,
+            monitor_needs_rendered_system,
+            poll_rendering_chunks,
+            toggle_logic_lights,
+        )
             .chain()
             .run_if(in_state(GameState::Playing))
             .before(unload_chunks_far_from_players)
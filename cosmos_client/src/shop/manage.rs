@@ -0,0 +1,531 @@
+//! Lets the owner of a shop choose what it buys & sells
+
+use bevy::{
+    app::{App, Update},
+    asset::AssetServer,
+    core::Name,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Added, With},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::BuildChildren,
+    log::error,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{
+        node_bundles::{NodeBundle, TextBundle},
+        AlignItems, FlexDirection, JustifyContent, Style, UiRect, Val,
+    },
+};
+use bevy_renet::renet::RenetClient;
+use cosmos_core::{
+    ecs::NeedsDespawned,
+    item::Item,
+    netty::{cosmos_encoder, system_sets::NetworkingSystemsSet, NettyChannelClient},
+    registry::{identifiable::Identifiable, Registry},
+    shop::{
+        netty::{ClientShopMessages, ShopEditError},
+        Shop, ShopEntry,
+    },
+    structure::structure_block::StructureBlock,
+};
+
+use crate::{
+    lang::Lang,
+    state::game_state::GameState,
+    ui::{
+        components::{
+            button::{register_button, Button, ButtonBundle, ButtonEvent, ButtonStyles},
+            scollable_container::{ScrollBox, ScrollBundle},
+            text_input::{InputType, InputValue, TextInput, TextInputBundle},
+            window::{GuiWindow, WindowBundle},
+        },
+        message::{HudMessage, HudMessages},
+        UiSystemSet,
+    },
+};
+
+use super::EditedEvent;
+
+#[derive(Event)]
+pub(super) struct OpenManageShopUiEvent {
+    pub shop: Shop,
+    pub structure_block: StructureBlock,
+    /// # ⚠️ WARNING ⚠️
+    ///
+    /// This refers to the server's entity NOT the client's
+    pub structure_entity: Entity,
+}
+
+#[derive(Component, Debug)]
+struct ManageShopUi {
+    shop: Shop,
+    structure_block: StructureBlock,
+    structure_entity: Entity,
+}
+
+#[derive(Component, Debug)]
+struct ManageShopInputs {
+    item: Entity,
+    price: Entity,
+    credits: Entity,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ManageAction {
+    Sell,
+    Buy,
+    Remove(ShopEntry),
+    Withdraw,
+    Deposit,
+}
+
+#[derive(Component, Debug)]
+struct ManageShopButton {
+    manage_ui: Entity,
+    action: ManageAction,
+}
+
+#[derive(Event, Debug)]
+struct ManageShopBtnEvent(Entity);
+
+impl ButtonEvent for ManageShopBtnEvent {
+    fn create_event(entity: Entity) -> Self {
+        Self(entity)
+    }
+}
+
+fn open_manage_shop_ui(
+    mut commands: Commands,
+    mut ev_reader: EventReader<OpenManageShopUiEvent>,
+    q_open_manage_uis: Query<Entity, With<ManageShopUi>>,
+) {
+    for ev in ev_reader.read() {
+        for ent in q_open_manage_uis.iter() {
+            commands.entity(ent).insert(NeedsDespawned);
+        }
+
+        commands.spawn(ManageShopUi {
+            shop: ev.shop.clone(),
+            structure_block: ev.structure_block,
+            structure_entity: ev.structure_entity,
+        });
+    }
+}
+
+/// Re-opens the manage window with the shop's new contents
+fn on_shop_edited(
+    mut ev_reader: EventReader<EditedEvent>,
+    q_manage_ui: Query<&ManageShopUi>,
+    mut ev_writer: EventWriter<OpenManageShopUiEvent>,
+    mut hud_messages: ResMut<HudMessages>,
+) {
+    for ev in ev_reader.read() {
+        let shop = match &ev.details {
+            Ok(shop) => shop,
+            Err(error) => {
+                let text = match error {
+                    ShopEditError::NotOwner => "Only the owner of this shop can change it.",
+                    ShopEditError::InsufficientFunds => "You don't have enough credits.",
+                };
+
+                hud_messages.display_message(HudMessage::with_colored_string(text.into(), Color::ORANGE_RED));
+                continue;
+            }
+        };
+
+        if q_manage_ui
+            .iter()
+            .any(|x| x.structure_entity == ev.structure_entity && x.structure_block.coords() == ev.shop_block)
+        {
+            ev_writer.send(OpenManageShopUiEvent {
+                shop: shop.clone(),
+                structure_block: StructureBlock::new(ev.shop_block),
+                structure_entity: ev.structure_entity,
+            });
+        }
+    }
+}
+
+fn item_name<'a>(item_id: u16, items: &'a Registry<Item>, lang: &'a Lang<Item>) -> &'a str {
+    lang.get_name_from_numeric_id(item_id)
+        .or_else(|| items.try_from_numeric_id(item_id).map(|item| item.unlocalized_name()))
+        .unwrap_or("Unknown Item")
+}
+
+/// Finds the item the player typed, by either its display name or its unlocalized name
+fn find_item<'a>(name: &str, items: &'a Registry<Item>, lang: &Lang<Item>) -> Option<&'a Item> {
+    let name = name.trim();
+
+    items
+        .iter()
+        .find(|item| item.unlocalized_name() == name || lang.get_name(*item).map(|x| x.eq_ignore_ascii_case(name)).unwrap_or(false))
+}
+
+fn describe_entry(entry: &ShopEntry, items: &Registry<Item>, lang: &Lang<Item>) -> String {
+    match *entry {
+        ShopEntry::Selling {
+            item_id,
+            max_quantity_selling,
+            price_per,
+        } => format!(
+            "Selling {} for ${price_per} ({max_quantity_selling} in stock)",
+            item_name(item_id, items, lang)
+        ),
+        ShopEntry::Buying { item_id, price_per, .. } => format!("Buying {} for ${price_per}", item_name(item_id, items, lang)),
+    }
+}
+
+fn button_bundle(text: &str, color: &str, width: f32, text_style: &TextStyle) -> ButtonBundle<ManageShopBtnEvent> {
+    ButtonBundle {
+        node_bundle: NodeBundle {
+            style: Style {
+                width: Val::Px(width),
+                height: Val::Px(40.0),
+                margin: UiRect::left(Val::Px(10.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        button: Button {
+            button_styles: Some(ButtonStyles {
+                background_color: Color::hex(color).unwrap(),
+                hover_background_color: Color::hex(color).unwrap(),
+                press_background_color: Color::hex(color).unwrap(),
+                ..Default::default()
+            }),
+            text: Some((text.into(), text_style.clone())),
+            ..Default::default()
+        },
+    }
+}
+
+fn input_bundle(input_type: InputType, width: f32, text_style: &TextStyle) -> TextInputBundle {
+    TextInputBundle {
+        text_input: TextInput {
+            style: text_style.clone(),
+            input_type,
+            ..Default::default()
+        },
+        node_bundle: NodeBundle {
+            border_color: Color::hex("111111").unwrap().into(),
+            background_color: Color::hex("555555").unwrap().into(),
+            style: Style {
+                border: UiRect::all(Val::Px(2.0)),
+                width: Val::Px(width),
+                margin: UiRect::left(Val::Px(10.0)),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn render_manage_shop_ui(
+    mut commands: Commands,
+    q_manage_ui: Query<(Entity, &ManageShopUi), Added<ManageShopUi>>,
+    asset_server: Res<AssetServer>,
+    items: Res<Registry<Item>>,
+    lang: Res<Lang<Item>>,
+) {
+    let Ok((ui_ent, manage_ui)) = q_manage_ui.get_single() else {
+        return;
+    };
+
+    let text_style = TextStyle {
+        color: Color::WHITE,
+        font_size: 24.0,
+        font: asset_server.load("fonts/PixeloidSans.ttf"),
+    };
+
+    let row_style = Style {
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Center,
+        margin: UiRect::bottom(Val::Px(10.0)),
+        ..Default::default()
+    };
+
+    let mut inputs = ManageShopInputs {
+        item: Entity::PLACEHOLDER,
+        price: Entity::PLACEHOLDER,
+        credits: Entity::PLACEHOLDER,
+    };
+
+    commands
+        .entity(ui_ent)
+        .insert((
+            Name::new("Manage Shop UI"),
+            WindowBundle {
+                node_bundle: NodeBundle {
+                    background_color: Color::hex("2D2D2D").unwrap().into(),
+                    style: Style {
+                        width: Val::Px(800.0),
+                        height: Val::Px(600.0),
+                        margin: UiRect {
+                            // Centers it vertically
+                            top: Val::Auto,
+                            bottom: Val::Auto,
+                            left: Val::Auto,
+                            right: Val::Auto,
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                window: GuiWindow {
+                    title: format!("Manage {}", manage_ui.shop.name),
+                    body_styles: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Name::new("Credits"),
+                NodeBundle {
+                    style: row_style.clone(),
+                    ..Default::default()
+                },
+            ))
+            .with_children(|p| {
+                p.spawn(TextBundle {
+                    text: Text::from_section(format!("Shop credits: {}", manage_ui.shop.credits), text_style.clone()),
+                    ..Default::default()
+                });
+
+                inputs.credits = p
+                    .spawn(input_bundle(InputType::Integer { min: 0, max: i64::MAX }, 150.0, &text_style))
+                    .id();
+
+                p.spawn((
+                    ManageShopButton {
+                        manage_ui: ui_ent,
+                        action: ManageAction::Deposit,
+                    },
+                    button_bundle("Deposit", "008000", 120.0, &text_style),
+                ));
+
+                p.spawn((
+                    ManageShopButton {
+                        manage_ui: ui_ent,
+                        action: ManageAction::Withdraw,
+                    },
+                    button_bundle("Withdraw", "880000", 120.0, &text_style),
+                ));
+            });
+
+            p.spawn((
+                Name::new("New Entry"),
+                NodeBundle {
+                    style: row_style.clone(),
+                    ..Default::default()
+                },
+            ))
+            .with_children(|p| {
+                p.spawn(TextBundle {
+                    text: Text::from_section("Item", text_style.clone()),
+                    ..Default::default()
+                });
+
+                inputs.item = p
+                    .spawn(input_bundle(InputType::Text { max_length: Some(40) }, 250.0, &text_style))
+                    .id();
+
+                p.spawn(TextBundle {
+                    text: Text::from_section("$", text_style.clone()),
+                    ..Default::default()
+                });
+
+                inputs.price = p
+                    .spawn(input_bundle(
+                        InputType::Integer {
+                            min: 0,
+                            max: u32::MAX as i64,
+                        },
+                        100.0,
+                        &text_style,
+                    ))
+                    .id();
+
+                p.spawn((
+                    ManageShopButton {
+                        manage_ui: ui_ent,
+                        action: ManageAction::Sell,
+                    },
+                    button_bundle("Sell", "008000", 80.0, &text_style),
+                ));
+
+                p.spawn((
+                    ManageShopButton {
+                        manage_ui: ui_ent,
+                        action: ManageAction::Buy,
+                    },
+                    button_bundle("Buy", "880000", 80.0, &text_style),
+                ));
+            });
+
+            p.spawn((
+                Name::new("Entries"),
+                ScrollBundle {
+                    node_bundle: NodeBundle {
+                        style: Style {
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    slider: ScrollBox { ..Default::default() },
+                },
+            ))
+            .with_children(|p| {
+                p.spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|p| {
+                    for entry in manage_ui.shop.contents.iter() {
+                        p.spawn(NodeBundle {
+                            style: row_style.clone(),
+                            ..Default::default()
+                        })
+                        .with_children(|p| {
+                            p.spawn(TextBundle {
+                                text: Text::from_section(describe_entry(entry, &items, &lang), text_style.clone()),
+                                ..Default::default()
+                            });
+
+                            p.spawn((
+                                ManageShopButton {
+                                    manage_ui: ui_ent,
+                                    action: ManageAction::Remove(*entry),
+                                },
+                                button_bundle("Remove", "880000", 120.0, &text_style),
+                            ));
+                        });
+                    }
+                });
+            });
+        });
+
+    commands.entity(ui_ent).insert(inputs);
+}
+
+fn on_manage_button(
+    mut client: ResMut<RenetClient>,
+    q_manage_ui: Query<(&ManageShopUi, &ManageShopInputs)>,
+    q_button: Query<&ManageShopButton>,
+    q_input_value: Query<&InputValue>,
+    items: Res<Registry<Item>>,
+    lang: Res<Lang<Item>>,
+    mut hud_messages: ResMut<HudMessages>,
+    mut ev_reader: EventReader<ManageShopBtnEvent>,
+) {
+    for ev in ev_reader.read() {
+        let Ok(button) = q_button.get(ev.0) else {
+            error!("Manage shop button event missing manage shop button entity");
+            continue;
+        };
+
+        let Ok((manage_ui, inputs)) = q_manage_ui.get(button.manage_ui) else {
+            continue;
+        };
+
+        let input = |ent: Entity| q_input_value.get(ent).map(|x| x.value().to_owned()).unwrap_or_default();
+
+        let shop_block = manage_ui.structure_block.coords();
+        let structure_entity = manage_ui.structure_entity;
+
+        let message = match button.action {
+            ManageAction::Sell | ManageAction::Buy => {
+                let Some(item) = find_item(&input(inputs.item), &items, &lang) else {
+                    hud_messages.display_message(HudMessage::with_colored_string(
+                        "There is no item with that name.".into(),
+                        Color::ORANGE_RED,
+                    ));
+                    continue;
+                };
+
+                let Ok(price_per) = input(inputs.price).parse::<u32>() else {
+                    hud_messages.display_message(HudMessage::with_colored_string(
+                        "Enter a price for the item.".into(),
+                        Color::ORANGE_RED,
+                    ));
+                    continue;
+                };
+
+                let entry = if matches!(button.action, ManageAction::Sell) {
+                    ShopEntry::Selling {
+                        item_id: item.id(),
+                        // The server sets this to how many of this item are in the shop's storage
+                        max_quantity_selling: 0,
+                        price_per,
+                    }
+                } else {
+                    ShopEntry::Buying {
+                        item_id: item.id(),
+                        max_quantity_buying: None,
+                        price_per,
+                    }
+                };
+
+                ClientShopMessages::SetEntry {
+                    shop_block,
+                    structure_entity,
+                    entry,
+                }
+            }
+            ManageAction::Remove(entry) => ClientShopMessages::RemoveEntry {
+                shop_block,
+                structure_entity,
+                entry,
+            },
+            ManageAction::Withdraw => ClientShopMessages::WithdrawCredits {
+                shop_block,
+                structure_entity,
+            },
+            ManageAction::Deposit => {
+                let Ok(amount) = input(inputs.credits).parse::<u64>() else {
+                    hud_messages.display_message(HudMessage::with_colored_string(
+                        "Enter an amount of credits.".into(),
+                        Color::ORANGE_RED,
+                    ));
+                    continue;
+                };
+
+                ClientShopMessages::DepositCredits {
+                    shop_block,
+                    structure_entity,
+                    amount,
+                }
+            }
+        };
+
+        client.send_message(NettyChannelClient::Shop, cosmos_encoder::serialize(&message));
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    register_button::<ManageShopBtnEvent>(app);
+
+    app.add_event::<OpenManageShopUiEvent>().add_systems(
+        Update,
+        (on_shop_edited, open_manage_shop_ui, render_manage_shop_ui, on_manage_button)
+            .chain()
+            .after(NetworkingSystemsSet::ProcessReceivedMessages)
+            .before(UiSystemSet::DoUi)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
@@ -6,12 +6,13 @@ use bevy::{
 };
 use cosmos_core::{
     shop::{
-        netty::{ShopPurchaseError, ShopSellError},
+        netty::{ShopEditError, ShopPurchaseError, ShopSellError},
         Shop,
     },
     structure::coordinates::BlockCoordinate,
 };
 
+mod manage;
 mod netty;
 mod ui;
 
@@ -41,9 +42,37 @@ pub struct SoldEvent {
     pub details: Result<Shop, ShopSellError>,
 }
 
+#[derive(Event, Debug)]
+/// Sent whenever the owner of a shop tries to change it.
+///
+/// The change may have been unsuccessful, so make sure to check the details field.
+pub struct EditedEvent {
+    /// The structure that holds the shop
+    pub structure_entity: Entity,
+    /// The shop's block's coordinates.
+    pub shop_block: BlockCoordinate,
+    /// The shop after the change, or why it couldn't be changed.
+    pub details: Result<Shop, ShopEditError>,
+}
+
+#[derive(Event, Debug)]
+/// Sent whenever someone else changes a shop this player is looking at
+pub struct ShopUpdatedEvent {
+    /// The structure that holds the shop
+    pub structure_entity: Entity,
+    /// The shop's block's coordinates.
+    pub shop_block: BlockCoordinate,
+    /// The shop's new data
+    pub shop: Shop,
+}
+
 pub(super) fn register(app: &mut App) {
     ui::register(app);
+    manage::register(app);
     netty::register(app);
 
-    app.add_event::<PurchasedEvent>().add_event::<SoldEvent>();
+    app.add_event::<PurchasedEvent>()
+        .add_event::<SoldEvent>()
+        .add_event::<EditedEvent>()
+        .add_event::<ShopUpdatedEvent>();
 }
@@ -16,13 +16,15 @@ use cosmos_core::{
 
 use crate::state::game_state::GameState;
 
-use super::{ui::OpenShopUiEvent, PurchasedEvent, SoldEvent};
+use super::{ui::OpenShopUiEvent, EditedEvent, PurchasedEvent, ShopUpdatedEvent, SoldEvent};
 
 fn shop_listen_netty(
     mut client: ResMut<RenetClient>,
     mut ev_writer_open_shop_ui: EventWriter<MutEvent<OpenShopUiEvent>>,
     mut ev_writer_purchased: EventWriter<PurchasedEvent>,
     mut ev_writer_sold: EventWriter<SoldEvent>,
+    mut ev_writer_edited: EventWriter<EditedEvent>,
+    mut ev_writer_updated: EventWriter<ShopUpdatedEvent>,
 ) {
     while let Some(message) = client.receive_message(NettyChannelServer::Shop) {
         let msg: ServerShopMessages = cosmos_encoder::deserialize(&message).expect("Bad shop message");
@@ -64,6 +66,28 @@ fn shop_listen_netty(
                     structure_entity,
                 });
             }
+            ServerShopMessages::EditResult {
+                shop_block,
+                structure_entity,
+                details,
+            } => {
+                ev_writer_edited.send(EditedEvent {
+                    details,
+                    shop_block,
+                    structure_entity,
+                });
+            }
+            ServerShopMessages::ShopContents {
+                shop_block,
+                structure_entity,
+                shop_data,
+            } => {
+                ev_writer_updated.send(ShopUpdatedEvent {
+                    shop: shop_data,
+                    shop_block,
+                    structure_entity,
+                });
+            }
         }
     }
 }
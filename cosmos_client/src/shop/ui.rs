@@ -5,10 +5,10 @@ use bevy::{
     ecs::{
         component::Component,
         entity::Entity,
-        event::{Event, EventReader},
-        query::{Added, Changed, Or, With},
+        event::{Event, EventReader, EventWriter},
+        query::{Added, Changed, Or, With, Without},
         schedule::{common_conditions::in_state, IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
-        system::{Commands, Query, Res, ResMut},
+        system::{Commands, Local, Query, Res, ResMut},
     },
     hierarchy::{BuildChildren, DespawnRecursiveExt},
     log::{error, info},
@@ -28,11 +28,12 @@ use cosmos_core::{
         mut_events::{MutEvent, MutEventsCommand},
         NeedsDespawned,
     },
+    entities::player::Player,
     item::Item,
     netty::{client::LocalPlayer, cosmos_encoder, system_sets::NetworkingSystemsSet, NettyChannelClient},
     registry::{identifiable::Identifiable, Registry},
     shop::{netty::ClientShopMessages, Shop, ShopEntry},
-    structure::structure_block::StructureBlock,
+    structure::{coordinates::BlockCoordinate, structure_block::StructureBlock},
 };
 
 use crate::{
@@ -52,7 +53,7 @@ use crate::{
     },
 };
 
-use super::{PurchasedEvent, SoldEvent};
+use super::{manage::OpenManageShopUiEvent, EditedEvent, PurchasedEvent, ShopUpdatedEvent, SoldEvent};
 
 #[derive(Event)]
 pub(super) struct OpenShopUiEvent {
@@ -241,6 +242,7 @@ fn render_shop_ui(
     q_shop_ui: Query<(&ShopUi, Entity), Added<ShopUi>>,
     asset_server: Res<AssetServer>,
     player_credits: Query<(Entity, &Credits), With<LocalPlayer>>,
+    q_local_player: Query<&Player, With<LocalPlayer>>,
 ) {
     let Ok((shop_ui, ui_ent)) = q_shop_ui.get_single() else {
         return;
@@ -253,6 +255,11 @@ fn render_shop_ui(
 
     let name = &shop_ui.shop.name;
 
+    let is_owner = q_local_player
+        .get_single()
+        .map(|player| shop_ui.shop.is_owned_by(player.name()))
+        .unwrap_or(false);
+
     let text_style = TextStyle {
         color: Color::WHITE,
         font_size: 32.0,
@@ -369,6 +376,31 @@ fn render_shop_ui(
                         },
                     },
                 ));
+
+                if is_owner {
+                    p.spawn((
+                        ShopUiEntity(ui_ent),
+                        ButtonBundle::<ClickManageTabEvent> {
+                            node_bundle: NodeBundle {
+                                style: Style {
+                                    flex_grow: 1.0,
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            button: Button {
+                                button_styles: Some(ButtonStyles {
+                                    background_color: Color::hex("444444").unwrap(),
+                                    hover_background_color: Color::hex("444444").unwrap(),
+                                    press_background_color: Color::hex("444444").unwrap(),
+                                    ..Default::default()
+                                }),
+                                text: Some(("Manage".into(), text_style.clone())),
+                                ..Default::default()
+                            },
+                        },
+                    ));
+                }
             });
 
             p.spawn((
@@ -850,6 +882,15 @@ impl ButtonEvent for ClickBuyTabEvent {
     }
 }
 
+#[derive(Event, Debug)]
+struct ClickManageTabEvent(Entity);
+
+impl ButtonEvent for ClickManageTabEvent {
+    fn create_event(e: Entity) -> Self {
+        Self(e)
+    }
+}
+
 #[derive(Component)]
 struct BuyOrSellButton {
     shop_entity: Entity,
@@ -1250,6 +1291,81 @@ fn click_sell_tab(
     }
 }
 
+fn click_manage_tab(
+    q_shop_ui: Query<&ShopUi>,
+    q_shop_ui_entity: Query<&ShopUiEntity>,
+    mut ev_reader: EventReader<ClickManageTabEvent>,
+    mut ev_writer: EventWriter<OpenManageShopUiEvent>,
+) {
+    for ev in ev_reader.read() {
+        let Ok(shop_ui_ent) = q_shop_ui_entity.get(ev.0) else {
+            continue;
+        };
+
+        let Ok(shop_ui) = q_shop_ui.get(shop_ui_ent.0) else {
+            continue;
+        };
+
+        ev_writer.send(OpenManageShopUiEvent {
+            shop: shop_ui.shop.clone(),
+            structure_block: shop_ui.structure_block,
+            structure_entity: shop_ui.structure_entity,
+        });
+    }
+}
+
+fn on_shop_edited(mut q_shop_ui: Query<&mut ShopUi>, mut ev_reader: EventReader<EditedEvent>) {
+    for ev in ev_reader.read() {
+        let Ok(shop) = &ev.details else {
+            continue;
+        };
+
+        for mut shop_ui in q_shop_ui.iter_mut() {
+            if shop_ui.structure_entity == ev.structure_entity && shop_ui.structure_block.coords() == ev.shop_block {
+                shop_ui.shop = shop.clone();
+            }
+        }
+    }
+}
+
+fn on_shop_updated(mut q_shop_ui: Query<&mut ShopUi>, mut ev_reader: EventReader<ShopUpdatedEvent>) {
+    for ev in ev_reader.read() {
+        for mut shop_ui in q_shop_ui.iter_mut() {
+            if shop_ui.structure_entity == ev.structure_entity && shop_ui.structure_block.coords() == ev.shop_block {
+                shop_ui.shop = ev.shop.clone();
+            }
+        }
+    }
+}
+
+/// Tells the server when the shop that was open is closed, so it stops sending its changes
+fn send_close_shop(
+    mut client: ResMut<RenetClient>,
+    q_shop_ui: Query<&ShopUi, Without<NeedsDespawned>>,
+    mut open_shop: Local<Option<(Entity, BlockCoordinate)>>,
+) {
+    let now_open = q_shop_ui
+        .iter()
+        .next()
+        .map(|shop_ui| (shop_ui.structure_entity, shop_ui.structure_block.coords()));
+
+    if *open_shop == now_open {
+        return;
+    }
+
+    if let Some((structure_entity, shop_block)) = open_shop.take() {
+        client.send_message(
+            NettyChannelClient::Shop,
+            cosmos_encoder::serialize(&ClientShopMessages::CloseShop {
+                shop_block,
+                structure_entity,
+            }),
+        );
+    }
+
+    *open_shop = now_open;
+}
+
 /*
 SelectedItemName::default(),
 SelectedItemDescription::default(),
@@ -1346,6 +1462,7 @@ pub(super) fn register(app: &mut App) {
 
     register_button::<ClickSellTabEvent>(app);
     register_button::<ClickBuyTabEvent>(app);
+    register_button::<ClickManageTabEvent>(app);
     register_button::<BuyOrSellBtnEvent>(app);
     register_button::<ClickItemEvent>(app);
 
@@ -1364,6 +1481,7 @@ pub(super) fn register(app: &mut App) {
                 open_shop_ui,
                 click_buy_tab,
                 click_sell_tab,
+                click_manage_tab,
                 on_change_shop_mode,
                 click_item_event,
                 on_change_selected_item,
@@ -1372,7 +1490,10 @@ pub(super) fn register(app: &mut App) {
                 render_shop_ui,
                 enable_buy_button,
                 enable_sell_button,
+                on_shop_edited,
+                on_shop_updated,
                 on_buy,
+                send_close_shop,
             )
                 .in_set(ShopLogicSet::ShopLogic)
                 .chain(),
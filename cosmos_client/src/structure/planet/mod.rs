@@ -11,7 +11,7 @@ use cosmos_core::{
     structure::{
         chunk::{Chunk, ChunkUnloadEvent},
         coordinates::{UnboundChunkCoordinate, UnboundCoordinateType},
-        planet::Planet,
+        planet::{Planet, CHUNK_RENDER_DISTANCE},
         structure_iterator::ChunkIteratorResult,
         ChunkState, Structure,
     },
@@ -26,11 +26,6 @@ pub mod generation;
 mod lod;
 mod lods;
 
-#[cfg(debug_assertions)]
-const RENDER_DISTANCE: UnboundCoordinateType = 2;
-#[cfg(not(debug_assertions))]
-const RENDER_DISTANCE: UnboundCoordinateType = 4;
-
 fn load_planet_chunks(
     query: Query<&Location, With<LocalPlayer>>,
     mut planet: Query<(Entity, &Location, &mut Structure), With<Planet>>,
@@ -54,14 +49,14 @@ fn load_planet_chunks(
 
                 for chunk in best_planet.chunk_iter(
                     UnboundChunkCoordinate::new(
-                        ub_chunk_coords.x - RENDER_DISTANCE,
-                        ub_chunk_coords.y - RENDER_DISTANCE,
-                        ub_chunk_coords.z - RENDER_DISTANCE,
+                        ub_chunk_coords.x - CHUNK_RENDER_DISTANCE,
+                        ub_chunk_coords.y - CHUNK_RENDER_DISTANCE,
+                        ub_chunk_coords.z - CHUNK_RENDER_DISTANCE,
                     ),
                     UnboundChunkCoordinate::new(
-                        ub_chunk_coords.x + RENDER_DISTANCE,
-                        ub_chunk_coords.y + RENDER_DISTANCE,
-                        ub_chunk_coords.z + RENDER_DISTANCE,
+                        ub_chunk_coords.x + CHUNK_RENDER_DISTANCE,
+                        ub_chunk_coords.y + CHUNK_RENDER_DISTANCE,
+                        ub_chunk_coords.z + CHUNK_RENDER_DISTANCE,
                     ),
                     true,
                 ) {
@@ -105,7 +100,7 @@ pub fn unload_chunks_far_from_players(
 
             let ub_chunk_coords = UnboundChunkCoordinate::for_unbound_block_coordinate(ub_coords);
 
-            let rd = RENDER_DISTANCE + 1;
+            let rd = CHUNK_RENDER_DISTANCE + 1;
 
             let mut chunks = Vec::new();
 
@@ -16,6 +16,7 @@ use crate::state::game_state::GameState;
 
 pub mod client_ship_builder;
 pub mod create_ship;
+pub(crate) mod prediction;
 pub mod ship_movement;
 mod ui;
 
@@ -121,6 +122,7 @@ pub(super) fn register(app: &mut App) {
     client_ship_builder::register(app);
     ship_movement::register(app);
     create_ship::register(app);
+    prediction::register(app);
     ui::register(app);
 
     app.add_systems(
@@ -0,0 +1,123 @@
+//! Predicts the movement of the ship the local player is piloting.
+//!
+//! Instead of waiting a round trip for the server to move the ship, the client moves it the same way the server does.
+//! Whenever the server sends the ship's actual body, the prediction is pulled towards it to correct any drift.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::{ExternalImpulse, ReadMassProperties, Velocity};
+use bevy_renet::renet::RenetClient;
+use cosmos_core::{
+    netty::{
+        client::LocalPlayer,
+        interpolation::lerp_location,
+        netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation},
+    },
+    physics::location::Location,
+    structure::{
+        ship::{
+            pilot::Pilot,
+            ship_movement::{limit_speed, ShipMovement},
+        },
+        systems::{dock_system::Docked, thruster_system::ThrusterSystem, StructureSystems},
+    },
+};
+
+use crate::state::game_state::GameState;
+
+/// If the prediction is this far (in blocks) from the server, it is snapped to the server's position
+const SNAP_DISTANCE: f32 = 20.0;
+/// How much of the difference between the prediction & the server is corrected each time the server sends the ship's body
+const CORRECTION_FACTOR: f32 = 0.1;
+
+#[derive(Component, Debug)]
+/// The latest body the server sent for the ship being predicted, that the prediction hasn't been corrected by yet
+pub(crate) struct ServerBody(pub NettyRigidBody);
+
+fn predict_piloted_ship(
+    q_local_pilot: Query<&Pilot, With<LocalPlayer>>,
+    mut q_ship: Query<
+        (
+            &ShipMovement,
+            &StructureSystems,
+            &Transform,
+            &mut Velocity,
+            &mut ExternalImpulse,
+            &ReadMassProperties,
+        ),
+        Without<Docked>,
+    >,
+    q_thrusters: Query<&ThrusterSystem>,
+    time: Res<Time>,
+) {
+    let Ok(pilot) = q_local_pilot.get_single() else {
+        return;
+    };
+
+    let Ok((movement, systems, transform, mut velocity, mut external_impulse, readmass)) = q_ship.get_mut(pilot.entity) else {
+        return;
+    };
+
+    let Ok(thrusters) = systems.query(&q_thrusters) else {
+        return;
+    };
+
+    // The client doesn't know how much energy the ship has, so assume every thruster always has full power.
+    // The server's corrections will slow it down if it's actually out of energy.
+    let output = thrusters.full_output();
+
+    external_impulse.torque_impulse += movement.torque_impulse(transform, velocity.angvel, &readmass.0, &output, time.delta_seconds());
+
+    limit_speed(&mut velocity);
+
+    external_impulse.impulse += movement.thrust_impulse(
+        transform,
+        velocity.linvel,
+        readmass.0.mass,
+        thrusters,
+        &output,
+        time.delta_seconds(),
+    );
+}
+
+fn reconcile_piloted_ship(
+    mut commands: Commands,
+    client: Res<RenetClient>,
+    mut q_ship: Query<(Entity, &ServerBody, &mut Location, &mut Transform, &mut Velocity)>,
+) {
+    for (entity, server_body, mut location, mut transform, mut velocity) in q_ship.iter_mut() {
+        commands.entity(entity).remove::<ServerBody>();
+
+        let NettyRigidBodyLocation::Absolute(server_location) = server_body.0.location else {
+            continue;
+        };
+
+        let server_velocity = server_body.0.create_velocity();
+
+        // The server's body is from about a round trip before the inputs being predicted now
+        let rtt = client.rtt() as f32;
+        let server_location = server_location + server_velocity.linvel * rtt;
+        let server_rotation = Quat::from_scaled_axis(server_velocity.angvel * rtt) * server_body.0.rotation;
+
+        if location.distance_sqrd(&server_location) > SNAP_DISTANCE * SNAP_DISTANCE {
+            location.set_from(&server_location);
+            transform.rotation = server_rotation;
+            *velocity = server_velocity;
+        } else {
+            let corrected = lerp_location(&location, &server_location, CORRECTION_FACTOR);
+
+            location.set_from(&corrected);
+            transform.rotation = transform.rotation.slerp(server_rotation, CORRECTION_FACTOR);
+            velocity.linvel = velocity.linvel.lerp(server_velocity.linvel, CORRECTION_FACTOR);
+            velocity.angvel = velocity.angvel.lerp(server_velocity.angvel, CORRECTION_FACTOR);
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        (reconcile_piloted_ship, predict_piloted_ship)
+            .chain()
+            .run_if(in_state(GameState::Playing)),
+    );
+}
@@ -31,6 +31,7 @@ fn process_ship_movement(
     cursor_delta_position: Res<DeltaCursorPosition>,
     primary_query: Query<&Window, With<PrimaryWindow>>,
     cursor_flags: Res<CursorFlags>,
+    mut q_movement: Query<&mut ShipMovement>,
 ) {
     let Ok(pilot) = q_local_pilot.get_single() else {
         return;
@@ -138,6 +139,11 @@ fn process_ship_movement(
         ));
     }
 
+    // Set locally too so the ship's movement can be predicted
+    if let Ok(mut ship_movement) = q_movement.get_mut(pilot.entity) {
+        *ship_movement = movement;
+    }
+
     client.send_message(
         NettyChannelClient::Unreliable,
         cosmos_encoder::serialize(&ClientUnreliableMessages::SetMovement { movement }),
@@ -11,6 +11,7 @@ pub mod player_interactions;
 mod shield_system;
 mod sync;
 pub mod thruster_system;
+mod turret_system;
 
 use bevy::prelude::App;
 
@@ -25,5 +26,6 @@ pub(super) fn register(app: &mut App) {
     energy_generation_system::register(app);
     energy_storage_system::register(app);
     missile_launcher_system::register(app);
+    turret_system::register(app);
     sync::register(app);
 }
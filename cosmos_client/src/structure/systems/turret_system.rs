@@ -0,0 +1,8 @@
+use bevy::app::App;
+use cosmos_core::structure::systems::turret_system::TurretSystem;
+
+use super::sync::sync_system;
+
+pub(super) fn register(app: &mut App) {
+    sync_system::<TurretSystem>(app);
+}
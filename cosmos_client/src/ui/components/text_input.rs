@@ -13,7 +13,7 @@ use bevy::{
         component::Component,
         entity::Entity,
         event::EventReader,
-        query::{Added, Changed, Or},
+        query::{Added, Changed, Or, With},
         schedule::{common_conditions::resource_changed, IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
         system::{Commands, Query, Res, ResMut, Resource},
     },
@@ -554,6 +554,13 @@ fn handle_keyboard_shortcuts(
     }
 }
 
+/// A system condition that returns true if the user isn't typing in a [`TextInput`].
+///
+/// Use this for keyboard shortcuts that shouldn't trigger while the user is typing.
+pub fn not_typing(focused: Res<Focus>, q_text_inputs: Query<(), With<TextInput>>) -> bool {
+    focused.0.map(|x| !q_text_inputs.contains(x)).unwrap_or(true)
+}
+
 fn verify_input(text_input: &TextInput, test_value: &str) -> bool {
     match text_input.input_type {
         InputType::Text { max_length } => max_length.map(|max_len| test_value.len() <= max_len).unwrap_or(true),
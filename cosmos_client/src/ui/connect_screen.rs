@@ -0,0 +1,84 @@
+//! Shown while connecting to a server, and displays why the connection failed if it does
+
+use bevy::prelude::*;
+
+use crate::{
+    netty::connect::{ConnectionFailed, HostConfig},
+    state::game_state::GameState,
+};
+
+#[derive(Component)]
+struct ConnectScreen;
+
+#[derive(Component)]
+struct ConnectStatusText;
+
+fn create_connect_screen(mut commands: Commands, asset_server: Res<AssetServer>, host_config: Res<HostConfig>) {
+    commands.spawn((Camera2dBundle::default(), ConnectScreen));
+
+    let text_style = TextStyle {
+        color: Color::WHITE,
+        font_size: 32.0,
+        font: asset_server.load("fonts/PixeloidSans.ttf"),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            ConnectScreen,
+        ))
+        .with_children(|p| {
+            p.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        format!("Connecting to {}:{}...", host_config.host_name, host_config.port),
+                        text_style,
+                    )
+                    .with_justify(JustifyText::Center),
+                    ..default()
+                },
+                ConnectStatusText,
+            ));
+        });
+}
+
+fn show_connection_failed(connection_failed: Res<ConnectionFailed>, mut q_text: Query<&mut Text, With<ConnectStatusText>>) {
+    for mut text in q_text.iter_mut() {
+        let style = TextStyle {
+            color: Color::RED,
+            ..text.sections[0].style.clone()
+        };
+
+        *text = Text::from_sections([
+            TextSection::new("Unable to join server\n\n", style.clone()),
+            TextSection::new(connection_failed.0.clone(), style),
+        ])
+        .with_justify(JustifyText::Center);
+    }
+}
+
+fn remove_connect_screen(mut commands: Commands, q_connect_screen: Query<Entity, With<ConnectScreen>>) {
+    for ent in q_connect_screen.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Connecting), create_connect_screen)
+        .add_systems(
+            Update,
+            show_connection_failed
+                .run_if(in_state(GameState::Connecting))
+                .run_if(resource_exists_and_changed::<ConnectionFailed>),
+        )
+        .add_systems(OnExit(GameState::Connecting), remove_connect_screen);
+}
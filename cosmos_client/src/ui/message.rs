@@ -1,22 +1,44 @@
 //! Displays any messages the player needs to see
 
-use std::{collections::VecDeque, time::Duration};
+use std::{collections::VecDeque, ops::Range, time::Duration};
 
 use bevy::{
+    a11y::Focus,
+    input::mouse::MouseWheel,
     prelude::{
-        in_state, App, AssetServer, BuildChildren, Color, Commands, Component, DespawnRecursiveExt, IntoSystemConfigs, Name, NodeBundle,
-        Parent, Query, Res, ResMut, Resource, TextBundle, Update,
+        in_state, App, AssetServer, BuildChildren, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader, EventWriter,
+        IntoSystemConfigs, KeyCode, Local, Name, NodeBundle, OnEnter, Parent, Query, Res, ResMut, Resource, TextBundle, Update, With,
     },
     text::{JustifyText, Text, TextSection, TextStyle},
     time::Time,
-    ui::{JustifyContent, PositionType, Style, Val},
+    ui::{BackgroundColor, FlexDirection, JustifyContent, PositionType, Style, UiRect, Val},
 };
+use cosmos_core::chat::MAX_CHAT_MESSAGE_LENGTH;
 
-use crate::state::game_state::GameState;
+use crate::{
+    chat::SendChatMessageEvent,
+    input::inputs::{CosmosInputs, InputChecker, InputHandler},
+    state::game_state::GameState,
+};
+
+use super::{
+    components::{
+        show_cursor::ShowCursor,
+        text_input::{InputType, InputValue, TextInput, TextInputBundle},
+    },
+    UiSystemSet,
+};
 
 const HUD_DISPLAY_DURATION: Duration = Duration::from_secs(7);
 const FADE_DURATION: Duration = Duration::from_secs(3);
 
+/// Only this many chat messages are remembered
+const MAX_CHAT_HISTORY: usize = 100;
+/// The most chat messages that are shown at once
+const VISIBLE_CHAT_LINES: usize = 10;
+/// How long chat messages stay on screen while the chat isn't open
+const CHAT_DISPLAY_DURATION: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 /// A way of describing colored text used in the HUD message
 pub struct RichText {
@@ -204,7 +226,247 @@ fn display_hud_messages(
     }
 }
 
+#[derive(Debug, Clone)]
+struct ChatLine {
+    message: HudMessage,
+    received_at: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+/// Every chat message the player has received, and how far back they have scrolled through them
+pub struct ChatHistory {
+    lines: VecDeque<ChatLine>,
+    /// How many lines up from the newest message the player has scrolled
+    scroll_offset: usize,
+}
+
+impl ChatHistory {
+    /// Adds this message to the bottom of the chat
+    pub fn add_message(&mut self, message: HudMessage, time: &Time) {
+        self.lines.push_back(ChatLine {
+            message,
+            received_at: time.elapsed_seconds(),
+        });
+
+        if self.lines.len() > MAX_CHAT_HISTORY {
+            self.lines.pop_front();
+        } else if self.scroll_offset != 0 {
+            // Keep the lines the player scrolled to in place
+            self.scroll_offset += 1;
+        }
+
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll_offset());
+    }
+
+    fn max_scroll_offset(&self) -> usize {
+        self.lines.len().saturating_sub(VISIBLE_CHAT_LINES)
+    }
+
+    /// The lines that should currently be shown
+    fn visible_lines(&self, chat_open: bool, time_now: f32) -> Range<usize> {
+        let end = self.lines.len() - self.scroll_offset;
+        let mut start = end.saturating_sub(VISIBLE_CHAT_LINES);
+
+        if !chat_open {
+            // Lines are in the order they were received, so any old ones will be at the start
+            while start < end && time_now - self.lines[start].received_at > CHAT_DISPLAY_DURATION.as_secs_f32() {
+                start += 1;
+            }
+        }
+
+        start..end
+    }
+}
+
+#[derive(Component)]
+struct ChatWindow;
+
+#[derive(Component)]
+struct ChatText;
+
+#[derive(Component)]
+/// The text box the player types their chat messages in
+struct ChatInput;
+
+fn create_chat_window(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Chat Window"),
+            ChatWindow,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(170.0),
+                    width: Val::Px(500.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn((Name::new("Chat Text"), ChatText, TextBundle::default()));
+        });
+}
+
+/// Opens the chat when the player presses the chat key, and sends what they typed when they press enter
+fn toggle_chat(
+    mut commands: Commands,
+    inputs: InputChecker,
+    q_chat_window: Query<Entity, With<ChatWindow>>,
+    q_chat_input: Query<(Entity, &InputValue), With<ChatInput>>,
+    q_show_cursor: Query<(), With<ShowCursor>>,
+    mut focus: ResMut<Focus>,
+    mut chat_history: ResMut<ChatHistory>,
+    mut ev_writer: EventWriter<SendChatMessageEvent>,
+) {
+    if let Ok((entity, value)) = q_chat_input.get_single() {
+        if inputs.key_inputs().just_pressed(KeyCode::Enter) {
+            if !value.value().trim().is_empty() {
+                ev_writer.send(SendChatMessageEvent(value.value().to_owned()));
+            }
+
+            commands.entity(entity).despawn_recursive();
+        } else if inputs.key_inputs().just_pressed(KeyCode::Escape) {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        return;
+    }
+
+    // Don't open the chat over another menu
+    if !q_show_cursor.is_empty() || !inputs.check_just_pressed(CosmosInputs::OpenChat) {
+        return;
+    }
+
+    let Ok(chat_window) = q_chat_window.get_single() else {
+        return;
+    };
+
+    chat_history.scroll_offset = 0;
+
+    commands.entity(chat_window).with_children(|p| {
+        let chat_input = p
+            .spawn((
+                Name::new("Chat Input"),
+                ChatInput,
+                ShowCursor,
+                TextInputBundle {
+                    text_input: TextInput {
+                        input_type: InputType::Text {
+                            max_length: Some(MAX_CHAT_MESSAGE_LENGTH),
+                        },
+                        style: TextStyle {
+                            color: Color::WHITE,
+                            font_size: 18.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    node_bundle: NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(24.0),
+                            margin: UiRect::top(Val::Px(5.0)),
+                            ..Default::default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ))
+            .id();
+
+        focus.0 = Some(chat_input);
+    });
+}
+
+fn scroll_chat(mut evr_scroll: EventReader<MouseWheel>, q_chat_input: Query<(), With<ChatInput>>, mut chat_history: ResMut<ChatHistory>) {
+    if q_chat_input.is_empty() {
+        evr_scroll.clear();
+        return;
+    }
+
+    for ev in evr_scroll.read() {
+        if ev.y > 0.0 {
+            chat_history.scroll_offset = (chat_history.scroll_offset + 1).min(chat_history.max_scroll_offset());
+        } else if ev.y < 0.0 {
+            chat_history.scroll_offset = chat_history.scroll_offset.saturating_sub(1);
+        }
+    }
+}
+
+fn update_chat_text(
+    chat_history: Res<ChatHistory>,
+    q_chat_input: Query<(), With<ChatInput>>,
+    mut q_chat_window: Query<&mut BackgroundColor, With<ChatWindow>>,
+    mut q_chat_text: Query<&mut Text, With<ChatText>>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut last_shown: Local<Option<(Range<usize>, bool)>>,
+) {
+    let chat_open = !q_chat_input.is_empty();
+    let visible_lines = chat_history.visible_lines(chat_open, time.elapsed_seconds());
+
+    let shown = Some((visible_lines.clone(), chat_open));
+    if !chat_history.is_changed() && *last_shown == shown {
+        return;
+    }
+    *last_shown = shown;
+
+    if let Ok(mut background_color) = q_chat_window.get_single_mut() {
+        background_color.0 = if chat_open && !chat_history.lines.is_empty() {
+            Color::rgba(0.0, 0.0, 0.0, 0.4)
+        } else {
+            Color::NONE
+        };
+    }
+
+    let Ok(mut text) = q_chat_text.get_single_mut() else {
+        return;
+    };
+
+    let font = asset_server.load("fonts/PixeloidSans.ttf");
+
+    text.sections.clear();
+
+    for (i, line_idx) in visible_lines.enumerate() {
+        if i != 0 {
+            text.sections.push(TextSection::new(
+                "\n",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    ..Default::default()
+                },
+            ));
+        }
+
+        text.sections
+            .extend(chat_history.lines[line_idx].message.text.iter().map(|x| TextSection {
+                value: x.text.clone(),
+                style: TextStyle {
+                    color: x.color,
+                    font: font.clone(),
+                    font_size: 18.0,
+                },
+            }));
+    }
+}
+
 pub(super) fn register(app: &mut App) {
     app.init_resource::<HudMessages>()
-        .add_systems(Update, display_hud_messages.run_if(in_state(GameState::Playing)));
+        .init_resource::<ChatHistory>()
+        .add_systems(OnEnter(GameState::Playing), create_chat_window)
+        .add_systems(
+            Update,
+            (
+                display_hud_messages,
+                (toggle_chat, scroll_chat, update_chat_text).chain().before(UiSystemSet::DoUi),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
 }
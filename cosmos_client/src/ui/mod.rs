@@ -10,6 +10,7 @@ use bevy::{
 };
 
 pub mod components;
+mod connect_screen;
 pub mod crosshair;
 pub mod debug_info_display;
 pub mod hotbar;
@@ -41,6 +42,7 @@ pub struct UiRoot;
 pub struct UiTopRoot;
 
 pub(super) fn register(app: &mut App) {
+    connect_screen::register(app);
     crosshair::register(app);
     hotbar::register(app);
     debug_info_display::register(app);
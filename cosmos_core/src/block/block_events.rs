@@ -4,6 +4,7 @@ use bevy::prelude::*;
 
 use crate::{
     blockitems::BlockItems,
+    entities::player::game_mode::GameMode,
     events::block_events::BlockChangedEvent,
     inventory::Inventory,
     item::Item,
@@ -63,7 +64,7 @@ fn handle_block_break_events(
     blocks: Res<Registry<Block>>,
     items: Res<Registry<Item>>,
     block_items: Res<BlockItems>, // TODO: Replace this with drop table
-    mut inventory_query: Query<(&mut Inventory, Option<&BuildMode>, Option<&Parent>), Without<BlockData>>,
+    mut inventory_query: Query<(&mut Inventory, Option<&BuildMode>, Option<&Parent>, Option<&GameMode>), Without<BlockData>>,
     mut event_writer: EventWriter<BlockChangedEvent>,
     mut q_inventory_block_data: Query<(&BlockData, &mut Inventory)>,
     mut commands: Commands,
@@ -115,7 +116,9 @@ fn handle_block_break_events(
             }
 
             structure.remove_block_at(coord, &blocks, Some(&mut event_writer));
-        } else if let Ok((mut inventory, build_mode, parent)) = inventory_query.get_mut(ev.breaker) {
+        } else if let Ok((mut inventory, build_mode, parent, game_mode)) = inventory_query.get_mut(ev.breaker) {
+            let uses_items = game_mode.copied().unwrap_or_default().uses_items();
+
             if let Ok(mut structure) = q_structure.get_mut(ev.structure_entity) {
                 let mut structure_blocks = vec![(ev.block.coords(), BlockRotation::default())];
 
@@ -146,10 +149,12 @@ fn handle_block_break_events(
                     }
 
                     if block.id() != AIR_BLOCK_ID {
-                        if let Some(item_id) = block_items.item_from_block(block) {
-                            let item = items.from_numeric_id(item_id);
+                        if uses_items {
+                            if let Some(item_id) = block_items.item_from_block(block) {
+                                let item = items.from_numeric_id(item_id);
 
-                            inventory.insert(item, 1);
+                                inventory.insert(item, 1);
+                            }
                         }
 
                         structure.remove_block_at(coord, &blocks, Some(&mut event_writer));
@@ -330,16 +335,18 @@ fn handle_block_place_events(
     mut query: Query<&mut Structure>,
     mut event_reader: EventReader<BlockPlaceEvent>,
     mut event_writer: EventWriter<BlockChangedEvent>,
-    mut player_query: Query<(&mut Inventory, Option<&BuildMode>, Option<&Parent>)>,
+    mut player_query: Query<(&mut Inventory, Option<&BuildMode>, Option<&Parent>, Option<&GameMode>)>,
     items: Res<Registry<Item>>,
     blocks: Res<Registry<Block>>,
     block_items: Res<BlockItems>,
 ) {
     for ev in event_reader.read() {
-        let Ok((mut inv, build_mode, parent)) = player_query.get_mut(ev.placer) else {
+        let Ok((mut inv, build_mode, parent, game_mode)) = player_query.get_mut(ev.placer) else {
             continue;
         };
 
+        let uses_items = game_mode.copied().unwrap_or_default().uses_items();
+
         let Ok(mut structure) = query.get_mut(ev.structure_entity) else {
             continue;
         };
@@ -375,7 +382,7 @@ fn handle_block_place_events(
                 break;
             }
 
-            if inv.decrease_quantity_at(ev.inventory_slot, 1) == 0 {
+            if !uses_items || inv.decrease_quantity_at(ev.inventory_slot, 1) == 0 {
                 structure.set_block_at(coords, block, block_up, &blocks, Some(&mut event_writer));
             } else {
                 break;
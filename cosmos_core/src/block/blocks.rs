@@ -61,6 +61,8 @@ fn add_cosmos_blocks(
     blocks.register(
         BlockBuilder::new("cosmos:ship_core", 2.0, 20.0, 20.0)
             .add_property(BlockProperty::Full)
+            .add_connection_group("cosmos:stores_power")
+            .add_connection_group("cosmos:produces_power")
             .create(),
     );
 
@@ -96,6 +98,7 @@ fn add_cosmos_blocks(
     blocks.register(
         BlockBuilder::new("cosmos:thruster", 2.0, 20.0, 10.0)
             .add_property(BlockProperty::Full)
+            .add_property(BlockProperty::FaceFront)
             .add_connection_group("cosmos:consumes_power")
             .create(),
     );
@@ -333,6 +336,7 @@ fn add_cosmos_blocks(
     blocks.register(
         BlockBuilder::new("cosmos:shield_projector", 2.0, 20.0, 5.0)
             .add_property(BlockProperty::Full)
+            .add_connection_group("cosmos:consumes_power")
             .create(),
     );
 
@@ -375,6 +379,44 @@ fn add_cosmos_blocks(
             .create(),
     );
 
+    blocks.register(
+        BlockBuilder::new("cosmos:logic_button", 0.1, 20.0, 5.0)
+            .add_property(BlockProperty::Full)
+            .add_connection_group("cosmos:uses_logic")
+            .create(),
+    );
+
+    for gate in ["and", "or", "not", "delay"] {
+        blocks.register(
+            BlockBuilder::new(format!("cosmos:logic_{gate}"), 0.1, 20.0, 5.0)
+                .add_property(BlockProperty::Full)
+                .add_property(BlockProperty::FaceFront)
+                .add_connection_group("cosmos:uses_logic")
+                .create(),
+        );
+    }
+
+    blocks.register(
+        BlockBuilder::new("cosmos:fabricator", 2.0, 20.0, 5.0)
+            .add_property(BlockProperty::Full)
+            .create(),
+    );
+
+    blocks.register(
+        BlockBuilder::new("cosmos:turret", 2.0, 20.0, 5.0)
+            .add_property(BlockProperty::Full)
+            .add_connection_group("cosmos:consumes_power")
+            .create(),
+    );
+
+    // Takes the place of any saved blocks that no longer exist. Keep this registered last so
+    // worlds saved before block palettes existed keep their ids.
+    blocks.register(
+        BlockBuilder::new("cosmos:unknown", 2.0, 20.0, 5.0)
+            .add_property(BlockProperty::Full)
+            .create(),
+    );
+
     loading.finish_loading(id, &mut end_writer);
 }
 
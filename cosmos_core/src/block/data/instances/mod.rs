@@ -2,8 +2,10 @@
 
 use bevy::app::App;
 
+pub mod shop;
 pub mod storage;
 
 pub(super) fn register(app: &mut App) {
     storage::register(app);
+    shop::register(app);
 }
@@ -0,0 +1,52 @@
+//! Handles the deserialization of shops
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        event::EventReader,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query},
+    },
+    log::warn,
+};
+
+use crate::{
+    block::data::{persistence::ChunkLoadBlockDataEvent, BlockData},
+    shop::Shop,
+    structure::{loading::StructureLoadingSet, Structure},
+};
+
+fn deserialize_shop(
+    q_structure: Query<&Structure>,
+    mut q_block_data: Query<&mut BlockData>,
+    mut commands: Commands,
+    mut ev_reader: EventReader<ChunkLoadBlockDataEvent>,
+) {
+    for ev in ev_reader.read() {
+        let Ok(structure) = q_structure.get(ev.structure_entity) else {
+            warn!("No structure but tried to deserialize shop.");
+            continue;
+        };
+
+        let first = ev.chunk.first_structure_block();
+        for (data_coord, serialized) in ev.data.iter() {
+            let Some(shop) = serialized.deserialize_data::<Shop>("cosmos:shop") else {
+                continue;
+            };
+
+            let data_ent = structure
+                .block_data(first + *data_coord)
+                .expect("Missing data entity despite having data here");
+
+            commands.entity(data_ent).insert(shop);
+            q_block_data
+                .get_mut(data_ent)
+                .expect("Block data missing `BlockData` component!")
+                .increment();
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Update, deserialize_shop.in_set(StructureLoadingSet::LoadChunkData));
+}
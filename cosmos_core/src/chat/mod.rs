@@ -0,0 +1,75 @@
+//! Text chat between players & the server
+
+use bevy::ecs::component::Component;
+use serde::{Deserialize, Serialize};
+
+/// Chat messages longer than this are cut off
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+
+#[derive(Debug, Serialize, Deserialize, Component)]
+/// Chat messages the client sends to the server
+pub enum ClientChatMessages {
+    /// The player wants to send this message to everyone
+    SendMessage {
+        /// What they typed
+        message: String,
+    },
+    /// The player wants to run a command
+    SendCommand {
+        /// The command they typed, without the leading `/`
+        command: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Component)]
+/// Chat messages the server sends to clients
+pub enum ServerChatMessages {
+    /// A message was sent in chat
+    Message {
+        /// The name of the player that sent this, or `None` if the server sent it
+        sender: Option<String>,
+        /// The message's text
+        message: String,
+    },
+}
+
+/// Removes control characters, trims the message & cuts it off at [`MAX_CHAT_MESSAGE_LENGTH`] characters.
+///
+/// Returns `None` if there is nothing left to send.
+pub fn sanitize_chat_message(message: &str) -> Option<String> {
+    // Control characters are removed before trimming, so a message made of them & whitespace ends up empty
+    let message = message.chars().filter(|c| !c.is_control()).collect::<String>();
+    let message = message.trim();
+
+    if message.is_empty() {
+        return None;
+    }
+
+    Some(message.chars().take(MAX_CHAT_MESSAGE_LENGTH).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sanitize_chat_message, MAX_CHAT_MESSAGE_LENGTH};
+
+    #[test]
+    fn empty_messages_are_ignored() {
+        assert_eq!(sanitize_chat_message("   "), None);
+        assert_eq!(sanitize_chat_message("\u{7}\u{1b}"), None);
+        assert_eq!(sanitize_chat_message(" \u{7} \u{0} "), None);
+    }
+
+    #[test]
+    fn control_characters_are_removed() {
+        assert_eq!(sanitize_chat_message("\u{7} hi\u{1b} "), Some("hi".into()));
+    }
+
+    #[test]
+    fn messages_are_trimmed_and_cut_off() {
+        assert_eq!(sanitize_chat_message("  hi\n"), Some("hi".into()));
+        assert_eq!(
+            sanitize_chat_message(&"a".repeat(1000)).map(|x| x.len()),
+            Some(MAX_CHAT_MESSAGE_LENGTH)
+        );
+    }
+}
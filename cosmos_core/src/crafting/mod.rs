@@ -0,0 +1,120 @@
+//! Turning items into other items.
+//!
+//! Every [`Recipe`] is registered on the server & synced to clients, and is crafted at a fabricator block.
+
+use bevy::{app::App, reflect::Reflect, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{self, identifiable::Identifiable};
+
+pub mod netty;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Reflect, PartialEq, Eq)]
+/// An amount of some item that is used or made by a recipe
+pub struct RecipeItem {
+    /// The item's id
+    pub item_id: u16,
+    /// How many of this item
+    pub quantity: u16,
+}
+
+impl RecipeItem {
+    /// Creates an amount of some item
+    pub fn new(item_id: u16, quantity: u16) -> Self {
+        Self { item_id, quantity }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+/// Turns a set of items into another item
+pub struct Recipe {
+    id: u16,
+    unlocalized_name: String,
+
+    /// The items this recipe uses up every time it is crafted
+    pub inputs: Vec<RecipeItem>,
+    /// The item this recipe makes every time it is crafted
+    pub output: RecipeItem,
+}
+
+impl Identifiable for Recipe {
+    fn id(&self) -> u16 {
+        self.id
+    }
+
+    fn set_numeric_id(&mut self, id: u16) {
+        self.id = id;
+    }
+
+    fn unlocalized_name(&self) -> &str {
+        &self.unlocalized_name
+    }
+}
+
+impl Recipe {
+    /// Creates a new recipe
+    pub fn new(unlocalized_name: impl Into<String>, inputs: Vec<RecipeItem>, output: RecipeItem) -> Self {
+        Self {
+            id: 0,
+            unlocalized_name: unlocalized_name.into(),
+            inputs,
+            output,
+        }
+    }
+
+    /// Returns how many times this recipe can be crafted if `quantity_of` returns how many of each item are available.
+    ///
+    /// Recipes without any inputs can be crafted any amount of times.
+    pub fn max_crafts(&self, quantity_of: impl Fn(u16) -> usize) -> usize {
+        let mut needed: HashMap<u16, usize> = HashMap::default();
+
+        for input in self.inputs.iter() {
+            *needed.entry(input.item_id).or_default() += input.quantity as usize;
+        }
+
+        needed
+            .into_iter()
+            .filter(|(_, quantity)| *quantity != 0)
+            .map(|(item_id, quantity)| quantity_of(item_id) / quantity)
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    registry::create_registry::<Recipe>(app, "cosmos:recipes");
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Recipe, RecipeItem};
+
+    fn quantities(item_id: u16) -> usize {
+        match item_id {
+            1 => 10,
+            2 => 3,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn limited_by_scarcest_input() {
+        let recipe = Recipe::new("test", vec![RecipeItem::new(1, 2), RecipeItem::new(2, 1)], RecipeItem::new(3, 1));
+
+        assert_eq!(recipe.max_crafts(quantities), 3);
+    }
+
+    #[test]
+    fn duplicate_inputs_add_up() {
+        let recipe = Recipe::new("test", vec![RecipeItem::new(1, 3), RecipeItem::new(1, 3)], RecipeItem::new(3, 1));
+
+        assert_eq!(recipe.max_crafts(quantities), 1);
+    }
+
+    #[test]
+    fn missing_input() {
+        let recipe = Recipe::new("test", vec![RecipeItem::new(1, 1), RecipeItem::new(4, 1)], RecipeItem::new(3, 1));
+
+        assert_eq!(recipe.max_crafts(quantities), 0);
+    }
+}
@@ -0,0 +1,62 @@
+//! Represents the communications about crafting between the client & server
+
+use bevy::ecs::entity::Entity;
+use serde::{Deserialize, Serialize};
+
+use crate::structure::coordinates::BlockCoordinate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+/// An error occurred when trying to craft something
+pub enum CraftingError {
+    /// There is no recipe with that id
+    UnknownRecipe,
+    /// That block isn't a fabricator
+    NotAFabricator,
+    /// The crafter doesn't have enough items to craft that many times
+    NotEnoughItems,
+    /// The crafter didn't have enough room in their inventory to fit the crafted items
+    NotEnoughInventorySpace,
+    /// The crafter is too far away from the fabricator to use it
+    TooFarAway,
+    /// The crafter isn't allowed to use fabricators on this structure
+    NotAllowed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Messages about crafting the server will send to the player
+pub enum ServerCraftingMessages {
+    /// Tells the client to open a fabricator menu
+    OpenFabricator {
+        /// The fabricator's block
+        fabricator_block: BlockCoordinate,
+        /// The fabricator's structure entity
+        structure_entity: Entity,
+    },
+    /// Sent whenever an attempt to craft something is handled
+    CraftResult {
+        /// The fabricator's block
+        fabricator_block: BlockCoordinate,
+        /// The fabricator's structure entity
+        structure_entity: Entity,
+        /// The recipe that was being crafted
+        recipe_id: u16,
+        /// How many times it was crafted if it was successful
+        details: Result<u32, CraftingError>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Sent from the client to the server to communicate about crafting.
+pub enum ClientCraftingMessages {
+    /// Client requests to craft a recipe
+    Craft {
+        /// The fabricator's block
+        fabricator_block: BlockCoordinate,
+        /// The fabricator's structure entity
+        structure_entity: Entity,
+        /// The recipe they want to craft
+        recipe_id: u16,
+        /// How many times they want to craft it
+        quantity: u32,
+    },
+}
@@ -3,3 +3,9 @@
 //! This is far to generic of a module, and should be removed at some point in favor of more specific modules.
 
 pub mod player;
+
+use bevy::app::App;
+
+pub(super) fn register(app: &mut App) {
+    player::register(app);
+}
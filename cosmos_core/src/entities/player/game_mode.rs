@@ -0,0 +1,59 @@
+//! Changes the rules a player plays by
+
+use bevy::{app::App, ecs::component::Component, reflect::Reflect};
+use serde::{Deserialize, Serialize};
+
+use crate::netty::sync::{sync_component, SyncType, SyncableComponent};
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
+#[serde(rename_all = "snake_case")]
+/// Changes the rules a player plays by.
+///
+/// Players without this component should be treated as being in survival.
+pub enum GameMode {
+    #[default]
+    /// Blocks are used up when placed & give their items back when broken
+    Survival,
+    /// Placing blocks never uses them up, and breaking blocks never gives their items
+    Creative,
+}
+
+impl GameMode {
+    /// Gets the game mode with this name (`survival` or `creative`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "survival" => Some(Self::Survival),
+            "creative" => Some(Self::Creative),
+            _ => None,
+        }
+    }
+
+    /// The name of this game mode, as used in commands & config files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Survival => "survival",
+            Self::Creative => "creative",
+        }
+    }
+
+    /// Returns true if placing & breaking blocks should use up & give back items
+    pub fn uses_items(&self) -> bool {
+        matches!(self, Self::Survival)
+    }
+}
+
+impl SyncableComponent for GameMode {
+    fn get_sync_type() -> SyncType {
+        SyncType::ServerAuthoritative
+    }
+
+    fn get_component_unlocalized_name() -> &'static str {
+        "cosmos:game_mode"
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    sync_component::<GameMode>(app);
+
+    app.register_type::<GameMode>();
+}
@@ -1,9 +1,10 @@
 //! Represents a player
 
 // pub mod apart_of_ship;
+pub mod game_mode;
 pub mod render_distance;
 
-use bevy::prelude::Component;
+use bevy::prelude::{App, Component};
 use bevy_renet::renet::ClientId;
 
 #[derive(Component, Debug)]
@@ -31,3 +32,7 @@ impl Player {
         self.id
     }
 }
+
+pub(super) fn register(app: &mut App) {
+    game_mode::register(app);
+}
@@ -0,0 +1,370 @@
+//! Factions are groups of players that share structures with each other.
+//!
+//! Each player can be in at most one faction, and has a [`FactionRank`] in it that decides what they can do
+//! to the faction & its structures. How factions treat each other is decided by their [`relations`].
+
+use bevy::{ecs::system::Resource, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use self::relations::{DeclaredRelation, PIRATE_FACTION_NAME};
+
+pub mod relations;
+
+/// The most characters a faction's name can have
+pub const MAX_FACTION_NAME_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+/// Uniquely identifies a faction, even if it is renamed
+pub struct FactionId(u64);
+
+impl FactionId {
+    /// The built-in faction every pirate is a part of. No players can be in it.
+    pub const PIRATES: Self = Self(u64::MAX);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+/// How much power a member has in their faction. Ranks later in this list are higher.
+pub enum FactionRank {
+    /// Can use the faction's structures, but not change them
+    Member,
+    /// Can also build on the faction's structures & invite or kick members
+    Officer,
+    /// Can do anything, including promoting & demoting members. Every faction has exactly one leader.
+    Leader,
+}
+
+impl FactionRank {
+    /// The name of this rank, as shown to players
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Officer => "officer",
+            Self::Leader => "leader",
+        }
+    }
+
+    /// The rank right above this one, if this isn't already the leader
+    pub fn promoted(&self) -> Option<Self> {
+        match self {
+            Self::Member => Some(Self::Officer),
+            Self::Officer | Self::Leader => None,
+        }
+    }
+
+    /// The rank right below this one, if this isn't already a member
+    pub fn demoted(&self) -> Option<Self> {
+        match self {
+            Self::Officer => Some(Self::Member),
+            Self::Member | Self::Leader => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A group of players that share structures
+pub struct Faction {
+    id: FactionId,
+    name: String,
+    /// Player names to their rank
+    members: HashMap<String, FactionRank>,
+    /// The names of players that were invited, but haven't joined yet
+    invites: Vec<String>,
+}
+
+impl Faction {
+    /// This faction's unique id
+    pub fn id(&self) -> FactionId {
+        self.id
+    }
+
+    /// This faction's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the rank of this player, if they are a member of this faction
+    pub fn rank(&self, player_name: &str) -> Option<FactionRank> {
+        self.members.get(player_name).copied()
+    }
+
+    /// Iterates over every member's name & their rank
+    pub fn members(&self) -> impl Iterator<Item = (&str, FactionRank)> {
+        self.members.iter().map(|(name, rank)| (name.as_str(), *rank))
+    }
+
+    /// Returns true if this player was invited to this faction
+    pub fn is_invited(&self, player_name: &str) -> bool {
+        self.invites.iter().any(|x| x == player_name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Why changing a faction didn't work
+pub enum FactionError {
+    /// The player is already in a faction
+    AlreadyInFaction,
+    /// The player isn't in a faction
+    NotInFaction,
+    /// The player isn't in the same faction
+    NotInSameFaction,
+    /// No faction has this name
+    NoSuchFaction,
+    /// Another faction already has this name
+    NameTaken,
+    /// The name is empty or too long
+    InvalidName,
+    /// The player wasn't invited to this faction
+    NotInvited,
+    /// The player's rank isn't high enough to do this
+    RankTooLow,
+    /// The player's rank can't be changed any further
+    CannotChangeRank,
+    /// A faction can't change how it feels about itself
+    SameFaction,
+}
+
+impl FactionError {
+    /// A message explaining this error to a player
+    pub fn message(&self) -> String {
+        match self {
+            Self::AlreadyInFaction => "That player is already in a faction.".into(),
+            Self::NotInFaction => "That player isn't in a faction.".into(),
+            Self::NotInSameFaction => "That player isn't in your faction.".into(),
+            Self::NoSuchFaction => "There is no faction with that name.".into(),
+            Self::NameTaken => "There is already a faction with that name.".into(),
+            Self::InvalidName => format!("Faction names must be between 1 and {MAX_FACTION_NAME_LENGTH} characters."),
+            Self::NotInvited => "You haven't been invited to that faction.".into(),
+            Self::RankTooLow => "Your rank isn't high enough to do that.".into(),
+            Self::CannotChangeRank => "That player's rank can't be changed any further.".into(),
+            Self::SameFaction => "Your faction can't change its relation with itself.".into(),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Every faction in the world
+pub struct Factions {
+    next_id: u64,
+    factions: Vec<Faction>,
+    /// How each faction has declared it feels about other factions
+    #[serde(default)]
+    relations: Vec<DeclaredRelation>,
+}
+
+impl Factions {
+    /// Gets the faction with this id
+    pub fn faction(&self, id: FactionId) -> Option<&Faction> {
+        self.factions.iter().find(|x| x.id == id)
+    }
+
+    /// Gets the faction with this name, ignoring case
+    pub fn from_name(&self, name: &str) -> Option<&Faction> {
+        self.factions.iter().find(|x| x.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Gets the faction this player is in
+    pub fn player_faction(&self, player_name: &str) -> Option<&Faction> {
+        self.factions.iter().find(|x| x.members.contains_key(player_name))
+    }
+
+    /// Iterates over every faction
+    pub fn iter(&self) -> impl Iterator<Item = &Faction> {
+        self.factions.iter()
+    }
+
+    fn player_faction_mut(&mut self, player_name: &str) -> Result<&mut Faction, FactionError> {
+        self.factions
+            .iter_mut()
+            .find(|x| x.members.contains_key(player_name))
+            .ok_or(FactionError::NotInFaction)
+    }
+
+    /// Creates a new faction with this player as its leader
+    pub fn create(&mut self, name: impl Into<String>, leader: impl Into<String>) -> Result<FactionId, FactionError> {
+        let name = name.into();
+        let leader = leader.into();
+
+        if name.is_empty() || name.chars().count() > MAX_FACTION_NAME_LENGTH {
+            return Err(FactionError::InvalidName);
+        }
+
+        if self.from_name(&name).is_some() || name.eq_ignore_ascii_case(PIRATE_FACTION_NAME) {
+            return Err(FactionError::NameTaken);
+        }
+
+        if self.player_faction(&leader).is_some() {
+            return Err(FactionError::AlreadyInFaction);
+        }
+
+        let id = FactionId(self.next_id);
+        self.next_id += 1;
+
+        let mut members = HashMap::default();
+        members.insert(leader, FactionRank::Leader);
+
+        self.factions.push(Faction {
+            id,
+            name,
+            members,
+            invites: vec![],
+        });
+
+        Ok(id)
+    }
+
+    /// Invites a player to the faction the inviter is in. Officers & leaders can invite players.
+    pub fn invite(&mut self, inviter: &str, invited: impl Into<String>) -> Result<(), FactionError> {
+        let invited = invited.into();
+
+        if self.player_faction(&invited).is_some() {
+            return Err(FactionError::AlreadyInFaction);
+        }
+
+        let faction = self.player_faction_mut(inviter)?;
+
+        if faction.members[inviter] < FactionRank::Officer {
+            return Err(FactionError::RankTooLow);
+        }
+
+        if !faction.is_invited(&invited) {
+            faction.invites.push(invited);
+        }
+
+        Ok(())
+    }
+
+    /// Has this player join the faction with this name, if they were invited to it
+    pub fn join(&mut self, player_name: &str, faction_name: &str) -> Result<FactionId, FactionError> {
+        if self.player_faction(player_name).is_some() {
+            return Err(FactionError::AlreadyInFaction);
+        }
+
+        let faction = self
+            .factions
+            .iter_mut()
+            .find(|x| x.name.eq_ignore_ascii_case(faction_name))
+            .ok_or(FactionError::NoSuchFaction)?;
+        let faction_id = faction.id;
+
+        let Some(idx) = faction.invites.iter().position(|x| x == player_name) else {
+            return Err(FactionError::NotInvited);
+        };
+
+        faction.invites.remove(idx);
+        faction.members.insert(player_name.into(), FactionRank::Member);
+
+        Ok(faction_id)
+    }
+
+    /// Removes this player from their faction.
+    ///
+    /// If they were the leader, the highest ranking remaining member becomes the leader. If nobody is left, the faction is disbanded.
+    pub fn leave(&mut self, player_name: &str) -> Result<FactionId, FactionError> {
+        let faction = self.player_faction_mut(player_name)?;
+        let faction_id = faction.id;
+
+        let rank = faction.members.remove(player_name).expect("Found above");
+
+        if rank == FactionRank::Leader {
+            // Sorted by name too so the new leader doesn't depend on the HashMap's order
+            let new_leader = faction
+                .members
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(name, _)| name.clone());
+
+            match new_leader {
+                Some(new_leader) => {
+                    faction.members.insert(new_leader, FactionRank::Leader);
+                }
+                None => {
+                    self.factions.retain(|x| x.id != faction_id);
+                    self.relations.retain(|x| x.from != faction_id && x.to != faction_id);
+                }
+            }
+        }
+
+        Ok(faction_id)
+    }
+
+    /// Has `kicker` remove `kicked` from their faction. Players can only kick members with a lower rank than them.
+    pub fn kick(&mut self, kicker: &str, kicked: &str) -> Result<(), FactionError> {
+        let faction = self.player_faction_mut(kicker)?;
+
+        let kicker_rank = faction.members[kicker];
+        let kicked_rank = faction.rank(kicked).ok_or(FactionError::NotInSameFaction)?;
+
+        if kicker_rank < FactionRank::Officer || kicked_rank >= kicker_rank {
+            return Err(FactionError::RankTooLow);
+        }
+
+        faction.members.remove(kicked);
+
+        Ok(())
+    }
+
+    /// Has the leader of a faction promote (`promote = true`) or demote a member of it
+    ///
+    /// Returns the member's new rank
+    pub fn change_rank(&mut self, leader: &str, member: &str, promote: bool) -> Result<FactionRank, FactionError> {
+        let faction = self.player_faction_mut(leader)?;
+
+        if faction.members[leader] != FactionRank::Leader {
+            return Err(FactionError::RankTooLow);
+        }
+
+        let rank = faction.members.get_mut(member).ok_or(FactionError::NotInSameFaction)?;
+
+        let new_rank = if promote { rank.promoted() } else { rank.demoted() }.ok_or(FactionError::CannotChangeRank)?;
+
+        *rank = new_rank;
+
+        Ok(new_rank)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FactionError, FactionRank, Factions};
+
+    #[test]
+    fn invite_and_join() {
+        let mut factions = Factions::default();
+        let id = factions.create("Traders", "alice").unwrap();
+
+        assert_eq!(factions.join("bob", "traders"), Err(FactionError::NotInvited));
+
+        factions.invite("alice", "bob").unwrap();
+        assert_eq!(factions.join("bob", "traders"), Ok(id));
+
+        let faction = factions.faction(id).unwrap();
+        assert_eq!(faction.rank("alice"), Some(FactionRank::Leader));
+        assert_eq!(faction.rank("bob"), Some(FactionRank::Member));
+
+        // Members can't invite others
+        assert_eq!(factions.invite("bob", "carol"), Err(FactionError::RankTooLow));
+        assert_eq!(factions.create("Traders", "carol"), Err(FactionError::NameTaken));
+    }
+
+    #[test]
+    fn ranks() {
+        let mut factions = Factions::default();
+        let id = factions.create("Miners", "alice").unwrap();
+        factions.invite("alice", "bob").unwrap();
+        factions.join("bob", "Miners").unwrap();
+
+        assert_eq!(factions.kick("bob", "alice"), Err(FactionError::RankTooLow));
+        assert_eq!(factions.change_rank("alice", "bob", true), Ok(FactionRank::Officer));
+        assert_eq!(factions.change_rank("alice", "bob", true), Err(FactionError::CannotChangeRank));
+        assert_eq!(factions.kick("bob", "alice"), Err(FactionError::RankTooLow));
+
+        // The leader leaving hands the faction over to the next highest rank
+        factions.leave("alice").unwrap();
+        assert_eq!(factions.faction(id).unwrap().rank("bob"), Some(FactionRank::Leader));
+
+        // And the last member leaving disbands it
+        factions.leave("bob").unwrap();
+        assert!(factions.faction(id).is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//! How factions feel about each other, and who is allowed to damage whose structures.
+//!
+//! Each faction's leader declares a [`FactionRelation`] towards other factions. Two factions are only as friendly as the
+//! least friendly of their declarations, so one faction can't force an alliance onto another.
+//!
+//! Pirates are a built-in faction ([`FactionId::PIRATES`]) that is hostile to everyone unless it declares otherwise.
+
+use serde::{Deserialize, Serialize};
+
+use super::{FactionError, FactionId, FactionRank, Factions};
+
+/// The name of the built-in pirate faction. No player faction can use this name.
+pub const PIRATE_FACTION_NAME: &str = "Pirates";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+/// How one faction feels about another. Relations later in this list are friendlier.
+pub enum FactionRelation {
+    /// Their structures can be attacked, and pirates will hunt them down
+    Hostile,
+    /// Neither friend nor foe
+    Neutral,
+    /// Their structures can't be damaged
+    Ally,
+}
+
+impl FactionRelation {
+    /// The name of this relation, as shown to players
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hostile => "hostile",
+            Self::Neutral => "neutral",
+            Self::Ally => "ally",
+        }
+    }
+
+    /// Gets the relation with this name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Hostile, Self::Neutral, Self::Ally]
+            .into_iter()
+            .find(|x| x.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// How the faction `from` said it feels about the faction `to`
+pub(super) struct DeclaredRelation {
+    pub(super) from: FactionId,
+    pub(super) to: FactionId,
+    pub(super) relation: FactionRelation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Which players are allowed to damage each other's structures
+pub enum PvpMode {
+    #[default]
+    /// Players can damage any structure that isn't owned by themselves or an ally
+    On,
+    /// Players can only damage structures owned by factions hostile to theirs
+    FactionOnly,
+    /// Players can never damage each other's structures
+    Off,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Who something belongs to, which decides how others treat it
+pub enum Allegiance {
+    /// Nobody, such as asteroids, planets & structures without an owner
+    Nobody,
+    /// The built-in pirate faction
+    Pirates,
+    /// A player, and the faction they're in (if any)
+    Player {
+        /// The player's name
+        name: String,
+        /// The faction the player is in
+        faction: Option<FactionId>,
+    },
+}
+
+impl Allegiance {
+    /// The faction this belongs to, if any
+    pub fn faction(&self) -> Option<FactionId> {
+        match self {
+            Self::Nobody => None,
+            Self::Pirates => Some(FactionId::PIRATES),
+            Self::Player { faction, .. } => *faction,
+        }
+    }
+}
+
+impl Factions {
+    /// Gets the name of the faction with this id, including the pirate faction
+    pub fn faction_name(&self, id: FactionId) -> Option<&str> {
+        if id == FactionId::PIRATES {
+            Some(PIRATE_FACTION_NAME)
+        } else {
+            self.faction(id).map(|x| x.name())
+        }
+    }
+
+    /// Gets the id of the faction with this name (ignoring case), including the pirate faction
+    pub fn faction_id_from_name(&self, name: &str) -> Option<FactionId> {
+        if name.eq_ignore_ascii_case(PIRATE_FACTION_NAME) {
+            Some(FactionId::PIRATES)
+        } else {
+            self.from_name(name).map(|x| x.id())
+        }
+    }
+
+    /// Gets who this player belongs to
+    pub fn player_allegiance(&self, player_name: &str) -> Allegiance {
+        Allegiance::Player {
+            name: player_name.into(),
+            faction: self.player_faction(player_name).map(|x| x.id()),
+        }
+    }
+
+    /// How `from` said it feels about `to`. Without a declaration, pirates are hostile & everyone else is neutral.
+    pub fn declared_relation(&self, from: FactionId, to: FactionId) -> FactionRelation {
+        self.relations
+            .iter()
+            .find(|x| x.from == from && x.to == to)
+            .map(|x| x.relation)
+            .unwrap_or(if from == FactionId::PIRATES || to == FactionId::PIRATES {
+                FactionRelation::Hostile
+            } else {
+                FactionRelation::Neutral
+            })
+    }
+
+    /// Iterates over every relation this faction has declared towards others
+    pub fn declared_relations(&self, from: FactionId) -> impl Iterator<Item = (FactionId, FactionRelation)> + '_ {
+        self.relations.iter().filter(move |x| x.from == from).map(|x| (x.to, x.relation))
+    }
+
+    /// The relation between two factions, which is the least friendly of what each declared towards the other
+    pub fn relation(&self, a: FactionId, b: FactionId) -> FactionRelation {
+        if a == b {
+            return FactionRelation::Ally;
+        }
+
+        self.declared_relation(a, b).min(self.declared_relation(b, a))
+    }
+
+    /// The relation between two things based on who they belong to
+    pub fn allegiance_relation(&self, a: &Allegiance, b: &Allegiance) -> FactionRelation {
+        match (a, b) {
+            (Allegiance::Nobody, _) | (_, Allegiance::Nobody) => FactionRelation::Neutral,
+            (Allegiance::Player { name: a_name, .. }, Allegiance::Player { name: b_name, .. }) if a_name == b_name => FactionRelation::Ally,
+            _ => match (a.faction(), b.faction()) {
+                (Some(a), Some(b)) => self.relation(a, b),
+                // Players without a faction can't declare relations, so only the pirates' default applies
+                (Some(faction), None) | (None, Some(faction)) if faction == FactionId::PIRATES => FactionRelation::Hostile,
+                _ => FactionRelation::Neutral,
+            },
+        }
+    }
+
+    /// Returns true if something belonging to `attacker` is allowed to damage something belonging to `victim`.
+    ///
+    /// Allies can never damage each other. Whether players can damage other players' structures depends on the `pvp` mode.
+    /// Unless pvp is on, things that belong to nobody can't damage players either - otherwise an unowned ship could be used
+    /// to get around the pvp mode.
+    pub fn can_damage(&self, attacker: &Allegiance, victim: &Allegiance, pvp: PvpMode) -> bool {
+        let relation = self.allegiance_relation(attacker, victim);
+
+        match (attacker, victim) {
+            (Allegiance::Player { name: a, .. }, Allegiance::Player { name: b, .. }) => {
+                if a == b {
+                    // Players can always damage their own structures
+                    return true;
+                }
+
+                match pvp {
+                    PvpMode::On => relation != FactionRelation::Ally,
+                    PvpMode::FactionOnly => relation == FactionRelation::Hostile,
+                    PvpMode::Off => false,
+                }
+            }
+            (Allegiance::Nobody, Allegiance::Player { .. }) => pvp == PvpMode::On,
+            _ => relation != FactionRelation::Ally,
+        }
+    }
+
+    /// Has the leader of a faction declare how their faction feels about another faction (which can be the pirates).
+    ///
+    /// Returns the new relation between the two factions, which may not be as friendly as what was declared.
+    pub fn declare_relation(
+        &mut self,
+        leader: &str,
+        faction_name: &str,
+        relation: FactionRelation,
+    ) -> Result<FactionRelation, FactionError> {
+        let from = self.player_faction(leader).ok_or(FactionError::NotInFaction)?;
+
+        if from.rank(leader) != Some(FactionRank::Leader) {
+            return Err(FactionError::RankTooLow);
+        }
+
+        let from = from.id();
+        let to = self.faction_id_from_name(faction_name).ok_or(FactionError::NoSuchFaction)?;
+
+        if from == to {
+            return Err(FactionError::SameFaction);
+        }
+
+        self.relations.retain(|x| !(x.from == from && x.to == to));
+        self.relations.push(DeclaredRelation { from, to, relation });
+
+        Ok(self.relation(from, to))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::faction::{FactionError, FactionId, Factions};
+
+    use super::{Allegiance, FactionRelation, PvpMode};
+
+    fn two_factions() -> (Factions, FactionId, FactionId) {
+        let mut factions = Factions::default();
+        let a = factions.create("Traders", "alice").unwrap();
+        let b = factions.create("Miners", "bob").unwrap();
+
+        (factions, a, b)
+    }
+
+    #[test]
+    fn relations_need_both_sides() {
+        let (mut factions, a, b) = two_factions();
+
+        assert_eq!(factions.relation(a, b), FactionRelation::Neutral);
+        assert_eq!(factions.relation(a, FactionId::PIRATES), FactionRelation::Hostile);
+
+        // One faction can't make an alliance alone, but can declare war alone
+        assert_eq!(
+            factions.declare_relation("alice", "miners", FactionRelation::Ally),
+            Ok(FactionRelation::Neutral)
+        );
+        assert_eq!(
+            factions.declare_relation("bob", "traders", FactionRelation::Ally),
+            Ok(FactionRelation::Ally)
+        );
+        assert_eq!(
+            factions.declare_relation("alice", "miners", FactionRelation::Hostile),
+            Ok(FactionRelation::Hostile)
+        );
+        assert_eq!(factions.relation(b, a), FactionRelation::Hostile);
+
+        // Pirates stay hostile no matter what players declare
+        assert_eq!(
+            factions.declare_relation("alice", "pirates", FactionRelation::Ally),
+            Ok(FactionRelation::Hostile)
+        );
+        assert_eq!(
+            factions.declare_relation("alice", "traders", FactionRelation::Ally),
+            Err(FactionError::SameFaction)
+        );
+        assert_eq!(factions.create("pirates", "carol"), Err(FactionError::NameTaken));
+    }
+
+    #[test]
+    fn pvp_modes() {
+        let (mut factions, _, _) = two_factions();
+        factions.invite("alice", "carol").unwrap();
+        factions.join("carol", "traders").unwrap();
+
+        let alice = factions.player_allegiance("alice");
+        let bob = factions.player_allegiance("bob");
+        let carol = factions.player_allegiance("carol");
+
+        assert!(factions.can_damage(&alice, &bob, PvpMode::On));
+        assert!(!factions.can_damage(&alice, &carol, PvpMode::On));
+        assert!(!factions.can_damage(&alice, &bob, PvpMode::FactionOnly));
+        assert!(!factions.can_damage(&alice, &bob, PvpMode::Off));
+        assert!(factions.can_damage(&alice, &alice, PvpMode::Off));
+
+        factions.declare_relation("bob", "traders", FactionRelation::Hostile).unwrap();
+        assert!(factions.can_damage(&alice, &bob, PvpMode::FactionOnly));
+
+        // Pirates aren't affected by the pvp mode, and players can always damage unowned structures
+        assert!(factions.can_damage(&Allegiance::Pirates, &alice, PvpMode::Off));
+        assert!(factions.can_damage(&alice, &Allegiance::Pirates, PvpMode::Off));
+        assert!(factions.can_damage(&alice, &Allegiance::Nobody, PvpMode::Off));
+        assert!(!factions.can_damage(&Allegiance::Pirates, &Allegiance::Pirates, PvpMode::On));
+
+        // Unowned structures can only damage players when pvp is on
+        assert!(factions.can_damage(&Allegiance::Nobody, &alice, PvpMode::On));
+        assert!(!factions.can_damage(&Allegiance::Nobody, &alice, PvpMode::FactionOnly));
+        assert!(!factions.can_damage(&Allegiance::Nobody, &alice, PvpMode::Off));
+        assert!(factions.can_damage(&Allegiance::Nobody, &Allegiance::Pirates, PvpMode::Off));
+    }
+}
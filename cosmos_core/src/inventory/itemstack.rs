@@ -3,7 +3,10 @@
 use bevy::{prelude::App, reflect::Reflect};
 use serde::{Deserialize, Serialize};
 
-use crate::{item::Item, registry::identifiable::Identifiable};
+use crate::{
+    item::Item,
+    registry::{id_palette::IdMapping, identifiable::Identifiable, Registry},
+};
 
 #[derive(Serialize, Deserialize, Debug, Reflect, Clone, PartialEq, Eq)]
 /// An item & the quantity of that item
@@ -110,6 +113,16 @@ impl ItemStack {
     pub fn is_same_as(&self, other: &ItemStack) -> bool {
         self.item_id == other.item_id
     }
+
+    /// Changes this to be whatever item its id now maps to.
+    ///
+    /// Used when loading item ids that were saved with a potentially different item registry.
+    pub fn remap_item_id(&mut self, mapping: &IdMapping, items: &Registry<Item>) {
+        let item = items.from_numeric_id(mapping.map(self.item_id));
+
+        self.item_id = item.id();
+        self.max_stack_size = item.max_stack_size();
+    }
 }
 
 pub(super) fn register(app: &mut App) {
@@ -10,7 +10,10 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{item::Item, registry::identifiable::Identifiable};
+use crate::{
+    item::Item,
+    registry::{id_palette::IdMapping, identifiable::Identifiable, Registry},
+};
 
 use self::itemstack::ItemStack;
 
@@ -134,6 +137,15 @@ impl Inventory {
         Ok(())
     }
 
+    /// Changes every item in this inventory to whatever item its id now maps to.
+    ///
+    /// See [`ItemStack::remap_item_id`]
+    pub fn remap_item_ids(&mut self, mapping: &IdMapping, items: &Registry<Item>) {
+        for is in self.items.iter_mut().flatten() {
+            is.remap_item_id(mapping, items);
+        }
+    }
+
     /// Returns true if there is enough space in this inventory to insert this itemstack.
     pub fn can_insert_itemstack(&self, itemstack: &ItemStack) -> bool {
         self.can_insert_raw(itemstack.item_id(), itemstack.max_stack_size(), itemstack.quantity())
@@ -5,13 +5,17 @@
 
 pub mod block;
 pub mod blockitems;
+pub mod chat;
+pub mod crafting;
 pub mod economy;
 pub mod ecs;
 pub mod entities;
 pub mod events;
+pub mod faction;
 pub mod inventory;
 pub mod item;
 pub mod loader;
+pub mod logic;
 pub mod netty;
 pub mod persistence;
 pub mod physics;
@@ -0,0 +1,467 @@
+//! Logic signals are on/off values that travel through logic wires on a structure.
+//!
+//! Every logic tick, each network of touching [`LogicBlockKind::Wire`] blocks is on if anything outputting into it is on.
+//! Gates then read the signals around them to decide their output for the next tick, and blocks that react to logic
+//! (such as lights or laser cannons) are told if they are receiving a signal via the [`LogicSignals`] component.
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{app::App, ecs::component::Component, reflect::Reflect};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::{Block, BlockFace, ALL_BLOCK_FACES},
+    netty::sync::{sync_component, SyncType, SyncableComponent},
+    registry::{create_registry, identifiable::Identifiable, Registry},
+    structure::coordinates::BlockCoordinate,
+};
+
+/// How long a button outputs a signal for after being pressed
+pub const BUTTON_TICKS: u32 = 10;
+/// How many ticks a delay block waits before outputting the signal going into it
+pub const DELAY_TICKS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect, Hash)]
+/// How a block behaves in a logic circuit
+pub enum LogicBlockKind {
+    /// Every wire touching another wire shares the same signal
+    Wire,
+    /// Always outputs a signal out of every face
+    On,
+    /// Outputs a signal out of every face for [`BUTTON_TICKS`] after being interacted with
+    Button,
+    /// Outputs a signal out of its front if everything connected to its other faces is on
+    And,
+    /// Outputs a signal out of its front if anything connected to its other faces is on
+    Or,
+    /// Outputs a signal out of its front if nothing connected to its other faces is on
+    Not,
+    /// Outputs the signal going into its other faces out of its front [`DELAY_TICKS`] later
+    Delay,
+    /// Doesn't output anything, but reacts to the signals going into it (such as lights & laser cannons)
+    Receiver,
+}
+
+impl LogicBlockKind {
+    /// Returns true if this only outputs out of its front face
+    pub fn is_gate(&self) -> bool {
+        matches!(self, Self::And | Self::Or | Self::Not | Self::Delay)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+/// Links a block to how it behaves in a logic circuit
+pub struct LogicBlock {
+    id: u16,
+    unlocalized_name: String,
+
+    /// How this block behaves in a logic circuit
+    pub kind: LogicBlockKind,
+}
+
+impl Identifiable for LogicBlock {
+    fn id(&self) -> u16 {
+        self.id
+    }
+
+    fn set_numeric_id(&mut self, id: u16) {
+        self.id = id;
+    }
+
+    fn unlocalized_name(&self) -> &str {
+        &self.unlocalized_name
+    }
+}
+
+impl LogicBlock {
+    /// Creates a new logic block entry
+    ///
+    /// You can also use the `insert` method in the `Registry<LogicBlock>` if that is easier.
+    pub fn new(block: &Block, kind: LogicBlockKind) -> Self {
+        Self {
+            kind,
+            id: 0,
+            unlocalized_name: block.unlocalized_name().to_owned(),
+        }
+    }
+}
+
+impl Registry<LogicBlock> {
+    /// Gets the corrusponding logic block if there is an entry for this block
+    pub fn from_block(&self, block: &Block) -> Option<&LogicBlock> {
+        self.from_id(block.unlocalized_name())
+    }
+
+    /// Inserts a block with the specified logic kind
+    pub fn insert(&mut self, block: &Block, kind: LogicBlockKind) {
+        self.register(LogicBlock::new(block, kind));
+    }
+}
+
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Every block on this structure that reacts to logic & is connected to something, and if it is receiving a signal.
+///
+/// Blocks that aren't connected to anything aren't in here, and should act like they normally would.
+pub struct LogicSignals(HashMap<BlockCoordinate, bool>);
+
+impl LogicSignals {
+    /// Returns `None` if this block isn't connected to any logic, otherwise if it is receiving a signal
+    pub fn signal(&self, coords: BlockCoordinate) -> Option<bool> {
+        self.0.get(&coords).copied()
+    }
+
+    /// Returns true if this block is receiving a signal
+    pub fn is_on(&self, coords: BlockCoordinate) -> bool {
+        self.signal(coords) == Some(true)
+    }
+
+    /// Iterates over every block connected to logic & if it is receiving a signal
+    pub fn iter(&self) -> impl Iterator<Item = (BlockCoordinate, bool)> + '_ {
+        self.0.iter().map(|(coords, on)| (*coords, *on))
+    }
+
+    /// Returns true if no blocks are connected to any logic
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl SyncableComponent for LogicSignals {
+    fn get_sync_type() -> SyncType {
+        SyncType::ServerAuthoritative
+    }
+
+    fn get_component_unlocalized_name() -> &'static str {
+        "cosmos:logic_signals"
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CircuitBlock {
+    kind: LogicBlockKind,
+    front: BlockFace,
+}
+
+impl CircuitBlock {
+    fn outputs_towards(&self, face: BlockFace) -> bool {
+        match self.kind {
+            LogicBlockKind::On | LogicBlockKind::Button => true,
+            LogicBlockKind::And | LogicBlockKind::Or | LogicBlockKind::Not | LogicBlockKind::Delay => face == self.front,
+            LogicBlockKind::Wire | LogicBlockKind::Receiver => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct WireNetworks {
+    network_of: HashMap<BlockCoordinate, usize>,
+    count: usize,
+}
+
+#[derive(Component, Debug, Default)]
+/// All the logic blocks on a structure & the state of its gates.
+///
+/// Call [`LogicCircuit::tick`] to move every signal forward one step.
+pub struct LogicCircuit {
+    blocks: HashMap<BlockCoordinate, CircuitBlock>,
+    /// What each gate output last tick
+    outputs: HashMap<BlockCoordinate, bool>,
+    delays: HashMap<BlockCoordinate, VecDeque<bool>>,
+    /// How many more ticks each pressed button will be on for
+    buttons: HashMap<BlockCoordinate, u32>,
+    /// Recalculated whenever a wire is added or removed
+    networks: Option<WireNetworks>,
+}
+
+fn neighbor(coords: BlockCoordinate, face: BlockFace) -> Option<BlockCoordinate> {
+    BlockCoordinate::try_from(face.direction_coordinates() + coords).ok()
+}
+
+impl LogicCircuit {
+    /// Adds a logic block to this circuit, replacing whatever was there before.
+    ///
+    /// `front` is the face of the structure the block's front is facing, which is where gates output their signal.
+    pub fn add_block(&mut self, coords: BlockCoordinate, kind: LogicBlockKind, front: BlockFace) {
+        self.remove_block(coords);
+
+        if kind == LogicBlockKind::Wire {
+            self.networks = None;
+        }
+
+        self.blocks.insert(coords, CircuitBlock { kind, front });
+    }
+
+    /// Removes the logic block here, if there is one
+    pub fn remove_block(&mut self, coords: BlockCoordinate) {
+        let Some(block) = self.blocks.remove(&coords) else {
+            return;
+        };
+
+        if block.kind == LogicBlockKind::Wire {
+            self.networks = None;
+        }
+
+        self.outputs.remove(&coords);
+        self.delays.remove(&coords);
+        self.buttons.remove(&coords);
+    }
+
+    /// Presses the button here, making it output a signal for [`BUTTON_TICKS`].
+    ///
+    /// Returns false if there is no button here.
+    pub fn press_button(&mut self, coords: BlockCoordinate) -> bool {
+        if !self.blocks.get(&coords).is_some_and(|x| x.kind == LogicBlockKind::Button) {
+            return false;
+        }
+
+        self.buttons.insert(coords, BUTTON_TICKS);
+
+        true
+    }
+
+    /// Returns true if there are no logic blocks in this circuit
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    fn calculate_networks(&self) -> WireNetworks {
+        let mut networks = WireNetworks::default();
+        let mut todo = vec![];
+
+        for (&coords, _) in self.blocks.iter().filter(|(_, block)| block.kind == LogicBlockKind::Wire) {
+            if networks.network_of.contains_key(&coords) {
+                continue;
+            }
+
+            let network = networks.count;
+            networks.count += 1;
+
+            networks.network_of.insert(coords, network);
+            todo.push(coords);
+
+            while let Some(coords) = todo.pop() {
+                for face in ALL_BLOCK_FACES {
+                    let Some(next) = neighbor(coords, face) else {
+                        continue;
+                    };
+
+                    if networks.network_of.contains_key(&next) || !self.blocks.get(&next).is_some_and(|x| x.kind == LogicBlockKind::Wire) {
+                        continue;
+                    }
+
+                    networks.network_of.insert(next, network);
+                    todo.push(next);
+                }
+            }
+        }
+
+        networks
+    }
+
+    fn output(&self, coords: BlockCoordinate, block: &CircuitBlock) -> bool {
+        match block.kind {
+            LogicBlockKind::On => true,
+            LogicBlockKind::Button => self.buttons.contains_key(&coords),
+            _ => self.outputs.get(&coords).copied().unwrap_or(false),
+        }
+    }
+
+    /// Moves every signal in this circuit forward one step.
+    ///
+    /// Returns the signal going into every connected [`LogicBlockKind::Receiver`].
+    pub fn tick(&mut self) -> LogicSignals {
+        let networks = self.networks.take().unwrap_or_else(|| self.calculate_networks());
+
+        let mut network_on = vec![false; networks.count];
+
+        for (&coords, block) in self.blocks.iter() {
+            if !self.output(coords, block) {
+                continue;
+            }
+
+            for face in ALL_BLOCK_FACES.iter().filter(|face| block.outputs_towards(**face)) {
+                if let Some(&network) = neighbor(coords, *face).and_then(|x| networks.network_of.get(&x)) {
+                    network_on[network] = true;
+                }
+            }
+        }
+
+        // The signal going into this block from the block touching this face, if they're connected
+        let signal_into = |coords: BlockCoordinate, face: BlockFace| -> Option<bool> {
+            let neighbor_coords = neighbor(coords, face)?;
+            let neighbor_block = self.blocks.get(&neighbor_coords)?;
+
+            if neighbor_block.kind == LogicBlockKind::Wire {
+                networks.network_of.get(&neighbor_coords).map(|&network| network_on[network])
+            } else if neighbor_block.outputs_towards(face.inverse()) {
+                Some(self.output(neighbor_coords, neighbor_block))
+            } else {
+                None
+            }
+        };
+
+        let mut signals = HashMap::new();
+        let mut new_outputs = vec![];
+        let mut delay_inputs = vec![];
+
+        for (&coords, block) in self.blocks.iter() {
+            let mut inputs = ALL_BLOCK_FACES
+                .iter()
+                .filter(|face| !block.kind.is_gate() || **face != block.front)
+                .filter_map(|face| signal_into(coords, *face))
+                .peekable();
+
+            match block.kind {
+                LogicBlockKind::Receiver => {
+                    if inputs.peek().is_some() {
+                        signals.insert(coords, inputs.any(|x| x));
+                    }
+                }
+                LogicBlockKind::And => {
+                    let connected = inputs.peek().is_some();
+                    new_outputs.push((coords, connected && inputs.all(|x| x)));
+                }
+                LogicBlockKind::Or => new_outputs.push((coords, inputs.any(|x| x))),
+                LogicBlockKind::Not => new_outputs.push((coords, !inputs.any(|x| x))),
+                LogicBlockKind::Delay => delay_inputs.push((coords, inputs.any(|x| x))),
+                LogicBlockKind::Wire | LogicBlockKind::On | LogicBlockKind::Button => {}
+            }
+        }
+
+        self.outputs.extend(new_outputs);
+
+        for (coords, input) in delay_inputs {
+            let queue = self.delays.entry(coords).or_default();
+
+            queue.push_back(input);
+
+            let output = if queue.len() > DELAY_TICKS {
+                queue.pop_front().unwrap_or(false)
+            } else {
+                false
+            };
+
+            self.outputs.insert(coords, output);
+        }
+
+        self.buttons.retain(|_, ticks| {
+            *ticks -= 1;
+            *ticks != 0
+        });
+
+        self.networks = Some(networks);
+
+        LogicSignals(signals)
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    create_registry::<LogicBlock>(app, "cosmos:logic_blocks");
+
+    sync_component::<LogicSignals>(app);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{block::BlockFace, structure::coordinates::BlockCoordinate};
+
+    use super::{LogicBlockKind, LogicCircuit, BUTTON_TICKS, DELAY_TICKS};
+
+    fn coords(x: u64) -> BlockCoordinate {
+        BlockCoordinate::new(x, 0, 0)
+    }
+
+    /// Builds a line of blocks along the x axis, starting at x = 0
+    fn line(kinds: &[LogicBlockKind]) -> LogicCircuit {
+        let mut circuit = LogicCircuit::default();
+
+        for (x, kind) in kinds.iter().enumerate() {
+            circuit.add_block(coords(x as u64), *kind, BlockFace::Right);
+        }
+
+        circuit
+    }
+
+    #[test]
+    fn unconnected_receiver() {
+        let mut circuit = line(&[LogicBlockKind::Receiver]);
+
+        assert_eq!(circuit.tick().signal(coords(0)), None);
+    }
+
+    #[test]
+    fn wire_carries_signal() {
+        let mut circuit = line(&[
+            LogicBlockKind::On,
+            LogicBlockKind::Wire,
+            LogicBlockKind::Wire,
+            LogicBlockKind::Receiver,
+        ]);
+
+        assert_eq!(circuit.tick().signal(coords(3)), Some(true));
+
+        circuit.remove_block(coords(0));
+
+        assert_eq!(circuit.tick().signal(coords(3)), Some(false));
+    }
+
+    #[test]
+    fn not_gate() {
+        let mut circuit = line(&[
+            LogicBlockKind::Wire,
+            LogicBlockKind::Not,
+            LogicBlockKind::Wire,
+            LogicBlockKind::Receiver,
+        ]);
+
+        // Gates take a tick to react
+        circuit.tick();
+        assert!(circuit.tick().is_on(coords(3)));
+
+        circuit.add_block(BlockCoordinate::new(0, 1, 0), LogicBlockKind::On, BlockFace::Top);
+
+        circuit.tick();
+        assert!(!circuit.tick().is_on(coords(3)));
+    }
+
+    #[test]
+    fn and_gate() {
+        let mut circuit = line(&[LogicBlockKind::On, LogicBlockKind::And, LogicBlockKind::Receiver]);
+        circuit.add_block(BlockCoordinate::new(1, 1, 0), LogicBlockKind::Wire, BlockFace::Top);
+
+        circuit.tick();
+        assert!(!circuit.tick().is_on(coords(2)));
+
+        circuit.add_block(BlockCoordinate::new(1, 2, 0), LogicBlockKind::On, BlockFace::Top);
+
+        circuit.tick();
+        assert!(circuit.tick().is_on(coords(2)));
+    }
+
+    #[test]
+    fn delay() {
+        let mut circuit = line(&[LogicBlockKind::On, LogicBlockKind::Delay, LogicBlockKind::Receiver]);
+
+        for _ in 0..DELAY_TICKS {
+            assert!(!circuit.tick().is_on(coords(2)));
+        }
+
+        circuit.tick();
+        assert!(circuit.tick().is_on(coords(2)));
+    }
+
+    #[test]
+    fn button() {
+        let mut circuit = line(&[LogicBlockKind::Button, LogicBlockKind::Wire, LogicBlockKind::Receiver]);
+
+        assert!(!circuit.tick().is_on(coords(2)));
+        assert!(circuit.press_button(coords(0)));
+        assert!(!circuit.press_button(coords(1)));
+
+        for _ in 0..BUTTON_TICKS {
+            assert!(circuit.tick().is_on(coords(2)));
+        }
+
+        assert!(!circuit.tick().is_on(coords(2)));
+    }
+}
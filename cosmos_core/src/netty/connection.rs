@@ -0,0 +1,457 @@
+//! Shared information clients & servers need to agree on to establish a connection.
+//!
+//! Servers that use secure authentication also run a small TCP endpoint that hands out renet connect tokens.
+//! Clients send a [`TokenRequest`] to that endpoint, and get a [`TokenResponse`] back, both encrypted by a [`TokenChannel`].
+//!
+//! Once connected, the server checks the client's [`GameVersion`] & replies with a [`HandshakeResponse`] on the
+//! [`super::NettyChannelServer::Handshake`] channel, so clients running a different version are told why they can't join.
+
+use std::io::{Read, Write};
+
+use bevy_renet::renet::transport::NETCODE_USER_DATA_BYTES;
+use bincode::Options;
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::{
+    block::Block,
+    item::Item,
+    registry::{identifiable::Identifiable, Registry},
+};
+
+/// The port servers run on if none is specified
+pub const DEFAULT_PORT: u16 = 1337;
+
+/// The version of the game, shown to players when their version doesn't match the server's
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Bump this whenever the layout of any networked message or channel changes.
+///
+/// Clients & servers with different protocol versions cannot play together.
+pub const PROTOCOL_VERSION: u32 = 9;
+
+/// Token endpoint messages larger than this are rejected, since anyone can send them
+const MAX_TOKEN_MESSAGE_BYTES: u64 = 4096;
+
+fn token_message_options() -> impl Options {
+    // Same format as `bincode::serialize`, but with a size limit
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_TOKEN_MESSAGE_BYTES)
+}
+
+/// Writes a [`TokenRequest`] or [`TokenResponse`] to the token endpoint's stream
+pub fn write_token_message<T: Serialize>(writer: &mut impl Write, message: &T) -> bincode::Result<()> {
+    token_message_options().serialize_into(writer, message)
+}
+
+/// Reads a [`TokenRequest`] or [`TokenResponse`] from the token endpoint's stream
+pub fn read_token_message<T: DeserializeOwned>(reader: &mut impl Read) -> bincode::Result<T> {
+    token_message_options().deserialize_from(reader)
+}
+
+/// The public key a server's token endpoint proves who it is with.
+///
+/// Clients remember this the first time they join a server, and refuse to send their password to a server with a different one.
+pub type TokenEndpointKey = [u8; 32];
+
+/// Used to derive the [`TokenChannel`]'s key, so it can't be confused with a key used for anything else
+const TOKEN_CHANNEL_CONTEXT: &str = "cosmos 2024-03-01 token endpoint channel key";
+
+/// The secret half of a server's [`TokenEndpointKey`]
+pub struct TokenEndpointSecret(StaticSecret);
+
+impl TokenEndpointSecret {
+    /// Generates a new random secret
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(rand::thread_rng()))
+    }
+
+    /// Creates the secret from the bytes returned by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// The bytes of this secret, used to save it
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key clients will see for this secret
+    pub fn public_key(&self) -> TokenEndpointKey {
+        PublicKey::from(&self.0).to_bytes()
+    }
+}
+
+/// Encrypts the [`TokenRequest`] & [`TokenResponse`], so nobody else can read the password or connect token.
+///
+/// When a client connects, the server sends its [`TokenEndpointKey`]. The client replies with a one-time public key, and both
+/// sides combine these into the same secret key without ever sending it. Only the server that owns the [`TokenEndpointKey`]
+/// can read what the client sends.
+///
+/// A channel is only used for one message in each direction.
+pub struct TokenChannel {
+    cipher: ChaCha20Poly1305,
+    is_server: bool,
+}
+
+impl TokenChannel {
+    /// Starts a channel with the server that has this key.
+    ///
+    /// Returns the channel, and the one-time public key that must be sent to the server.
+    pub fn client(server_key: &TokenEndpointKey) -> (Self, [u8; 32]) {
+        let secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+        let client_key = PublicKey::from(&secret).to_bytes();
+        let shared = secret.diffie_hellman(&PublicKey::from(*server_key));
+
+        let channel = Self {
+            cipher: Self::cipher(shared.as_bytes(), &client_key, server_key),
+            is_server: false,
+        };
+
+        (channel, client_key)
+    }
+
+    /// Starts a channel with the client that sent this one-time public key
+    pub fn server(secret: &TokenEndpointSecret, client_key: &[u8; 32]) -> Self {
+        let shared = secret.0.diffie_hellman(&PublicKey::from(*client_key));
+
+        Self {
+            cipher: Self::cipher(shared.as_bytes(), client_key, &secret.public_key()),
+            is_server: true,
+        }
+    }
+
+    fn cipher(shared: &[u8; 32], client_key: &[u8; 32], server_key: &TokenEndpointKey) -> ChaCha20Poly1305 {
+        let mut key_material = Vec::with_capacity(96);
+        key_material.extend_from_slice(shared);
+        key_material.extend_from_slice(client_key);
+        key_material.extend_from_slice(server_key);
+
+        ChaCha20Poly1305::new(&blake3::derive_key(TOKEN_CHANNEL_CONTEXT, &key_material).into())
+    }
+
+    /// Each side only sends one message per channel, so the nonce only has to differ between the two sides
+    fn nonce(from_server: bool) -> Nonce {
+        let mut nonce = [0; 12];
+        nonce[0] = from_server as u8;
+        nonce.into()
+    }
+
+    /// Encrypts & writes a [`TokenRequest`] or [`TokenResponse`] to the token endpoint's stream
+    pub fn write<T: Serialize>(&self, writer: &mut impl Write, message: &T) -> bincode::Result<()> {
+        let plain = bincode::serialize(message)?;
+        let encrypted = self
+            .cipher
+            .encrypt(&Self::nonce(self.is_server), plain.as_slice())
+            .map_err(|_| bincode::ErrorKind::Custom("Unable to encrypt token message".into()))?;
+
+        write_token_message(writer, &encrypted)
+    }
+
+    /// Reads & decrypts a [`TokenRequest`] or [`TokenResponse`] from the token endpoint's stream
+    pub fn read<T: DeserializeOwned>(&self, reader: &mut impl Read) -> bincode::Result<T> {
+        let encrypted = read_token_message::<Vec<u8>>(reader)?;
+        let plain = self
+            .cipher
+            .decrypt(&Self::nonce(!self.is_server), encrypted.as_slice())
+            .map_err(|_| bincode::ErrorKind::Custom("Token message was not encrypted for this channel".into()))?;
+
+        token_message_options().deserialize(&plain)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// A hash of a registry that the client & server each build themselves, rather than syncing it.
+///
+/// Things like blocks & items are referred to by their numeric ids over the network, so these must be identical.
+pub struct RegistryHash {
+    /// The registry's unlocalized name
+    pub registry_name: String,
+    /// Hash of every entry's numeric id & unlocalized name
+    pub hash: u64,
+}
+
+impl RegistryHash {
+    /// Hashes this registry's contents.
+    ///
+    /// This uses FNV-1a rather than the std hasher, since the std hasher isn't guaranteed to be the same between builds.
+    pub fn new<T: Identifiable>(registry: &Registry<T>) -> Self {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        let mut hash = FNV_OFFSET;
+        for item in registry.iter() {
+            for byte in item.id().to_le_bytes().iter().chain(item.unlocalized_name().as_bytes()).chain(&[0]) {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+
+        Self {
+            registry_name: registry.name().into(),
+            hash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Everything a client & server need to agree on to play together
+pub struct GameVersion {
+    /// The game's version, see [`GAME_VERSION`]
+    pub game_version: String,
+    /// The networking protocol's version, see [`PROTOCOL_VERSION`]
+    pub protocol_version: u32,
+    /// Hashes of the registries that must be identical on both sides
+    pub registry_hashes: Vec<RegistryHash>,
+}
+
+impl GameVersion {
+    /// The version of this build of the game.
+    ///
+    /// Blocks & items are referred to by their numeric ids everywhere, so those registries must match.
+    pub fn current(blocks: &Registry<Block>, items: &Registry<Item>) -> Self {
+        Self {
+            game_version: GAME_VERSION.into(),
+            protocol_version: PROTOCOL_VERSION,
+            registry_hashes: vec![RegistryHash::new(blocks), RegistryHash::new(items)],
+        }
+    }
+
+    /// Checks if a client with this version can join a server with the `server` version.
+    ///
+    /// Returns the reason they can't if they can't.
+    pub fn check_compatible(&self, server: &GameVersion) -> Result<(), String> {
+        if self.protocol_version != server.protocol_version {
+            return Err(format!(
+                "The server is running Cosmos {} (protocol {}), but you are running Cosmos {} (protocol {}).",
+                server.game_version, server.protocol_version, self.game_version, self.protocol_version
+            ));
+        }
+
+        for server_hash in server.registry_hashes.iter() {
+            let Some(hash) = self.registry_hashes.iter().find(|x| x.registry_name == server_hash.registry_name) else {
+                return Err(format!(
+                    "Your game is missing the {} registry the server has.",
+                    server_hash.registry_name
+                ));
+            };
+
+            if hash.hash != server_hash.hash {
+                return Err(format!(
+                    "Your {} don't match the server's. Make sure your game's assets are the same as the server's.",
+                    server_hash.registry_name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// The server's reply to a client that just connected, sent on the [`super::NettyChannelServer::Handshake`] channel.
+///
+/// Clients of any version must be able to read this, so never change its layout.
+pub enum HandshakeResponse {
+    /// The client can join
+    Accepted,
+    /// The client can't join, for this reason. The server will disconnect them shortly.
+    Rejected(String),
+}
+
+/// A random key a client generates for each server it joins, used to prove it is the same player as last time.
+///
+/// The first client to join a server with a given name claims that name, and only clients with the same key can use it
+/// from then on. Clients use a different key for each server, so a server can't use the key it was sent to pretend to be
+/// that player somewhere else.
+pub type IdentityKey = [u8; 32];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Sent from the client to the server in the renet user data when connecting
+pub struct ConnectionUserData {
+    /// The name of the player connecting
+    pub name: String,
+    /// The server's password, if the client was given one.
+    ///
+    /// This is never sent when using secure authentication, since the password is checked before the connect token is given out.
+    pub password: Option<String>,
+    /// Proves this client is the player that owns this name, see [`IdentityKey`]
+    pub identity_key: IdentityKey,
+    /// The version of the game the client is running
+    pub version: GameVersion,
+}
+
+impl ConnectionUserData {
+    /// Encodes this into the fixed-size user data renet sends.
+    ///
+    /// Returns `None` if this is too large to fit.
+    pub fn to_user_data(&self) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
+        // Bincode because this is stored in a u8 array with a fixed length of 256
+        let serialized = bincode::serialize(self).ok()?;
+
+        if serialized.len() > NETCODE_USER_DATA_BYTES {
+            return None;
+        }
+
+        let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+        user_data[0..serialized.len()].copy_from_slice(&serialized);
+
+        Some(user_data)
+    }
+
+    /// Decodes this from the user data renet received
+    pub fn from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<Self> {
+        bincode::deserialize(user_data.as_slice()).ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Sent by a client to the server's token endpoint to request a connect token
+pub struct TokenRequest {
+    /// The name of the player that wants to connect
+    pub name: String,
+    /// The server's password, if the client was given one
+    pub password: Option<String>,
+    /// Proves this client is the player that owns this name, see [`IdentityKey`]
+    pub identity_key: IdentityKey,
+    /// The version of the game the client is running
+    pub version: GameVersion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The server's token endpoint's response to a [`TokenRequest`]
+pub enum TokenResponse {
+    /// The bytes of a renet `ConnectToken` that can be used to connect to the server
+    Token(Vec<u8>),
+    /// The client is not allowed to join, for this reason
+    Denied(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_token_message, write_token_message, ConnectionUserData, GameVersion, RegistryHash, TokenChannel, TokenEndpointSecret,
+        TokenRequest, TokenResponse, GAME_VERSION, PROTOCOL_VERSION,
+    };
+
+    fn version() -> GameVersion {
+        GameVersion {
+            game_version: GAME_VERSION.into(),
+            protocol_version: PROTOCOL_VERSION,
+            registry_hashes: vec![
+                RegistryHash {
+                    registry_name: "cosmos:blocks".into(),
+                    hash: 1,
+                },
+                RegistryHash {
+                    registry_name: "cosmos:items".into(),
+                    hash: 2,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn user_data_round_trip() {
+        let data = ConnectionUserData {
+            name: "CoolPlayer".into(),
+            password: Some("hunter2".into()),
+            identity_key: [7; 32],
+            version: version(),
+        };
+
+        let user_data = data.to_user_data().expect("Should fit in user data");
+
+        assert_eq!(ConnectionUserData::from_user_data(&user_data), Some(data));
+    }
+
+    #[test]
+    fn user_data_too_large() {
+        let data = ConnectionUserData {
+            name: "a".repeat(300),
+            password: None,
+            identity_key: [0; 32],
+            version: version(),
+        };
+
+        assert!(data.to_user_data().is_none());
+    }
+
+    #[test]
+    fn token_message_round_trip() {
+        let mut bytes = vec![];
+        write_token_message(
+            &mut bytes,
+            &TokenRequest {
+                name: "CoolPlayer".into(),
+                password: None,
+                identity_key: [7; 32],
+                version: version(),
+            },
+        )
+        .unwrap();
+
+        let request = read_token_message::<TokenRequest>(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(request.name, "CoolPlayer");
+        assert_eq!(request.password, None);
+        assert_eq!(request.identity_key, [7; 32]);
+    }
+
+    #[test]
+    fn token_channel_round_trip() {
+        let secret = TokenEndpointSecret::generate();
+        let (client, client_key) = TokenChannel::client(&secret.public_key());
+        let server = TokenChannel::server(&secret, &client_key);
+
+        let mut bytes = vec![];
+        client.write(&mut bytes, &"hunter2").unwrap();
+
+        // The password can't be read by anyone watching, and only the server can decrypt it
+        assert!(!bytes.windows(7).any(|x| x == b"hunter2"));
+        assert!(client.read::<String>(&mut bytes.as_slice()).is_err());
+        assert_eq!(server.read::<String>(&mut bytes.as_slice()).unwrap(), "hunter2");
+
+        let mut bytes = vec![];
+        server.write(&mut bytes, &TokenResponse::Denied("No".into())).unwrap();
+        assert!(matches!(client.read::<TokenResponse>(&mut bytes.as_slice()), Ok(TokenResponse::Denied(x)) if x == "No"));
+
+        // A server without the secret can't read what the client sent
+        let (client, client_key) = TokenChannel::client(&secret.public_key());
+        let imposter = TokenChannel::server(&TokenEndpointSecret::generate(), &client_key);
+
+        let mut bytes = vec![];
+        client.write(&mut bytes, &"hunter2").unwrap();
+        assert!(imposter.read::<String>(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn token_message_too_large() {
+        let mut bytes = vec![];
+        bincode::serialize_into(&mut bytes, &"a".repeat(10_000)).unwrap();
+
+        assert!(read_token_message::<String>(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn version_compatibility() {
+        let server = version();
+
+        assert_eq!(version().check_compatible(&server), Ok(()));
+
+        let mut old_protocol = version();
+        old_protocol.protocol_version -= 1;
+        assert!(old_protocol.check_compatible(&server).is_err());
+
+        let mut different_blocks = version();
+        different_blocks.registry_hashes[0].hash = 3;
+        assert!(different_blocks.check_compatible(&server).unwrap_err().contains("cosmos:blocks"));
+
+        let mut missing_items = version();
+        missing_items.registry_hashes.pop();
+        assert!(missing_items.check_compatible(&server).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Used by clients to smoothly display entities between the snapshots the server sends them.
+//!
+//! Bodies are sent with the server tick they were taken at. The [`TickClock`] figures out when each tick happened
+//! according to the client's clock, and each entity's [`SnapshotBuffer`] is then sampled slightly in the past so there is
+//! almost always a snapshot on either side of the time being displayed.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::{Component, Resource};
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::physics::location::Location;
+
+use super::netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation};
+
+/// How far in the past (in seconds) entities are displayed, so snapshots can arrive late or be lost without causing stutters
+pub const INTERPOLATION_DELAY: f64 = 0.1;
+/// If no newer snapshots have been received, entities keep moving with their last velocity for at most this long (in seconds)
+const MAX_EXTRAPOLATION: f64 = 0.25;
+/// Only this many snapshots are kept per entity
+const MAX_SNAPSHOTS: usize = 32;
+/// Assumed before enough ticks have been received to measure the server's tick rate
+const DEFAULT_TICKS_PER_SECOND: f64 = 60.0;
+/// How long (in seconds) ticks need to be received for before the server's tick rate is measured
+const MIN_MEASURE_SECONDS: f64 = 1.0;
+/// How much each received tick corrects the clock. Lower is smoother, but slower to adjust.
+const CLOCK_SMOOTHING: f64 = 0.05;
+
+#[derive(Resource, Debug, Default, Clone, Copy)]
+/// Estimates when (in the client's time) the server sent each tick.
+///
+/// Network jitter means ticks don't arrive evenly spaced, so this smooths out their arrival times.
+pub struct TickClock {
+    /// The first tick received & when it was received
+    first: Option<(u64, f64)>,
+    ticks_per_second: f64,
+    /// Corrects the measured time of ticks towards when they've actually been arriving
+    offset: f64,
+}
+
+impl TickClock {
+    /// Call this every time a tick is received from the server.
+    ///
+    /// * `now` The client's current time in seconds
+    pub fn receive_tick(&mut self, tick: u64, now: f64) {
+        let Some((first_tick, first_time)) = self.first else {
+            self.first = Some((tick, now));
+            self.ticks_per_second = DEFAULT_TICKS_PER_SECOND;
+            return;
+        };
+
+        let elapsed = now - first_time;
+        if elapsed >= MIN_MEASURE_SECONDS && tick > first_tick {
+            let measured = (tick - first_tick) as f64 / elapsed;
+            self.ticks_per_second += (measured - self.ticks_per_second) * CLOCK_SMOOTHING;
+        }
+
+        self.offset += (now - self.tick_time(tick)) * CLOCK_SMOOTHING;
+    }
+
+    /// Gets when this tick was received, in the client's time. Returns 0 if no ticks have been received yet.
+    pub fn tick_time(&self, tick: u64) -> f64 {
+        let Some((first_tick, first_time)) = self.first else {
+            return 0.0;
+        };
+
+        first_time + (tick as f64 - first_tick as f64) / self.ticks_per_second + self.offset
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    time: f64,
+    body: NettyRigidBody,
+}
+
+#[derive(Component, Debug, Default, Clone)]
+/// The most recent bodies received from the server for this entity
+pub struct SnapshotBuffer {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl SnapshotBuffer {
+    /// Adds a body the server sent. `time` should be from [`TickClock::tick_time`].
+    pub fn push(&mut self, time: f64, body: NettyRigidBody) {
+        let idx = self.snapshots.iter().rposition(|x| x.time <= time).map(|x| x + 1).unwrap_or(0);
+
+        self.snapshots.insert(idx, Snapshot { time, body });
+
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Removes every snapshot
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Returns true if there are no snapshots
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Calculates where the entity was at this time.
+    ///
+    /// Snapshots older than what's needed to calculate this are removed, so `render_time` should only ever increase.
+    pub fn sample(&mut self, render_time: f64) -> Option<NettyRigidBody> {
+        while self.snapshots.len() > 1 && self.snapshots[1].time <= render_time {
+            self.snapshots.pop_front();
+        }
+
+        let from = self.snapshots.front()?;
+
+        let Some(to) = self.snapshots.get(1) else {
+            let extrapolate = (render_time - from.time).clamp(0.0, MAX_EXTRAPOLATION) as f32;
+            return Some(extrapolate_body(&from.body, extrapolate));
+        };
+
+        let t = ((render_time - from.time) / (to.time - from.time).max(f64::EPSILON)).clamp(0.0, 1.0) as f32;
+
+        Some(interpolate_bodies(&from.body, &to.body, t))
+    }
+}
+
+fn extrapolate_body(body: &NettyRigidBody, seconds: f32) -> NettyRigidBody {
+    let linvel = body.body_vel.map(|x| x.linvel).unwrap_or_default();
+
+    let location = match body.location {
+        NettyRigidBodyLocation::Absolute(location) => NettyRigidBodyLocation::Absolute(location + linvel * seconds),
+        relative => relative,
+    };
+
+    NettyRigidBody { location, ..*body }
+}
+
+/// Interpolates between two bodies. `t` of 0 is `from`, 1 is `to`.
+pub fn interpolate_bodies(from: &NettyRigidBody, to: &NettyRigidBody, t: f32) -> NettyRigidBody {
+    let location = match (from.location, to.location) {
+        (NettyRigidBodyLocation::Absolute(a), NettyRigidBodyLocation::Absolute(b)) => {
+            NettyRigidBodyLocation::Absolute(lerp_location(&a, &b, t))
+        }
+        (NettyRigidBodyLocation::Relative(a, a_parent), NettyRigidBodyLocation::Relative(b, b_parent)) if a_parent == b_parent => {
+            NettyRigidBodyLocation::Relative(a.lerp(b, t), b_parent)
+        }
+        // Can't interpolate between different reference points, so just pick the closest one
+        (a, b) => {
+            if t < 0.5 {
+                a
+            } else {
+                b
+            }
+        }
+    };
+
+    let body_vel = match (from.body_vel, to.body_vel) {
+        (None, None) => None,
+        (a, b) => {
+            let (a, b) = (a.unwrap_or_default(), b.unwrap_or_default());
+
+            Some(Velocity {
+                linvel: a.linvel.lerp(b.linvel, t),
+                angvel: a.angvel.lerp(b.angvel, t),
+            })
+        }
+    };
+
+    NettyRigidBody {
+        body_vel,
+        location,
+        rotation: from.rotation.slerp(to.rotation, t),
+    }
+}
+
+/// Moves `t` of the way from `from` to `to`
+pub fn lerp_location(from: &Location, to: &Location, t: f32) -> Location {
+    *from + from.relative_coords_to(to) * t
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::{Quat, Vec3};
+    use bevy_rapier3d::prelude::Velocity;
+
+    use crate::{
+        netty::netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation},
+        physics::location::Location,
+    };
+
+    use super::{SnapshotBuffer, TickClock};
+
+    fn body_at(x: f32) -> NettyRigidBody {
+        NettyRigidBody::new(
+            Some(Velocity::linear(Vec3::new(10.0, 0.0, 0.0))),
+            Quat::IDENTITY,
+            NettyRigidBodyLocation::Absolute(Location::default() + Vec3::new(x, 0.0, 0.0)),
+        )
+    }
+
+    fn x_of(body: &NettyRigidBody) -> f32 {
+        let NettyRigidBodyLocation::Absolute(location) = body.location else {
+            panic!("Expected absolute location");
+        };
+
+        Location::default().relative_coords_to(&location).x
+    }
+
+    #[test]
+    fn interpolates_between_snapshots() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push(1.0, body_at(0.0));
+        buffer.push(2.0, body_at(10.0));
+
+        assert!((x_of(&buffer.sample(1.5).unwrap()) - 5.0).abs() < 0.001);
+        assert!((x_of(&buffer.sample(2.0).unwrap()) - 10.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn orders_late_snapshots() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push(2.0, body_at(10.0));
+        buffer.push(1.0, body_at(0.0));
+
+        assert!((x_of(&buffer.sample(1.25).unwrap()) - 2.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn extrapolates_a_limited_amount() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push(1.0, body_at(0.0));
+
+        assert!((x_of(&buffer.sample(1.1).unwrap()) - 1.0).abs() < 0.001);
+        assert!((x_of(&buffer.sample(5.0).unwrap()) - 2.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn clock_smooths_jitter() {
+        let mut clock = TickClock::default();
+
+        for tick in 0..600_u64 {
+            let jitter = if tick % 2 == 0 { 0.01 } else { -0.01 };
+            clock.receive_tick(tick, tick as f64 / 60.0 + jitter);
+        }
+
+        assert!((clock.tick_time(600) - 10.0).abs() < 0.02);
+    }
+}
@@ -4,7 +4,9 @@
 pub mod client;
 pub mod client_reliable_messages;
 pub mod client_unreliable_messages;
+pub mod connection;
 pub mod cosmos_encoder;
+pub mod interpolation;
 pub mod netty_rigidbody;
 #[cfg(feature = "server")]
 pub mod server;
@@ -52,6 +54,14 @@ pub enum NettyChannelServer {
     Shop,
     /// Generalized component syncing
     ComponentReplication,
+    /// Chat messages
+    Chat,
+    /// Used for crafting
+    Crafting,
+    /// Tells clients if they're allowed to join, see [`connection::HandshakeResponse`].
+    ///
+    /// This channel's id never changes, so clients of every version can read it.
+    Handshake,
 }
 
 /// Network channels that clients send to the server
@@ -68,6 +78,10 @@ pub enum NettyChannelClient {
     Shop,
     /// Generalized component syncing
     ComponentReplication,
+    /// Chat messages
+    Chat,
+    /// Used for crafting
+    Crafting,
 }
 
 impl From<NettyChannelClient> for u8 {
@@ -78,10 +92,15 @@ impl From<NettyChannelClient> for u8 {
             NettyChannelClient::Inventory => 2,
             NettyChannelClient::Shop => 3,
             NettyChannelClient::ComponentReplication => 4,
+            NettyChannelClient::Chat => 5,
+            NettyChannelClient::Crafting => 6,
         }
     }
 }
 
+/// Never change this, see [`NettyChannelServer::Handshake`]
+const HANDSHAKE_CHANNEL_ID: u8 = u8::MAX;
+
 const KB: usize = 1024;
 const MB: usize = KB * KB;
 
@@ -122,6 +141,20 @@ impl NettyChannelClient {
                     resend_time: Duration::from_millis(200),
                 },
             },
+            ChannelConfig {
+                channel_id: Self::Chat.into(),
+                max_memory_usage_bytes: 5 * MB,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::Crafting.into(),
+                max_memory_usage_bytes: 5 * MB,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
         ]
     }
 }
@@ -139,6 +172,9 @@ impl From<NettyChannelServer> for u8 {
             NettyChannelServer::Registry => 7,
             NettyChannelServer::Shop => 8,
             NettyChannelServer::ComponentReplication => 9,
+            NettyChannelServer::Chat => 10,
+            NettyChannelServer::Crafting => 11,
+            NettyChannelServer::Handshake => HANDSHAKE_CHANNEL_ID,
         }
     }
 }
@@ -213,13 +249,35 @@ impl NettyChannelServer {
                     resend_time: Duration::from_millis(200),
                 },
             },
+            ChannelConfig {
+                channel_id: Self::Chat.into(),
+                max_memory_usage_bytes: 5 * MB,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::Crafting.into(),
+                max_memory_usage_bytes: 5 * MB,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::Handshake.into(),
+                max_memory_usage_bytes: KB,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
         ]
     }
 }
 
-/// In the future, this should be based off the game version.
+/// Identifies Cosmos connections to renet.
 ///
-/// Must have the same protocol to connect to something
+/// Never change this - renet silently ignores clients with a different protocol id, so they would never find out why
+/// they can't connect. Bump [`connection::PROTOCOL_VERSION`] instead, which is checked when a client connects.
 pub const PROTOCOL_ID: u64 = 7;
 
 /// Assembles the configuration for a renet connection
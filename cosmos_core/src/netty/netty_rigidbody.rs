@@ -55,3 +55,217 @@ impl NettyRigidBody {
         self.body_vel.unwrap_or_default()
     }
 }
+
+/// How many quantized units make up 1 block per second of linear velocity.
+///
+/// This gives a precision of 1/16th of a block per second, and a max speed of ~2048 blocks per second.
+const LINVEL_SCALE: f32 = 16.0;
+/// How many quantized units make up 1 radian per second of angular velocity.
+///
+/// This gives a max angular velocity of ~32 radians per second.
+const ANGVEL_SCALE: f32 = 1024.0;
+/// The three smallest components of a normalized quaternion are all within +/- 1/sqrt(2)
+const QUAT_SCALE: f32 = i16::MAX as f32 * std::f32::consts::SQRT_2;
+/// Bodies that have moved less than this (in blocks) are considered to not have moved
+const POSITION_EPSILON: f32 = 0.001;
+
+fn quantize(value: f32, scale: f32) -> i16 {
+    (value * scale).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// A rotation compressed into 7 bytes.
+///
+/// Only the three smallest components are sent, since the largest can be calculated from them.
+pub struct QuantizedQuat {
+    largest: u8,
+    values: [i16; 3],
+}
+
+impl QuantizedQuat {
+    /// Compresses this rotation. The quaternion should be normalized.
+    pub fn quantize(rotation: Quat) -> Self {
+        let mut components = rotation.normalize().to_array();
+
+        let largest = (0..4)
+            .max_by(|&a, &b| components[a].abs().total_cmp(&components[b].abs()))
+            .expect("Quaternions have 4 components");
+
+        // q & -q represent the same rotation, so make the largest positive to avoid sending its sign
+        if components[largest] < 0.0 {
+            components.iter_mut().for_each(|c| *c = -*c);
+        }
+
+        let mut values = [0; 3];
+        for (value, component) in values
+            .iter_mut()
+            .zip(components.iter().enumerate().filter(|(i, _)| *i != largest).map(|(_, c)| *c))
+        {
+            *value = quantize(component, QUAT_SCALE);
+        }
+
+        Self {
+            largest: largest as u8,
+            values,
+        }
+    }
+
+    /// Decompresses this into a normalized quaternion
+    pub fn dequantize(&self) -> Quat {
+        let mut smallest = self.values.iter().map(|x| *x as f32 / QUAT_SCALE);
+        let mut components = [0.0; 4];
+
+        for (i, component) in components.iter_mut().enumerate() {
+            if i != self.largest as usize {
+                *component = smallest.next().expect("There are 3 smallest components");
+            }
+        }
+
+        let sum_sqrd: f32 = components.iter().map(|x| x * x).sum();
+        components[self.largest as usize] = (1.0 - sum_sqrd).max(0.0).sqrt();
+
+        Quat::from_array(components).normalize()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// A velocity compressed into 12 bytes
+pub struct QuantizedVelocity {
+    linvel: [i16; 3],
+    angvel: [i16; 3],
+}
+
+impl QuantizedVelocity {
+    /// Compresses this velocity. Velocities too large to be represented are clamped.
+    pub fn quantize(velocity: Velocity) -> Self {
+        Self {
+            linvel: velocity.linvel.to_array().map(|x| quantize(x, LINVEL_SCALE)),
+            angvel: velocity.angvel.to_array().map(|x| quantize(x, ANGVEL_SCALE)),
+        }
+    }
+
+    /// Decompresses this velocity
+    pub fn dequantize(&self) -> Velocity {
+        Velocity {
+            linvel: Vec3::from_array(self.linvel.map(|x| x as f32 / LINVEL_SCALE)),
+            angvel: Vec3::from_array(self.angvel.map(|x| x as f32 / ANGVEL_SCALE)),
+        }
+    }
+
+    /// Returns true if this velocity doesn't move anything
+    pub fn is_zero(&self) -> bool {
+        self.linvel == [0; 3] && self.angvel == [0; 3]
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+/// A [`NettyRigidBody`] with its rotation & velocity compressed.
+///
+/// This is what is sent to clients every tick, so it should be kept as small as possible.
+pub struct QuantizedRigidBody {
+    /// The velocity - `None` if the body isn't moving
+    pub body_vel: Option<QuantizedVelocity>,
+    /// The location
+    pub location: NettyRigidBodyLocation,
+    /// The rotation
+    pub rotation: QuantizedQuat,
+}
+
+impl QuantizedRigidBody {
+    /// Compresses this rigidbody
+    pub fn quantize(body: &NettyRigidBody) -> Self {
+        Self {
+            body_vel: body.body_vel.map(QuantizedVelocity::quantize).filter(|x| !x.is_zero()),
+            location: body.location,
+            rotation: QuantizedQuat::quantize(body.rotation),
+        }
+    }
+
+    /// Decompresses this into a usable rigidbody
+    pub fn dequantize(&self) -> NettyRigidBody {
+        NettyRigidBody::new(self.body_vel.map(|x| x.dequantize()), self.rotation.dequantize(), self.location)
+    }
+
+    /// Returns true if sending this body would tell the client anything new compared to the `other` body it has.
+    pub fn differs_from(&self, other: &QuantizedRigidBody) -> bool {
+        if self.body_vel != other.body_vel || self.rotation != other.rotation {
+            return true;
+        }
+
+        match (&self.location, &other.location) {
+            (NettyRigidBodyLocation::Absolute(a), NettyRigidBodyLocation::Absolute(b)) => {
+                a.distance_sqrd(b) > POSITION_EPSILON * POSITION_EPSILON
+            }
+            (NettyRigidBodyLocation::Relative(a, a_parent), NettyRigidBodyLocation::Relative(b, b_parent)) => {
+                a_parent != b_parent || a.distance_squared(*b) > POSITION_EPSILON * POSITION_EPSILON
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::{Quat, Vec3};
+    use bevy_rapier3d::prelude::Velocity;
+
+    use crate::physics::location::Location;
+
+    use super::{NettyRigidBody, NettyRigidBodyLocation, QuantizedQuat, QuantizedRigidBody, QuantizedVelocity};
+
+    #[test]
+    fn quat_round_trip() {
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(std::f32::consts::PI),
+            Quat::from_euler(bevy::math::EulerRot::XYZ, 1.2, -2.5, 0.3),
+            Quat::from_xyzw(-0.9, 0.1, 0.2, 0.1).normalize(),
+        ];
+
+        for rotation in rotations {
+            let result = QuantizedQuat::quantize(rotation).dequantize();
+
+            assert!(rotation.dot(result).abs() > 0.9999, "{rotation} became {result}");
+        }
+    }
+
+    #[test]
+    fn velocity_round_trip() {
+        let velocity = Velocity {
+            linvel: Vec3::new(10.5, -300.25, 0.0625),
+            angvel: Vec3::new(0.5, -1.25, 3.0),
+        };
+
+        let result = QuantizedVelocity::quantize(velocity).dequantize();
+
+        assert!((result.linvel - velocity.linvel).abs().max_element() <= 1.0 / 32.0);
+        assert!((result.angvel - velocity.angvel).abs().max_element() <= 1.0 / 2048.0);
+    }
+
+    #[test]
+    fn still_bodies_have_no_velocity() {
+        let body = NettyRigidBody::new(
+            Some(Velocity::zero()),
+            Quat::IDENTITY,
+            NettyRigidBodyLocation::Absolute(Location::default()),
+        );
+
+        assert!(QuantizedRigidBody::quantize(&body).body_vel.is_none());
+    }
+
+    #[test]
+    fn detects_changes() {
+        let body = NettyRigidBody::new(None, Quat::IDENTITY, NettyRigidBodyLocation::Absolute(Location::default()));
+        let a = QuantizedRigidBody::quantize(&body);
+
+        assert!(!a.differs_from(&a));
+
+        let mut moved = body;
+        moved.location = NettyRigidBodyLocation::Absolute(Location::default() + Vec3::new(0.5, 0.0, 0.0));
+        assert!(QuantizedRigidBody::quantize(&moved).differs_from(&a));
+
+        let mut rotated = body;
+        rotated.rotation = Quat::from_rotation_x(0.1);
+        assert!(QuantizedRigidBody::quantize(&rotated).differs_from(&a));
+    }
+}
@@ -8,15 +8,17 @@ use serde::{Deserialize, Serialize};
 
 use crate::structure::ship::ship_movement::ShipMovement;
 
-use super::netty_rigidbody::NettyRigidBody;
+use super::netty_rigidbody::QuantizedRigidBody;
 
 #[derive(Debug, Serialize, Deserialize, Component)]
 /// Movement & position data of entities
 pub enum ServerUnreliableMessages {
     /// Contains position information of entities relevant to the player that receives it
     BulkBodies {
-        /// All the entities with their corresponding rigidbody
-        bodies: Vec<(Entity, NettyRigidBody)>,
+        /// The entities whose rigidbodies have changed, with their compressed rigidbody.
+        ///
+        /// Entities that haven't changed are not sent every tick.
+        bodies: Vec<(Entity, QuantizedRigidBody)>,
         /// The server tick this was sent at
         time_stamp: u64,
     },
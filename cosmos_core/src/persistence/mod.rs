@@ -4,9 +4,12 @@ use bevy::{
     prelude::{App, Component},
     reflect::Reflect,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::physics::location::SECTOR_DIMENSIONS;
+use crate::{
+    physics::location::{Location, SECTOR_DIMENSIONS},
+    structure::chunk::netty::SaveData,
+};
 
 /// The default loading distance for structures
 pub const LOAD_DISTANCE: f32 = SECTOR_DIMENSIONS * 8.0;
@@ -65,6 +68,93 @@ impl LoadingDistance {
     }
 }
 
+#[derive(Component, Debug, Reflect, Serialize, Deserialize)]
+/// Stores the serialized data for an entity.
+///
+/// This is either read from or written to a save file depending on if an entity is being loaded or saved.
+pub struct SerializedData {
+    save_data: SaveData,
+
+    /// Used to identify the location this should be saved under
+    location: Option<Location>,
+    should_save: bool,
+}
+
+impl SerializedData {
+    /// Use this to set location. This will make sure the folder name
+    /// reflects the actual location.
+    pub fn set_location(&mut self, loc: &Location) {
+        self.serialize_data("cosmos:location", loc);
+        self.location = Some(*loc);
+    }
+
+    /// The location this will be saved under, if one was set
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+}
+
+impl Default for SerializedData {
+    fn default() -> Self {
+        Self {
+            save_data: SaveData::default(),
+            location: None,
+            should_save: true,
+        }
+    }
+}
+
+impl SerializedData {
+    /// Saves the data to that data id. Will overwrite any existing data at that id.
+    ///
+    /// Will only save if `should_save()` returns true.
+    pub fn save(&mut self, data_id: impl Into<String>, data: Vec<u8>) {
+        if self.should_save() {
+            self.save_data.save(data_id, data);
+        }
+    }
+
+    /// Calls `cosmos_encoder::serialize` on the passed in data.
+    /// Then sends that data into the `save` method, with the given data id.
+    ///
+    /// Will only serialize & save if `should_save()` returns true.
+
+    pub fn serialize_data(&mut self, data_id: impl Into<String>, data: &impl Serialize) {
+        if self.should_save() {
+            self.save_data.serialize_data(data_id, data);
+        }
+    }
+
+    /// Reads the data as raw bytes at the given data id. Use `deserialize_data` for a streamlined way to read the data.
+    pub fn read_data(&self, data_id: &str) -> Option<&Vec<u8>> {
+        self.save_data.read_data(data_id)
+    }
+
+    /// Iterates over every data id that has data saved to it
+    pub fn data_ids(&self) -> impl Iterator<Item = &str> {
+        self.save_data.data_ids()
+    }
+
+    /// Deserializes the data as the given type (via `cosmos_encoder::deserialize`) at the given id. Will panic if the
+    /// data is not properly serialized.
+    pub fn deserialize_data<T: DeserializeOwned>(&self, data_id: &str) -> Option<T> {
+        self.save_data.deserialize_data(data_id)
+    }
+
+    /// Sets whether this should actually be saved - if false, when save and serialize_data is called,
+    /// nothing will happen.
+    pub fn set_should_save(&mut self, should_save: bool) {
+        self.should_save = should_save;
+    }
+
+    /// If this is false, no data will be saved/serialized when `save` and `serialize_data` is called.
+    ///
+    /// No data will be written to the disk either if this is false.
+    pub fn should_save(&self) -> bool {
+        self.should_save
+    }
+}
+
 #[derive(Component, Debug, Reflect, Default, Clone, Copy)]
 /// Signifies that this entity can be blueprinted.
 pub struct Blueprintable;
@@ -9,7 +9,7 @@ use bevy_rapier3d::prelude::RapierPhysicsPlugin;
 use crate::physics::collision_handling::CosmosPhysicsFilter;
 use crate::{block, economy, ecs, inventory, netty, persistence, projectiles, shop, universe};
 use crate::{blockitems, structure};
-use crate::{events, loader};
+use crate::{crafting, entities, events, loader, logic};
 use crate::{item, physics};
 
 /// This plugin group should contain everything needed for a cosmos application to run
@@ -92,6 +92,9 @@ impl<T: States + Clone + Copy> Plugin for CosmosCorePlugin<T> {
         netty::register(app);
         economy::register(app);
         shop::register(app);
+        logic::register(app);
+        crafting::register(app);
+        entities::register(app);
     }
 }
 
@@ -41,6 +41,7 @@ pub struct LaserCollideEvent {
     entity_hit: Entity,
     local_position_hit: Vec3,
     laser_strength: f32,
+    causer: Option<Entity>,
 }
 
 impl LaserCollideEvent {
@@ -60,6 +61,13 @@ impl LaserCollideEvent {
     pub fn local_position_hit(&self) -> Vec3 {
         self.local_position_hit
     }
+
+    /// The entity that fired this laser, if it was fired by something
+    ///
+    /// *NOTE*: Make sure to verify this entity still exists before processing it
+    pub fn causer(&self) -> Option<Entity> {
+        self.causer
+    }
 }
 
 #[derive(Component)]
@@ -256,6 +264,7 @@ fn handle_events(
                             entity_hit: entity,
                             local_position_hit: lph,
                             laser_strength: laser.strength,
+                            causer: no_collide_entity.map(|x| x.0),
                         });
                     }
                 } else if let Ok(transform) = transform_query.get(entity) {
@@ -267,6 +276,7 @@ fn handle_events(
                         entity_hit: entity,
                         local_position_hit: lph,
                         laser_strength: laser.strength,
+                        causer: no_collide_entity.map(|x| x.0),
                     });
                 }
 
@@ -1,10 +1,16 @@
 //! Contains information about the different projectiles
 
-use bevy::prelude::App;
+use bevy::prelude::{App, Component, Entity};
 
 pub mod laser;
 pub mod missile;
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+/// The structure that fired this projectile.
+///
+/// Explosions keep the causer of the missile that created them, so any damage they do can be blamed on it.
+pub struct Causer(pub Entity);
+
 pub(super) fn register(app: &mut App) {
     laser::register(app);
     missile::register(app);
@@ -0,0 +1,137 @@
+//! Numeric ids are assigned based on the order things are registered in, so they can change
+//! whenever something is added or removed. Anything that is saved with numeric ids should also save
+//! an [`IdPalette`], so those ids can be translated back into whatever they are now when loaded.
+
+use serde::{Deserialize, Serialize};
+
+use super::{identifiable::Identifiable, Registry};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// The unlocalized names of everything in a registry, indexed by their numeric id at the time this was created.
+pub struct IdPalette(Vec<String>);
+
+impl IdPalette {
+    /// Creates a palette of every value currently in this registry
+    pub fn from_registry<T: Identifiable>(registry: &Registry<T>) -> Self {
+        Self(registry.iter().map(|x| x.unlocalized_name().to_owned()).collect())
+    }
+
+    /// Gets what this id referred to when this palette was created
+    pub fn unlocalized_name(&self, id: u16) -> Option<&str> {
+        self.0.get(id as usize).map(|x| x.as_str())
+    }
+
+    /// Creates a mapping from the ids in this palette to the ids they now have in this registry.
+    ///
+    /// Anything no longer in the registry will be mapped to `fallback_id`.
+    pub fn create_mapping<T: Identifiable>(&self, registry: &Registry<T>, fallback_id: u16) -> IdMapping {
+        IdMapping {
+            ids: self
+                .0
+                .iter()
+                .map(|unlocalized_name| registry.from_id(unlocalized_name).map(|x| x.id()).unwrap_or(fallback_id))
+                .collect(),
+            fallback_id,
+        }
+    }
+
+    /// Replaces every occurrence of `from` with `to`, so anything saved as `from` will be loaded as `to` instead.
+    ///
+    /// Returns true if anything was replaced.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        let mut renamed = false;
+
+        for unlocalized_name in self.0.iter_mut().filter(|x| *x == from) {
+            *unlocalized_name = to.to_owned();
+            renamed = true;
+        }
+
+        renamed
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Maps the numeric ids an [`IdPalette`] was created with to the numeric ids they currently have.
+pub struct IdMapping {
+    ids: Vec<u16>,
+    fallback_id: u16,
+}
+
+impl IdMapping {
+    /// Gets the current id of something that was saved with this old id.
+    ///
+    /// If it no longer exists, the fallback id is returned.
+    pub fn map(&self, old_id: u16) -> u16 {
+        self.ids.get(old_id as usize).copied().unwrap_or(self.fallback_id)
+    }
+
+    /// Returns true if every id maps to itself, meaning nothing needs to be changed.
+    pub fn is_identity(&self) -> bool {
+        self.ids.iter().enumerate().all(|(old_id, &new_id)| old_id == new_id as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        item::Item,
+        registry::{identifiable::Identifiable, Registry},
+    };
+
+    use super::IdPalette;
+
+    fn registry(names: &[&str]) -> Registry<Item> {
+        let mut registry = Registry::new("cosmos:test");
+
+        for name in names {
+            registry.register(Item::new(*name, 10));
+        }
+
+        registry
+    }
+
+    #[test]
+    fn unchanged_registry_is_identity() {
+        let registry = registry(&["cosmos:a", "cosmos:b", "cosmos:c"]);
+
+        let mapping = IdPalette::from_registry(&registry).create_mapping(&registry, 0);
+
+        assert!(mapping.is_identity());
+        assert_eq!(mapping.map(2), 2);
+    }
+
+    #[test]
+    fn reordered_and_removed() {
+        let old = registry(&["cosmos:a", "cosmos:b", "cosmos:c", "cosmos:removed"]);
+        let new = registry(&["cosmos:a", "cosmos:new", "cosmos:c", "cosmos:b", "cosmos:unknown"]);
+
+        let fallback = new.from_id("cosmos:unknown").unwrap().id();
+        let mapping = IdPalette::from_registry(&old).create_mapping(&new, fallback);
+
+        assert!(!mapping.is_identity());
+        assert_eq!(mapping.map(0), 0);
+        assert_eq!(mapping.map(1), 3);
+        assert_eq!(mapping.map(2), 2);
+        assert_eq!(mapping.map(3), fallback);
+        // Ids that were never in the palette
+        assert_eq!(mapping.map(100), fallback);
+    }
+
+    #[test]
+    fn renamed_entries_map_to_new_name() {
+        let old = registry(&["cosmos:a", "cosmos:old", "cosmos:c"]);
+        let new = registry(&["cosmos:a", "cosmos:c", "cosmos:new", "cosmos:unknown"]);
+
+        let fallback = new.from_id("cosmos:unknown").unwrap().id();
+        let mut palette = IdPalette::from_registry(&old);
+
+        assert!(palette.rename("cosmos:old", "cosmos:new"));
+        assert!(!palette.rename("cosmos:missing", "cosmos:new"));
+
+        let mapping = palette.create_mapping(&new, fallback);
+
+        assert_eq!(mapping.map(0), 0);
+        assert_eq!(mapping.map(1), 2);
+        assert_eq!(mapping.map(2), 1);
+    }
+}
@@ -1,5 +1,6 @@
 //! Handles the various types of registries you can use to register data.
 
+pub mod id_palette;
 pub mod identifiable;
 pub mod many_to_one;
 pub mod one_to_one;
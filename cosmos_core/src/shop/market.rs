@@ -0,0 +1,380 @@
+//! Supply & demand for shops that aren't owned by players.
+//!
+//! Each item a shop trades has a base price that is changed by how scarce that item is in the shop's region,
+//! how much of it the shop has in stock, and how much players have recently been buying or selling it.
+
+use bevy::ecs::component::Component;
+use serde::{Deserialize, Serialize};
+
+use crate::registry::id_palette::IdMapping;
+
+use super::{Shop, ShopEntry};
+
+/// How much being out of stock (or overstocked) changes the price
+const STOCK_INFLUENCE: f32 = 0.5;
+/// How much recent trading changes the price, relative to the shop's target stock
+const DEMAND_INFLUENCE: f32 = 0.5;
+/// The fraction of its target stock a shop restocks (or sells off) every second
+const RESTOCK_RATE: f32 = 0.002;
+/// How quickly recent trading stops mattering, per second
+const DEMAND_DECAY: f32 = 0.005;
+/// Shops buy items for this fraction of what they sell them for
+const BUY_SPREAD: f32 = 0.9;
+/// A shop won't buy more once it has this many times its target stock
+const MAX_STOCK_MULTIPLIER: u32 = 2;
+/// Prices never go below this multiple of the base price
+const MIN_PRICE_MULTIPLIER: f32 = 0.25;
+/// Prices never go above this multiple of the base price
+const MAX_PRICE_MULTIPLIER: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// The supply & demand of one item at one shop
+pub struct MarketItem {
+    /// The item's id
+    pub item_id: u16,
+    /// The price of this item before supply & demand are considered
+    pub base_price: u32,
+    /// How rare this item is in this shop's region. 1.0 is normal, higher is rarer & more expensive.
+    pub scarcity: f32,
+    /// How many of this item the shop has
+    pub stock: u32,
+    /// The amount of stock the shop restocks towards
+    pub target_stock: u32,
+    /// How many more of this item players have recently bought than sold. Negative if they've sold more.
+    pub demand: f32,
+}
+
+impl MarketItem {
+    /// Creates a market item that starts fully stocked with no recent trading
+    pub fn new(item_id: u16, base_price: u32, scarcity: f32, target_stock: u32) -> Self {
+        Self {
+            item_id,
+            base_price,
+            scarcity,
+            stock: target_stock,
+            target_stock,
+            demand: 0.0,
+        }
+    }
+
+    /// How much the base price is multiplied by
+    pub fn price_multiplier(&self) -> f32 {
+        let target = self.target_stock.max(1) as f32;
+
+        let stock_factor = 1.0 + STOCK_INFLUENCE * (1.0 - self.stock as f32 / target);
+        let demand_factor = 1.0 + DEMAND_INFLUENCE * self.demand / target;
+
+        (self.scarcity * stock_factor * demand_factor).clamp(MIN_PRICE_MULTIPLIER, MAX_PRICE_MULTIPLIER)
+    }
+
+    /// The price the shop sells one of this item for
+    pub fn selling_price(&self) -> u32 {
+        ((self.base_price as f32 * self.price_multiplier()).round() as u32).max(1)
+    }
+
+    /// The price the shop pays for one of this item
+    pub fn buying_price(&self) -> u32 {
+        (self.selling_price() as f32 * BUY_SPREAD) as u32
+    }
+
+    /// The total price of buying this many of this item.
+    ///
+    /// Every item bought raises the price of the next one, so each is priced at what the shop would sell it for once it's
+    /// been bought. [`Self::value_of_selling`] prices each item the same way in reverse, so buying items & selling them
+    /// straight back can never make a profit.
+    pub fn cost_of_buying(&self, quantity: u32) -> u64 {
+        let mut after = *self;
+        let mut cost = 0;
+
+        for bought in 0..quantity {
+            after.bought(1);
+            let price = after.selling_price() as u64;
+
+            if after.price_multiplier() >= MAX_PRICE_MULTIPLIER {
+                // Buying more can't raise the price any further, so the rest all cost the same
+                return cost + price * (quantity - bought) as u64;
+            }
+
+            cost += price;
+        }
+
+        cost
+    }
+
+    /// The total price the shop pays for this many of this item.
+    ///
+    /// Every item sold lowers the price of the next one, so each is priced at what the shop would pay for it before it's sold.
+    pub fn value_of_selling(&self, quantity: u32) -> u64 {
+        let mut before = *self;
+        let mut value = 0;
+
+        for sold in 0..quantity {
+            let price = before.buying_price() as u64;
+
+            if before.price_multiplier() <= MIN_PRICE_MULTIPLIER {
+                // Selling more can't lower the price any further, so the rest are all worth the same
+                return value + price * (quantity - sold) as u64;
+            }
+
+            before.sold(1);
+            value += price;
+        }
+
+        value
+    }
+
+    /// The most of this item the shop will hold
+    pub fn max_stock(&self) -> u32 {
+        self.target_stock.saturating_mul(MAX_STOCK_MULTIPLIER)
+    }
+
+    /// Call this when players buy this item from the shop
+    pub fn bought(&mut self, quantity: u32) {
+        self.stock = self.stock.saturating_sub(quantity);
+        self.demand += quantity as f32;
+    }
+
+    /// Call this when players sell this item to the shop
+    pub fn sold(&mut self, quantity: u32) {
+        self.stock = self.stock.saturating_add(quantity).min(self.max_stock());
+        self.demand -= quantity as f32;
+    }
+
+    /// Moves the stock towards its target & lets recent trading fade
+    pub fn simulate(&mut self, delta_seconds: f32) {
+        let restock = (self.target_stock as f32 * RESTOCK_RATE * delta_seconds).ceil() as u32;
+
+        if self.stock < self.target_stock {
+            self.stock = (self.stock + restock).min(self.target_stock);
+        } else {
+            self.stock = self.stock.saturating_sub(restock).max(self.target_stock);
+        }
+
+        self.demand *= (-DEMAND_DECAY * delta_seconds).exp();
+    }
+}
+
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+/// The supply & demand of everything a shop trades.
+///
+/// This is stored next to the [`Shop`] in its block data, and decides that shop's prices & stock.
+pub struct ShopMarket {
+    /// Every item this shop trades
+    pub items: Vec<MarketItem>,
+}
+
+impl ShopMarket {
+    /// Gets the market for this item, if the shop trades it
+    pub fn item(&self, item_id: u16) -> Option<&MarketItem> {
+        self.items.iter().find(|x| x.item_id == item_id)
+    }
+
+    /// Gets the market for this item, if the shop trades it
+    pub fn item_mut(&mut self, item_id: u16) -> Option<&mut MarketItem> {
+        self.items.iter_mut().find(|x| x.item_id == item_id)
+    }
+
+    /// Changes every item to whatever item its id now maps to.
+    ///
+    /// Used when loading a market that was saved with a potentially different item registry.
+    pub fn remap_item_ids(&mut self, mapping: &IdMapping) {
+        for item in self.items.iter_mut() {
+            item.item_id = mapping.map(item.item_id);
+        }
+    }
+
+    /// Simulates every item for this amount of time
+    pub fn simulate(&mut self, delta_seconds: f32) {
+        for item in self.items.iter_mut() {
+            item.simulate(delta_seconds);
+        }
+    }
+
+    /// Sets the shop's prices & quantities to match this market
+    pub fn update_shop(&self, shop: &mut Shop) {
+        for entry in shop.contents.iter_mut() {
+            let Some(item) = self.items.iter().find(|x| x.item_id == entry.item_id()) else {
+                continue;
+            };
+
+            match entry {
+                ShopEntry::Selling {
+                    max_quantity_selling,
+                    price_per,
+                    ..
+                } => {
+                    *max_quantity_selling = item.stock;
+                    *price_per = item.selling_price();
+                }
+                ShopEntry::Buying {
+                    max_quantity_buying,
+                    price_per,
+                    ..
+                } => {
+                    *max_quantity_buying = Some(item.max_stock() - item.stock);
+                    *price_per = item.buying_price();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::shop::{Shop, ShopEntry};
+
+    use super::{MarketItem, ShopMarket, MAX_PRICE_MULTIPLIER};
+
+    #[test]
+    fn scarce_stock_is_expensive() {
+        let mut item = MarketItem::new(1, 100, 1.0, 1000);
+        assert_eq!(item.selling_price(), 100);
+
+        item.bought(500);
+        let after_buying = item.selling_price();
+        assert!(after_buying > 100);
+
+        let mut rare = MarketItem::new(1, 100, 1.5, 1000);
+        rare.bought(500);
+        assert!(rare.selling_price() > after_buying);
+    }
+
+    #[test]
+    fn selling_lowers_price() {
+        let mut item = MarketItem::new(1, 100, 1.0, 1000);
+
+        item.sold(1000);
+
+        assert_eq!(item.stock, 2000);
+        assert!(item.selling_price() < 100);
+        assert!(item.buying_price() < item.selling_price());
+    }
+
+    #[test]
+    fn bulk_trades_are_priced_along_the_curve() {
+        let item = MarketItem::new(1, 100, 1.0, 1000);
+
+        let cost = item.cost_of_buying(500);
+        let mut after = item;
+        after.bought(500);
+
+        assert!(cost > 500 * item.selling_price() as u64);
+        assert!(cost <= 500 * after.selling_price() as u64);
+
+        // Buying in smaller batches costs the same
+        let mut partway = item;
+        partway.bought(200);
+        assert_eq!(cost, item.cost_of_buying(200) + partway.cost_of_buying(300));
+    }
+
+    #[test]
+    fn bulk_prices_stop_at_the_price_limits() {
+        fn priced_one_at_a_time(item: MarketItem, quantity: u32, buying: bool) -> u64 {
+            let mut item = item;
+
+            (0..quantity)
+                .map(|_| {
+                    if buying {
+                        item.bought(1);
+                        item.selling_price() as u64
+                    } else {
+                        let price = item.buying_price() as u64;
+                        item.sold(1);
+                        price
+                    }
+                })
+                .sum()
+        }
+
+        // Small enough stock that both price limits are reached well before this many are traded
+        let item = MarketItem::new(1, 100, 1.0, 10);
+
+        assert_eq!(item.cost_of_buying(1000), priced_one_at_a_time(item, 1000, true));
+        assert_eq!(item.value_of_selling(1000), priced_one_at_a_time(item, 1000, false));
+
+        // These finish straight away instead of pricing every item
+        let max_price = (100.0 * MAX_PRICE_MULTIPLIER) as u64;
+        assert!(item.cost_of_buying(u32::MAX) >= (u32::MAX as u64 - 1000) * max_price);
+        assert!(item.value_of_selling(u32::MAX) > 0);
+    }
+
+    #[test]
+    fn round_trips_never_profit() {
+        for (scarcity, target_stock) in [(1.0, 1000), (1.5, 1000), (3.0, 50), (0.5, 10), (1.0, 1)] {
+            for quantity in [1, 5, target_stock / 2, target_stock] {
+                let mut item = MarketItem::new(1, 100, scarcity, target_stock);
+
+                let paid = item.cost_of_buying(quantity);
+                item.bought(quantity);
+
+                // Selling back one at a time shouldn't be worth more than selling back all at once
+                let received = (0..quantity)
+                    .map(|_| {
+                        let value = item.value_of_selling(1);
+                        item.sold(1);
+                        value
+                    })
+                    .sum::<u64>();
+
+                assert!(received <= paid, "Bought {quantity} for {paid} but sold them for {received}");
+            }
+        }
+    }
+
+    #[test]
+    fn restocks_over_time() {
+        let mut item = MarketItem::new(1, 100, 1.0, 1000);
+        item.bought(1000);
+
+        for _ in 0..1000 {
+            item.simulate(1.0);
+        }
+
+        assert_eq!(item.stock, 1000);
+        assert!(item.demand.abs() < 10.0);
+        assert_eq!(item.selling_price(), 100);
+    }
+
+    #[test]
+    fn updates_shop_entries() {
+        let mut market = ShopMarket {
+            items: vec![MarketItem::new(1, 100, 1.0, 50)],
+        };
+        market.item_mut(1).unwrap().bought(10);
+
+        let mut shop = Shop {
+            contents: vec![
+                ShopEntry::Selling {
+                    item_id: 1,
+                    max_quantity_selling: 0,
+                    price_per: 0,
+                },
+                ShopEntry::Buying {
+                    item_id: 1,
+                    max_quantity_buying: None,
+                    price_per: 0,
+                },
+            ],
+            ..Default::default()
+        };
+
+        market.update_shop(&mut shop);
+
+        let item = market.items[0];
+        assert_eq!(
+            shop.contents,
+            vec![
+                ShopEntry::Selling {
+                    item_id: 1,
+                    max_quantity_selling: 40,
+                    price_per: item.selling_price(),
+                },
+                ShopEntry::Buying {
+                    item_id: 1,
+                    max_quantity_buying: Some(60),
+                    price_per: item.buying_price(),
+                },
+            ]
+        );
+    }
+}
@@ -1,12 +1,32 @@
 //! Facilitate the trading of goods
 
-use bevy::{app::App, ecs::component::Component, reflect::Reflect};
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        system::{Commands, Query, Res},
+    },
+    log::warn,
+    reflect::Reflect,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::economy::Credits;
+use crate::{
+    block::{data::BlockData, Block},
+    economy::Credits,
+    events::block_events::BlockChangedEvent,
+    registry::{id_palette::IdMapping, identifiable::Identifiable, Registry},
+    structure::{structure_block::StructureBlock, Structure},
+};
 
-use self::netty::{ShopPurchaseError, ShopSellError};
+use self::{
+    market::MarketItem,
+    netty::{ShopPurchaseError, ShopSellError},
+};
 
+pub mod market;
 pub mod netty;
 
 #[derive(Debug, Serialize, Deserialize, Reflect, Component, Clone, Copy, PartialEq, Eq)]
@@ -32,6 +52,24 @@ pub enum ShopEntry {
     },
 }
 
+impl ShopEntry {
+    /// The item this entry is buying or selling
+    pub fn item_id(&self) -> u16 {
+        match *self {
+            Self::Selling { item_id, .. } => item_id,
+            Self::Buying { item_id, .. } => item_id,
+        }
+    }
+
+    /// Returns true if both entries are selling or both are buying the same item
+    pub fn same_listing(&self, other: &ShopEntry) -> bool {
+        matches!(
+            (self, other),
+            (Self::Selling { .. }, Self::Selling { .. }) | (Self::Buying { .. }, Self::Buying { .. })
+        ) && self.item_id() == other.item_id()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Reflect, Default, Component, Clone)]
 /// Block data that indiciates this is a shop
 pub struct Shop {
@@ -39,11 +77,77 @@ pub struct Shop {
     pub name: String,
     /// What the shop is buying/selling
     pub contents: Vec<ShopEntry>,
+    /// The name of the player that owns this shop.
+    ///
+    /// Shops generated with the universe have no owner, and have an endless supply of credits.
+    pub owner: Option<String>,
+    /// The credits this shop has earned.
+    ///
+    /// Only used for player-owned shops, which pay sellers out of this.
+    pub credits: Credits,
 }
 
 impl Shop {
-    /// Buys an item from this shop, or returns an error if the purchase was unsuccessful
-    pub fn buy(&mut self, item_id: u16, quantity: u32, credits: &mut Credits) -> Result<(), ShopPurchaseError> {
+    /// Returns true if this player owns this shop
+    pub fn is_owned_by(&self, player_name: &str) -> bool {
+        self.owner.as_ref().map(|x| x == player_name).unwrap_or(false)
+    }
+
+    /// Adds this entry to the shop, replacing the entry for this same listing if there is one
+    pub fn set_entry(&mut self, entry: ShopEntry) {
+        if let Some(existing) = self.contents.iter_mut().find(|x| x.same_listing(&entry)) {
+            *existing = entry;
+        } else {
+            self.contents.push(entry);
+        }
+    }
+
+    /// Removes the entry for this same listing. Returns false if there was no entry for it.
+    pub fn remove_entry(&mut self, entry: &ShopEntry) -> bool {
+        let len = self.contents.len();
+
+        self.contents.retain(|x| !x.same_listing(entry));
+
+        self.contents.len() != len
+    }
+
+    /// Changes every entry's item to whatever item its id now maps to.
+    ///
+    /// Used when loading a shop that was saved with a potentially different item registry.
+    pub fn remap_item_ids(&mut self, mapping: &IdMapping) {
+        for entry in self.contents.iter_mut() {
+            match entry {
+                ShopEntry::Selling { item_id, .. } | ShopEntry::Buying { item_id, .. } => *item_id = mapping.map(*item_id),
+            }
+        }
+    }
+
+    /// Sets how many of each item this shop is selling to how many it has in stock.
+    ///
+    /// Used by player-owned shops, which sell what is in their storage.
+    pub fn update_stock(&mut self, quantity_of: impl Fn(u16) -> u32) {
+        for entry in self.contents.iter_mut() {
+            if let ShopEntry::Selling {
+                item_id,
+                max_quantity_selling,
+                ..
+            } = entry
+            {
+                *max_quantity_selling = quantity_of(*item_id);
+            }
+        }
+    }
+
+    /// Buys an item from this shop, or returns an error if the purchase was unsuccessful.
+    ///
+    /// If the shop's prices are set by a market, pass in that item's market so the price changes along with the purchase.
+    pub fn buy(
+        &mut self,
+        item_id: u16,
+        quantity: u32,
+        credits: &mut Credits,
+        market: Option<&MarketItem>,
+    ) -> Result<(), ShopPurchaseError> {
         for entry in self.contents.iter_mut() {
             let ShopEntry::Selling {
                 item_id: entry_id,
@@ -57,26 +161,35 @@ impl Shop {
                 continue;
             }
 
-            let cost = *price_per as u64 * quantity as u64;
-
+            // Checked before pricing, since that takes longer the more items are bought
             if *max_quantity_selling < quantity {
                 return Err(ShopPurchaseError::NoStock(self.clone()));
             }
 
+            let cost = market
+                .map(|x| x.cost_of_buying(quantity))
+                .unwrap_or(*price_per as u64 * quantity as u64);
+
             if !credits.decrease(cost) {
                 return Err(ShopPurchaseError::InsufficientFunds);
             }
 
             *max_quantity_selling -= quantity;
 
+            if self.owner.is_some() {
+                self.credits.increase(cost);
+            }
+
             return Ok(());
         }
 
         Err(ShopPurchaseError::NoStock(self.clone()))
     }
 
-    /// Sells an item to this shop, or returns an error if the selling was unsuccessful
-    pub fn sell(&mut self, item_id: u16, quantity: u32, credits: &mut Credits) -> Result<(), ShopSellError> {
+    /// Sells an item to this shop, or returns an error if the selling was unsuccessful.
+    ///
+    /// If the shop's prices are set by a market, pass in that item's market so the price changes along with the sale.
+    pub fn sell(&mut self, item_id: u16, quantity: u32, credits: &mut Credits, market: Option<&MarketItem>) -> Result<(), ShopSellError> {
         for entry in self.contents.iter_mut() {
             let ShopEntry::Buying {
                 item_id: entry_id,
@@ -90,12 +203,19 @@ impl Shop {
                 continue;
             }
 
-            let credits_gain = *price_per as u64 * quantity as u64;
-
+            // Checked before pricing, since that takes longer the more items are sold
             if max_quantity_buying.unwrap_or(u32::MAX) < quantity {
                 return Err(ShopSellError::NotWillingToBuyThatMany(self.clone()));
             }
 
+            let credits_gain = market
+                .map(|x| x.value_of_selling(quantity))
+                .unwrap_or(*price_per as u64 * quantity as u64);
+
+            if self.owner.is_some() && !self.credits.decrease(credits_gain) {
+                return Err(ShopSellError::InsufficientFunds);
+            }
+
             if let Some(max_qty_buying) = max_quantity_buying {
                 *max_qty_buying -= quantity;
             }
@@ -109,6 +229,171 @@ impl Shop {
     }
 }
 
+#[derive(Event, Debug)]
+/// Sent whenever a shop block is removed, with the shop that was there.
+///
+/// The shop's credits are not paid to anyone by this, so whatever handles this event must do that.
+pub struct ShopRemovedEvent {
+    /// The structure the shop was on
+    pub structure_entity: Entity,
+    /// The shop's block
+    pub block: StructureBlock,
+    /// The shop as it was before it was removed
+    pub shop: Shop,
+}
+
+/// Removes the shop data from shop blocks that are no longer there
+fn on_remove_shop(
+    q_structure: Query<&Structure>,
+    blocks: Res<Registry<Block>>,
+    mut evr_block_changed: EventReader<BlockChangedEvent>,
+    mut commands: Commands,
+    mut q_block_data: Query<&mut BlockData>,
+    q_shop: Query<&Shop>,
+    mut evw_shop_removed: EventWriter<ShopRemovedEvent>,
+) {
+    let Some(shop_block) = blocks.from_id("cosmos:shop") else {
+        return;
+    };
+
+    for ev in evr_block_changed.read() {
+        if ev.new_block == ev.old_block || ev.old_block != shop_block.id() {
+            continue;
+        }
+
+        let Ok(structure) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        let Some(data_ent) = structure.block_data(ev.block.coords()) else {
+            continue;
+        };
+
+        let Ok(shop) = q_shop.get(data_ent) else {
+            continue;
+        };
+
+        evw_shop_removed.send(ShopRemovedEvent {
+            structure_entity: ev.structure_entity,
+            block: ev.block,
+            shop: shop.clone(),
+        });
+
+        if let Ok(mut block_data) = q_block_data.get_mut(data_ent) {
+            block_data.decrement();
+        } else {
+            warn!("Missing BlockData on block data component?");
+        }
+
+        if let Some(mut ecmds) = commands.get_entity(data_ent) {
+            ecmds.remove::<Shop>();
+        }
+    }
+}
+
 pub(super) fn register(app: &mut App) {
-    app.register_type::<Shop>().register_type::<ShopEntry>();
+    app.add_systems(Update, on_remove_shop)
+        .add_event::<ShopRemovedEvent>()
+        .register_type::<Shop>()
+        .register_type::<ShopEntry>();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::economy::Credits;
+
+    use super::{
+        market::MarketItem,
+        netty::{ShopPurchaseError, ShopSellError},
+        Shop, ShopEntry,
+    };
+
+    fn owned_shop() -> Shop {
+        Shop {
+            name: "Test Shop".into(),
+            contents: vec![
+                ShopEntry::Selling {
+                    item_id: 1,
+                    max_quantity_selling: 0,
+                    price_per: 10,
+                },
+                ShopEntry::Buying {
+                    item_id: 2,
+                    max_quantity_buying: None,
+                    price_per: 5,
+                },
+            ],
+            owner: Some("owner".into()),
+            credits: Credits::new(0),
+        }
+    }
+
+    #[test]
+    fn owned_shop_earns_credits() {
+        let mut shop = owned_shop();
+        shop.update_stock(|id| if id == 1 { 3 } else { 0 });
+
+        let mut buyer = Credits::new(100);
+
+        assert!(shop.buy(1, 4, &mut buyer, None).is_err());
+        assert!(shop.buy(1, 3, &mut buyer, None).is_ok());
+        assert_eq!(buyer.amount(), 70);
+        assert_eq!(shop.credits.amount(), 30);
+    }
+
+    #[test]
+    fn owned_shop_pays_from_its_credits() {
+        let mut shop = owned_shop();
+        let mut seller = Credits::new(0);
+
+        assert!(matches!(shop.sell(2, 1, &mut seller, None), Err(ShopSellError::InsufficientFunds)));
+
+        shop.credits = Credits::new(12);
+
+        assert!(shop.sell(2, 2, &mut seller, None).is_ok());
+        assert_eq!(seller.amount(), 10);
+        assert_eq!(shop.credits.amount(), 2);
+    }
+
+    #[test]
+    fn buying_more_than_the_stock_fails_before_pricing() {
+        let mut shop = owned_shop();
+        shop.update_stock(|_| 3);
+
+        let mut market = MarketItem::new(1, 10, 1.0, u32::MAX);
+        // Far from either price limit, so pricing this many would have to go through every item
+        market.stock = u32::MAX / 2;
+
+        let mut buyer = Credits::new(u64::MAX);
+
+        assert!(matches!(
+            shop.buy(1, u32::MAX, &mut buyer, Some(&market)),
+            Err(ShopPurchaseError::NoStock(_))
+        ));
+        assert_eq!(buyer.amount(), u64::MAX);
+    }
+
+    #[test]
+    fn set_entry_replaces_same_listing() {
+        let mut shop = owned_shop();
+
+        shop.set_entry(ShopEntry::Buying {
+            item_id: 2,
+            max_quantity_buying: Some(10),
+            price_per: 7,
+        });
+        shop.set_entry(ShopEntry::Buying {
+            item_id: 1,
+            max_quantity_buying: None,
+            price_per: 7,
+        });
+
+        assert_eq!(shop.contents.len(), 3);
+        assert!(shop.remove_entry(&ShopEntry::Selling {
+            item_id: 1,
+            max_quantity_selling: 0,
+            price_per: 0,
+        }));
+        assert_eq!(shop.contents.len(), 2);
+    }
 }
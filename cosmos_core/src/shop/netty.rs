@@ -1,11 +1,11 @@
-//! Represents the communications a laser cannon system sends
+//! Represents the communications about shops
 
 use bevy::{ecs::entity::Entity, prelude::Component};
 use serde::{Deserialize, Serialize};
 
 use crate::structure::coordinates::BlockCoordinate;
 
-use super::Shop;
+use super::{Shop, ShopEntry};
 
 #[derive(Debug, Serialize, Deserialize)]
 /// An error occurred when trying to buy something from the shop
@@ -21,16 +21,25 @@ pub enum ShopPurchaseError {
 #[derive(Debug, Serialize, Deserialize)]
 /// An error occurred when trying to sell something to the shop
 pub enum ShopSellError {
-    /// never thrown yet (eventually shops will have their own money)
+    /// The shop doesn't have enough credits to pay for the items
     InsufficientFunds,
     /// The buyer did not have enough items to sell
     NotEnoughItems,
-    /// never thrown yet (eventually shops will store their items in an inventory)
+    /// The shop's storage doesn't have enough room to fit the items
     NotEnoughInventorySpace,
     /// The shop isn't willing to buy that many items
     NotWillingToBuyThatMany(Shop),
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+/// An error occurred when trying to change a shop
+pub enum ShopEditError {
+    /// Only the owner of a shop can change it
+    NotOwner,
+    /// The owner doesn't have enough credits to deposit that many
+    InsufficientFunds,
+}
+
 #[derive(Debug, Serialize, Deserialize, Component)]
 /// Messages about shops the server will send to the player
 pub enum ServerShopMessages {
@@ -61,12 +70,30 @@ pub enum ServerShopMessages {
         /// The details about the selling
         details: Result<Shop, ShopSellError>,
     },
+    /// Sent whenever an attempt to change the shop is handled
+    EditResult {
+        /// The shop's block
+        shop_block: BlockCoordinate,
+        /// The shop's entity
+        structure_entity: Entity,
+        /// The shop after the change, or why it couldn't be changed
+        details: Result<Shop, ShopEditError>,
+    },
+    /// Sent to everyone looking at a shop whenever someone else changes it, so they see its current stock & prices
+    ShopContents {
+        /// The shop's block
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+        /// The shop's new data
+        shop_data: Shop,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Component)]
 /// Sent from the client to the server to communicate about shop items.
 pub enum ClientShopMessages {
-    /// Client qequests to buy something from the shop
+    /// Client requests to buy something from the shop
     Buy {
         /// The shop they're buying from's block coordinates
         shop_block: BlockCoordinate,
@@ -88,4 +115,45 @@ pub enum ClientShopMessages {
         /// The quantity they want to sell
         quantity: u32,
     },
+    /// The owner of the shop wants to add or change what it buys/sells
+    SetEntry {
+        /// The shop's block coordinates
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+        /// The entry to add, replacing the existing one for this item if there is one
+        entry: ShopEntry,
+    },
+    /// The owner of the shop no longer wants it to buy/sell this
+    RemoveEntry {
+        /// The shop's block coordinates
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+        /// The entry to remove
+        entry: ShopEntry,
+    },
+    /// The owner of the shop wants to take the credits the shop has earned
+    WithdrawCredits {
+        /// The shop's block coordinates
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+    },
+    /// The owner of the shop wants to give it credits to buy items with
+    DepositCredits {
+        /// The shop's block coordinates
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+        /// The amount of credits to give the shop
+        amount: u64,
+    },
+    /// The client closed the shop they were looking at, and no longer needs to know when it changes
+    CloseShop {
+        /// The shop's block coordinates
+        shop_block: BlockCoordinate,
+        /// The shop's structure entity
+        structure_entity: Entity,
+    },
 }
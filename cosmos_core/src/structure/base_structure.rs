@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     block::{blocks::AIR_BLOCK_ID, Block, BlockRotation},
     physics::location::Location,
-    registry::Registry,
+    registry::{id_palette::IdMapping, Registry},
 };
 
 use super::{
@@ -332,6 +332,13 @@ impl BaseStructure {
         &self.chunks
     }
 
+    /// Changes every block in every loaded chunk to whatever block its id now maps to.
+    pub fn remap_block_ids(&mut self, mapping: &IdMapping) {
+        for chunk in self.chunks.values_mut() {
+            chunk.remap_block_ids(mapping);
+        }
+    }
+
     /// Removes the chunk at the given coordinate -- does NOT remove the chunk entity
     pub(super) fn unload_chunk(&mut self, coords: ChunkCoordinate) {
         self.chunks.remove(&self.flatten(coords));
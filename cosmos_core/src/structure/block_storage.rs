@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     block::{blocks::AIR_BLOCK_ID, Block, BlockRotation},
-    registry::{identifiable::Identifiable, Registry},
+    registry::{id_palette::IdMapping, identifiable::Identifiable, Registry},
     structure::chunk::CHUNK_DIMENSIONS,
 };
 
@@ -116,6 +116,17 @@ impl BlockStorage {
             }
         }
     }
+
+    /// Changes every block to whatever block its id now maps to.
+    ///
+    /// Used when loading block ids that were saved with a potentially different block registry.
+    pub fn remap_block_ids(&mut self, mapping: &IdMapping) {
+        for block in self.blocks.iter_mut() {
+            *block = mapping.map(*block);
+        }
+
+        self.non_air_blocks = self.blocks.iter().filter(|&&id| id != AIR_BLOCK_ID).count() as u32;
+    }
 }
 
 impl BlockStorer for BlockStorage {
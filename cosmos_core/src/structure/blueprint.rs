@@ -0,0 +1,416 @@
+//! Blueprints are structures saved to a file so they can be spawned again later.
+//!
+//! A blueprint file starts with a small header describing the blueprint (see [`BlueprintMetadata`]), followed by the
+//! structure's [`SerializedData`]. The header can be read on its own, so a library of blueprints can be browsed without
+//! decoding every structure in it.
+//!
+//! Blueprints made before headers existed are just the [`SerializedData`], and are still read as format version 0.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    io::{self, Read},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    block::Block,
+    blockitems::BlockItems,
+    item::Item,
+    netty::cosmos_encoder,
+    persistence::SerializedData,
+    registry::{id_palette::IdPalette, identifiable::Identifiable, Registry},
+};
+
+use super::{coordinates::BlockCoordinate, Structure};
+
+/// The format version of blueprints made by this version of the game.
+///
+/// Increase this whenever the format changes in a way older versions can't read.
+pub const BLUEPRINT_FORMAT_VERSION: u32 = 1;
+
+/// Every blueprint file with a header starts with these bytes
+const BLUEPRINT_MAGIC: &[u8; 4] = b"CSBP";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Describes a blueprint without having to load the structure in it
+pub struct BlueprintMetadata {
+    /// The blueprint's name
+    pub name: String,
+    /// The type of structure this is a blueprint of, such as `ship` or `station`
+    pub blueprint_type: String,
+    /// The name of the player that made this blueprint, or `None` if it wasn't made by a player
+    pub author: Option<String>,
+    /// When this blueprint was made, in seconds since the unix epoch
+    pub created_at: u64,
+    /// The size of the structure, in blocks
+    pub dimensions: BlockCoordinate,
+    /// How many of each block (by unlocalized name) are in this blueprint
+    pub block_counts: BTreeMap<String, u64>,
+    /// How many of each item (by unlocalized name) are needed to build this blueprint
+    pub required_items: BTreeMap<String, u64>,
+    /// How many credits it would cost to buy every required item that has a price
+    pub credit_cost: u64,
+}
+
+impl BlueprintMetadata {
+    /// Counts every block in this structure & works out which items are needed to build it.
+    ///
+    /// `price_of` returns how many credits one of an item costs, or `None` if it can't be bought.
+    /// Items without a price aren't included in the [`Self::credit_cost`].
+    pub fn new(
+        name: impl Into<String>,
+        blueprint_type: impl Into<String>,
+        author: Option<String>,
+        created_at: u64,
+        structure: &Structure,
+        blocks: &Registry<Block>,
+        items: &Registry<Item>,
+        block_items: &BlockItems,
+        price_of: impl Fn(&Item) -> Option<u64>,
+    ) -> Self {
+        let mut block_ids = BTreeMap::<u16, u64>::new();
+        for block in structure.all_blocks_iter(false) {
+            *block_ids.entry(structure.block_id_at(block.coords())).or_default() += 1;
+        }
+
+        let mut block_counts = BTreeMap::new();
+        let mut required_items = BTreeMap::new();
+        let mut credit_cost = 0;
+
+        for (block_id, count) in block_ids {
+            let block = blocks.from_numeric_id(block_id);
+            block_counts.insert(block.unlocalized_name().to_owned(), count);
+
+            let Some(item_id) = block_items.item_from_block(block) else {
+                continue;
+            };
+
+            let item = items.from_numeric_id(item_id);
+            *required_items.entry(item.unlocalized_name().to_owned()).or_default() += count;
+            credit_cost += price_of(item).unwrap_or(0) * count;
+        }
+
+        Self {
+            name: name.into(),
+            blueprint_type: blueprint_type.into(),
+            author,
+            created_at,
+            dimensions: structure.block_dimensions(),
+            block_counts,
+            required_items,
+            credit_cost,
+        }
+    }
+
+    /// The total number of blocks in this blueprint
+    pub fn total_blocks(&self) -> u64 {
+        self.block_counts.values().sum()
+    }
+
+    /// Makes sure every block in this blueprint still exists.
+    ///
+    /// Blocks that no longer exist will be loaded as `cosmos:unknown` instead.
+    pub fn validate(&self, blocks: &Registry<Block>) -> Result<(), BlueprintError> {
+        let unknown_blocks = self
+            .block_counts
+            .keys()
+            .filter(|unlocalized_name| blocks.from_id(unlocalized_name).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if unknown_blocks.is_empty() {
+            Ok(())
+        } else {
+            Err(BlueprintError::UnknownBlocks(unknown_blocks))
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+/// Something is wrong with a blueprint
+pub enum BlueprintError {
+    /// The blueprint was made by a newer version of the game
+    #[error("this blueprint was made by a newer version of the game (format version {0})")]
+    UnsupportedVersion(u32),
+    /// The blueprint couldn't be decoded
+    #[error("this blueprint is corrupted")]
+    Corrupted,
+    /// The blueprint contains blocks that aren't registered
+    #[error("this blueprint contains blocks that no longer exist: {}", .0.join(", "))]
+    UnknownBlocks(Vec<String>),
+    /// The blueprint couldn't be read
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+/// The start of a blueprint file, which describes the blueprint
+pub struct BlueprintHeader {
+    /// The format version the blueprint was saved with
+    pub format_version: u32,
+    /// The blueprint's metadata, or `None` if it was made before blueprints had any
+    pub metadata: Option<BlueprintMetadata>,
+}
+
+impl BlueprintHeader {
+    /// Reads only the header from the start of a blueprint file
+    pub fn read(mut reader: impl Read) -> Result<Self, BlueprintError> {
+        let mut magic = [0; BLUEPRINT_MAGIC.len()];
+        if reader.read_exact(&mut magic).is_err() || magic != *BLUEPRINT_MAGIC {
+            return Ok(Self {
+                format_version: 0,
+                metadata: None,
+            });
+        }
+
+        let format_version = read_u32(&mut reader)?;
+        if format_version > BLUEPRINT_FORMAT_VERSION {
+            return Err(BlueprintError::UnsupportedVersion(format_version));
+        }
+
+        let metadata_len = read_u32(&mut reader)? as usize;
+
+        // The length could be anything if the file is corrupted, so don't allocate it all up front
+        let mut metadata = Vec::new();
+        reader.take(metadata_len as u64).read_to_end(&mut metadata)?;
+        if metadata.len() != metadata_len {
+            return Err(BlueprintError::Corrupted);
+        }
+
+        let metadata = cosmos_encoder::deserialize(&metadata).map_err(|_| BlueprintError::Corrupted)?;
+
+        Ok(Self {
+            format_version,
+            metadata: Some(metadata),
+        })
+    }
+
+    /// How many bytes this header takes up at the start of the file
+    fn len(bytes: &[u8]) -> usize {
+        if !bytes.starts_with(BLUEPRINT_MAGIC) {
+            return 0;
+        }
+
+        let metadata_len_start = BLUEPRINT_MAGIC.len() + 4;
+
+        bytes
+            .get(metadata_len_start..metadata_len_start + 4)
+            .map(|x| metadata_len_start + 4 + u32::from_le_bytes(x.try_into().expect("This is 4 bytes")) as usize)
+            .unwrap_or(bytes.len())
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, BlueprintError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(|_| BlueprintError::Corrupted)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// A structure that has been saved as a blueprint
+pub struct Blueprint {
+    /// Describes this blueprint
+    pub header: BlueprintHeader,
+    /// The blueprinted structure's data
+    pub data: SerializedData,
+}
+
+impl Blueprint {
+    /// Encodes this blueprint's data with a header describing it, ready to be written to a file
+    pub fn encode(metadata: &BlueprintMetadata, data: &SerializedData) -> Vec<u8> {
+        let metadata = cosmos_encoder::serialize(metadata);
+        let data = cosmos_encoder::serialize(data);
+
+        let mut bytes = Vec::with_capacity(BLUEPRINT_MAGIC.len() + 8 + metadata.len() + data.len());
+        bytes.extend_from_slice(BLUEPRINT_MAGIC);
+        bytes.extend_from_slice(&BLUEPRINT_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&metadata);
+        bytes.extend_from_slice(&data);
+
+        bytes
+    }
+
+    /// Decodes the contents of a blueprint file, with or without a header
+    pub fn decode(bytes: &[u8]) -> Result<Self, BlueprintError> {
+        let header = BlueprintHeader::read(bytes)?;
+
+        let data = cosmos_encoder::deserialize(&bytes[BlueprintHeader::len(bytes)..]).map_err(|_| BlueprintError::Corrupted)?;
+
+        Ok(Self { header, data })
+    }
+
+    /// Makes sure every block in this blueprint's structure still exists.
+    ///
+    /// Unlike [`BlueprintMetadata::validate`], this checks the blocks the structure actually contains, by what they were
+    /// saved as in the blueprint's block palette. Blueprints saved without a palette are assumed to use the current block ids.
+    pub fn validate(&self, blocks: &Registry<Block>) -> Result<(), BlueprintError> {
+        let structure = self
+            .data
+            .deserialize_data::<Structure>("cosmos:structure")
+            .ok_or(BlueprintError::Corrupted)?;
+        let palette = self.data.deserialize_data::<IdPalette>("cosmos:block_palette");
+
+        let used_ids = structure
+            .all_blocks_iter(false)
+            .map(|block| structure.block_id_at(block.coords()))
+            .collect::<HashSet<u16>>();
+
+        let unknown_blocks = unknown_blocks(used_ids, palette.as_ref(), blocks);
+
+        if unknown_blocks.is_empty() {
+            Ok(())
+        } else {
+            Err(BlueprintError::UnknownBlocks(unknown_blocks))
+        }
+    }
+}
+
+/// Finds which of these block ids refer to blocks that aren't registered anymore, by what they were saved as
+fn unknown_blocks(used_ids: impl IntoIterator<Item = u16>, palette: Option<&IdPalette>, blocks: &Registry<Block>) -> Vec<String> {
+    used_ids
+        .into_iter()
+        .filter_map(|id| {
+            let unlocalized_name = match palette {
+                Some(palette) => palette.unlocalized_name(id),
+                None => blocks.try_from_numeric_id(id).map(|block| block.unlocalized_name()),
+            };
+
+            match unlocalized_name {
+                Some(unlocalized_name) if blocks.from_id(unlocalized_name).is_some() => None,
+                Some(unlocalized_name) => Some(unlocalized_name.to_owned()),
+                None => Some(format!("block id {id}")),
+            }
+        })
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        block::Block,
+        persistence::SerializedData,
+        registry::{id_palette::IdPalette, Registry},
+        structure::coordinates::BlockCoordinate,
+    };
+
+    use super::{unknown_blocks, Blueprint, BlueprintError, BlueprintHeader, BlueprintMetadata, BLUEPRINT_FORMAT_VERSION, BLUEPRINT_MAGIC};
+
+    fn metadata() -> BlueprintMetadata {
+        BlueprintMetadata {
+            name: "test".into(),
+            blueprint_type: "ship".into(),
+            author: Some("player".into()),
+            created_at: 1_700_000_000,
+            dimensions: BlockCoordinate::new(3, 4, 5),
+            block_counts: BTreeMap::from([("cosmos:ship_core".into(), 1), ("cosmos:ship_hull_grey".into(), 20)]),
+            required_items: BTreeMap::from([("cosmos:ship_core".into(), 1), ("cosmos:ship_hull_grey".into(), 20)]),
+            credit_cost: 500,
+        }
+    }
+
+    fn data() -> SerializedData {
+        let mut data = SerializedData::default();
+        data.serialize_data("cosmos:is_ship", &true);
+        data
+    }
+
+    #[test]
+    fn encode_decode() {
+        let bytes = Blueprint::encode(&metadata(), &data());
+
+        let blueprint = Blueprint::decode(&bytes).unwrap();
+
+        assert_eq!(blueprint.header.format_version, BLUEPRINT_FORMAT_VERSION);
+        assert_eq!(blueprint.header.metadata, Some(metadata()));
+        assert_eq!(blueprint.data.deserialize_data::<bool>("cosmos:is_ship"), Some(true));
+        assert_eq!(metadata().total_blocks(), 21);
+    }
+
+    #[test]
+    fn header_is_read_alone() {
+        let bytes = Blueprint::encode(&metadata(), &data());
+
+        // Only the header is needed, so the structure's data can be cut off
+        let header_len = BlueprintHeader::len(&bytes);
+        let header = BlueprintHeader::read(&bytes[..header_len]).unwrap();
+
+        assert_eq!(header.metadata, Some(metadata()));
+    }
+
+    #[test]
+    fn legacy_blueprint() {
+        let bytes = crate::netty::cosmos_encoder::serialize(&data());
+
+        let blueprint = Blueprint::decode(&bytes).unwrap();
+
+        assert_eq!(blueprint.header.format_version, 0);
+        assert_eq!(blueprint.header.metadata, None);
+        assert_eq!(blueprint.data.deserialize_data::<bool>("cosmos:is_ship"), Some(true));
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut bytes = Blueprint::encode(&metadata(), &data());
+        bytes[BLUEPRINT_MAGIC.len()..BLUEPRINT_MAGIC.len() + 4].copy_from_slice(&(BLUEPRINT_FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(Blueprint::decode(&bytes), Err(BlueprintError::UnsupportedVersion(v)) if v == BLUEPRINT_FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn corrupted() {
+        let bytes = Blueprint::encode(&metadata(), &data());
+
+        assert!(matches!(
+            Blueprint::decode(&bytes[..bytes.len() - 3]),
+            Err(BlueprintError::Corrupted)
+        ));
+        assert!(matches!(Blueprint::decode(&bytes[..10]), Err(BlueprintError::Corrupted)));
+    }
+
+    #[test]
+    fn validate_blocks() {
+        let mut blocks = Registry::<Block>::new("cosmos:blocks");
+        blocks.register(Block::new(&[], 0, "cosmos:ship_core".into(), 1.0, 1.0, 1.0, vec![], vec![]));
+
+        assert!(matches!(
+            metadata().validate(&blocks),
+            Err(BlueprintError::UnknownBlocks(unknown)) if unknown == vec!["cosmos:ship_hull_grey".to_owned()]
+        ));
+
+        blocks.register(Block::new(&[], 0, "cosmos:ship_hull_grey".into(), 1.0, 1.0, 1.0, vec![], vec![]));
+
+        assert!(metadata().validate(&blocks).is_ok());
+    }
+
+    fn block_registry(names: &[&str]) -> Registry<Block> {
+        let mut blocks = Registry::<Block>::new("cosmos:blocks");
+
+        for name in names {
+            blocks.register(Block::new(&[], 0, (*name).into(), 1.0, 1.0, 1.0, vec![], vec![]));
+        }
+
+        blocks
+    }
+
+    #[test]
+    fn unknown_blocks_by_palette() {
+        let old = block_registry(&["cosmos:air", "cosmos:removed", "cosmos:ship_core"]);
+        let new = block_registry(&["cosmos:air", "cosmos:ship_core"]);
+
+        let palette = IdPalette::from_registry(&old);
+
+        assert_eq!(unknown_blocks([2], Some(&palette), &new), Vec::<String>::new());
+        assert_eq!(unknown_blocks([1, 2, 1], Some(&palette), &new), vec!["cosmos:removed".to_owned()]);
+        assert_eq!(unknown_blocks([7], Some(&palette), &new), vec!["block id 7".to_owned()]);
+
+        // Without a palette, the ids are assumed to be the current ones
+        assert_eq!(unknown_blocks([1], None, &new), Vec::<String>::new());
+        assert_eq!(unknown_blocks([1, 2], None, &new), vec!["block id 2".to_owned()]);
+    }
+}
@@ -8,7 +8,7 @@ use bevy::utils::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::block::{Block, BlockFace, BlockRotation, BlockSubRotation};
-use crate::registry::Registry;
+use crate::registry::{id_palette::IdMapping, Registry};
 
 use super::block_health::BlockHealth;
 use super::block_storage::{BlockStorage, BlockStorer};
@@ -120,6 +120,13 @@ impl Chunk {
         }
     }
 
+    /// Changes every block in this chunk to whatever block its id now maps to.
+    ///
+    /// Used when loading chunks that were saved with a potentially different block registry.
+    pub fn remap_block_ids(&mut self, mapping: &IdMapping) {
+        self.block_storage.remap_block_ids(mapping);
+    }
+
     #[inline]
     /// The position of this chunk in the structure.
     pub fn chunk_coordinates(&self) -> ChunkCoordinate {
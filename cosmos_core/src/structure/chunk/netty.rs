@@ -9,7 +9,11 @@ use bevy::{
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
+    inventory::Inventory,
+    item::Item,
     netty::cosmos_encoder,
+    registry::{id_palette::IdMapping, Registry},
+    shop::{market::ShopMarket, Shop},
     structure::coordinates::{ChunkBlockCoordinate, ChunkCoordinate},
 };
 
@@ -17,6 +21,30 @@ use crate::{
 /// Contains all the serialized block data for each block that has it in a chunk
 pub struct SerializedChunkBlockData(HashMap<ChunkBlockCoordinate, SaveData>);
 
+impl SerializedChunkBlockData {
+    /// Changes the items of every serialized block inventory & shop to whatever item their ids now map to.
+    ///
+    /// Used when loading block data that was saved with a potentially different item registry.
+    pub fn remap_item_ids(&mut self, mapping: &IdMapping, items: &Registry<Item>) {
+        for save_data in self.0.values_mut() {
+            if let Some(mut inventory) = save_data.deserialize_data::<Inventory>("cosmos:inventory") {
+                inventory.remap_item_ids(mapping, items);
+                save_data.serialize_data("cosmos:inventory", &inventory);
+            }
+
+            if let Some(mut shop) = save_data.deserialize_data::<Shop>("cosmos:shop") {
+                shop.remap_item_ids(mapping);
+                save_data.serialize_data("cosmos:shop", &shop);
+            }
+
+            if let Some(mut market) = save_data.deserialize_data::<ShopMarket>("cosmos:shop_market") {
+                market.remap_item_ids(mapping);
+                save_data.serialize_data("cosmos:shop_market", &market);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Reflect, Serialize, Deserialize, Default, Clone)]
 /// A version of `SerializedData` without the location field and the inability to disable saving
 pub struct SaveData(HashMap<String, Vec<u8>>);
@@ -43,6 +71,11 @@ impl SaveData {
         self.0.get(data_id)
     }
 
+    /// Iterates over every data id that has data saved to it
+    pub fn data_ids(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(|x| x.as_str())
+    }
+
     /// Deserializes the data as the given type (via `cosmos_encoder::deserialize`) at the given id. Will panic if the
     /// data is not properly serialized.
     pub fn deserialize_data<T: DeserializeOwned>(&self, data_id: &str) -> Option<T> {
@@ -111,3 +144,79 @@ impl SerializedBlockData {
         std::mem::take(&mut self.save_data)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        item::Item,
+        registry::{id_palette::IdPalette, identifiable::Identifiable, Registry},
+        shop::{
+            market::{MarketItem, ShopMarket},
+            Shop, ShopEntry,
+        },
+        structure::coordinates::ChunkBlockCoordinate,
+    };
+
+    use super::{SaveData, SerializedChunkBlockData};
+
+    fn registry(names: &[&str]) -> Registry<Item> {
+        let mut registry = Registry::new("cosmos:test");
+
+        for name in names {
+            registry.register(Item::new(*name, 10));
+        }
+
+        registry
+    }
+
+    #[test]
+    fn remaps_shop_items() {
+        let old = registry(&["cosmos:unknown", "cosmos:iron", "cosmos:gold"]);
+        let new = registry(&["cosmos:unknown", "cosmos:gold", "cosmos:iron"]);
+
+        let id = |registry: &Registry<Item>, name: &str| registry.from_id(name).unwrap().id();
+        let (old_iron, old_gold) = (id(&old, "cosmos:iron"), id(&old, "cosmos:gold"));
+
+        let shop = Shop {
+            contents: vec![
+                ShopEntry::Selling {
+                    item_id: old_iron,
+                    max_quantity_selling: 10,
+                    price_per: 5,
+                },
+                ShopEntry::Buying {
+                    item_id: old_gold,
+                    max_quantity_buying: None,
+                    price_per: 20,
+                },
+            ],
+            ..Default::default()
+        };
+        let market = ShopMarket {
+            items: vec![MarketItem::new(old_iron, 5, 1.0, 100), MarketItem::new(old_gold, 20, 1.0, 100)],
+        };
+
+        let mut save_data = SaveData::default();
+        save_data.serialize_data("cosmos:shop", &shop);
+        save_data.serialize_data("cosmos:shop_market", &market);
+
+        let coords = ChunkBlockCoordinate::new(1, 2, 3);
+        let mut block_data = SerializedChunkBlockData::default();
+        block_data.insert(coords, save_data);
+
+        let mapping = IdPalette::from_registry(&old).create_mapping(&new, id(&new, "cosmos:unknown"));
+        block_data.remap_item_ids(&mapping, &new);
+
+        let save_data = &block_data[&coords];
+        let shop = save_data.deserialize_data::<Shop>("cosmos:shop").unwrap();
+        let market = save_data.deserialize_data::<ShopMarket>("cosmos:shop_market").unwrap();
+
+        let (new_iron, new_gold) = (id(&new, "cosmos:iron"), id(&new, "cosmos:gold"));
+
+        assert_eq!(
+            shop.contents.iter().map(|x| x.item_id()).collect::<Vec<_>>(),
+            vec![new_iron, new_gold]
+        );
+        assert_eq!(market.items.iter().map(|x| x.item_id).collect::<Vec<_>>(), vec![new_iron, new_gold]);
+    }
+}
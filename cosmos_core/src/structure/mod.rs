@@ -17,6 +17,7 @@ pub mod asteroid;
 pub mod base_structure;
 pub mod block_health;
 pub mod block_storage;
+pub mod blueprint;
 pub mod chunk;
 pub mod coordinates;
 pub mod dynamic_structure;
@@ -25,6 +26,7 @@ pub mod full_structure;
 pub mod loading;
 pub mod lod;
 pub mod lod_chunk;
+pub mod ownership;
 pub mod planet;
 pub mod shared;
 pub mod shields;
@@ -41,6 +43,7 @@ use crate::ecs::NeedsDespawned;
 use crate::events::block_events::BlockChangedEvent;
 use crate::netty::NoSendEntity;
 use crate::physics::location::Location;
+use crate::registry::id_palette::IdMapping;
 use crate::registry::Registry;
 use crate::structure::chunk::Chunk;
 use bevy::prelude::{
@@ -284,6 +287,16 @@ impl Structure {
         }
     }
 
+    /// Changes every block in every loaded chunk to whatever block its id now maps to.
+    ///
+    /// Used when loading structures that were saved with a potentially different block registry.
+    pub fn remap_block_ids(&mut self, mapping: &IdMapping) {
+        match self {
+            Self::Full(fs) => fs.remap_block_ids(mapping),
+            Self::Dynamic(ds) => ds.remap_block_ids(mapping),
+        }
+    }
+
     /// Removes the block at the given coordinates
     ///
     /// * `event_writer` If this is None, no event will be generated.
@@ -769,6 +782,7 @@ pub(super) fn register<T: States + Clone + Copy>(app: &mut App, playing_state: T
     shields::register(app);
     block_health::register(app);
     structure_block::register(app);
+    ownership::register(app);
 
     app.add_systems(Update, add_chunks_system.in_set(StructureLoadingSet::CreateChunkEntities))
         .add_systems(PreUpdate, remove_empty_chunks);
@@ -0,0 +1,133 @@
+//! Who owns a structure, and who else is allowed to use it.
+//!
+//! Structures without a [`StructureOwner`] (such as planets, asteroids & structures from before ownership existed)
+//! can be used by anyone.
+
+use bevy::{app::App, ecs::component::Component};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    faction::{FactionRank, Factions},
+    netty::sync::{sync_component, SyncType, SyncableComponent},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Something a player could try to do to a structure
+pub enum StructurePermission {
+    /// Fly a ship
+    Pilot,
+    /// Place or break blocks
+    Build,
+    /// Open storage blocks
+    AccessStorage,
+    /// Dock a ship to it
+    Dock,
+    /// Craft at its fabricators
+    Craft,
+}
+
+impl StructurePermission {
+    /// The lowest rank in the owner's faction that can do this
+    pub fn required_rank(&self) -> FactionRank {
+        match self {
+            Self::Build => FactionRank::Officer,
+            Self::Pilot | Self::AccessStorage | Self::Dock | Self::Craft => FactionRank::Member,
+        }
+    }
+
+    /// Describes this action in a sentence, such as "You cannot {description} this structure."
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Pilot => "pilot",
+            Self::Build => "build on",
+            Self::AccessStorage => "open the storage of",
+            Self::Dock => "dock with",
+            Self::Craft => "use the fabricators of",
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The player that owns this structure.
+///
+/// Members of the owner's faction can also use it, depending on their rank.
+pub struct StructureOwner {
+    player_name: String,
+}
+
+impl StructureOwner {
+    /// Makes this player the owner
+    pub fn new(player_name: impl Into<String>) -> Self {
+        Self {
+            player_name: player_name.into(),
+        }
+    }
+
+    /// The name of the player that owns this
+    pub fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    /// Returns true if this player is allowed to do this to the structure
+    pub fn allows(&self, player_name: &str, permission: StructurePermission, factions: &Factions) -> bool {
+        if self.player_name == player_name {
+            return true;
+        }
+
+        factions
+            .player_faction(&self.player_name)
+            .and_then(|faction| faction.rank(player_name))
+            .is_some_and(|rank| rank >= permission.required_rank())
+    }
+}
+
+/// Returns true if this player is allowed to do this to a structure with this owner.
+///
+/// Structures without an owner can be used by anyone.
+pub fn has_permission(owner: Option<&StructureOwner>, player_name: &str, permission: StructurePermission, factions: &Factions) -> bool {
+    owner.map_or(true, |owner| owner.allows(player_name, permission, factions))
+}
+
+impl SyncableComponent for StructureOwner {
+    fn get_sync_type() -> SyncType {
+        SyncType::ServerAuthoritative
+    }
+
+    fn get_component_unlocalized_name() -> &'static str {
+        "cosmos:structure_owner"
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    sync_component::<StructureOwner>(app);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::faction::Factions;
+
+    use super::{has_permission, StructureOwner, StructurePermission};
+
+    #[test]
+    fn faction_members_share_structures() {
+        let mut factions = Factions::default();
+        factions.create("Builders", "alice").unwrap();
+        factions.invite("alice", "bob").unwrap();
+        factions.join("bob", "Builders").unwrap();
+
+        let owner = StructureOwner::new("alice");
+
+        assert!(owner.allows("alice", StructurePermission::Build, &factions));
+        assert!(owner.allows("bob", StructurePermission::Pilot, &factions));
+        assert!(!owner.allows("bob", StructurePermission::Build, &factions));
+        assert!(!owner.allows("carol", StructurePermission::Pilot, &factions));
+
+        factions.change_rank("alice", "bob", true).unwrap();
+        assert!(owner.allows("bob", StructurePermission::Build, &factions));
+    }
+
+    #[test]
+    fn unowned_structures_are_public() {
+        assert!(has_permission(None, "anyone", StructurePermission::Build, &Factions::default()));
+    }
+}
@@ -0,0 +1,524 @@
+//! A CPU implementation of the biosphere terrain generation shaders (`assets/cosmos/shaders/biosphere`).
+//!
+//! This is used when there is no GPU available (such as dedicated servers), and should produce the same
+//! [`TerrainData`] as the shaders do. If you change the shaders, make sure to change this as well, and capture the
+//! golden terrain again (see [`super::terrain_golden`]).
+
+use bevy::math::{DVec2, DVec3, IVec2, Vec3, Vec4, Vec4Swizzles};
+
+use crate::{
+    block::BlockFace,
+    structure::{
+        chunk::{CHUNK_DIMENSIONS, CHUNK_DIMENSIONS_USIZE},
+        planet::Planet,
+    },
+};
+
+use super::terrain_generation::{GenerationParams, GpuPermutationTable, TerrainData, GRAD_TABLE};
+
+// Stolen from: https://github.com/Mapet13/opensimplex_noise_rust/blob/master/src/open_simplex_noise_3d.rs#L40
+// These are kept the same as the shader's constants.
+const STRETCH: f64 = 1.0 / 6.0; // -(1 / sqrt(3 + 1) - 1) / 3
+const SQUISH: f64 = 1.0 / 3.0; // (sqrt(3 + 1) - 1) / 3
+
+const STRETCH_POINT: DVec3 = DVec3::splat(STRETCH);
+const SQUISH_POINT: DVec3 = DVec3::splat(SQUISH);
+
+const NORMALIZING_SCALAR: f64 = 103.0;
+
+#[derive(Debug, Clone)]
+/// Generates the same terrain data as the biosphere shaders, but on the CPU.
+pub struct CpuTerrainGenerator {
+    permutation_table: Vec<u32>,
+}
+
+impl CpuTerrainGenerator {
+    /// Creates a generator that uses the same permutation table that would be sent to the GPU
+    pub fn new(permutation_table: &GpuPermutationTable) -> Self {
+        Self {
+            permutation_table: permutation_table.0.iter().flat_map(|v| [v.x, v.y, v.z, v.w]).collect(),
+        }
+    }
+
+    /// Generates the terrain data for every block in the chunk these params are for.
+    ///
+    /// The data is ordered the same way the GPU returns it (see [`crate::utils::array_utils::flatten`]).
+    pub fn generate_chunk(&self, params: &GenerationParams) -> Vec<TerrainData> {
+        let mut values = Vec::with_capacity(CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE);
+
+        for z in 0..CHUNK_DIMENSIONS {
+            for y in 0..CHUNK_DIMENSIONS {
+                for x in 0..CHUNK_DIMENSIONS {
+                    values.push(self.generate(params, Vec3::new(x as f32, y as f32, z as f32)));
+                }
+            }
+        }
+
+        values
+    }
+
+    /// Mirrors `default_generate` in `default_generation.wgsl`
+    fn generate(&self, param: &GenerationParams, coords: Vec3) -> TerrainData {
+        let coords_f32 = coords.extend(0.0) * param.scale + param.chunk_coords;
+        let coords_vec3 = coords_f32.xyz();
+        let sea_level = param.sea_level.y;
+
+        let mut depth_here = self.calculate_depth_at(coords_vec3, sea_level);
+
+        if depth_here >= 0 && depth_here < (10.0 * param.scale.x) as i32 {
+            let delta = Planet::planet_face_relative(coords_vec3).direction_vec3();
+
+            let value_above = self.calculate_depth_at(coords_vec3 + delta * param.scale.xyz(), sea_level);
+            if value_above < 0 {
+                // There is no block above us, so make sure we're the top layer.
+                depth_here = 0;
+            } else if depth_here == 0 {
+                // There is a block above us, so ensure we're not the top layer.
+                depth_here = 1;
+            }
+        }
+
+        let data = self.calculate_biome_parameters(coords_f32, param.structure_pos);
+
+        TerrainData { depth: depth_here, data }
+    }
+
+    fn calculate_depth_at(&self, coords_f32: Vec3, sea_level: f32) -> i32 {
+        let delta = 0.01;
+
+        let amplitude_delta = 0.01;
+        let amplitude = self
+            .noise(
+                (coords_f32.x + 537.0) as f64 * amplitude_delta,
+                (coords_f32.y - 1123.0) as f64 * amplitude_delta,
+                (coords_f32.z + 1458.0) as f64 * amplitude_delta,
+            )
+            .abs()
+            * 20.0;
+
+        let mut depth = 0.0;
+
+        for iteration in (1..=9).rev() {
+            let iteration = iteration as f64;
+
+            depth += self.noise(
+                coords_f32.x as f64 * (delta / iteration),
+                coords_f32.y as f64 * (delta / iteration),
+                coords_f32.z as f64 * (delta / iteration),
+            ) * amplitude
+                * iteration;
+        }
+
+        let coord = match Planet::planet_face_relative(coords_f32) {
+            BlockFace::Top | BlockFace::Bottom => coords_f32.y,
+            BlockFace::Front | BlockFace::Back => coords_f32.z,
+            BlockFace::Left | BlockFace::Right => coords_f32.x,
+        };
+
+        let depth_here = sea_level + depth as f32;
+
+        (depth_here - coord.abs()).floor() as i32
+    }
+
+    fn calculate_biome_parameters(&self, coords_f32: Vec4, s_loc: Vec4) -> u32 {
+        // Random values I made up
+        const ELEVATION_SEED: DVec3 = DVec3::new(903.0, 278.0, 510.0);
+        const HUMIDITY_SEED: DVec3 = DVec3::new(630.0, 238.0, 129.0);
+        const TEMPERATURE_SEED: DVec3 = DVec3::new(410.0, 378.0, 160.0);
+
+        let delta = 0.001;
+
+        let l = DVec3::new(
+            (s_loc.x as f64 + coords_f32.x as f64) * delta,
+            (s_loc.y as f64 + coords_f32.y as f64) * delta,
+            (s_loc.z as f64 + coords_f32.z as f64) * delta,
+        );
+
+        let noise_at = |seed: DVec3| {
+            let at = seed + l;
+            let value = self.noise(at.x, at.y, at.z);
+
+            // Clamps the value to be [0, 100.0)
+            ((value.clamp(-1.0, 0.999) * 0.5 + 0.5) * 100.0) as u32
+        };
+
+        let temperature = noise_at(TEMPERATURE_SEED);
+        let humidity = noise_at(HUMIDITY_SEED);
+        let elevation = noise_at(ELEVATION_SEED);
+
+        temperature << 16 | humidity << 8 | elevation
+    }
+
+    #[inline]
+    fn perm(&self, i: u32) -> u32 {
+        self.permutation_table[i as usize]
+    }
+
+    fn noise(&self, x: f64, y: f64, z: f64) -> f64 {
+        let input = DVec3::new(x, y, z);
+        let stretch = input + (-STRETCH_POINT) * (input.x + input.y + input.z);
+        let grid = stretch.floor();
+
+        let squashed = grid + SQUISH_POINT * (grid.x + grid.y + grid.z);
+        let ins = stretch - grid;
+        let origin = input - squashed;
+
+        self.get_value(grid, origin, ins)
+    }
+
+    fn get_grad_table_index(&self, grid: DVec3) -> usize {
+        // `as u32` saturates negative numbers to 0, which is the same thing the shader's `u32()` does.
+        let index0 = self.perm(grid.x as u32 & 0xFF).wrapping_add(grid.y as u32) & 0xFF;
+        let index1 = self.perm(index0).wrapping_add(grid.z as u32) & 0xFF;
+
+        (self.perm(index1) % GRAD_TABLE.len() as u32) as usize
+    }
+
+    fn extrapolate(&self, grid: DVec3, delta: DVec3) -> f64 {
+        let point = GRAD_TABLE[self.get_grad_table_index(grid)];
+
+        point.x as f64 * delta.x + point.y as f64 * delta.y + point.z as f64 * delta.z
+    }
+
+    fn contribute(&self, delta: DVec3, origin: DVec3, grid: DVec3) -> f64 {
+        let shifted = origin - delta - SQUISH_POINT * (delta.x + delta.y + delta.z);
+        let attn = 2.0 - (shifted.x * shifted.x + shifted.y * shifted.y + shifted.z * shifted.z);
+
+        if attn > 0.0 {
+            (attn * attn * attn * attn) * self.extrapolate(grid + delta, shifted)
+        } else {
+            0.0
+        }
+    }
+
+    fn get_value(&self, grid: DVec3, origin: DVec3, ins: DVec3) -> f64 {
+        // Sum those together to get a value that determines the region.
+        let in_sum = ins.x + ins.y + ins.z;
+
+        let value = if in_sum <= 1.0 {
+            // Inside the tetrahedron (3-Simplex) at (0, 0, 0)
+            self.inside_tetrahedron_at_0_0_0(ins, in_sum, origin, grid)
+        } else if in_sum >= 2.0 {
+            // Inside the tetrahedron (3-Simplex) at (1, 1, 1)
+            self.inside_tetrahedron_at_1_1_1(ins, in_sum, origin, grid)
+        } else {
+            // Inside the octahedron (Rectified 3-Simplex) in between.
+            self.inside_octahedron_in_between(ins, origin, grid)
+        };
+
+        value / NORMALIZING_SCALAR
+    }
+
+    fn inside_tetrahedron_at_0_0_0(&self, ins: DVec3, in_sum: f64, origin: DVec3, grid: DVec3) -> f64 {
+        // Determine which two of (0, 0, 1), (0, 1, 0), (1, 0, 0) are closest.
+        let (score, point) = determine_closest_point(DVec2::new(ins.x, ins.y), IVec2::new(1, 2), IVec2::new(4, 4), ins);
+
+        // Now we determine the two lattice points not part of the tetrahedron that may contribute.
+        // This depends on the closest two tetrahedral vertices, including (0, 0, 0)
+        let value = self.determine_lattice_points_including_0_0_0(in_sum, score, point, origin, grid);
+
+        value
+            + self.contribute(DVec3::new(0.0, 0.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(1.0, 0.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 1.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 0.0, 1.0), origin, grid)
+    }
+
+    fn determine_lattice_points_including_0_0_0(&self, in_sum: f64, score: DVec2, point: IVec2, origin: DVec3, grid: DVec3) -> f64 {
+        let wins = 1.0 - in_sum;
+
+        if wins > score.x || wins > score.y {
+            // (0, 0, 0) is one of the closest two tetrahedral vertices.
+            // Our other closest vertex is the closest out of a and b.
+            let closest = if score.y > score.x { point.y } else { point.x };
+
+            match closest {
+                1 => self.contribute(DVec3::new(1.0, -1.0, 0.0), origin, grid) + self.contribute(DVec3::new(1.0, 0.0, -1.0), origin, grid),
+                2 => self.contribute(DVec3::new(-1.0, 1.0, 0.0), origin, grid) + self.contribute(DVec3::new(0.0, 1.0, -1.0), origin, grid),
+                // closest == 4
+                _ => self.contribute(DVec3::new(-1.0, 0.0, 1.0), origin, grid) + self.contribute(DVec3::new(0.0, -1.0, 1.0), origin, grid),
+            }
+        } else {
+            // (0, 0, 0) is not one of the closest two tetrahedral vertices.
+            // Our two extra vertices are determined by the closest two.
+            let closest = point.x | point.y;
+
+            match closest {
+                3 => self.contribute(DVec3::new(1.0, 1.0, 0.0), origin, grid) + self.contribute(DVec3::new(1.0, 1.0, -1.0), origin, grid),
+                5 => self.contribute(DVec3::new(1.0, 0.0, 1.0), origin, grid) + self.contribute(DVec3::new(1.0, -1.0, 1.0), origin, grid),
+                // closest == 6
+                _ => self.contribute(DVec3::new(0.0, 1.0, 1.0), origin, grid) + self.contribute(DVec3::new(-1.0, 1.0, 1.0), origin, grid),
+            }
+        }
+    }
+
+    fn inside_tetrahedron_at_1_1_1(&self, ins: DVec3, in_sum: f64, origin: DVec3, grid: DVec3) -> f64 {
+        // Determine which two tetrahedral vertices are the closest, out of (1, 1, 0), (1, 0, 1), (0, 1, 1) but not (1, 1, 1).
+        let (score, point) = determine_closest_point(DVec2::new(ins.x, ins.y), IVec2::new(6, 5), IVec2::new(3, 3), ins);
+
+        // Now we determine the two lattice points not part of the tetrahedron that may contribute.
+        // This depends on the closest two tetrahedral vertices, including (1, 1, 1)
+        let value = self.determine_lattice_points_including_1_1_1(in_sum, score, point, origin, grid);
+
+        value
+            + self.contribute(DVec3::new(1.0, 1.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(1.0, 0.0, 1.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 1.0, 1.0), origin, grid)
+            + self.contribute(DVec3::new(1.0, 1.0, 1.0), origin, grid)
+    }
+
+    fn determine_lattice_points_including_1_1_1(&self, in_sum: f64, score: DVec2, point: IVec2, origin: DVec3, grid: DVec3) -> f64 {
+        let wins = 3.0 - in_sum;
+
+        if wins < score.x || wins < score.y {
+            // (1, 1, 1) is one of the closest two tetrahedral vertices.
+            // Our other closest vertex is the closest out of a and b.
+            let closest = if score.y < score.x { point.y } else { point.x };
+
+            match closest {
+                3 => self.contribute(DVec3::new(2.0, 1.0, 0.0), origin, grid) + self.contribute(DVec3::new(1.0, 2.0, 0.0), origin, grid),
+                5 => self.contribute(DVec3::new(2.0, 0.0, 1.0), origin, grid) + self.contribute(DVec3::new(1.0, 0.0, 2.0), origin, grid),
+                // closest == 6
+                _ => self.contribute(DVec3::new(0.0, 2.0, 1.0), origin, grid) + self.contribute(DVec3::new(0.0, 1.0, 2.0), origin, grid),
+            }
+        } else {
+            // (1, 1, 1) is not one of the closest two tetrahedral vertices.
+            // Our two extra vertices are determined by the closest two.
+            let closest = point.x & point.y;
+
+            match closest {
+                1 => self.contribute(DVec3::new(1.0, 0.0, 0.0), origin, grid) + self.contribute(DVec3::new(2.0, 0.0, 0.0), origin, grid),
+                2 => self.contribute(DVec3::new(0.0, 1.0, 0.0), origin, grid) + self.contribute(DVec3::new(0.0, 2.0, 0.0), origin, grid),
+                // closest == 4
+                _ => self.contribute(DVec3::new(0.0, 0.0, 1.0), origin, grid) + self.contribute(DVec3::new(0.0, 0.0, 2.0), origin, grid),
+            }
+        }
+    }
+
+    fn inside_octahedron_in_between(&self, ins: DVec3, origin: DVec3, grid: DVec3) -> f64 {
+        let (is_further_side, point) = determine_further_side(ins);
+
+        // Where each of the two closest points are determines how the extra two vertices are calculated.
+        let value = if is_further_side.0 == is_further_side.1 {
+            if is_further_side.0 {
+                // Both closest points on (1, 1, 1) side
+                // One of the two extra points is (1, 1, 1)
+                // Other extra point is based on the shared axis.
+                let closest = point.x & point.y;
+
+                let cont = self.contribute(DVec3::new(1.0, 1.0, 1.0), origin, grid);
+
+                match closest {
+                    1 => cont + self.contribute(DVec3::new(2.0, 0.0, 0.0), origin, grid),
+                    2 => cont + self.contribute(DVec3::new(0.0, 2.0, 0.0), origin, grid),
+                    // closest == 4
+                    _ => cont + self.contribute(DVec3::new(0.0, 0.0, 2.0), origin, grid),
+                }
+            } else {
+                // Both closest points on (0, 0, 0) side
+                // One of the two extra points is (0, 0, 0)
+                // Other extra point is based on the omitted axis.
+                let closest = point.x | point.y;
+
+                let cont = self.contribute(DVec3::new(0.0, 0.0, 0.0), origin, grid);
+
+                match closest {
+                    3 => cont + self.contribute(DVec3::new(1.0, 1.0, -1.0), origin, grid),
+                    4 => cont + self.contribute(DVec3::new(1.0, -1.0, 1.0), origin, grid),
+                    // closest == 6
+                    _ => cont + self.contribute(DVec3::new(-1.0, 1.0, 1.0), origin, grid),
+                }
+            }
+        } else {
+            // One point on (0, 0, 0) side, one point on (1, 1, 1) side
+            let (c1, c2) = if is_further_side.0 {
+                (point.x, point.y)
+            } else {
+                (point.y, point.x)
+            };
+
+            // One contribution is a permutation of (1, 1, -1)
+            // One contribution is a permutation of (0, 0, 2)
+            let res = match c1 {
+                3 => self.contribute(DVec3::new(1.0, 1.0, -1.0), origin, grid),
+                5 => self.contribute(DVec3::new(1.0, -1.0, 1.0), origin, grid),
+                // c1 == 6
+                _ => self.contribute(DVec3::new(-1.0, 1.0, 1.0), origin, grid),
+            };
+
+            match c2 {
+                1 => res + self.contribute(DVec3::new(2.0, 0.0, 0.0), origin, grid),
+                2 => res + self.contribute(DVec3::new(0.0, 2.0, 0.0), origin, grid),
+                // c2 == 4
+                _ => res + self.contribute(DVec3::new(0.0, 0.0, 2.0), origin, grid),
+            }
+        };
+
+        value
+            + self.contribute(DVec3::new(1.0, 0.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 1.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 0.0, 1.0), origin, grid)
+            + self.contribute(DVec3::new(1.0, 1.0, 0.0), origin, grid)
+            + self.contribute(DVec3::new(1.0, 0.0, 1.0), origin, grid)
+            + self.contribute(DVec3::new(0.0, 1.0, 1.0), origin, grid)
+    }
+}
+
+fn determine_closest_point(score: DVec2, point: IVec2, factor: IVec2, ins: DVec3) -> (DVec2, IVec2) {
+    let mut score = score;
+    let mut point = point;
+
+    if ins.x >= ins.y && ins.z > ins.y {
+        score.y = ins.z;
+        point.y = factor.y;
+    } else if ins.x < ins.y && ins.z > ins.x {
+        score.x = ins.z;
+        point.x = factor.x;
+    }
+
+    (score, point)
+}
+
+/// Returns (score, point, is_further_side)
+fn decide_between_points_inner(p: f64, point_val: IVec2) -> (f64, i32, bool) {
+    if p > 1.0 {
+        (p - 1.0, point_val.x, true)
+    } else {
+        (1.0 - p, point_val.y, false)
+    }
+}
+
+fn determine_further_side(ins: DVec3) -> ((bool, bool), IVec2) {
+    // Decide between point (0, 0, 1) and (1, 1, 0) as closest
+    let (score_x, point_x, is_further_side_x) = decide_between_points_inner(ins.x + ins.y, IVec2::new(3, 4));
+    // Decide between point (0, 1, 0) and (1, 0, 1) as closest
+    let (score_y, point_y, is_further_side_y) = decide_between_points_inner(ins.x + ins.z, IVec2::new(5, 2));
+
+    let score = DVec2::new(score_x, score_y);
+    let mut point = IVec2::new(point_x, point_y);
+    let mut is_further_side = (is_further_side_x, is_further_side_y);
+
+    // The closest out of the two (1, 0, 0) and (0, 1, 1) will replace
+    // the furthest out of the two decided above, if closer.
+    let p = ins.y + ins.z;
+    if p > 1.0 {
+        let score_value = p - 1.0;
+        if score.x <= score.y && score.x < score_value {
+            point.x = 6;
+            is_further_side.0 = true;
+        } else if score.x > score.y && score.y < score_value {
+            point.y = 6;
+            is_further_side.1 = true;
+        }
+    } else {
+        let score_value = 1.0 - p;
+        if score.x <= score.y && score.x < score_value {
+            point.x = 1;
+            is_further_side.0 = false;
+        } else if score.x > score.y && score.y < score_value {
+            point.y = 1;
+            is_further_side.1 = false;
+        }
+    }
+
+    (is_further_side, point)
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec4;
+
+    use crate::{
+        structure::{
+            chunk::{CHUNK_DIMENSIONSF, CHUNK_DIMENSIONS_USIZE},
+            planet::generation::{
+                terrain_generation::{GenerationParams, GpuPermutationTable, U32Vec4},
+                terrain_golden::{deserialize_golden, golden_params, GOLDEN_SEED},
+            },
+        },
+        utils::array_utils::expand_4,
+    };
+
+    use super::CpuTerrainGenerator;
+
+    const SEED: u64 = 0x00c0_5305;
+
+    fn generator() -> CpuTerrainGenerator {
+        CpuTerrainGenerator::new(&GpuPermutationTable::from_seed(SEED))
+    }
+
+    fn params(chunk_coords: Vec4, sea_level: f32) -> GenerationParams {
+        GenerationParams {
+            chunk_coords,
+            structure_pos: Vec4::ZERO,
+            sea_level: Vec4::splat(sea_level),
+            scale: Vec4::splat(1.0),
+            biosphere_id: U32Vec4::splat(0),
+        }
+    }
+
+    #[test]
+    fn permutation_table_is_a_permutation() {
+        let table = GpuPermutationTable::from_seed(SEED);
+
+        let mut values = table.0.iter().flat_map(|v| [v.x, v.y, v.z, v.w]).collect::<Vec<u32>>();
+        values.sort();
+
+        assert_eq!(values, (0..GpuPermutationTable::TALBE_SIZE as u32).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn core_is_solid_and_space_is_empty() {
+        let generator = generator();
+
+        // The noise can never move the surface by more than a few hundred blocks
+        let core = generator.generate_chunk(&params(Vec4::splat(-CHUNK_DIMENSIONSF / 2.0), 5000.0));
+        assert!(core.iter().all(|x| x.depth > 0));
+
+        let space = generator.generate_chunk(&params(Vec4::new(0.0, 20000.0, 0.0, 0.0), 5000.0));
+        assert!(space.iter().all(|x| x.depth < 0));
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let params = params(Vec4::new(-16.0, 4980.0, -16.0, 0.0), 5000.0);
+
+        let a = generator().generate_chunk(&params);
+        let b = generator().generate_chunk(&params);
+
+        assert_eq!(a.len(), b.len());
+        assert!(a.iter().zip(b.iter()).all(|(a, b)| a.depth == b.depth && a.data == b.data));
+    }
+
+    #[test]
+    fn matches_gpu_golden_terrain() {
+        let generator = CpuTerrainGenerator::new(&GpuPermutationTable::from_seed(GOLDEN_SEED));
+        let chunks = golden_params(0);
+
+        let golden = deserialize_golden(include_bytes!("terrain_golden.bin")).expect("Invalid golden terrain file");
+
+        // Generated the same way the server does when it has no GPU
+        let values = chunks
+            .iter()
+            .flat_map(|params| generator.generate_chunk(params))
+            .collect::<Vec<_>>();
+
+        assert_eq!(values.len(), golden.len());
+
+        for (idx, (value, expected)) in values.iter().zip(golden.iter()).enumerate() {
+            let (x, y, z, w) = expand_4(idx, CHUNK_DIMENSIONS_USIZE, CHUNK_DIMENSIONS_USIZE, CHUNK_DIMENSIONS_USIZE);
+
+            assert_eq!(
+                (value.depth, value.data),
+                (expected.depth, expected.data),
+                "Block {x} {y} {z} of chunk {w}"
+            );
+        }
+
+        // Every one of these chunks is on the surface, so each should have a top layer
+        for chunk in values.chunks_exact(CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE) {
+            assert!(chunk.iter().any(|x| x.depth == 0));
+            assert!(chunk.iter().any(|x| x.depth < 0));
+        }
+    }
+}
@@ -4,7 +4,9 @@ use bevy::prelude::App;
 
 pub mod biome;
 pub mod block_layers;
+pub mod cpu_terrain_generation;
 pub mod terrain_generation;
+pub mod terrain_golden;
 
 pub(super) fn register(app: &mut App) {
     biome::register(app);
@@ -134,37 +134,38 @@ impl U32Vec4 {
     }
 }
 
+/// The gradients used by the terrain generation noise
+pub const GRAD_TABLE: [Vec3; 24] = [
+    Vec3::new(-11.0, 4.0, 4.0),
+    Vec3::new(-4.0, 11.0, 4.0),
+    Vec3::new(-4.0, 4.0, 11.0),
+    Vec3::new(11.0, 4.0, 4.0),
+    Vec3::new(4.0, 11.0, 4.0),
+    Vec3::new(4.0, 4.0, 11.0),
+    Vec3::new(-11.0, -4.0, 4.0),
+    Vec3::new(-4.0, -11.0, 4.0),
+    Vec3::new(-4.0, -4.0, 11.0),
+    Vec3::new(11.0, -4.0, 4.0),
+    Vec3::new(4.0, -11.0, 4.0),
+    Vec3::new(4.0, -4.0, 11.0),
+    Vec3::new(-11.0, 4.0, -4.0),
+    Vec3::new(-4.0, 11.0, -4.0),
+    Vec3::new(-4.0, 4.0, -11.0),
+    Vec3::new(11.0, 4.0, -4.0),
+    Vec3::new(4.0, 11.0, -4.0),
+    Vec3::new(4.0, 4.0, -11.0),
+    Vec3::new(-11.0, -4.0, -4.0),
+    Vec3::new(-4.0, -11.0, -4.0),
+    Vec3::new(-4.0, -4.0, -11.0),
+    Vec3::new(11.0, -4.0, -4.0),
+    Vec3::new(4.0, -11.0, -4.0),
+    Vec3::new(4.0, -4.0, -11.0),
+];
+
 impl ComputeWorker for BiosphereShaderWorker {
     fn build(world: &mut bevy::prelude::World) -> AppComputeWorker<Self> {
         assert!(DIMS as u32 % WORKGROUP_SIZE == 0);
 
-        const GRAD_TABLE: [Vec3; 24] = [
-            Vec3::new(-11.0, 4.0, 4.0),
-            Vec3::new(-4.0, 11.0, 4.0),
-            Vec3::new(-4.0, 4.0, 11.0),
-            Vec3::new(11.0, 4.0, 4.0),
-            Vec3::new(4.0, 11.0, 4.0),
-            Vec3::new(4.0, 4.0, 11.0),
-            Vec3::new(-11.0, -4.0, 4.0),
-            Vec3::new(-4.0, -11.0, 4.0),
-            Vec3::new(-4.0, -4.0, 11.0),
-            Vec3::new(11.0, -4.0, 4.0),
-            Vec3::new(4.0, -11.0, 4.0),
-            Vec3::new(4.0, -4.0, 11.0),
-            Vec3::new(-11.0, 4.0, -4.0),
-            Vec3::new(-4.0, 11.0, -4.0),
-            Vec3::new(-4.0, 4.0, -11.0),
-            Vec3::new(11.0, 4.0, -4.0),
-            Vec3::new(4.0, 11.0, -4.0),
-            Vec3::new(4.0, 4.0, -11.0),
-            Vec3::new(-11.0, -4.0, -4.0),
-            Vec3::new(-4.0, -11.0, -4.0),
-            Vec3::new(-4.0, -4.0, -11.0),
-            Vec3::new(11.0, -4.0, -4.0),
-            Vec3::new(4.0, -11.0, -4.0),
-            Vec3::new(4.0, -4.0, -11.0),
-        ];
-
         let worker = AppComputeWorkerBuilder::new(world)
             .one_shot()
             .add_empty_uniform(
@@ -198,4 +199,30 @@ impl GpuPermutationTable {
     /// Note the actual vector will be 1/4 this size because it stores
     /// the u32s in pairs of 4.
     pub const TALBE_SIZE: usize = 2048;
+
+    /// Generates the permutation table for this seed.
+    ///
+    /// This is used by both the GPU & CPU terrain generation, so they generate the same terrain for the same seed.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut perm = [0; Self::TALBE_SIZE];
+
+        let mut source: Vec<i64> = (0..Self::TALBE_SIZE).map(|x| x as i64).collect();
+
+        let seed: i128 = (seed as i128 * 6_364_136_223_846_793_005) + 1_442_695_040_888_963_407;
+        for i in (0..Self::TALBE_SIZE).rev() {
+            let mut r = ((seed + 31) % (i as i128 + 1)) as i64;
+            if r < 0 {
+                r += (i + 1) as i64;
+            }
+            perm[i] = source[r as usize];
+            source[r as usize] = source[i];
+        }
+
+        Self(
+            perm.chunks_exact(4)
+                // Unfortunately must truncate the i64 to u32 to play nice with the gpu
+                .map(|c| U32Vec4::new(c[0] as u32, c[1] as u32, c[2] as u32, c[3] as u32))
+                .collect(),
+        )
+    }
 }
@@ -0,0 +1,48 @@
+//! Terrain generated by the biosphere shaders for a fixed seed, used to make sure the
+//! [`CpuTerrainGenerator`](super::cpu_terrain_generation::CpuTerrainGenerator) generates the same terrain as the GPU.
+//!
+//! Run the server with `--capture-terrain-golden <file>` on a machine with a GPU to capture these again
+//! whenever the shaders change.
+
+use bevy::math::Vec4;
+
+use crate::netty::cosmos_encoder;
+
+use super::terrain_generation::{GenerationParams, TerrainData, U32Vec4};
+
+/// The seed the golden terrain is generated with
+pub const GOLDEN_SEED: u64 = 0x00c0_5305;
+/// The sea level of the planet the golden terrain is a part of
+const GOLDEN_SEA_LEVEL: f32 = 5000.0;
+
+/// The chunks that make up the golden terrain - a few on the surface of different faces, plus one at a lower level of detail.
+///
+/// Every biosphere generates its terrain the same way, so any biosphere can be used to capture these.
+pub fn golden_params(biosphere_id: u32) -> Vec<GenerationParams> {
+    let params = |chunk_coords: Vec4, scale: f32| GenerationParams {
+        chunk_coords,
+        structure_pos: Vec4::ZERO,
+        sea_level: Vec4::splat(GOLDEN_SEA_LEVEL),
+        scale: Vec4::splat(scale),
+        biosphere_id: U32Vec4::splat(biosphere_id),
+    };
+
+    vec![
+        params(Vec4::new(-16.0, 4980.0, -16.0, 0.0), 1.0),
+        params(Vec4::new(-5010.0, -16.0, 100.0, 0.0), 1.0),
+        params(Vec4::new(200.0, -300.0, -5010.0, 0.0), 1.0),
+        params(Vec4::new(-64.0, 4940.0, -64.0, 0.0), 4.0),
+    ]
+}
+
+/// Converts the terrain data into the format the golden terrain is stored as
+pub fn serialize_golden(values: &[TerrainData]) -> Vec<u8> {
+    cosmos_encoder::serialize(&values.iter().map(|x| (x.depth, x.data)).collect::<Vec<(i32, u32)>>())
+}
+
+/// Reads terrain data stored with [`serialize_golden`]
+pub fn deserialize_golden(raw: &[u8]) -> Option<Vec<TerrainData>> {
+    let values = cosmos_encoder::deserialize::<Vec<(i32, u32)>>(raw).ok()?;
+
+    Some(values.into_iter().map(|(depth, data)| TerrainData { depth, data }).collect())
+}
@@ -13,7 +13,7 @@ use crate::{block::BlockFace, physics::location::SYSTEM_SECTORS};
 
 use super::{
     chunk::CHUNK_DIMENSIONS,
-    coordinates::{BlockCoordinate, CoordinateType},
+    coordinates::{BlockCoordinate, CoordinateType, UnboundCoordinateType},
     dynamic_structure::DynamicStructure,
     Structure,
 };
@@ -22,6 +22,17 @@ pub mod biosphere;
 pub mod generation;
 pub mod planet_builder;
 
+#[cfg(debug_assertions)]
+/// How many chunks away from the player a client loads planet chunks
+pub const CHUNK_RENDER_DISTANCE: UnboundCoordinateType = 2;
+#[cfg(not(debug_assertions))]
+/// How many chunks away from the player a client loads planet chunks
+pub const CHUNK_RENDER_DISTANCE: UnboundCoordinateType = 4;
+/// The furthest away from the player any client loads planet chunks, whether it's a debug or release build.
+///
+/// The server doesn't know how the client was built, so it uses this when deciding if a client has a chunk loaded.
+pub const MAX_CHUNK_RENDER_DISTANCE: UnboundCoordinateType = 4;
+
 #[derive(Component, Debug, Reflect, Serialize, Deserialize, Clone, Copy)]
 /// If a structure has this, it is a planet.
 pub struct Planet {
@@ -3,11 +3,14 @@
 use std::fmt::Display;
 
 use bevy::{
-    prelude::{App, Component, Query, Update, Vec3, Without},
+    prelude::{App, Component, Query, Transform, Update, Vec3, Without},
     reflect::Reflect,
 };
+use bevy_rapier3d::prelude::{MassProperties, Velocity};
 use serde::{Deserialize, Serialize};
 
+use crate::structure::systems::thruster_system::{ThrusterOutput, ThrusterSystem};
+
 use super::pilot::Pilot;
 
 #[derive(Component, Default, Serialize, Deserialize, Debug, Clone, Copy, Reflect)]
@@ -21,11 +24,87 @@ pub struct ShipMovement {
     pub torque: Vec3,
 }
 
+/// The fastest a ship can move in blocks per second
+pub const MAX_SHIP_SPEED: f32 = 200.0;
+/// The fastest a ship can be told to rotate in radians per second
+const MAX_ANGULAR_SPEED: f32 = 4.0;
+const MAX_BRAKE_DELTA_PER_THRUST: f32 = 300.0;
+/// How much angular impulse each unit of thruster torque can give per second
+const MAX_TORQUE_DELTA_PER_TORQUE: f32 = 60.0;
+
+/// Keeps this ship's velocity under the max speed
+pub fn limit_speed(velocity: &mut Velocity) {
+    velocity.linvel = velocity.linvel.clamp_length(0.0, MAX_SHIP_SPEED);
+}
+
 impl ShipMovement {
     /// Normalizes the movement vector
     pub fn into_normal_vector(&self) -> Vec3 {
         self.movement.normalize_or_zero()
     }
+
+    /// Calculates the angular impulse the thrusters apply to turn the ship this frame.
+    ///
+    /// The torque is treated as the angular velocity the pilot wants, so thrusters push towards that (or against any spin
+    /// if no rotation is wanted) as hard as their placement around the center of mass lets them.
+    ///
+    /// This is shared between the server & the client predicting the ship it's piloting, so both move the ship the same way.
+    ///
+    /// * `output` How hard the thrusters can turn the ship. This is lower if the thrusters' power networks are low on energy.
+    pub fn torque_impulse(
+        &self,
+        transform: &Transform,
+        angvel: Vec3,
+        mass_properties: &MassProperties,
+        output: &ThrusterOutput,
+        delta_seconds: f32,
+    ) -> Vec3 {
+        let target = (self.torque * 5.0).clamp_length_max(MAX_ANGULAR_SPEED);
+        let local_angvel = transform.rotation.inverse() * angvel;
+
+        // The angular momentum needed to reach the target, in the ship's local coordinates
+        let inertia_frame = mass_properties.principal_inertia_local_frame;
+        let needed = inertia_frame * (mass_properties.principal_inertia * (inertia_frame.inverse() * (target - local_angvel)));
+
+        // Scaled by the frame's length so ships turn at the same rate no matter the frame rate
+        let available = output.torque_along(needed) * delta_seconds * MAX_TORQUE_DELTA_PER_TORQUE;
+
+        transform.rotation * needed.clamp(-available, available)
+    }
+
+    /// Calculates the impulse that moves the ship this frame.
+    ///
+    /// Thrusters can only push the ship away from the direction they face, so the ship can't move in directions it has
+    /// no thrusters for. Braking doesn't need power, so a ship that has run out of energy can still stop.
+    ///
+    /// * `output` How hard the thrusters can push the ship. This is lower if the thrusters' power networks are low on energy.
+    pub fn thrust_impulse(
+        &self,
+        transform: &Transform,
+        linvel: Vec3,
+        mass: f32,
+        thrusters: &ThrusterSystem,
+        output: &ThrusterOutput,
+        delta_seconds: f32,
+    ) -> Vec3 {
+        let normal = self.into_normal_vector();
+
+        // Forward is -Z
+        let local_direction = Vec3::new(normal.x, normal.y, -normal.z);
+
+        let mut local_impulse = local_direction * output.thrust_along(local_direction);
+
+        if self.braking {
+            let local_linvel = transform.rotation.inverse() * linvel;
+
+            let brake = -local_linvel * mass;
+            let delta = thrusters.thrust_along(brake) * delta_seconds * MAX_BRAKE_DELTA_PER_THRUST;
+
+            local_impulse += brake.clamp(-delta, delta);
+        }
+
+        transform.rotation * local_impulse
+    }
 }
 
 impl Display for ShipMovement {
@@ -7,10 +7,14 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{block::Block, registry::identifiable::Identifiable};
+use crate::{block::Block, registry::identifiable::Identifiable, structure::coordinates::BlockCoordinate};
+
+use self::power_grid::{PowerBlock, PowerGrid, PowerNetworkId};
 
 use super::{sync::SyncableSystem, StructureSystemImpl};
 
+pub mod power_grid;
+
 #[derive(Default, Reflect, Clone, Copy)]
 /// Every block that can store energy should have this property
 pub struct EnergyStorageProperty {
@@ -37,10 +41,15 @@ impl EnergyStorageBlocks {
 }
 
 #[derive(Component, Default, Reflect, Serialize, Deserialize, Debug)]
-/// Represents the energy storage of a structure
+/// Represents the energy storage of a structure.
+///
+/// Energy is stored per power network (see [`PowerGrid`]), but the totals are what get sent to clients.
 pub struct EnergyStorageSystem {
     energy: f32,
     capacity: f32,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    grid: PowerGrid,
 }
 
 impl SyncableSystem for EnergyStorageSystem {}
@@ -52,38 +61,110 @@ impl StructureSystemImpl for EnergyStorageSystem {
 }
 
 impl EnergyStorageSystem {
-    /// Call this whenever a block is added to the system
-    pub fn block_added(&mut self, prop: &EnergyStorageProperty) {
-        self.capacity += prop.capacity;
+    /// Call this whenever a block that is part of the power grid is added to the structure
+    pub fn block_added(&mut self, coords: BlockCoordinate, block: PowerBlock) {
+        self.grid.add_block(coords, block);
+    }
+
+    /// Call this whenever a block that is part of the power grid is removed from the structure
+    pub fn block_removed(&mut self, coords: BlockCoordinate) {
+        self.grid.remove_block(coords);
     }
 
-    /// Call this whenever a block is removed from the system
-    pub fn block_removed(&mut self, prop: &EnergyStorageProperty) {
-        self.capacity -= prop.capacity;
+    /// Recalculates the power networks if any blocks were added or removed since they were last calculated
+    pub fn recalculate_networks(&mut self) {
+        if self.grid.needs_recalculated() {
+            self.grid.recalculate_networks();
+            self.update_totals();
+        }
+    }
+
+    fn update_totals(&mut self) {
+        self.energy = self.grid.total_energy();
+        self.capacity = self.grid.total_capacity();
     }
 
-    /// Increases the energy stored in this system
-    pub fn increase_energy(&mut self, delta: f32) {
-        self.energy = self.capacity.min(self.energy + delta);
+    /// Every power network generates its energy for this amount of time
+    pub fn generate(&mut self, delta_seconds: f32) {
+        self.grid.generate(delta_seconds);
+        self.update_totals();
     }
 
-    /// Decreases the energy stored in this system - does not go below 0.
+    /// Increases the energy stored in the network this block is on
+    pub fn increase_energy_at(&mut self, coords: BlockCoordinate, delta: f32) {
+        if let Some(network) = self.grid.network_at(coords) {
+            self.grid.increase_energy(network, delta);
+            self.update_totals();
+        }
+    }
+
+    /// Decreases the energy stored in the network this block is on - does not go below 0.
     ///
-    /// You can use `get_energy` to see if there is enough to use.
+    /// You can use `energy_at` to see if there is enough to use.
     ///
     /// Returns 0.0 if there is enough power to perform this operation, however much power was not able to be taken if not.
-    pub fn decrease_energy(&mut self, delta: f32) -> f32 {
-        let amount_left = self.energy - delta;
-        self.energy = amount_left.max(0.0);
-
-        if amount_left < 0.0 {
-            -amount_left
-        } else {
-            0.0
-        }
+    /// Blocks that aren't connected to any network can't take any power.
+    pub fn decrease_energy_at(&mut self, coords: BlockCoordinate, delta: f32) -> f32 {
+        let Some(network) = self.grid.network_at(coords) else {
+            return delta;
+        };
+
+        let not_used = self.grid.decrease_energy(network, delta);
+        self.update_totals();
+
+        not_used
+    }
+
+    /// Takes this energy from every network with blocks that `is_consumer` returns true for, split by how many of those
+    /// blocks each network has. Useful for systems like thrusters that act as a whole.
+    ///
+    /// Returns 0.0 if there is enough power to perform this operation, however much power was not able to be taken if not.
+    pub fn decrease_energy_spread(&mut self, is_consumer: impl Fn(u16) -> bool, delta: f32) -> f32 {
+        let not_used = self.grid.decrease_energy_spread(is_consumer, delta);
+        self.update_totals();
+
+        not_used
+    }
+
+    /// Takes energy the same way as [`Self::decrease_energy_spread`], but returns how much of its share each network was
+    /// able to give [0.0, 1.0], so consumers on networks without enough power can do less.
+    ///
+    /// Use [`Self::network_at`] to find which network a consumer is on. Consumers that aren't on any network get no power.
+    pub fn decrease_energy_spread_per_network(
+        &mut self,
+        is_consumer: impl Fn(u16) -> bool,
+        delta: f32,
+    ) -> std::collections::HashMap<PowerNetworkId, f32> {
+        let powered = self.grid.decrease_energy_spread_per_network(is_consumer, delta);
+        self.update_totals();
+
+        powered
+    }
+
+    /// Returns the power network the block here is on, if it is part of one
+    pub fn network_at(&self, coords: BlockCoordinate) -> Option<PowerNetworkId> {
+        self.grid.network_at(coords)
+    }
+
+    /// Gets the energy stored in the network this block is on
+    pub fn energy_at(&self, coords: BlockCoordinate) -> f32 {
+        self.grid.network_at(coords).map(|network| self.grid.energy(network)).unwrap_or(0.0)
+    }
+
+    /// How much energy each storage block holds. The power grid isn't saved, so save this instead.
+    pub fn stored_energy(&self) -> Vec<(BlockCoordinate, f32)> {
+        self.grid.stored_energy()
+    }
+
+    /// Gives each storage block back the energy it held, once all the structure's blocks have been added.
+    ///
+    /// See [`Self::stored_energy`]
+    pub fn restore_stored_energy(&mut self, stored: &[(BlockCoordinate, f32)]) {
+        self.grid.restore_stored_energy(stored);
+        self.update_totals();
     }
 
-    /// Gets the current stored energy of the system
+    /// Gets the current stored energy of every network in the system
     pub fn get_energy(&self) -> f32 {
         self.energy
     }
@@ -0,0 +1,429 @@
+//! Splits the blocks that use power into separate networks.
+//!
+//! Every block that power cables connect to is part of the grid, and touching blocks are on the same network.
+//! Each network has its own energy, so destroying the cables linking two parts of a structure leaves each part with
+//! only the power it can generate & store itself.
+
+use std::collections::HashMap;
+
+use crate::{
+    block::{BlockFace, ALL_BLOCK_FACES},
+    structure::coordinates::BlockCoordinate,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// A block that is part of a power grid
+pub struct PowerBlock {
+    /// The id of this block
+    pub block_id: u16,
+    /// How much energy this block can store
+    pub capacity: f32,
+    /// How much energy this block generates per second
+    pub generation_rate: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Identifies a network of connected blocks in a [`PowerGrid`].
+///
+/// These are only valid until the grid's networks are next recalculated.
+pub struct PowerNetworkId(usize);
+
+#[derive(Debug, Default, Clone)]
+struct PowerNetwork {
+    energy: f32,
+    capacity: f32,
+    generation_rate: f32,
+    /// How many of each block id are on this network
+    block_counts: HashMap<u16, usize>,
+}
+
+#[derive(Debug, Default, Clone)]
+/// Every block on a structure that uses power, split into the networks they form.
+///
+/// Adding & removing blocks doesn't change the networks until [`PowerGrid::recalculate_networks`] is called.
+pub struct PowerGrid {
+    blocks: HashMap<BlockCoordinate, PowerBlock>,
+    network_of: HashMap<BlockCoordinate, usize>,
+    networks: Vec<PowerNetwork>,
+    needs_recalculated: bool,
+}
+
+fn neighbor(coords: BlockCoordinate, face: BlockFace) -> Option<BlockCoordinate> {
+    BlockCoordinate::try_from(face.direction_coordinates() + coords).ok()
+}
+
+impl PowerGrid {
+    /// Adds a block to the grid, replacing whatever was there before
+    pub fn add_block(&mut self, coords: BlockCoordinate, block: PowerBlock) {
+        self.blocks.insert(coords, block);
+        self.needs_recalculated = true;
+    }
+
+    /// Removes the block here from the grid, if there is one
+    pub fn remove_block(&mut self, coords: BlockCoordinate) {
+        if self.blocks.remove(&coords).is_some() {
+            self.needs_recalculated = true;
+        }
+    }
+
+    /// Returns true if blocks were added or removed since the networks were last calculated
+    pub fn needs_recalculated(&self) -> bool {
+        self.needs_recalculated
+    }
+
+    /// Recalculates which blocks are on which network.
+    ///
+    /// Each storage block keeps its share of the energy its old network had, so merging networks combines their
+    /// energy and splitting a network divides it up. Energy stored in removed blocks is lost.
+    pub fn recalculate_networks(&mut self) {
+        if !self.needs_recalculated {
+            return;
+        }
+
+        self.needs_recalculated = false;
+
+        let mut network_of = HashMap::with_capacity(self.blocks.len());
+        let mut networks = vec![];
+        let mut todo = vec![];
+
+        for &start in self.blocks.keys() {
+            if network_of.contains_key(&start) {
+                continue;
+            }
+
+            let network_id = networks.len();
+            let mut network = PowerNetwork::default();
+
+            network_of.insert(start, network_id);
+            todo.push(start);
+
+            while let Some(coords) = todo.pop() {
+                let block = self.blocks[&coords];
+
+                network.capacity += block.capacity;
+                network.generation_rate += block.generation_rate;
+                *network.block_counts.entry(block.block_id).or_default() += 1;
+
+                if let Some(old_network) = self.network_of.get(&coords).map(|&x| &self.networks[x]) {
+                    if old_network.capacity > 0.0 {
+                        network.energy += old_network.energy * block.capacity / old_network.capacity;
+                    }
+                }
+
+                for face in ALL_BLOCK_FACES {
+                    let Some(next) = neighbor(coords, face) else {
+                        continue;
+                    };
+
+                    if self.blocks.contains_key(&next) && !network_of.contains_key(&next) {
+                        network_of.insert(next, network_id);
+                        todo.push(next);
+                    }
+                }
+            }
+
+            network.energy = network.energy.min(network.capacity);
+            networks.push(network);
+        }
+
+        self.network_of = network_of;
+        self.networks = networks;
+    }
+
+    /// Returns the network the block here is on, if it is part of this grid
+    pub fn network_at(&self, coords: BlockCoordinate) -> Option<PowerNetworkId> {
+        self.network_of.get(&coords).map(|&x| PowerNetworkId(x))
+    }
+
+    /// Returns how much energy this network has stored
+    pub fn energy(&self, network: PowerNetworkId) -> f32 {
+        self.networks.get(network.0).map(|x| x.energy).unwrap_or(0.0)
+    }
+
+    /// Returns how much energy this network can store
+    pub fn capacity(&self, network: PowerNetworkId) -> f32 {
+        self.networks.get(network.0).map(|x| x.capacity).unwrap_or(0.0)
+    }
+
+    /// Increases the energy stored in this network, up to its capacity
+    pub fn increase_energy(&mut self, network: PowerNetworkId, delta: f32) {
+        if let Some(network) = self.networks.get_mut(network.0) {
+            network.energy = network.capacity.min(network.energy + delta);
+        }
+    }
+
+    /// Decreases the energy stored in this network - does not go below 0.
+    ///
+    /// Returns 0.0 if there is enough power to perform this operation, however much power was not able to be taken if not.
+    pub fn decrease_energy(&mut self, network: PowerNetworkId, delta: f32) -> f32 {
+        let Some(network) = self.networks.get_mut(network.0) else {
+            return delta;
+        };
+
+        let amount_left = network.energy - delta;
+        network.energy = amount_left.max(0.0);
+
+        if amount_left < 0.0 {
+            -amount_left
+        } else {
+            0.0
+        }
+    }
+
+    /// Takes this energy from every network with blocks that `is_consumer` returns true for, split by how many of those
+    /// blocks each network has.
+    ///
+    /// Returns 0.0 if there is enough power to perform this operation, however much power was not able to be taken if not.
+    pub fn decrease_energy_spread(&mut self, is_consumer: impl Fn(u16) -> bool, delta: f32) -> f32 {
+        let Some(shares) = self.consumer_shares(is_consumer, delta) else {
+            return delta;
+        };
+
+        shares
+            .into_iter()
+            .map(|(network, share)| self.decrease_energy(network, share))
+            .sum()
+    }
+
+    /// Takes energy the same way as [`Self::decrease_energy_spread`], but returns how much of its share each network was
+    /// able to give [0.0, 1.0]. Networks without any consumers aren't included.
+    pub fn decrease_energy_spread_per_network(&mut self, is_consumer: impl Fn(u16) -> bool, delta: f32) -> HashMap<PowerNetworkId, f32> {
+        let Some(shares) = self.consumer_shares(is_consumer, delta) else {
+            return HashMap::new();
+        };
+
+        shares
+            .into_iter()
+            .map(|(network, share)| {
+                let not_used = self.decrease_energy(network, share);
+
+                (network, if share > 0.0 { (share - not_used) / share } else { 1.0 })
+            })
+            .collect()
+    }
+
+    /// Splits this energy between every network with consumers, by how many consumers each has.
+    ///
+    /// Returns `None` if no network has any consumers.
+    fn consumer_shares(&self, is_consumer: impl Fn(u16) -> bool, delta: f32) -> Option<Vec<(PowerNetworkId, f32)>> {
+        let consumer_counts = self
+            .networks
+            .iter()
+            .map(|network| {
+                network
+                    .block_counts
+                    .iter()
+                    .filter(|(block_id, _)| is_consumer(**block_id))
+                    .map(|(_, count)| *count)
+                    .sum::<usize>()
+            })
+            .collect::<Vec<usize>>();
+
+        let total = consumer_counts.iter().sum::<usize>();
+
+        if total == 0 {
+            return None;
+        }
+
+        Some(
+            consumer_counts
+                .into_iter()
+                .enumerate()
+                .filter(|(_, count)| *count != 0)
+                .map(|(network, count)| (PowerNetworkId(network), delta * count as f32 / total as f32))
+                .collect(),
+        )
+    }
+
+    /// Every network generates its energy for this amount of time
+    pub fn generate(&mut self, delta_seconds: f32) {
+        for network in self.networks.iter_mut() {
+            network.energy = network.capacity.min(network.energy + network.generation_rate * delta_seconds);
+        }
+    }
+
+    /// How much energy each storage block holds, which is its share of its network's energy.
+    ///
+    /// The networks aren't saved, so this is what gets saved instead. Give this to [`Self::restore_stored_energy`]
+    /// once the grid is rebuilt to give every network its energy back.
+    pub fn stored_energy(&self) -> Vec<(BlockCoordinate, f32)> {
+        self.network_of
+            .iter()
+            .filter_map(|(coords, &network)| {
+                let network = &self.networks[network];
+                let capacity = self.blocks.get(coords)?.capacity;
+
+                (capacity > 0.0 && network.capacity > 0.0).then(|| (*coords, network.energy * capacity / network.capacity))
+            })
+            .collect()
+    }
+
+    /// Gives each block back the energy it held when [`Self::stored_energy`] was called.
+    ///
+    /// Energy stored in blocks that are no longer part of the grid is lost.
+    pub fn restore_stored_energy(&mut self, stored: &[(BlockCoordinate, f32)]) {
+        self.recalculate_networks();
+
+        for &(coords, energy) in stored {
+            if let Some(network) = self.network_at(coords) {
+                self.increase_energy(network, energy);
+            }
+        }
+    }
+
+    /// The energy stored across every network
+    pub fn total_energy(&self) -> f32 {
+        self.networks.iter().map(|x| x.energy).sum()
+    }
+
+    /// The energy that can be stored across every network
+    pub fn total_capacity(&self) -> f32 {
+        self.networks.iter().map(|x| x.capacity).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::structure::coordinates::BlockCoordinate;
+
+    use super::{PowerBlock, PowerGrid};
+
+    const CABLE: PowerBlock = PowerBlock {
+        block_id: 1,
+        capacity: 0.0,
+        generation_rate: 0.0,
+    };
+    const CELL: PowerBlock = PowerBlock {
+        block_id: 2,
+        capacity: 100.0,
+        generation_rate: 0.0,
+    };
+    const REACTOR: PowerBlock = PowerBlock {
+        block_id: 3,
+        capacity: 0.0,
+        generation_rate: 10.0,
+    };
+    const LASER: PowerBlock = PowerBlock {
+        block_id: 4,
+        capacity: 0.0,
+        generation_rate: 0.0,
+    };
+
+    fn coords(x: u64) -> BlockCoordinate {
+        BlockCoordinate::new(x, 0, 0)
+    }
+
+    /// Builds a line of blocks along the x axis, starting at x = 0
+    fn line(blocks: &[PowerBlock]) -> PowerGrid {
+        let mut grid = PowerGrid::default();
+
+        for (x, block) in blocks.iter().enumerate() {
+            grid.add_block(coords(x as u64), *block);
+        }
+
+        grid.recalculate_networks();
+
+        grid
+    }
+
+    #[test]
+    fn cables_connect_networks() {
+        let mut grid = line(&[REACTOR, CELL, CABLE, CABLE, LASER]);
+
+        let network = grid.network_at(coords(4)).unwrap();
+        assert_eq!(grid.network_at(coords(0)), Some(network));
+
+        grid.generate(1.0);
+
+        assert_eq!(grid.energy(network), 10.0);
+        assert_eq!(grid.decrease_energy(network, 15.0), 5.0);
+        assert_eq!(grid.energy(network), 0.0);
+    }
+
+    #[test]
+    fn cut_cable_isolates_network() {
+        let mut grid = line(&[REACTOR, CELL, CABLE, CABLE, LASER]);
+
+        grid.generate(1.0);
+        grid.remove_block(coords(2));
+        grid.recalculate_networks();
+
+        let laser_network = grid.network_at(coords(4)).unwrap();
+        let cell_network = grid.network_at(coords(1)).unwrap();
+
+        assert_ne!(laser_network, cell_network);
+        assert_eq!(grid.energy(laser_network), 0.0);
+        assert_eq!(grid.energy(cell_network), 10.0);
+    }
+
+    #[test]
+    fn splitting_and_merging_keeps_energy() {
+        let mut grid = line(&[REACTOR, CELL, CABLE, CELL]);
+
+        grid.generate(2.0);
+        assert_eq!(grid.total_energy(), 20.0);
+
+        grid.remove_block(coords(2));
+        grid.recalculate_networks();
+
+        assert_eq!(grid.energy(grid.network_at(coords(1)).unwrap()), 10.0);
+        assert_eq!(grid.energy(grid.network_at(coords(3)).unwrap()), 10.0);
+
+        grid.add_block(coords(2), CABLE);
+        grid.recalculate_networks();
+
+        assert_eq!(grid.energy(grid.network_at(coords(3)).unwrap()), 20.0);
+    }
+
+    #[test]
+    fn stored_energy_survives_rebuilding() {
+        let mut grid = line(&[REACTOR, CELL, CABLE, CELL, CABLE, LASER]);
+        grid.remove_block(coords(4));
+        grid.recalculate_networks();
+
+        grid.generate(3.0);
+        let laser_network = grid.network_at(coords(5)).unwrap();
+        grid.increase_energy(laser_network, 50.0);
+
+        let stored = grid.stored_energy();
+
+        // The grid isn't saved, so it is rebuilt from the structure's blocks
+        let mut rebuilt = line(&[REACTOR, CELL, CABLE, CELL]);
+        rebuilt.add_block(coords(5), LASER);
+        rebuilt.restore_stored_energy(&stored);
+
+        assert_eq!(rebuilt.energy(rebuilt.network_at(coords(1)).unwrap()), 30.0);
+        // The laser can't store anything, so its network had no energy to save
+        assert_eq!(rebuilt.energy(rebuilt.network_at(coords(5)).unwrap()), 0.0);
+        assert_eq!(rebuilt.total_energy(), 30.0);
+    }
+
+    #[test]
+    fn spread_between_networks() {
+        let mut grid = line(&[LASER, CELL, REACTOR, CABLE, LASER, CELL]);
+        grid.remove_block(coords(3));
+        grid.recalculate_networks();
+
+        grid.generate(1.0);
+
+        // Only the left network is generating power, so half of this can't be taken
+        assert_eq!(grid.decrease_energy_spread(|id| id == LASER.block_id, 10.0), 5.0);
+        assert_eq!(grid.total_energy(), 5.0);
+    }
+
+    #[test]
+    fn spread_power_per_network() {
+        let mut grid = line(&[LASER, CELL, REACTOR, CABLE, LASER, CELL]);
+        grid.remove_block(coords(3));
+        grid.recalculate_networks();
+
+        grid.generate(1.0);
+
+        let powered = grid.decrease_energy_spread_per_network(|id| id == LASER.block_id, 20.0);
+
+        // The left network had all 10 of its share, but the right network had nothing to give
+        assert_eq!(powered[&grid.network_at(coords(0)).unwrap()], 1.0);
+        assert_eq!(powered[&grid.network_at(coords(4)).unwrap()], 0.0);
+        assert_eq!(grid.total_energy(), 0.0);
+    }
+}
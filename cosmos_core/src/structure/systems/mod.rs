@@ -29,6 +29,7 @@ pub mod missile_launcher_system;
 pub mod shield_system;
 pub mod sync;
 pub mod thruster_system;
+pub mod turret_system;
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
@@ -506,4 +507,5 @@ pub(super) fn register(app: &mut App) {
     thruster_system::register(app);
     missile_launcher_system::register(app);
     dock_system::register(app);
+    turret_system::register(app);
 }
@@ -1,13 +1,18 @@
 //! Thruster block system
 
 use bevy::{
+    math::Vec3,
     prelude::{App, Component, Resource},
     reflect::Reflect,
     utils::HashMap,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{block::Block, registry::identifiable::Identifiable};
+use crate::{
+    block::{Block, BlockFace, BlockRotation},
+    registry::identifiable::Identifiable,
+    structure::coordinates::BlockCoordinate,
+};
 
 use super::{sync::SyncableSystem, StructureSystemImpl};
 
@@ -17,6 +22,10 @@ pub struct ThrusterProperty {
     pub strength: f32,
     /// How much energy this block consumes
     pub energy_consupmtion: f32,
+    /// If true, this block pushes equally in every direction & rotates the ship around every axis, regardless of where it is.
+    ///
+    /// Otherwise, it only pushes the structure away from its front face.
+    pub omnidirectional: bool,
 }
 
 #[derive(Default, Resource)]
@@ -35,13 +44,81 @@ impl ThrusterBlocks {
     pub fn get(&self, block: &Block) -> Option<&ThrusterProperty> {
         self.blocks.get(&block.id())
     }
+
+    /// Returns true if the block with this id is a thruster
+    pub fn is_thruster(&self, block_id: u16) -> bool {
+        self.blocks.contains_key(&block_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ThrusterBlock {
+    /// Relative to the structure's center
+    position: Vec3,
+    /// The direction this pushes the structure, or `None` if it pushes in every direction
+    push: Option<BlockFace>,
+    strength: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// How hard a structure's thrusters can push & turn it, given how much power each of them has
+pub struct ThrusterOutput {
+    /// Indexed by [`BlockFace::index`], the same as [`ThrusterSystem`]'s thrust
+    thrust: [f32; 6],
+    /// Indexed by [`BlockFace::index`], the same as [`ThrusterSystem`]'s torque
+    torque: [f32; 6],
+}
+
+impl ThrusterOutput {
+    /// The amount of force that can push the ship towards this side of it
+    pub fn thrust_towards(&self, face: BlockFace) -> f32 {
+        // Adding & removing thrusters can leave tiny negative values from floating point error
+        self.thrust[face.index()].max(0.0)
+    }
+
+    /// The thrust that can push the ship along each of its local axes.
+    ///
+    /// `direction`'s components pick which side of each axis (positive or negative) is used.
+    pub fn thrust_along(&self, direction: Vec3) -> Vec3 {
+        Vec3::new(
+            self.thrust_towards(if direction.x >= 0.0 { BlockFace::Right } else { BlockFace::Left }),
+            self.thrust_towards(if direction.y >= 0.0 { BlockFace::Top } else { BlockFace::Bottom }),
+            self.thrust_towards(if direction.z >= 0.0 { BlockFace::Front } else { BlockFace::Back }),
+        )
+    }
+
+    /// The torque that can rotate the ship around each of its local axes.
+    ///
+    /// `direction`'s components pick which way around each axis (right-hand rule) is used.
+    pub fn torque_along(&self, direction: Vec3) -> Vec3 {
+        let torque = |positive: BlockFace, negative: BlockFace, amount: f32| {
+            if amount >= 0.0 {
+                self.torque[positive.index()]
+            } else {
+                self.torque[negative.index()]
+            }
+        };
+
+        Vec3::new(
+            torque(BlockFace::Right, BlockFace::Left, direction.x),
+            torque(BlockFace::Top, BlockFace::Bottom, direction.y),
+            torque(BlockFace::Front, BlockFace::Back, direction.z),
+        )
+    }
 }
 
 #[derive(Component, Default, Reflect, Serialize, Deserialize, Debug)]
 /// Represents all the thruster blocks on this structure
 pub struct ThrusterSystem {
-    thrust_total: f32,
+    /// Thrust pushing the structure in each direction, indexed by [`BlockFace::index`]
+    thrust: [f32; 6],
+    /// Torque that can be applied around each axis, indexed by [`BlockFace::index`] of the axis (right-hand rule)
+    torque: [f32; 6],
     energy_consumption: f32,
+    /// Only the server needs to know where each thruster is to calculate the torque
+    #[serde(skip)]
+    #[reflect(ignore)]
+    thrusters: HashMap<BlockCoordinate, ThrusterBlock>,
 }
 
 impl StructureSystemImpl for ThrusterSystem {
@@ -53,21 +130,137 @@ impl StructureSystemImpl for ThrusterSystem {
 impl SyncableSystem for ThrusterSystem {}
 
 impl ThrusterSystem {
-    /// Called whenever a block is added
-    pub fn block_removed(&mut self, old_prop: &ThrusterProperty) {
+    /// Called whenever a block is removed
+    pub fn block_removed(&mut self, old_prop: &ThrusterProperty, coords: BlockCoordinate) {
         self.energy_consumption -= old_prop.energy_consupmtion;
-        self.thrust_total -= old_prop.strength;
+
+        if let Some(thruster) = self.thrusters.remove(&coords) {
+            self.add_thrust(thruster.push, -thruster.strength);
+        }
     }
 
-    /// Called whenever a block is removed
-    pub fn block_added(&mut self, prop: &ThrusterProperty) {
+    /// Called whenever a block is added
+    ///
+    /// * `position` The block's position relative to the structure's center
+    pub fn block_added(&mut self, prop: &ThrusterProperty, coords: BlockCoordinate, position: Vec3, rotation: BlockRotation) {
         self.energy_consumption += prop.energy_consupmtion;
-        self.thrust_total += prop.strength;
+
+        // Thrusters shoot out of their front, so they push the structure the opposite way
+        let push = (!prop.omnidirectional).then(|| rotation.local_front().inverse());
+
+        self.add_thrust(push, prop.strength);
+
+        self.thrusters.insert(
+            coords,
+            ThrusterBlock {
+                position,
+                push,
+                strength: prop.strength,
+            },
+        );
     }
 
-    /// Total amount of force exerted on the ship per second while the system is running
-    pub fn thrust_total(&self) -> f32 {
-        self.thrust_total
+    fn add_thrust(&mut self, push: Option<BlockFace>, strength: f32) {
+        match push {
+            Some(face) => self.thrust[face.index()] += strength,
+            None => self.thrust.iter_mut().for_each(|x| *x += strength),
+        }
+    }
+
+    /// Calculates the torque each axis can have applied to it when the structure rotates around this center of mass.
+    ///
+    /// This does not store the result - use [`Self::set_torque`] for that.
+    pub fn calculate_torque(&self, center_of_mass: Vec3) -> [f32; 6] {
+        self.powered_torque(center_of_mass, |_| 1.0)
+    }
+
+    /// The torque each axis can have applied to it when each thruster only pushes as hard as `power_at` its coordinates
+    fn powered_torque(&self, center_of_mass: Vec3, power_at: impl Fn(BlockCoordinate) -> f32) -> [f32; 6] {
+        let mut torque = [0.0; 6];
+
+        for (&coords, thruster) in self.thrusters.iter() {
+            let strength = thruster.strength * power_at(coords);
+
+            let Some(push) = thruster.push else {
+                torque.iter_mut().for_each(|x| *x += strength);
+                continue;
+            };
+
+            let thruster_torque = (thruster.position - center_of_mass).cross(push.direction_vec3()) * strength;
+
+            for (axis, amount) in [
+                (BlockFace::Right, thruster_torque.x),
+                (BlockFace::Top, thruster_torque.y),
+                (BlockFace::Front, thruster_torque.z),
+            ] {
+                if amount > 0.0 {
+                    torque[axis.index()] += amount;
+                } else {
+                    torque[axis.inverse().index()] -= amount;
+                }
+            }
+        }
+
+        torque
+    }
+
+    /// The torque each axis can have applied to it, indexed by [`BlockFace::index`] of the axis
+    pub fn torque(&self) -> [f32; 6] {
+        self.torque
+    }
+
+    /// Sets the torque each axis can have applied to it. See [`Self::calculate_torque`].
+    pub fn set_torque(&mut self, torque: [f32; 6]) {
+        self.torque = torque;
+    }
+
+    /// How hard the thrusters can push & turn the ship if every one of them has full power
+    pub fn full_output(&self) -> ThrusterOutput {
+        ThrusterOutput {
+            thrust: self.thrust,
+            torque: self.torque,
+        }
+    }
+
+    /// How hard the thrusters can push & turn the ship when each thruster only gets `power_at` its coordinates
+    /// of the power it needs [0.0, 1.0].
+    ///
+    /// Unlike [`Self::full_output`], this needs to know where every thruster is, so only the server can use this.
+    pub fn output(&self, center_of_mass: Vec3, power_at: impl Fn(BlockCoordinate) -> f32) -> ThrusterOutput {
+        let mut thrust = [0.0; 6];
+
+        for (&coords, thruster) in self.thrusters.iter() {
+            let strength = thruster.strength * power_at(coords);
+
+            match thruster.push {
+                Some(face) => thrust[face.index()] += strength,
+                None => thrust.iter_mut().for_each(|x| *x += strength),
+            }
+        }
+
+        ThrusterOutput {
+            thrust,
+            torque: self.powered_torque(center_of_mass, power_at),
+        }
+    }
+
+    /// The amount of force that can push the ship towards this side of it
+    pub fn thrust_towards(&self, face: BlockFace) -> f32 {
+        self.full_output().thrust_towards(face)
+    }
+
+    /// The thrust that can push the ship along each of its local axes if every thruster has full power.
+    ///
+    /// `direction`'s components pick which side of each axis (positive or negative) is used.
+    pub fn thrust_along(&self, direction: Vec3) -> Vec3 {
+        self.full_output().thrust_along(direction)
+    }
+
+    /// The torque that can rotate the ship around each of its local axes if every thruster has full power.
+    ///
+    /// `direction`'s components pick which way around each axis (right-hand rule) is used.
+    pub fn torque_along(&self, direction: Vec3) -> Vec3 {
+        self.full_output().torque_along(direction)
     }
 
     /// Amount of energy used per second to run the thruster system
@@ -79,3 +272,85 @@ impl ThrusterSystem {
 pub(super) fn register(app: &mut App) {
     app.insert_resource(ThrusterBlocks::default()).register_type::<ThrusterSystem>();
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use crate::{
+        block::{BlockFace, BlockRotation},
+        structure::coordinates::BlockCoordinate,
+    };
+
+    use super::{ThrusterProperty, ThrusterSystem};
+
+    const THRUSTER: ThrusterProperty = ThrusterProperty {
+        strength: 10.0,
+        energy_consupmtion: 100.0,
+        omnidirectional: false,
+    };
+
+    #[test]
+    fn thrusters_push_away_from_their_front() {
+        let mut system = ThrusterSystem::default();
+        system.block_added(&THRUSTER, BlockCoordinate::new(0, 0, 0), Vec3::ZERO, BlockRotation::default());
+
+        // The default rotation's front is +Z, so it can only push towards -Z
+        assert_eq!(system.thrust_towards(BlockFace::Back), 10.0);
+        assert_eq!(system.thrust_towards(BlockFace::Front), 0.0);
+        assert_eq!(system.thrust_along(Vec3::NEG_Z), Vec3::new(0.0, 0.0, 10.0));
+
+        system.block_removed(&THRUSTER, BlockCoordinate::new(0, 0, 0));
+
+        assert_eq!(system.thrust_towards(BlockFace::Back), 0.0);
+        assert_eq!(system.energy_consumption(), 0.0);
+    }
+
+    #[test]
+    fn torque_comes_from_placement() {
+        let mut system = ThrusterSystem::default();
+        system.block_added(
+            &THRUSTER,
+            BlockCoordinate::new(5, 0, 0),
+            Vec3::new(5.0, 0.0, 0.0),
+            BlockRotation::default(),
+        );
+
+        // Pushing forward from the right side turns the ship left
+        let torque = system.calculate_torque(Vec3::ZERO);
+        system.set_torque(torque);
+
+        assert_eq!(system.torque_along(Vec3::Y), Vec3::new(0.0, 50.0, 0.0));
+        assert_eq!(system.torque_along(Vec3::NEG_Y).y, 0.0);
+
+        // A thruster at the center of mass can't rotate the ship at all
+        assert!(system.calculate_torque(Vec3::new(5.0, 0.0, 0.0)).iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn output_only_uses_powered_thrusters() {
+        let mut system = ThrusterSystem::default();
+        system.block_added(
+            &THRUSTER,
+            BlockCoordinate::new(5, 0, 0),
+            Vec3::new(5.0, 0.0, 0.0),
+            BlockRotation::default(),
+        );
+        system.block_added(
+            &THRUSTER,
+            BlockCoordinate::new(0, 0, 0),
+            Vec3::new(-5.0, 0.0, 0.0),
+            BlockRotation::default(),
+        );
+        system.set_torque(system.calculate_torque(Vec3::ZERO));
+
+        assert_eq!(system.output(Vec3::ZERO, |_| 1.0), system.full_output());
+
+        // Only the thruster on the right has power, so it pushes at half strength & turns the ship left
+        let output = system.output(Vec3::ZERO, |coords| if coords.x == 5 { 0.5 } else { 0.0 });
+
+        assert_eq!(output.thrust_along(Vec3::NEG_Z), Vec3::new(0.0, 0.0, 5.0));
+        assert_eq!(output.torque_along(Vec3::Y), Vec3::new(0.0, 25.0, 0.0));
+        assert_eq!(output.torque_along(Vec3::NEG_Y).y, 0.0);
+    }
+}
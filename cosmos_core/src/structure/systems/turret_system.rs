@@ -0,0 +1,83 @@
+//! Turrets aim & fire at hostile targets on their own, without needing a pilot.
+//!
+//! This lets stations (or any other structure) defend themselves.
+
+use bevy::{
+    prelude::{App, Component, Resource},
+    reflect::Reflect,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{block::Block, registry::identifiable::Identifiable, structure::coordinates::BlockCoordinate};
+
+use super::{sync::SyncableSystem, StructureSystemImpl};
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// How a turret block behaves
+pub struct TurretProperty {
+    /// How much energy is used each time this turret fires
+    pub energy_per_shot: f32,
+    /// The strength of the lasers this turret fires
+    pub strength: f32,
+    /// How far away (in blocks) this turret will fire at targets
+    pub range: f32,
+}
+
+#[derive(Default, Resource)]
+/// All the turret blocks - register them here.
+pub struct TurretBlocks {
+    blocks: HashMap<u16, TurretProperty>,
+}
+
+impl TurretBlocks {
+    /// Inserts a block with a property
+    pub fn insert(&mut self, block: &Block, property: TurretProperty) {
+        self.blocks.insert(block.id(), property);
+    }
+
+    /// Gets a property from that block if it has one
+    pub fn get(&self, block: &Block) -> Option<&TurretProperty> {
+        self.blocks.get(&block.id())
+    }
+}
+
+#[derive(Component, Default, Reflect, Serialize, Deserialize, Debug, Clone)]
+/// Every turret on a structure
+pub struct TurretSystem {
+    turrets: HashMap<BlockCoordinate, TurretProperty>,
+}
+
+impl SyncableSystem for TurretSystem {}
+
+impl StructureSystemImpl for TurretSystem {
+    fn unlocalized_name() -> &'static str {
+        "cosmos:turret_system"
+    }
+}
+
+impl TurretSystem {
+    /// Call this whenever a turret block is added to the structure
+    pub fn block_added(&mut self, property: TurretProperty, coords: BlockCoordinate) {
+        self.turrets.insert(coords, property);
+    }
+
+    /// Call this whenever a turret block is removed from the structure
+    pub fn block_removed(&mut self, coords: BlockCoordinate) {
+        self.turrets.remove(&coords);
+    }
+
+    /// Iterates over every turret's coordinates & properties
+    pub fn turrets(&self) -> impl Iterator<Item = (BlockCoordinate, &TurretProperty)> {
+        self.turrets.iter().map(|(&coords, property)| (coords, property))
+    }
+
+    /// The furthest any of these turrets can fire, or 0 if there are no turrets
+    pub fn max_range(&self) -> f32 {
+        self.turrets.values().map(|x| x.range).fold(0.0, f32::max)
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.insert_resource(TurretBlocks::default()).register_type::<TurretSystem>();
+}
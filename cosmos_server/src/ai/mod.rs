@@ -18,6 +18,8 @@ use crate::persistence::{
 };
 
 mod pirate;
+mod targeting;
+mod turret;
 
 #[derive(Component)]
 /// This entity is controlled by NPCs
@@ -42,4 +44,5 @@ pub(super) fn register(app: &mut App) {
     app.add_systems(SAVING_SCHEDULE, on_save_ai_controlled.in_set(SavingSystemSet::DoSaving));
 
     pirate::register(app);
+    turret::register(app);
 }
@@ -4,7 +4,7 @@ use bevy::{
     ecs::{
         component::Component,
         entity::Entity,
-        query::{Or, With, Without},
+        query::{Has, Or, With, Without},
         schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
         system::{Commands, Query, Res},
     },
@@ -17,9 +17,14 @@ use bevy_rapier3d::dynamics::Velocity;
 use cosmos_core::{
     ecs::NeedsDespawned,
     entities::player::Player,
+    faction::{
+        relations::{Allegiance, FactionRelation},
+        Factions,
+    },
     physics::location::Location,
     projectiles::laser::LASER_LIVE_TIME,
     structure::{
+        ownership::StructureOwner,
         shared::{DespawnWithStructure, MeltingDown},
         ship::{pilot::Pilot, ship_movement::ShipMovement, Ship},
         systems::{laser_cannon_system::LaserCannonSystem, StructureSystems, SystemActive},
@@ -28,6 +33,7 @@ use cosmos_core::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    faction::relations::allegiance,
     persistence::{
         loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
         saving::{SavingSystemSet, SAVING_SCHEDULE},
@@ -37,7 +43,10 @@ use crate::{
     universe::spawners::pirate::Pirate,
 };
 
-use super::AiControlled;
+use super::{
+    targeting::{absolute_linvel, lead_target},
+    AiControlled,
+};
 
 #[derive(Component)]
 pub struct PirateTarget;
@@ -90,7 +99,7 @@ fn handle_pirate_movement(
         pirate_g_transform,
     ) in q_pirates.iter_mut()
     {
-        let Some((target_ent, target_loc, target_vel, _)) = q_targets
+        let Some((target_ent, target_loc, _, _)) = q_targets
             .iter()
             .filter(|x| x.1.is_within_reasonable_range(pirate_loc))
             // add a large penalty for something that's melting down so they prioritize non-melting down things
@@ -99,21 +108,8 @@ fn handle_pirate_movement(
             continue;
         };
 
-        let mut target_linvel = target_vel.linvel;
-
-        let mut entity = target_ent;
-        while let Ok(parent) = q_parent.get(entity) {
-            entity = parent.get();
-            target_linvel += q_velocity.get(entity).map(|x| x.linvel).unwrap_or(Vec3::ZERO);
-        }
-
-        let mut pirate_linvel = pirate_vel.linvel;
-
-        let mut entity = pirate_ent;
-        while let Ok(parent) = q_parent.get(entity) {
-            entity = parent.get();
-            pirate_linvel += q_velocity.get(entity).map(|x| x.linvel).unwrap_or(Vec3::ZERO);
-        }
+        let target_linvel = absolute_linvel(target_ent, &q_parent, &q_velocity);
+        let pirate_linvel = absolute_linvel(pirate_ent, &q_parent, &q_velocity);
 
         if rand::random::<f32>() < 0.01 {
             pirate_ai.randomize_inaccuracy();
@@ -130,13 +126,12 @@ fn handle_pirate_movement(
             - target_linvel;
 
         let distance = (*target_loc - *pirate_loc).absolute_coords_f32();
-        let laser_secs_to_reach_target = (distance.length() / laser_vel.length()).max(0.0);
 
         // Prevents a pirate from shooting the same spot repeatedly and missing and simulates inaccuracy in velocity predicting
         let max_fudge = (pirate_linvel - target_linvel).length() / 4.0;
         let velocity_fudging = pirate_ai.inaccuracy * max_fudge;
 
-        let direction = (distance + (target_linvel - pirate_linvel + velocity_fudging) * laser_secs_to_reach_target).normalize_or_zero();
+        let (direction, laser_secs_to_reach_target) = lead_target(distance, target_linvel - pirate_linvel + velocity_fudging, laser_vel);
 
         // I don't feel like doing the angle math to make it use angular acceleration to look towards it.
         pirate_transform.look_to(direction, Vec3::Y);
@@ -170,12 +165,24 @@ fn handle_pirate_movement(
     }
 }
 
+/// Pirates target every player & ship that the pirate faction is hostile to
 fn add_pirate_targets(
     mut commands: Commands,
-    q_should_be_targets: Query<Entity, (Without<PirateTarget>, Or<(With<Player>, (With<Ship>, Without<Pirate>))>)>,
+    q_could_be_targets: Query<
+        (Entity, Option<&Player>, Option<&StructureOwner>, Has<PirateTarget>),
+        Or<(With<Player>, (With<Ship>, Without<Pirate>))>,
+    >,
+    factions: Res<Factions>,
 ) {
-    for ent in &q_should_be_targets {
-        commands.entity(ent).insert(PirateTarget);
+    for (ent, player, owner, is_target) in &q_could_be_targets {
+        let allegiance = allegiance(player, owner, false, &factions);
+        let should_be_target = factions.allegiance_relation(&Allegiance::Pirates, &allegiance) == FactionRelation::Hostile;
+
+        if should_be_target && !is_target {
+            commands.entity(ent).insert(PirateTarget);
+        } else if !should_be_target && is_target {
+            commands.entity(ent).remove::<PirateTarget>();
+        }
     }
 }
 
@@ -0,0 +1,38 @@
+//! Shared math for AI that needs to hit moving targets
+
+use bevy::{
+    ecs::{entity::Entity, system::Query},
+    hierarchy::Parent,
+    math::Vec3,
+};
+use bevy_rapier3d::dynamics::Velocity;
+
+/// Gets the velocity of this entity including the velocity of everything it's a child of.
+///
+/// A player walking around a moving ship only has their velocity relative to the ship, so this gets their actual velocity.
+pub fn absolute_linvel(entity: Entity, q_parent: &Query<&Parent>, q_velocity: &Query<&Velocity>) -> Vec3 {
+    let mut linvel = q_velocity.get(entity).map(|x| x.linvel).unwrap_or(Vec3::ZERO);
+
+    let mut entity = entity;
+    while let Ok(parent) = q_parent.get(entity) {
+        entity = parent.get();
+        linvel += q_velocity.get(entity).map(|x| x.linvel).unwrap_or(Vec3::ZERO);
+    }
+
+    linvel
+}
+
+/// Figures out which direction to fire a projectile in so it hits a moving target.
+///
+/// * `target_offset` - Where the target is relative to the shooter
+/// * `target_relative_velocity` - The target's velocity minus the shooter's velocity
+/// * `projectile_relative_velocity` - The projectile's velocity relative to the target
+///
+/// Returns the direction to fire in & roughly how many seconds the projectile will take to reach the target.
+pub fn lead_target(target_offset: Vec3, target_relative_velocity: Vec3, projectile_relative_velocity: Vec3) -> (Vec3, f32) {
+    let secs_to_reach_target = (target_offset.length() / projectile_relative_velocity.length()).max(0.0);
+
+    let direction = (target_offset + target_relative_velocity * secs_to_reach_target).normalize_or_zero();
+
+    (direction, secs_to_reach_target)
+}
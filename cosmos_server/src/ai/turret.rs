@@ -0,0 +1,180 @@
+//! Turrets fire at the closest player or ship that is hostile to their structure's owner
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        entity::Entity,
+        query::{Added, Or, With, Without},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::Parent,
+    math::Vec3,
+    time::Time,
+    transform::components::GlobalTransform,
+};
+use bevy_rapier3d::{
+    dynamics::Velocity,
+    prelude::{PhysicsWorld, DEFAULT_WORLD_ID},
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    entities::player::Player,
+    faction::relations::FactionRelation,
+    netty::{cosmos_encoder, server_laser_cannon_system_messages::ServerStructureSystemMessages, NettyChannelServer},
+    physics::location::Location,
+    projectiles::laser::{Laser, LASER_LIVE_TIME},
+    structure::{
+        shared::MeltingDown,
+        ship::Ship,
+        systems::{
+            energy_storage_system::EnergyStorageSystem, laser_cannon_system::SystemCooldown, turret_system::TurretSystem, StructureSystem,
+            StructureSystems,
+        },
+        Structure,
+    },
+};
+
+use crate::{
+    faction::relations::DamageRules,
+    state::GameState,
+    structure::systems::{laser_cannon_system::LASER_BASE_VELOCITY, turret_system::TurretsDisabled},
+};
+
+use super::targeting::{absolute_linvel, lead_target};
+
+fn on_add_turret_system(mut commands: Commands, query: Query<Entity, Added<TurretSystem>>) {
+    for ent in query.iter() {
+        commands.entity(ent).insert(SystemCooldown {
+            cooldown_time: Duration::from_millis(1000),
+            ..Default::default()
+        });
+    }
+}
+
+fn fire_turrets(
+    mut q_turret_systems: Query<(&TurretSystem, &StructureSystem, &mut SystemCooldown)>,
+    mut q_energy_storage: Query<&mut EnergyStorageSystem>,
+    q_structure: Query<
+        (&StructureSystems, &Structure, &Location, &GlobalTransform, Option<&PhysicsWorld>),
+        (Without<TurretsDisabled>, Without<MeltingDown>),
+    >,
+    q_targets: Query<(Entity, &Location), (Or<(With<Player>, With<Ship>)>, Without<MeltingDown>)>,
+    q_parent: Query<&Parent>,
+    q_velocity: Query<&Velocity>,
+    damage_rules: DamageRules,
+    time: Res<Time>,
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+) {
+    for (turret_system, system, mut cooldown) in q_turret_systems.iter_mut() {
+        let structure_entity = system.structure_entity();
+
+        let Ok((systems, structure, location, global_transform, physics_world)) = q_structure.get(structure_entity) else {
+            continue;
+        };
+
+        let max_range = turret_system.max_range();
+        if max_range <= 0.0 {
+            continue;
+        }
+
+        let sec = time.elapsed_seconds();
+        if sec - cooldown.last_use_time <= cooldown.cooldown_time.as_secs_f32() {
+            continue;
+        }
+
+        let Some((target_ent, target_loc)) = q_targets
+            .iter()
+            .filter(|&(ent, target_loc)| {
+                ent != structure_entity
+                    && target_loc.distance_sqrd(location) <= max_range * max_range
+                    && damage_rules.relation(structure_entity, ent) == FactionRelation::Hostile
+            })
+            .min_by_key(|(_, target_loc)| target_loc.distance_sqrd(location).floor() as u64)
+        else {
+            continue;
+        };
+
+        let Ok(mut energy_storage_system) = systems.query_mut(&mut q_energy_storage) else {
+            continue;
+        };
+
+        cooldown.last_use_time = sec;
+
+        let world_id = physics_world.map(|bw| bw.world_id).unwrap_or(DEFAULT_WORLD_ID);
+
+        let shooter_linvel = absolute_linvel(structure_entity, &q_parent, &q_velocity);
+        let target_linvel = absolute_linvel(target_ent, &q_parent, &q_velocity);
+
+        let mut any_fired = false;
+
+        for (coords, turret) in turret_system.turrets() {
+            if energy_storage_system.energy_at(coords) < turret.energy_per_shot {
+                continue;
+            }
+
+            let turret_location = structure.block_world_location(coords, global_transform, location);
+
+            let target_offset = (*target_loc - turret_location).absolute_coords_f32();
+            if target_offset.length() > turret.range {
+                continue;
+            }
+
+            let laser_relative_velocity = shooter_linvel + target_offset.normalize_or_zero() * LASER_BASE_VELOCITY - target_linvel;
+            let (direction, laser_secs_to_reach_target) =
+                lead_target(target_offset, target_linvel - shooter_linvel, laser_relative_velocity);
+
+            if direction == Vec3::ZERO || laser_secs_to_reach_target >= LASER_LIVE_TIME.as_secs_f32() {
+                continue;
+            }
+
+            energy_storage_system.decrease_energy_at(coords, turret.energy_per_shot);
+            any_fired = true;
+
+            let laser_velocity = direction * LASER_BASE_VELOCITY;
+            let no_hit = Some(structure_entity);
+
+            Laser::spawn(
+                turret_location,
+                laser_velocity,
+                shooter_linvel,
+                turret.strength,
+                no_hit,
+                &time,
+                world_id,
+                &mut commands,
+            );
+
+            server.broadcast_message(
+                NettyChannelServer::StructureSystems,
+                cosmos_encoder::serialize(&ServerStructureSystemMessages::CreateLaser {
+                    color: None,
+                    location: turret_location,
+                    laser_velocity,
+                    firer_velocity: shooter_linvel,
+                    strength: turret.strength,
+                    no_hit,
+                }),
+            );
+        }
+
+        if any_fired {
+            server.broadcast_message(
+                NettyChannelServer::StructureSystems,
+                cosmos_encoder::serialize(&ServerStructureSystemMessages::LaserCannonSystemFired {
+                    ship_entity: structure_entity,
+                }),
+            );
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        (on_add_turret_system, fire_turrets).chain().run_if(in_state(GameState::Playing)),
+    );
+}
@@ -1,35 +1,56 @@
 use bevy::{prelude::*, utils::HashMap};
 use bevy_renet::renet::RenetServer;
 use cosmos_core::{
+    entities::player::Player,
     events::block_events::BlockChangedEvent,
     netty::{
         cosmos_encoder,
         server_reliable_messages::{BlockChanged, BlocksChangedPacket, ServerReliableMessages},
         NettyChannelServer,
     },
+    persistence::LoadingDistance,
+    physics::location::Location,
+    structure::Structure,
 };
 
-use crate::state::GameState;
+use crate::{netty::interest::has_block_loaded, state::GameState};
 
-fn handle_block_changed_event(mut event_reader: EventReader<BlockChangedEvent>, mut server: ResMut<RenetServer>) {
-    let iter_len = event_reader.read().len();
+/// Block changes are only sent to players that have the changed block's chunk loaded.
+///
+/// Players that load it later will get the changes when they request that chunk.
+fn handle_block_changed_event(
+    mut event_reader: EventReader<BlockChangedEvent>,
+    q_players: Query<(&Player, &Location)>,
+    q_structure: Query<(&Structure, &Location, Option<&LoadingDistance>)>,
+    mut server: ResMut<RenetServer>,
+) {
     let mut map = HashMap::new();
     for ev in event_reader.read() {
-        if !map.contains_key(&ev.structure_entity) {
-            map.insert(ev.structure_entity, Vec::with_capacity(iter_len));
+        let Ok((structure, structure_location, loading_distance)) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        for (player, player_location) in q_players.iter() {
+            if !has_block_loaded(player_location, structure, structure_location, loading_distance, ev.block.coords()) {
+                continue;
+            }
+
+            map.entry((player.id(), ev.structure_entity))
+                .or_insert_with(Vec::new)
+                .push(BlockChanged {
+                    coordinates: ev.block,
+                    block_id: ev.new_block,
+                    block_rotation: ev.new_block_rotation,
+                });
         }
-        map.get_mut(&ev.structure_entity).expect("Set above").push(BlockChanged {
-            coordinates: ev.block,
-            block_id: ev.new_block,
-            block_rotation: ev.new_block_rotation,
-        });
     }
 
-    for (entity, v) in map {
-        server.broadcast_message(
+    for ((client_id, structure_entity), v) in map {
+        server.send_message(
+            client_id,
             NettyChannelServer::Reliable,
             cosmos_encoder::serialize(&ServerReliableMessages::BlockChange {
-                structure_entity: entity,
+                structure_entity,
                 blocks_changed_packet: BlocksChangedPacket(v),
             }),
         );
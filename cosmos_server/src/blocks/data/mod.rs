@@ -1,7 +1,9 @@
 use bevy::app::App;
 
+mod shop;
 mod storage;
 
 pub(super) fn register(app: &mut App) {
     storage::register(app);
+    shop::register(app);
 }
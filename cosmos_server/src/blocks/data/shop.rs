@@ -0,0 +1,272 @@
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::With,
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res},
+    },
+    hierarchy::{BuildChildren, Parent},
+    log::warn,
+};
+use cosmos_core::{
+    block::{
+        block_events::{BlockEventsSet, BlockPlaceEvent},
+        data::{persistence::ChunkLoadBlockDataEvent, BlockData, BlockDataIdentifier},
+        Block,
+    },
+    entities::player::Player,
+    physics::location::Location,
+    registry::{identifiable::Identifiable, Registry},
+    shop::{market::ShopMarket, Shop},
+    structure::{
+        chunk::netty::SerializedBlockData,
+        coordinates::{ChunkBlockCoordinate, ChunkCoordinate},
+        loading::StructureLoadingSet,
+        station::Station,
+        structure_block::StructureBlock,
+        Structure,
+    },
+};
+
+use crate::{
+    init::init_world::ServerSeed,
+    persistence::{
+        loading::{LoadingBlueprintSystemSet, NeedsBlueprintLoaded, LOADING_SCHEDULE},
+        saving::SAVING_SCHEDULE,
+    },
+    shop::{market::generate_market, prices::DefaultShopEntries},
+    state::GameState,
+    structure::{
+        persistence::{chunk::BlockDataSavingSet, BlockDataNeedsSaved},
+        planet::chunk::SerializeChunkBlockDataSet,
+    },
+};
+
+#[derive(Event)]
+/// Sent whenever a shop block needs its shop data created
+struct PopulateShopEvent {
+    structure_entity: Entity,
+    block: StructureBlock,
+    shop: Shop,
+    /// Shops that aren't owned by players have their prices set by a market
+    market: Option<ShopMarket>,
+}
+
+fn save_shop(q_shop_blocks: Query<(&Parent, &Shop, &BlockData), With<BlockDataNeedsSaved>>, mut q_chunk: Query<&mut SerializedBlockData>) {
+    q_shop_blocks.iter().for_each(|(parent, shop, block_data)| {
+        let mut serialized_block_data = q_chunk
+            .get_mut(parent.get())
+            .expect("Block data's parent didn't have SerializedBlockData???");
+
+        serialized_block_data.serialize_data(
+            ChunkBlockCoordinate::for_block_coordinate(block_data.identifier.block.coords()),
+            "cosmos:shop",
+            shop,
+        );
+    });
+}
+
+fn save_shop_market(
+    q_shop_blocks: Query<(&Parent, &ShopMarket, &BlockData), With<BlockDataNeedsSaved>>,
+    mut q_chunk: Query<&mut SerializedBlockData>,
+) {
+    q_shop_blocks.iter().for_each(|(parent, market, block_data)| {
+        let mut serialized_block_data = q_chunk
+            .get_mut(parent.get())
+            .expect("Block data's parent didn't have SerializedBlockData???");
+
+        serialized_block_data.serialize_data(
+            ChunkBlockCoordinate::for_block_coordinate(block_data.identifier.block.coords()),
+            "cosmos:shop_market",
+            market,
+        );
+    });
+}
+
+/// The market is only ever added alongside a shop, so it doesn't count towards the block data's `data_count`.
+fn load_shop_market(q_structure: Query<&Structure>, mut commands: Commands, mut ev_reader: EventReader<ChunkLoadBlockDataEvent>) {
+    for ev in ev_reader.read() {
+        let Ok(structure) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        let first = ev.chunk.first_structure_block();
+        for (data_coord, serialized) in ev.data.iter() {
+            let Some(market) = serialized.deserialize_data::<ShopMarket>("cosmos:shop_market") else {
+                continue;
+            };
+
+            let Some(data_ent) = structure.block_data(first + *data_coord) else {
+                warn!("Missing data entity despite having data here");
+                continue;
+            };
+
+            commands.entity(data_ent).insert(market);
+        }
+    }
+}
+
+/// Shops generated with the universe sell the default shop entries, at prices set by their sector's market
+fn on_load_blueprint_shop(
+    needs_blueprint_loaded_structure: Query<(Entity, &Structure, Option<&Location>), With<NeedsBlueprintLoaded>>,
+    blocks: Res<Registry<Block>>,
+    default_shop_entries: Option<Res<DefaultShopEntries>>,
+    server_seed: Res<ServerSeed>,
+    mut ev_writer: EventWriter<PopulateShopEvent>,
+) {
+    let Some(shop_block) = blocks.from_id("cosmos:shop") else {
+        return;
+    };
+
+    for (structure_entity, structure, location) in needs_blueprint_loaded_structure.iter() {
+        for block in structure.all_blocks_iter(false) {
+            if block.block_id(structure) == shop_block.id() {
+                let mut shop = Shop {
+                    name: "Cool Shop".into(),
+                    contents: default_shop_entries.as_ref().map(|x| x.0.clone()).unwrap_or_default(),
+                    ..Default::default()
+                };
+
+                let market = generate_market(&shop.contents, location.map(|x| x.sector()).unwrap_or_default(), &server_seed);
+                market.update_shop(&mut shop);
+
+                ev_writer.send(PopulateShopEvent {
+                    block,
+                    structure_entity,
+                    shop,
+                    market: Some(market),
+                });
+            }
+        }
+    }
+}
+
+/// Shops placed by players on stations are owned by that player
+fn on_place_shop(
+    q_station: Query<&Structure, With<Station>>,
+    q_player: Query<&Player>,
+    q_shop: Query<(), With<Shop>>,
+    blocks: Res<Registry<Block>>,
+    mut ev_reader: EventReader<BlockPlaceEvent>,
+    mut ev_writer: EventWriter<PopulateShopEvent>,
+) {
+    let Some(shop_block) = blocks.from_id("cosmos:shop") else {
+        return;
+    };
+
+    for ev in ev_reader.read() {
+        if ev.block_id != shop_block.id() {
+            continue;
+        }
+
+        let Ok(structure) = q_station.get(ev.structure_entity) else {
+            continue;
+        };
+
+        let Ok(player) = q_player.get(ev.placer) else {
+            continue;
+        };
+
+        let coords = ev.structure_block.coords();
+
+        if structure.block_id_at(coords) != shop_block.id() || structure.block_data(coords).map(|x| q_shop.contains(x)).unwrap_or(false) {
+            continue;
+        }
+
+        ev_writer.send(PopulateShopEvent {
+            block: ev.structure_block,
+            structure_entity: ev.structure_entity,
+            shop: Shop {
+                name: format!("{}'s Shop", player.name()),
+                owner: Some(player.name().clone()),
+                ..Default::default()
+            },
+            market: None,
+        });
+    }
+}
+
+fn populate_shop(
+    mut q_structure: Query<&mut Structure>,
+    mut q_block_data: Query<&mut BlockData>,
+    q_shop: Query<(), With<Shop>>,
+    mut commands: Commands,
+    mut ev_reader: EventReader<PopulateShopEvent>,
+) {
+    for ev in ev_reader.read() {
+        let coords = ev.block.coords();
+
+        let Ok(mut structure) = q_structure.get_mut(ev.structure_entity) else {
+            continue;
+        };
+
+        if let Some(data_ent) = structure.block_data(coords) {
+            if q_shop.contains(data_ent) {
+                // This shop's data was already loaded
+                continue;
+            }
+
+            if let Ok(mut count) = q_block_data.get_mut(data_ent) {
+                count.increment();
+            }
+
+            if let Some(mut ecmds) = commands.get_entity(data_ent) {
+                ecmds.insert(ev.shop.clone());
+
+                if let Some(market) = &ev.market {
+                    ecmds.insert(market.clone());
+                }
+            }
+        } else {
+            let Some(chunk_ent) = structure.chunk_entity(ChunkCoordinate::for_block_coordinate(coords)) else {
+                warn!("Missing chunk entity for shop block data.");
+                continue;
+            };
+
+            let data_ent = commands
+                .spawn((
+                    BlockData {
+                        identifier: BlockDataIdentifier {
+                            block: ev.block,
+                            structure_entity: ev.structure_entity,
+                        },
+                        data_count: 1,
+                    },
+                    ev.shop.clone(),
+                ))
+                .id();
+
+            if let Some(market) = &ev.market {
+                commands.entity(data_ent).insert(market.clone());
+            }
+
+            commands.entity(chunk_ent).add_child(data_ent);
+            structure.set_block_data(coords, data_ent);
+        };
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_event::<PopulateShopEvent>()
+        .add_systems(
+            Update,
+            (on_place_shop.after(BlockEventsSet::ProcessEvents), populate_shop)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            SAVING_SCHEDULE,
+            (save_shop, save_shop_market).in_set(BlockDataSavingSet::SaveBlockData),
+        )
+        .add_systems(Update, load_shop_market.in_set(StructureLoadingSet::LoadChunkData))
+        .add_systems(Update, (save_shop, save_shop_market).in_set(SerializeChunkBlockDataSet::Serialize))
+        .add_systems(
+            LOADING_SCHEDULE,
+            // Need structure to be populated first, thus `DoneLoadingBlueprints` instead of `DoLoadingBlueprints`
+            on_load_blueprint_shop
+                .in_set(LoadingBlueprintSystemSet::DoneLoadingBlueprints)
+                .before(populate_shop),
+        );
+}
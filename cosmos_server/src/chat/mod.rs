@@ -0,0 +1,140 @@
+//! Relays chat messages between players, and lets the server send its own messages
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Query, Res, ResMut},
+    },
+    log::{info, warn},
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    chat::{sanitize_chat_message, ClientChatMessages, ServerChatMessages},
+    entities::player::Player,
+    netty::{cosmos_encoder, server::ServerLobby, system_sets::NetworkingSystemsSet, NettyChannelClient, NettyChannelServer},
+};
+
+use crate::{
+    commands::{CommandSender, CosmosCommandInfo, CosmosCommandSent, CosmosCommands, PlayerCommandRequestEvent},
+    state::GameState,
+};
+
+#[derive(Event, Debug, Clone)]
+/// Send this event to send a message to every player's chat
+pub struct SendChatMessageEvent {
+    /// The name of the player that sent this, or `None` if the server is sending it
+    pub sender: Option<String>,
+    /// The message to send
+    pub message: String,
+}
+
+fn receive_chat_messages(
+    mut server: ResMut<RenetServer>,
+    lobby: Res<ServerLobby>,
+    q_player: Query<&Player>,
+    mut ev_writer: EventWriter<SendChatMessageEvent>,
+    mut command_ev_writer: EventWriter<PlayerCommandRequestEvent>,
+) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, NettyChannelClient::Chat) {
+            let Ok(msg) = cosmos_encoder::deserialize::<ClientChatMessages>(&message) else {
+                warn!("Bad chat message from {client_id}");
+                continue;
+            };
+
+            let Some((player_entity, player)) = lobby.player_from_id(client_id).and_then(|x| q_player.get(x).ok().map(|p| (x, p))) else {
+                continue;
+            };
+
+            match msg {
+                ClientChatMessages::SendMessage { message } => {
+                    let Some(message) = sanitize_chat_message(&message) else {
+                        continue;
+                    };
+
+                    ev_writer.send(SendChatMessageEvent {
+                        sender: Some(player.name().clone()),
+                        message,
+                    });
+                }
+                ClientChatMessages::SendCommand { command } => {
+                    let Some(command) = sanitize_chat_message(&command) else {
+                        continue;
+                    };
+
+                    command_ev_writer.send(PlayerCommandRequestEvent {
+                        sender: CommandSender::Player {
+                            entity: player_entity,
+                            client_id,
+                            name: player.name().clone(),
+                        },
+                        text: command,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn send_chat_messages(mut server: ResMut<RenetServer>, mut ev_reader: EventReader<SendChatMessageEvent>) {
+    for ev in ev_reader.read() {
+        match &ev.sender {
+            Some(sender) => info!("[CHAT] <{sender}> {}", ev.message),
+            None => info!("[CHAT] [Server] {}", ev.message),
+        }
+
+        server.broadcast_message(
+            NettyChannelServer::Chat,
+            cosmos_encoder::serialize(&ServerChatMessages::Message {
+                sender: ev.sender.clone(),
+                message: ev.message.clone(),
+            }),
+        );
+    }
+}
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "say".into(),
+        usage: "say [message]".into(),
+        description: "Sends a message to every player's chat.".into(),
+        operator_only: true,
+    });
+}
+
+fn broadcast_command(
+    mut command_events: EventReader<CosmosCommandSent>,
+    cosmos_commands: Res<CosmosCommands>,
+    mut server: ResMut<RenetServer>,
+    mut ev_writer: EventWriter<SendChatMessageEvent>,
+) {
+    for ev in command_events.read() {
+        if ev.name != "say" {
+            continue;
+        }
+
+        // Use the raw text instead of the args, so the message's spacing is kept
+        let Some(message) = ev.text.split_once(' ').and_then(|(_, message)| sanitize_chat_message(message)) else {
+            if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+            }
+            continue;
+        };
+
+        ev_writer.send(SendChatMessageEvent { sender: None, message });
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Startup, register_commands)
+        .add_systems(
+            Update,
+            (receive_chat_messages, broadcast_command, send_chat_messages)
+                .chain()
+                .run_if(in_state(GameState::Playing))
+                .after(NetworkingSystemsSet::ProcessReceivedMessages),
+        )
+        .add_event::<SendChatMessageEvent>();
+}
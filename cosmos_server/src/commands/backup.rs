@@ -0,0 +1,81 @@
+//! Lets operators back up the world & restore it from those backups
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        event::{EventReader, EventWriter},
+        system::{Res, ResMut},
+    },
+};
+use bevy_renet::renet::RenetServer;
+
+use crate::persistence::backups::{list_backups, BackupWorldEvent, RestoreWorldEvent};
+
+use super::{CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "backup".into(),
+        usage: "backup {backup_name}".into(),
+        description: "Saves the world & backs it up while the server keeps running. The name defaults to the current time.".into(),
+        operator_only: true,
+    });
+
+    commands.add_command_info(CosmosCommandInfo {
+        name: "restore".into(),
+        usage: "restore {backup_name}".into(),
+        description: "Stops the server & replaces the world with that backup. Lists every backup if no name is given.".into(),
+        operator_only: true,
+    });
+}
+
+fn backup_commands(
+    mut command_events: EventReader<CosmosCommandSent>,
+    cosmos_commands: Res<CosmosCommands>,
+    mut server: ResMut<RenetServer>,
+    mut backup_writer: EventWriter<BackupWorldEvent>,
+    mut restore_writer: EventWriter<RestoreWorldEvent>,
+) {
+    for ev in command_events.read() {
+        match (ev.name.as_str(), ev.args.as_slice()) {
+            ("backup", []) => {
+                backup_writer.send(BackupWorldEvent {
+                    backup_name: None,
+                    requester: ev.sender.clone(),
+                });
+            }
+            ("backup", [backup_name]) => {
+                backup_writer.send(BackupWorldEvent {
+                    backup_name: Some(backup_name.clone()),
+                    requester: ev.sender.clone(),
+                });
+            }
+            ("restore", []) => {
+                let backups = list_backups();
+
+                if backups.is_empty() {
+                    ev.sender.write("There are no backups of this world.", &mut server);
+                } else {
+                    ev.sender
+                        .write(format!("Backups (oldest first):\n\t{}", backups.join("\n\t")), &mut server);
+                }
+            }
+            ("restore", [backup_name]) => {
+                restore_writer.send(RestoreWorldEvent {
+                    backup_name: backup_name.clone(),
+                    requester: ev.sender.clone(),
+                });
+            }
+            ("backup" | "restore", _) => {
+                if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                    ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Startup, register_commands).add_systems(Update, backup_commands);
+}
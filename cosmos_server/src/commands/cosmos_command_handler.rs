@@ -1,47 +1,53 @@
 //! Handles all the server console commands
 
 use std::{
-    fs::{self},
+    ffi::OsStr,
+    fs::{self, File},
     path::Path,
 };
 
 use bevy::{
     app::Update,
     ecs::schedule::IntoSystemConfigs,
-    log::warn,
     prelude::{App, Commands, Entity, EventReader, Name, Quat, Query, Res, ResMut, Startup, Vec3, With},
 };
+use bevy_renet::renet::RenetServer;
 use cosmos_core::{
     ecs::NeedsDespawned,
     persistence::Blueprintable,
     physics::location::{Location, Sector, SectorUnit},
+    structure::blueprint::{BlueprintError, BlueprintHeader},
 };
 use thiserror::Error;
 
 use crate::persistence::{
     loading::{LoadingSystemSet, NeedsBlueprintLoaded},
     saving::NeedsBlueprinted,
+    world::{is_valid_world_name, unix_time},
 };
 
-use super::{CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+use super::{CommandSender, CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
 
 fn register_commands(mut commands: ResMut<CosmosCommands>) {
     commands.add_command_info(CosmosCommandInfo {
         name: "help".into(),
         usage: "help [command?]".into(),
         description: "Gets information about every command.".into(),
+        operator_only: false,
     });
 
     commands.add_command_info(CosmosCommandInfo {
         name: "ping".into(),
         usage: "ping".into(),
         description: "Says 'Pong'.".into(),
+        operator_only: false,
     });
 
     commands.add_command_info(CosmosCommandInfo {
         name: "blueprint".into(),
         usage: "blueprint [entity_id] [file_name]".into(),
         description: "blueprints the given structure to that file. Do not specify the file extension.".into(),
+        operator_only: true,
     });
 
     commands.add_command_info(CosmosCommandInfo {
@@ -49,6 +55,14 @@ fn register_commands(mut commands: ResMut<CosmosCommands>) {
         usage: "blueprints {blueprint_type}".into(),
         description: "Lists all the blueprints available. The type is optional, and if provided will only list blueprints for that type."
             .into(),
+        operator_only: false,
+    });
+
+    commands.add_command_info(CosmosCommandInfo {
+        name: "blueprint_info".into(),
+        usage: "blueprint_info [blueprint_type] [blueprint_name]".into(),
+        description: "Shows the details of a blueprint, such as the blocks in it & what it costs to build.".into(),
+        operator_only: false,
     });
 
     commands.add_command_info(CosmosCommandInfo {
@@ -56,34 +70,66 @@ fn register_commands(mut commands: ResMut<CosmosCommands>) {
         usage: "load [blueprint_type] [blueprint_name] ([x], [y], [z]) ([x], [y], [z])".into(),
         description: "Loads the given structure from the file for that name. You can specify sector coords and the local coords to specify the coordinates to spawn it."
             .into(),
+        operator_only: true,
     });
 
     commands.add_command_info(CosmosCommandInfo {
         name: "list".into(),
         usage: "list".into(),
         description: "Lists all the savable entity ids".into(),
+        operator_only: true,
     });
 
     commands.add_command_info(CosmosCommandInfo {
         name: "despawn".into(),
         usage: "despawn [entity_id]".into(),
         description: "Despawns the given entity.".into(),
+        operator_only: true,
     });
 }
 
-fn display_help(command_name: Option<&str>, commands: &CosmosCommands) {
+fn display_help(command_name: Option<&str>, commands: &CosmosCommands, sender: &CommandSender, server: &mut RenetServer) {
     if let Some(command_name) = command_name {
         if let Some(info) = commands.command_info(command_name) {
-            println!("=== {} ===", info.name);
-            println!("\t{}\n\t{}", info.usage, info.description);
+            sender.write(format!("=== {} ===\n\t{}\n\t{}", info.name, info.usage, info.description), server);
 
             return;
         }
     }
 
-    println!("=== All Commands ===");
+    let mut help = "=== All Commands ===".to_owned();
     for (_, info) in commands.commands() {
-        println!("{}\n\t{}\n\t{}", info.name, info.usage, info.description);
+        help.push_str(&format!("\n{}\n\t{}\n\t{}", info.name, info.usage, info.description));
+    }
+
+    sender.write(help, server);
+}
+
+/// Reads only the header of this blueprint, which is enough to describe it
+fn read_blueprint_header(path: &str) -> Result<BlueprintHeader, BlueprintError> {
+    BlueprintHeader::read(File::open(path)?)
+}
+
+/// A one line summary of this blueprint
+fn describe_blueprint(path: &str) -> String {
+    match read_blueprint_header(path) {
+        Ok(BlueprintHeader {
+            metadata: Some(metadata), ..
+        }) => {
+            let dimensions = metadata.dimensions;
+            let author = metadata.author.as_deref().unwrap_or("the server");
+
+            format!(
+                "{} blocks, {}x{}x{}, costs {} credits, made by {author}",
+                metadata.total_blocks(),
+                dimensions.x,
+                dimensions.y,
+                dimensions.z,
+                metadata.credit_cost
+            )
+        }
+        Ok(BlueprintHeader { metadata: None, .. }) => "no details - this was made by an older version".into(),
+        Err(e) => format!("unable to read - {e}"),
     }
 }
 
@@ -99,50 +145,54 @@ fn cosmos_command_listener(
     mut commands: Commands,
     mut command_events: EventReader<CosmosCommandSent>,
     cosmos_commands: Res<CosmosCommands>,
+    mut server: ResMut<RenetServer>,
 
     all_blueprintable_entities: Query<(Entity, &Name, &Location), With<Blueprintable>>,
 ) {
     for ev in command_events.read() {
+        let sender = &ev.sender;
+
         match ev.name.as_str() {
             "help" => {
                 if ev.args.len() != 1 {
-                    display_help(None, &cosmos_commands);
+                    display_help(None, &cosmos_commands, sender, &mut server);
                 } else {
-                    display_help(Some(&ev.args[0]), &cosmos_commands);
+                    display_help(Some(&ev.args[0]), &cosmos_commands, sender, &mut server);
                 }
             }
             "ping" => {
-                println!("Pong");
+                sender.write("Pong", &mut server);
             }
             "list" => {
-                println!("All blueprintable entities: ");
-                println!("Name\tSector\t\tId");
+                let mut list = "All blueprintable entities: \nName\tSector\t\tId".to_owned();
                 for (entity, name, location) in all_blueprintable_entities.iter() {
-                    println!("{name}\t{}\t{} ", location.sector(), entity.to_bits());
+                    list.push_str(&format!("\n{name}\t{}\t{} ", location.sector(), entity.to_bits()));
                 }
-                println!("======================================")
+                list.push_str("\n======================================");
+
+                sender.write(list, &mut server);
             }
             "despawn" => {
                 if ev.args.len() != 1 {
-                    display_help(Some("despawn"), &cosmos_commands);
+                    display_help(Some("despawn"), &cosmos_commands, sender, &mut server);
                 } else if let Ok(index) = ev.args[0].parse::<u64>() {
                     if let Ok(entity) = Entity::try_from_bits(index) {
                         if let Some(mut entity_commands) = commands.get_entity(entity) {
                             entity_commands.insert(NeedsDespawned);
-                            println!("Despawned entity {index}");
+                            sender.write(format!("Despawned entity {index}"), &mut server);
                         } else {
-                            println!("Entity not found");
+                            sender.write("Entity not found", &mut server);
                         }
                     } else {
-                        println!("Invalid entity id - {index}.");
+                        sender.write(format!("Invalid entity id - {index}."), &mut server);
                     }
                 } else {
-                    println!("This must be the entity's ID (positive whole number)");
+                    sender.write("This must be the entity's ID (positive whole number)", &mut server);
                 }
             }
             "load" => {
                 if ev.args.len() < 2 || ev.args.len() > 8 {
-                    display_help(Some("load"), &cosmos_commands);
+                    display_help(Some("load"), &cosmos_commands, sender, &mut server);
                 } else {
                     let path = format!("blueprints/{}/{}.bp", ev.args[0], ev.args[1]);
 
@@ -172,7 +222,7 @@ fn cosmos_command_listener(
                         Ok(spawn_at)
                     }
 
-                    let Ok(spawn_at) = parse_args(ev).map_err(|e| warn!("{e}")) else {
+                    let Ok(spawn_at) = parse_args(ev).map_err(|e| sender.write(format!("{e}"), &mut server)) else {
                         continue;
                     };
 
@@ -188,28 +238,34 @@ fn cosmos_command_listener(
             }
             "blueprint" => {
                 if ev.args.len() != 2 {
-                    display_help(Some("blueprint"), &cosmos_commands);
+                    display_help(Some("blueprint"), &cosmos_commands, sender, &mut server);
                     continue;
                 }
                 let Ok(index) = ev.args[0].parse::<u64>() else {
-                    println!("The first argument must be the entity's index (positive number)");
+                    sender.write("The first argument must be the entity's index (positive number)", &mut server);
                     continue;
                 };
 
                 let Ok(entity) = Entity::try_from_bits(index) else {
-                    println!("Invalid entity index {index}");
+                    sender.write(format!("Invalid entity index {index}"), &mut server);
                     continue;
                 };
 
                 if !all_blueprintable_entities.contains(entity) {
-                    println!("This entity is not blueprintable!");
+                    sender.write("This entity is not blueprintable!", &mut server);
                     continue;
                 };
 
-                println!("Blueprinting entity!");
+                sender.write("Blueprinting entity!", &mut server);
+
+                let author = match sender {
+                    CommandSender::Player { name, .. } => Some(name.clone()),
+                    CommandSender::Server => None,
+                };
 
                 commands.entity(entity).insert(NeedsBlueprinted {
                     blueprint_name: ev.args[1].to_owned(),
+                    author,
                     ..Default::default()
                 });
             }
@@ -219,15 +275,17 @@ fn cosmos_command_listener(
                 } else if ev.args.is_empty() {
                     None
                 } else {
-                    display_help(Some("blueprints"), &cosmos_commands);
+                    display_help(Some("blueprints"), &cosmos_commands, sender, &mut server);
                     continue;
                 };
 
                 let Ok(files) = fs::read_dir("./blueprints") else {
-                    println!("No blueprints yet!");
+                    sender.write("No blueprints yet!", &mut server);
                     continue;
                 };
 
+                let mut output = String::new();
+
                 for blueprint_type in files {
                     let Ok(blueprint_type_dir) = blueprint_type else {
                         continue;
@@ -237,9 +295,9 @@ fn cosmos_command_listener(
                     let blueprint_type = file_name.to_str().expect("Unable to read string");
 
                     if check_for.map(|x| x == blueprint_type).unwrap_or(true) {
-                        println!("{blueprint_type}:");
+                        output.push_str(&format!("{blueprint_type}:\n"));
                         let Ok(blueprints) = fs::read_dir(format!("./blueprints/{blueprint_type}")) else {
-                            println!("Unable to list blueprints in this directory!");
+                            output.push_str("Unable to list blueprints in this directory!\n");
                             continue;
                         };
 
@@ -249,23 +307,91 @@ fn cosmos_command_listener(
                                 continue;
                             };
 
+                            let blueprint = blueprint.file_name();
+
+                            // Skips blueprints that are still being written
+                            if Path::new(&blueprint).extension() != Some(OsStr::new("bp")) {
+                                continue;
+                            }
+
                             printed = true;
 
-                            let blueprint = blueprint.file_name();
                             let file_name = Path::new(&blueprint).file_stem().expect("Unable to get file stem");
                             let file_name = file_name.to_str().expect("Unable to read string");
 
-                            println!("\t{file_name}");
+                            let description = describe_blueprint(&format!("./blueprints/{blueprint_type}/{file_name}.bp"));
+
+                            output.push_str(&format!("\t{file_name} - {description}\n"));
                         }
 
                         if !printed {
-                            println!("\tNo blueprints of this type");
+                            output.push_str("\tNo blueprints of this type\n");
                         }
                     }
                 }
+
+                sender.write(output.trim_end(), &mut server);
+            }
+            "blueprint_info" => {
+                if ev.args.len() != 2 {
+                    display_help(Some("blueprint_info"), &cosmos_commands, sender, &mut server);
+                    continue;
+                }
+
+                // Anyone can use this, so make sure it can't be used to read files outside of the blueprints directory
+                if !ev.args.iter().all(|x| is_valid_world_name(x)) {
+                    sender.write(
+                        "Blueprint types & names can only contain letters, numbers, '-' and '_'.",
+                        &mut server,
+                    );
+                    continue;
+                }
+
+                let path = format!("blueprints/{}/{}.bp", ev.args[0], ev.args[1]);
+
+                let metadata = match read_blueprint_header(&path) {
+                    Ok(BlueprintHeader {
+                        metadata: Some(metadata), ..
+                    }) => metadata,
+                    Ok(BlueprintHeader { metadata: None, .. }) => {
+                        sender.write("This blueprint was made by an older version, so it has no details.", &mut server);
+                        continue;
+                    }
+                    Err(e) => {
+                        sender.write(format!("Unable to read blueprint {} - {e}", ev.args[1]), &mut server);
+                        continue;
+                    }
+                };
+
+                let days_old = unix_time().saturating_sub(metadata.created_at) / (60 * 60 * 24);
+
+                let mut output = format!(
+                    "=== {} ({}) ===\nMade by {} {days_old} day(s) ago\nSize: {}x{}x{}\nCost: {} credits\nBlocks ({}):",
+                    metadata.name,
+                    metadata.blueprint_type,
+                    metadata.author.as_deref().unwrap_or("the server"),
+                    metadata.dimensions.x,
+                    metadata.dimensions.y,
+                    metadata.dimensions.z,
+                    metadata.credit_cost,
+                    metadata.total_blocks(),
+                );
+
+                for (block, count) in &metadata.block_counts {
+                    output.push_str(&format!("\n\t{block}: {count}"));
+                }
+
+                output.push_str("\nRequired items:");
+                for (item, count) in &metadata.required_items {
+                    output.push_str(&format!("\n\t{item}: {count}"));
+                }
+
+                sender.write(output, &mut server);
             }
+            // Commands registered elsewhere are handled by whatever registered them
+            name if cosmos_commands.command_exists(name) => {}
             _ => {
-                display_help(Some(&ev.text), &cosmos_commands);
+                display_help(Some(&ev.text), &cosmos_commands, sender, &mut server);
             }
         }
     }
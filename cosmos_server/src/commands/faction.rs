@@ -0,0 +1,197 @@
+//! Lets players create, join & manage factions
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        event::EventReader,
+        system::{Query, Res, ResMut},
+    },
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    chat::ServerChatMessages,
+    entities::player::Player,
+    faction::{relations::FactionRelation, FactionRank, Factions},
+    netty::{cosmos_encoder, NettyChannelServer},
+};
+
+use super::{CommandSender, CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "faction".into(),
+        usage: "faction [create/join/info] [faction_name] | faction [invite/kick/promote/demote] [player_name] | faction [leave/list] | faction relation [ally/neutral/hostile] [faction_name]"
+            .into(),
+        description: "Manages your faction. Members of your faction can use your ships & stations, and officers can build on them. Leaders can declare how they feel about other factions (including the Pirates) - two factions are only as friendly as the least friendly of their declarations.".into(),
+        operator_only: false,
+    });
+}
+
+fn tell_player(player_name: &str, message: String, q_players: &Query<&Player>, server: &mut RenetServer) {
+    if let Some(player) = q_players.iter().find(|x| x.name() == player_name) {
+        server.send_message(
+            player.id(),
+            NettyChannelServer::Chat,
+            cosmos_encoder::serialize(&ServerChatMessages::Message { sender: None, message }),
+        );
+    }
+}
+
+fn faction_command(
+    mut command_events: EventReader<CosmosCommandSent>,
+    cosmos_commands: Res<CosmosCommands>,
+    mut factions: ResMut<Factions>,
+    q_players: Query<&Player>,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in command_events.read() {
+        if ev.name != "faction" {
+            continue;
+        }
+
+        let CommandSender::Player { name, .. } = &ev.sender else {
+            ev.sender.write("Only players can be in factions.", &mut server);
+            continue;
+        };
+
+        let Some((sub_command, args)) = ev.args.split_first() else {
+            if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+            }
+            continue;
+        };
+
+        // Faction names can have spaces in them
+        let rest = args.join(" ");
+
+        let result = match (sub_command.to_lowercase().as_str(), args) {
+            ("create", [_, ..]) => factions
+                .create(rest.as_str(), name.as_str())
+                .map(|_| format!("Created the faction {rest}.")),
+            ("join", [_, ..]) => factions.join(name, &rest).map(|id| {
+                let faction = factions.faction(id).expect("Just joined");
+
+                for (member, _) in faction.members() {
+                    if member != name {
+                        tell_player(member, format!("{name} joined your faction."), &q_players, &mut server);
+                    }
+                }
+
+                format!("You joined {}.", faction.name())
+            }),
+            ("leave", []) => factions.leave(name).map(|_| "You left your faction.".to_owned()),
+            ("invite", [player_name]) => factions.invite(name, player_name.as_str()).map(|_| {
+                let faction_name = factions.player_faction(name).map(|x| x.name()).unwrap_or_default();
+
+                tell_player(
+                    player_name,
+                    format!("{name} invited you to {faction_name}. Type /faction join {faction_name} to join."),
+                    &q_players,
+                    &mut server,
+                );
+
+                format!("Invited {player_name}.")
+            }),
+            ("kick", [player_name]) => factions.kick(name, player_name).map(|_| {
+                tell_player(player_name, "You were kicked from your faction.".into(), &q_players, &mut server);
+
+                format!("Kicked {player_name}.")
+            }),
+            ("promote" | "demote", [player_name]) => factions
+                .change_rank(name, player_name, sub_command.eq_ignore_ascii_case("promote"))
+                .map(|rank| format!("{player_name} is now a {}.", rank.name())),
+            ("relation", [relation, faction_name, ..]) => match FactionRelation::from_name(relation) {
+                Some(relation) => {
+                    let faction_name = args[1..].join(" ");
+
+                    factions.declare_relation(name, &faction_name, relation).map(|result| {
+                        let own_name = factions.player_faction(name).map(|x| x.name()).unwrap_or_default();
+
+                        if let Some(other) = factions.from_name(&faction_name) {
+                            for (member, _) in other.members() {
+                                tell_player(
+                                    member,
+                                    format!(
+                                        "{own_name} declared your faction {}. Your relation is now {}.",
+                                        relation.name(),
+                                        result.name()
+                                    ),
+                                    &q_players,
+                                    &mut server,
+                                );
+                            }
+                        }
+
+                        format!(
+                            "Declared {faction_name} {}. Your relation is now {}.",
+                            relation.name(),
+                            result.name()
+                        )
+                    })
+                }
+                None => Ok(format!("{relation} isn't a relation - use ally, neutral or hostile.")),
+            },
+            ("info", _) => {
+                let faction = if args.is_empty() {
+                    factions.player_faction(name)
+                } else {
+                    factions.from_name(&rest)
+                };
+
+                match faction {
+                    Some(faction) => {
+                        let mut members = faction.members().collect::<Vec<(&str, FactionRank)>>();
+                        members.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+                        let members = members
+                            .into_iter()
+                            .map(|(member, rank)| format!("{member} ({})", rank.name()))
+                            .collect::<Vec<String>>()
+                            .join(", ");
+
+                        let relations = factions
+                            .declared_relations(faction.id())
+                            .flat_map(|(other, _)| {
+                                factions
+                                    .faction_name(other)
+                                    .map(|other_name| format!("{other_name} ({})", factions.relation(faction.id(), other).name()))
+                            })
+                            .collect::<Vec<String>>();
+
+                        if relations.is_empty() {
+                            Ok(format!("{}: {members}", faction.name()))
+                        } else {
+                            Ok(format!("{}: {members}\nRelations: {}", faction.name(), relations.join(", ")))
+                        }
+                    }
+                    None if args.is_empty() => Ok("You are not in a faction.".into()),
+                    None => Ok(format!("There is no faction named {rest}.")),
+                }
+            }
+            ("list", []) => {
+                let mut names = factions.iter().map(|x| x.name()).collect::<Vec<&str>>();
+                names.sort();
+
+                if names.is_empty() {
+                    Ok("There are no factions.".into())
+                } else {
+                    Ok(format!("Factions: {}", names.join(", ")))
+                }
+            }
+            _ => {
+                if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                    ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+                }
+                continue;
+            }
+        };
+
+        let message = result.unwrap_or_else(|e| e.message());
+
+        ev.sender.write(message, &mut server);
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Startup, register_commands).add_systems(Update, faction_command);
+}
@@ -0,0 +1,93 @@
+//! Lets operators switch players between game modes
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        system::{Commands, Query, Res, ResMut},
+    },
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    entities::player::{game_mode::GameMode, Player},
+    inventory::Inventory,
+    item::Item,
+    registry::Registry,
+};
+
+use crate::entities::player::game_mode::{enter_creative, leave_creative, SurvivalInventory};
+
+use super::{CommandSender, CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "gamemode".into(),
+        usage: "gamemode [survival/creative] {player_name}".into(),
+        description: "Switches a player to that game mode. Players can leave out the name to switch themselves.".into(),
+        operator_only: true,
+    });
+}
+
+fn game_mode_command(
+    mut command_events: EventReader<CosmosCommandSent>,
+    cosmos_commands: Res<CosmosCommands>,
+    mut server: ResMut<RenetServer>,
+    mut q_players: Query<(Entity, &Player, &mut Inventory, Option<&GameMode>, Option<&SurvivalInventory>)>,
+    items: Res<Registry<Item>>,
+    mut commands: Commands,
+) {
+    for ev in command_events.read() {
+        if ev.name != "gamemode" {
+            continue;
+        }
+
+        let (game_mode, player_name) = match (ev.args.as_slice(), &ev.sender) {
+            ([game_mode], CommandSender::Player { name, .. }) => (game_mode, name),
+            ([game_mode, player_name], _) => (game_mode, player_name),
+            _ => {
+                if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                    ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+                }
+                continue;
+            }
+        };
+
+        let Some(game_mode) = GameMode::from_name(game_mode) else {
+            ev.sender.write(
+                format!("Unknown game mode {game_mode} - must be survival or creative."),
+                &mut server,
+            );
+            continue;
+        };
+
+        let Some((player_entity, player, mut inventory, current_game_mode, survival_inventory)) =
+            q_players.iter_mut().find(|(_, player, ..)| player.name() == player_name)
+        else {
+            ev.sender.write(format!("No player named {player_name} is online."), &mut server);
+            continue;
+        };
+
+        let mut ecmds = commands.entity(player_entity);
+
+        match (current_game_mode.copied().unwrap_or_default(), game_mode) {
+            (GameMode::Survival, GameMode::Creative) => {
+                ecmds.insert(enter_creative(&mut inventory, &items));
+            }
+            (GameMode::Creative, GameMode::Survival) => {
+                leave_creative(&mut inventory, survival_inventory);
+                ecmds.remove::<SurvivalInventory>();
+            }
+            _ => {}
+        }
+
+        ecmds.insert(game_mode);
+
+        ev.sender
+            .write(format!("{} is now in {} mode.", player.name(), game_mode.name()), &mut server);
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Startup, register_commands).add_systems(Update, game_mode_command);
+}
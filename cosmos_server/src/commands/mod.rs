@@ -3,15 +3,65 @@
 use std::time::Duration;
 
 use bevy::{
-    prelude::{App, Event, EventWriter, ResMut, Resource, Update},
+    log::info,
+    prelude::{App, Entity, Event, EventReader, EventWriter, Res, ResMut, Resource, Update},
     reflect::Reflect,
     utils::HashMap,
 };
+use bevy_renet::renet::{ClientId, RenetServer};
+use cosmos_core::{
+    chat::ServerChatMessages,
+    netty::{cosmos_encoder, NettyChannelServer},
+};
 use crossterm::event::{poll, read, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use self::operators::Operators;
+
+pub mod backup;
 pub mod cosmos_command_handler;
+pub mod faction;
+pub mod game_mode;
+pub mod operators;
+pub mod stop;
+
+#[derive(Debug, Clone)]
+/// Who sent a command
+pub enum CommandSender {
+    /// The server admin typed it into the console
+    Server,
+    /// A player sent it from in the game
+    Player {
+        /// The player's entity
+        entity: Entity,
+        /// The player's client id
+        client_id: ClientId,
+        /// The player's name
+        name: String,
+    },
+}
+
+impl CommandSender {
+    /// Sends a message back to whoever sent the command.
+    ///
+    /// The server console has this printed out, and players get it in their chat.
+    pub fn write(&self, message: impl Into<String>, server: &mut RenetServer) {
+        let message = message.into();
+
+        match self {
+            Self::Server => println!("{message}"),
+            Self::Player { client_id, .. } => {
+                server.send_message(
+                    *client_id,
+                    NettyChannelServer::Chat,
+                    cosmos_encoder::serialize(&ServerChatMessages::Message { sender: None, message }),
+                );
+            }
+        }
+    }
+}
 
 #[derive(Debug, Event)]
-/// This event is sent when the server admin types a console command
+/// This event is sent when the server admin types a console command, or a player sends a command they are allowed to use
 pub struct CosmosCommandSent {
     /// The raw string the user typed
     pub text: String,
@@ -19,13 +69,16 @@ pub struct CosmosCommandSent {
     pub name: String,
     /// The args split around spaces
     pub args: Vec<String>,
+    /// Who sent this command
+    pub sender: CommandSender,
 }
 
 impl CosmosCommandSent {
     /// Creates a new command event.
     ///
     /// * `text` The entire string of text the user typed
-    pub fn new(text: String) -> Self {
+    /// * `sender` Who sent this command
+    pub fn new(text: String, sender: CommandSender) -> Self {
         let split: Vec<&str> = text.split(' ').collect();
         let (name_arr, args_arr) = split.split_at(1);
 
@@ -36,10 +89,21 @@ impl CosmosCommandSent {
             .map(|x| (*x).to_owned())
             .collect::<Vec<String>>();
 
-        Self { text, name, args }
+        Self { text, name, args, sender }
     }
 }
 
+#[derive(Debug, Event)]
+/// Sent when a player tries to run a command.
+///
+/// If the command exists and they are allowed to use it, this becomes a [`CosmosCommandSent`] event.
+pub struct PlayerCommandRequestEvent {
+    /// The player that sent this
+    pub sender: CommandSender,
+    /// The command they typed, without the leading `/`
+    pub text: String,
+}
+
 #[derive(Debug)]
 /// Information that describes how a command should be formatted by the user
 pub struct CosmosCommandInfo {
@@ -55,6 +119,10 @@ pub struct CosmosCommandInfo {
     ///
     /// Example: "Despawns the entity with the given entity id."
     pub description: String,
+    /// If this is true, only operators can use this command in game.
+    ///
+    /// The server console can always use every command.
+    pub operator_only: bool,
 }
 
 #[derive(Resource, Debug, Default)]
@@ -121,18 +189,54 @@ fn monitor_inputs(mut event_writer: EventWriter<CosmosCommandSent>, mut text: Re
     }
 
     if !text.0.trim().is_empty() && text.0.ends_with('\n') {
-        let cmd = CosmosCommandSent::new(text.0[0..text.0.len() - 1].to_owned());
+        let cmd = CosmosCommandSent::new(text.0[0..text.0.len() - 1].to_owned(), CommandSender::Server);
         event_writer.send(cmd);
 
         text.0.clear();
     }
 }
 
+fn check_player_commands(
+    mut ev_reader: EventReader<PlayerCommandRequestEvent>,
+    mut ev_writer: EventWriter<CosmosCommandSent>,
+    cosmos_commands: Res<CosmosCommands>,
+    operators: Res<Operators>,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in ev_reader.read() {
+        let command = CosmosCommandSent::new(ev.text.clone(), ev.sender.clone());
+
+        let Some(info) = cosmos_commands.command_info(&command.name) else {
+            command.sender.write(format!("Unknown command \"{}\".", command.name), &mut server);
+            continue;
+        };
+
+        if let CommandSender::Player { name, .. } = &command.sender {
+            if info.operator_only && !operators.is_operator(name) {
+                command
+                    .sender
+                    .write(format!("You do not have permission to use \"{}\".", command.name), &mut server);
+                continue;
+            }
+
+            info!("{name} ran command: {}", command.text);
+        }
+
+        ev_writer.send(command);
+    }
+}
+
 pub(super) fn register(app: &mut App) {
     app.insert_resource(CosmosCommands::default())
         .insert_resource(CurrentlyWriting::default())
-        .add_systems(Update, monitor_inputs)
-        .add_event::<CosmosCommandSent>();
-
+        .add_systems(Update, (monitor_inputs, check_player_commands))
+        .add_event::<CosmosCommandSent>()
+        .add_event::<PlayerCommandRequestEvent>();
+
+    operators::register(app);
+    game_mode::register(app);
+    faction::register(app);
+    stop::register(app);
+    backup::register(app);
     cosmos_command_handler::register(app);
 }
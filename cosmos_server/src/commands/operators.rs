@@ -0,0 +1,157 @@
+//! Operators are players that can use any command from in the game.
+//!
+//! The list of operators is saved with the world, so it persists between restarts.
+
+use std::{collections::HashSet, fs};
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        event::EventReader,
+        system::{Res, ResMut, Resource},
+    },
+    log::error,
+};
+use bevy_renet::renet::RenetServer;
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::{world::world_directory, write_atomically};
+
+use super::{CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+
+fn operators_file() -> String {
+    format!("{}/operators.json", world_directory())
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+/// The names of every player that is an operator
+pub struct Operators(HashSet<String>);
+
+impl Operators {
+    /// Returns true if the player with this name is an operator
+    pub fn is_operator(&self, player_name: &str) -> bool {
+        self.0.contains(player_name)
+    }
+
+    /// Makes this player an operator & saves the list of operators.
+    ///
+    /// Returns false if they were already an operator.
+    pub fn add_operator(&mut self, player_name: impl Into<String>) -> bool {
+        let added = self.0.insert(player_name.into());
+
+        if added {
+            self.save();
+        }
+
+        added
+    }
+
+    /// Makes this player no longer an operator & saves the list of operators.
+    ///
+    /// Returns false if they weren't an operator.
+    pub fn remove_operator(&mut self, player_name: &str) -> bool {
+        let removed = self.0.remove(player_name);
+
+        if removed {
+            self.save();
+        }
+
+        removed
+    }
+
+    fn load() -> Self {
+        let path = operators_file();
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|e| panic!("Invalid operators file ({path}) - {e}"))
+    }
+
+    fn save(&self) {
+        _ = fs::create_dir_all(world_directory());
+
+        let json = serde_json::to_string_pretty(self).expect("Unable to serialize operators");
+
+        if let Err(e) = write_atomically(operators_file(), json) {
+            error!("Unable to save operators - {e}");
+        }
+    }
+}
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "op".into(),
+        usage: "op [player_name]".into(),
+        description: "Makes this player an operator, letting them use every command in game.".into(),
+        operator_only: true,
+    });
+
+    commands.add_command_info(CosmosCommandInfo {
+        name: "deop".into(),
+        usage: "deop [player_name]".into(),
+        description: "Makes this player no longer an operator.".into(),
+        operator_only: true,
+    });
+
+    commands.add_command_info(CosmosCommandInfo {
+        name: "ops".into(),
+        usage: "ops".into(),
+        description: "Lists every operator.".into(),
+        operator_only: true,
+    });
+}
+
+fn operator_commands(
+    mut command_events: EventReader<CosmosCommandSent>,
+    mut operators: ResMut<Operators>,
+    cosmos_commands: Res<CosmosCommands>,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in command_events.read() {
+        match ev.name.as_str() {
+            "op" | "deop" => {
+                let [player_name] = ev.args.as_slice() else {
+                    if let Some(info) = cosmos_commands.command_info(&ev.name) {
+                        ev.sender.write(format!("Usage: {}", info.usage), &mut server);
+                    }
+                    continue;
+                };
+
+                let message = if ev.name == "op" {
+                    if operators.add_operator(player_name) {
+                        format!("{player_name} is now an operator.")
+                    } else {
+                        format!("{player_name} is already an operator.")
+                    }
+                } else if operators.remove_operator(player_name) {
+                    format!("{player_name} is no longer an operator.")
+                } else {
+                    format!("{player_name} is not an operator.")
+                };
+
+                ev.sender.write(message, &mut server);
+            }
+            "ops" => {
+                let mut names = operators.0.iter().map(|x| x.as_str()).collect::<Vec<&str>>();
+                names.sort();
+
+                let message = if names.is_empty() {
+                    "There are no operators.".to_owned()
+                } else {
+                    format!("Operators: {}", names.join(", "))
+                };
+
+                ev.sender.write(message, &mut server);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.insert_resource(Operators::load())
+        .add_systems(Startup, register_commands)
+        .add_systems(Update, operator_commands);
+}
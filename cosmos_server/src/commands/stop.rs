@@ -0,0 +1,42 @@
+//! Lets operators save everything & stop the server
+
+use bevy::{
+    app::{App, Startup, Update},
+    ecs::{
+        event::{EventReader, EventWriter},
+        system::ResMut,
+    },
+};
+use bevy_renet::renet::RenetServer;
+
+use crate::shutdown::RequestShutdownEvent;
+
+use super::{CosmosCommandInfo, CosmosCommandSent, CosmosCommands};
+
+fn register_commands(mut commands: ResMut<CosmosCommands>) {
+    commands.add_command_info(CosmosCommandInfo {
+        name: "stop".into(),
+        usage: "stop".into(),
+        description: "Saves everything, disconnects every player & stops the server.".into(),
+        operator_only: true,
+    });
+}
+
+fn stop_command(
+    mut command_events: EventReader<CosmosCommandSent>,
+    mut server: ResMut<RenetServer>,
+    mut shutdown_writer: EventWriter<RequestShutdownEvent>,
+) {
+    for ev in command_events.read() {
+        if ev.name != "stop" {
+            continue;
+        }
+
+        ev.sender.write("Stopping the server...", &mut server);
+        shutdown_writer.send_default();
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(Startup, register_commands).add_systems(Update, stop_command);
+}
@@ -0,0 +1,214 @@
+//! Crafting recipes at fabricator blocks
+
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, RenetServer};
+use cosmos_core::{
+    block::{block_events::BlockInteractEvent, Block},
+    crafting::{
+        netty::{ClientCraftingMessages, CraftingError, ServerCraftingMessages},
+        Recipe,
+    },
+    entities::player::Player,
+    faction::Factions,
+    inventory::Inventory,
+    item::Item,
+    netty::{cosmos_encoder, server::ServerLobby, system_sets::NetworkingSystemsSet, NettyChannelClient, NettyChannelServer},
+    physics::location::Location,
+    registry::{identifiable::Identifiable, Registry},
+    structure::{
+        coordinates::BlockCoordinate,
+        ownership::{has_permission, StructureOwner, StructurePermission},
+        Structure,
+    },
+};
+
+use crate::{commands::operators::Operators, state::GameState};
+
+const FABRICATOR: &str = "cosmos:fabricator";
+
+/// How far a player can be from a fabricator & still use it.
+///
+/// This is a bit further than players can reach, since they may have moved since they sent the request.
+const MAX_FABRICATOR_DISTANCE: f32 = 16.0;
+
+fn on_interact_with_fabricator(
+    mut server: ResMut<RenetServer>,
+    q_structure: Query<&Structure>,
+    q_player: Query<&Player>,
+    blocks: Res<Registry<Block>>,
+    mut ev_reader: EventReader<BlockInteractEvent>,
+) {
+    for ev in ev_reader.read() {
+        let Ok(player) = q_player.get(ev.interactor) else {
+            continue;
+        };
+
+        let Ok(structure) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        if ev.structure_block.block(structure, &blocks).unlocalized_name() == FABRICATOR {
+            server.send_message(
+                player.id(),
+                NettyChannelServer::Crafting,
+                cosmos_encoder::serialize(&ServerCraftingMessages::OpenFabricator {
+                    fabricator_block: ev.structure_block.coords(),
+                    structure_entity: ev.structure_entity,
+                }),
+            );
+        }
+    }
+}
+
+#[derive(Event)]
+struct CraftEvent {
+    client_id: ClientId,
+    fabricator_block: BlockCoordinate,
+    structure_entity: Entity,
+    recipe_id: u16,
+    quantity: u32,
+}
+
+/// Takes the recipe's inputs out of the inventory & puts its output in, or returns why it couldn't
+fn craft(recipe: &Recipe, quantity: u32, inventory: &mut Inventory, items: &Registry<Item>) -> Result<u32, CraftingError> {
+    let quantity_of = |item_id: u16| {
+        items
+            .try_from_numeric_id(item_id)
+            .map(|item| inventory.quantity_of(item))
+            .unwrap_or(0)
+    };
+
+    if quantity == 0 || recipe.max_crafts(quantity_of) < quantity as usize {
+        return Err(CraftingError::NotEnoughItems);
+    }
+
+    let Some(output) = items.try_from_numeric_id(recipe.output.item_id) else {
+        return Err(CraftingError::UnknownRecipe);
+    };
+
+    let Ok(output_quantity) = u16::try_from(recipe.output.quantity as u64 * quantity as u64) else {
+        return Err(CraftingError::NotEnoughInventorySpace);
+    };
+
+    if !inventory.can_insert(output, output_quantity) {
+        return Err(CraftingError::NotEnoughInventorySpace);
+    }
+
+    for input in recipe.inputs.iter() {
+        if let Some(item) = items.try_from_numeric_id(input.item_id) {
+            inventory.take_item(item, input.quantity as usize * quantity as usize);
+        }
+    }
+
+    inventory.insert(output, output_quantity);
+
+    Ok(quantity)
+}
+
+fn listen_craft_events(
+    mut server: ResMut<RenetServer>,
+    mut ev_reader: EventReader<CraftEvent>,
+    q_structure: Query<(&Structure, &Location, &GlobalTransform, Option<&StructureOwner>)>,
+    lobby: Res<ServerLobby>,
+    mut q_player: Query<(&Player, &Location, &mut Inventory)>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+    recipes: Res<Registry<Recipe>>,
+    factions: Res<Factions>,
+    operators: Res<Operators>,
+) {
+    for &CraftEvent {
+        client_id,
+        fabricator_block,
+        structure_entity,
+        recipe_id,
+        quantity,
+    } in ev_reader.read()
+    {
+        let Some(player_ent) = lobby.player_from_id(client_id) else {
+            error!("Bad player id: {client_id}");
+            continue;
+        };
+
+        let Ok((player, player_location, mut inventory)) = q_player.get_mut(player_ent) else {
+            error!("No inventory on player entity: {player_ent:?}");
+            continue;
+        };
+
+        let fabricator = q_structure.get(structure_entity).ok().filter(|(structure, ..)| {
+            structure.is_within_blocks(fabricator_block) && structure.block_at(fabricator_block, &blocks).unlocalized_name() == FABRICATOR
+        });
+
+        let details = if let Some((structure, structure_location, g_trans, owner)) = fabricator {
+            // Locations don't account for the structure's rotation
+            let fabricator_location = *structure_location
+                + g_trans
+                    .affine()
+                    .matrix3
+                    .mul_vec3(structure.block_relative_position(fabricator_block));
+
+            if player_location.distance_sqrd(&fabricator_location) > MAX_FABRICATOR_DISTANCE * MAX_FABRICATOR_DISTANCE {
+                Err(CraftingError::TooFarAway)
+            } else if !operators.is_operator(player.name()) && !has_permission(owner, player.name(), StructurePermission::Craft, &factions)
+            {
+                Err(CraftingError::NotAllowed)
+            } else if let Some(recipe) = recipes.try_from_numeric_id(recipe_id) {
+                craft(recipe, quantity, &mut inventory, &items)
+            } else {
+                Err(CraftingError::UnknownRecipe)
+            }
+        } else {
+            Err(CraftingError::NotAFabricator)
+        };
+
+        server.send_message(
+            client_id,
+            NettyChannelServer::Crafting,
+            cosmos_encoder::serialize(&ServerCraftingMessages::CraftResult {
+                fabricator_block,
+                structure_entity,
+                recipe_id,
+                details,
+            }),
+        );
+    }
+}
+
+fn listen_client_crafting_messages(mut ev_writer: EventWriter<CraftEvent>, mut server: ResMut<RenetServer>) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, NettyChannelClient::Crafting) {
+            let Ok(msg) = cosmos_encoder::deserialize::<ClientCraftingMessages>(&message) else {
+                error!("Bad crafting message from {client_id}");
+                continue;
+            };
+
+            match msg {
+                ClientCraftingMessages::Craft {
+                    fabricator_block,
+                    structure_entity,
+                    recipe_id,
+                    quantity,
+                } => {
+                    ev_writer.send(CraftEvent {
+                        client_id,
+                        fabricator_block,
+                        structure_entity,
+                        recipe_id,
+                        quantity,
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        (on_interact_with_fabricator, listen_client_crafting_messages, listen_craft_events)
+            .chain()
+            .run_if(in_state(GameState::Playing))
+            .after(NetworkingSystemsSet::ProcessReceivedMessages),
+    )
+    .add_event::<CraftEvent>();
+}
@@ -0,0 +1,11 @@
+//! Server crafting logic
+
+use bevy::app::App;
+
+mod fabricator;
+mod recipes;
+
+pub(super) fn register(app: &mut App) {
+    recipes::register(app);
+    fabricator::register(app);
+}
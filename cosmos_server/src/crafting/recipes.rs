@@ -0,0 +1,61 @@
+//! Registers every recipe that can be crafted
+
+use bevy::{
+    app::App,
+    ecs::{
+        schedule::OnExit,
+        system::{Res, ResMut},
+    },
+    log::warn,
+};
+use cosmos_core::{
+    crafting::{Recipe, RecipeItem},
+    item::Item,
+    registry::{identifiable::Identifiable, Registry},
+};
+
+use crate::{registry::sync_registry, state::GameState};
+
+/// Each entry is the item crafted, how many are made each time, and the items used up to make them
+const RECIPES: &[(&str, u16, &[(&str, u16)])] = &[
+    ("cosmos:ship_hull_grey", 4, &[("cosmos:test_ore", 1), ("cosmos:stone", 1)]),
+    ("cosmos:glass", 1, &[("cosmos:sand", 2)]),
+    ("cosmos:power_cable", 4, &[("cosmos:test_ore", 1)]),
+    ("cosmos:logic_wire", 4, &[("cosmos:test_ore", 1)]),
+    ("cosmos:light", 1, &[("cosmos:test_ore", 1), ("cosmos:glass", 1)]),
+    ("cosmos:storage", 1, &[("cosmos:redwood_log", 4)]),
+    ("cosmos:energy_cell", 1, &[("cosmos:test_ore", 4), ("cosmos:stone", 2)]),
+    ("cosmos:reactor", 1, &[("cosmos:test_ore", 8), ("cosmos:stone", 4)]),
+    ("cosmos:thruster", 1, &[("cosmos:test_ore", 4), ("cosmos:redwood_log", 2)]),
+    ("cosmos:laser_cannon", 1, &[("cosmos:test_ore", 6), ("cosmos:glass", 2)]),
+    ("cosmos:plasma_drill", 1, &[("cosmos:test_ore", 6), ("cosmos:stone", 2)]),
+    ("cosmos:fabricator", 1, &[("cosmos:test_ore", 4), ("cosmos:stone", 4)]),
+    ("cosmos:ship_core", 1, &[("cosmos:test_ore", 10), ("cosmos:stone", 10)]),
+];
+
+fn register_recipes(items: Res<Registry<Item>>, mut recipes: ResMut<Registry<Recipe>>) {
+    for &(output, output_quantity, inputs) in RECIPES {
+        let Some(output_item) = items.from_id(output) else {
+            warn!("Missing item {output} - not registering its recipe.");
+            continue;
+        };
+
+        let Some(inputs) = inputs
+            .iter()
+            .map(|&(input, quantity)| items.from_id(input).map(|item| RecipeItem::new(item.id(), quantity)))
+            .collect::<Option<Vec<RecipeItem>>>()
+        else {
+            warn!("Missing an input item for {output} - not registering its recipe.");
+            continue;
+        };
+
+        recipes.register(Recipe::new(output, inputs, RecipeItem::new(output_item.id(), output_quantity)));
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    // Block items are only created once `PostLoading` is entered
+    app.add_systems(OnExit(GameState::PostLoading), register_recipes);
+
+    sync_registry::<Recipe>(app);
+}
@@ -0,0 +1,133 @@
+//! Gives players the items their game mode starts them with
+
+use bevy::{ecs::component::Component, log::warn};
+use cosmos_core::{
+    entities::player::game_mode::GameMode,
+    inventory::{itemstack::ItemStack, Inventory},
+    item::Item,
+    registry::{identifiable::Identifiable, Registry},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::ServerSettings;
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+/// The inventory a player had before switching to creative.
+///
+/// They get this back once they switch back to survival, so creative can't be used to get items.
+pub struct SurvivalInventory(pub Inventory);
+
+/// Creates the inventory a new player in this game mode starts with.
+///
+/// Survival players get the server's starter kit, and creative players get every item.
+pub fn starting_inventory(game_mode: GameMode, server_settings: &ServerSettings, items: &Registry<Item>) -> Inventory {
+    let mut inventory = Inventory::new("Inventory", 9 * 10, Some(0..9));
+
+    match game_mode {
+        GameMode::Survival => {
+            for kit_item in server_settings.starter_kit.iter() {
+                let Some(item) = items.from_id(&kit_item.item) else {
+                    warn!("Unknown starter kit item {} - skipping it.", kit_item.item);
+                    continue;
+                };
+
+                inventory.insert(item, kit_item.quantity);
+            }
+        }
+        GameMode::Creative => give_every_item(&mut inventory, items),
+    }
+
+    inventory
+}
+
+/// Gives a full stack of every item this inventory doesn't already have.
+///
+/// Creative players never use up their items, so they only need one stack of each.
+pub fn give_every_item(inventory: &mut Inventory, items: &Registry<Item>) {
+    for item in items.iter().rev().filter(|item| item.unlocalized_name() != "cosmos:air") {
+        if inventory.quantity_of(item) == 0 {
+            inventory.insert_itemstack(&ItemStack::with_quantity(item, item.max_stack_size()));
+        }
+    }
+}
+
+/// Gives a survival player every item, and returns the inventory they had so it can be given back when they leave creative.
+pub fn enter_creative(inventory: &mut Inventory, items: &Registry<Item>) -> SurvivalInventory {
+    let survival_inventory = SurvivalInventory(inventory.clone());
+
+    give_every_item(inventory, items);
+
+    survival_inventory
+}
+
+/// Takes away everything a creative player was given, giving back the inventory they had before entering creative.
+///
+/// Players that were never in survival (such as new players on creative servers) are left with an empty inventory.
+pub fn leave_creative(inventory: &mut Inventory, survival_inventory: Option<&SurvivalInventory>) {
+    if let Some(survival_inventory) = survival_inventory {
+        *inventory = survival_inventory.0.clone();
+    } else {
+        for slot in 0..inventory.len() {
+            inventory.remove_itemstack_at(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cosmos_core::{
+        inventory::Inventory,
+        item::Item,
+        registry::{identifiable::Identifiable, Registry},
+    };
+
+    use super::{enter_creative, leave_creative};
+
+    fn items() -> Registry<Item> {
+        let mut items = Registry::new("cosmos:items");
+
+        for name in ["cosmos:air", "cosmos:stone", "cosmos:grass", "cosmos:laser_cannon"] {
+            items.register(Item::new(name, 64));
+        }
+
+        items
+    }
+
+    fn contents(inventory: &Inventory) -> Vec<Option<(u16, u16)>> {
+        inventory
+            .iter()
+            .map(|x| x.as_ref().map(|is| (is.item_id(), is.quantity())))
+            .collect()
+    }
+
+    #[test]
+    fn leaving_creative_restores_survival_inventory() {
+        let items = items();
+        let stone = items.from_id("cosmos:stone").unwrap();
+
+        let mut inventory = Inventory::new("Inventory", 9 * 10, Some(0..9));
+        inventory.insert(stone, 5);
+        let before = contents(&inventory);
+
+        let survival_inventory = enter_creative(&mut inventory, &items);
+        assert_eq!(inventory.quantity_of(items.from_id("cosmos:laser_cannon").unwrap()), 64);
+
+        leave_creative(&mut inventory, Some(&survival_inventory));
+
+        assert_eq!(contents(&inventory), before);
+        assert_eq!(inventory.quantity_of(stone), 5);
+        assert!(items.iter().filter(|x| x.id() != stone.id()).all(|x| inventory.quantity_of(x) == 0));
+    }
+
+    #[test]
+    fn leaving_creative_without_survival_inventory_clears_it() {
+        let items = items();
+
+        let mut inventory = Inventory::new("Inventory", 9 * 10, Some(0..9));
+        enter_creative(&mut inventory, &items);
+
+        leave_creative(&mut inventory, None);
+
+        assert!(inventory.iter().all(|x| x.is_none()));
+    }
+}
@@ -2,7 +2,10 @@
 
 // mod apart_of_ship;
 
-use bevy::prelude::{Component, Quat};
+use bevy::prelude::{App, Component, Quat};
+
+pub mod game_mode;
+pub mod persistence;
 
 #[derive(Component)]
 /// The server doesn't have a camera, so this is used to track where the player is looking
@@ -10,3 +13,7 @@ pub struct PlayerLooking {
     /// What the player's camera rotation would be
     pub rotation: Quat,
 }
+
+pub(super) fn register(app: &mut App) {
+    persistence::register(app);
+}
@@ -0,0 +1,268 @@
+//! Saves + loads players, so they keep their stuff between disconnects & server restarts.
+//!
+//! Players are saved by name to `world/players/`, instead of in the sector they are in.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventWriter,
+        query::{With, Without},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::{BuildChildren, Parent},
+    log::{info, warn},
+    math::Vec3,
+    time::{common_conditions::on_timer, Time},
+    transform::components::Transform,
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    economy::Credits,
+    entities::player::{game_mode::GameMode, render_distance::RenderDistance, Player},
+    events::structure::change_pilot_event::ChangePilotEvent,
+    inventory::Inventory,
+    netty::{cosmos_encoder, server_reliable_messages::ServerReliableMessages, NettyChannelServer},
+    physics::location::Location,
+    structure::{
+        shared::build_mode::{BuildMode, EnterBuildModeEvent},
+        ship::pilot::Pilot,
+        Structure,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entities::player::game_mode::SurvivalInventory,
+    events::netty::netty_events::PlayerConnecting,
+    persistence::{
+        loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
+        saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
+        EntityId, SerializedData,
+    },
+    state::GameState,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+/// What the player was doing on the structure they were a child of
+enum PlayerParentContext {
+    /// Just walking around on it
+    Riding,
+    /// Piloting it
+    Piloting,
+    /// In build mode on it
+    BuildMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// The structure the player was a part of when they were saved
+struct SavedPlayerParent {
+    /// The parent's entity id
+    parent_id: EntityId,
+    /// The player's translation relative to the parent
+    relative_translation: Vec3,
+    /// What the player was doing on that structure
+    context: PlayerParentContext,
+}
+
+#[derive(Component, Debug)]
+/// The player was a child of this structure when they were saved, and should be put back once it's loaded
+struct PlayerParentNeedsRestored {
+    saved_parent: SavedPlayerParent,
+    /// When we started looking for the parent - used to give up if it was never loaded
+    started_at: f32,
+}
+
+/// If the parent isn't loaded by this point, it was probably destroyed while the player was offline.
+const PARENT_RESTORE_TIMEOUT_SECS: f32 = 30.0;
+
+fn on_save_player(
+    mut q_players: Query<
+        (
+            &mut SerializedData,
+            &Player,
+            &Transform,
+            Option<&Inventory>,
+            Option<&Credits>,
+            Option<&RenderDistance>,
+            Option<&GameMode>,
+            Option<&SurvivalInventory>,
+            Option<&Parent>,
+            Option<&Pilot>,
+            Option<&BuildMode>,
+        ),
+        With<NeedsSaved>,
+    >,
+    q_entity_id: Query<&EntityId>,
+    mut commands: Commands,
+) {
+    for (mut s_data, player, transform, inventory, credits, render_distance, game_mode, survival_inventory, parent, pilot, build_mode) in
+        q_players.iter_mut()
+    {
+        s_data.serialize_data("cosmos:player_name", player.name());
+
+        if let Some(inventory) = inventory {
+            s_data.serialize_data("cosmos:inventory", inventory);
+        }
+        if let Some(credits) = credits {
+            s_data.serialize_data("cosmos:credits", credits);
+        }
+        if let Some(render_distance) = render_distance {
+            s_data.serialize_data("cosmos:render_distance", render_distance);
+        }
+        if let Some(game_mode) = game_mode {
+            s_data.serialize_data("cosmos:game_mode", game_mode);
+        }
+        if let Some(survival_inventory) = survival_inventory {
+            s_data.serialize_data("cosmos:survival_inventory", survival_inventory);
+        }
+
+        let Some(parent) = parent else {
+            continue;
+        };
+
+        let parent_entity = parent.get();
+
+        let parent_id = if let Ok(entity_id) = q_entity_id.get(parent_entity) {
+            entity_id.clone()
+        } else {
+            // The parent has never been saved, so give it an id now. It will keep this id whenever it does get saved.
+            let entity_id = EntityId::generate();
+            commands.entity(parent_entity).insert(entity_id.clone());
+            entity_id
+        };
+
+        let context = if pilot.is_some() {
+            PlayerParentContext::Piloting
+        } else if build_mode.is_some() {
+            PlayerParentContext::BuildMode
+        } else {
+            PlayerParentContext::Riding
+        };
+
+        s_data.serialize_data(
+            "cosmos:player_parent",
+            &SavedPlayerParent {
+                parent_id,
+                relative_translation: transform.translation,
+                context,
+            },
+        );
+    }
+}
+
+fn on_load_player(
+    q_needs_loaded: Query<(Entity, &SerializedData), (With<NeedsLoaded>, With<Player>)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, s_data) in q_needs_loaded.iter() {
+        let mut ecmds = commands.entity(entity);
+
+        if let Some(inventory) = s_data.deserialize_data::<Inventory>("cosmos:inventory") {
+            ecmds.insert(inventory);
+        }
+        if let Some(credits) = s_data.deserialize_data::<Credits>("cosmos:credits") {
+            ecmds.insert(credits);
+        }
+        if let Some(render_distance) = s_data.deserialize_data::<RenderDistance>("cosmos:render_distance") {
+            ecmds.insert(render_distance);
+        }
+        if let Some(game_mode) = s_data.deserialize_data::<GameMode>("cosmos:game_mode") {
+            ecmds.insert(game_mode);
+        }
+        if let Some(survival_inventory) = s_data.deserialize_data::<SurvivalInventory>("cosmos:survival_inventory") {
+            ecmds.insert(survival_inventory);
+        }
+        if let Some(saved_parent) = s_data.deserialize_data::<SavedPlayerParent>("cosmos:player_parent") {
+            ecmds.insert(PlayerParentNeedsRestored {
+                saved_parent,
+                started_at: time.elapsed_seconds(),
+            });
+        }
+    }
+}
+
+/// Puts players back onto the structure they were on once that structure is loaded
+fn restore_player_parent(
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+    mut q_players: Query<(Entity, &Player, &PlayerParentNeedsRestored, &mut Transform), Without<PlayerConnecting>>,
+    q_structures: Query<(Entity, &EntityId), With<Structure>>,
+    q_pilot: Query<(), With<Pilot>>,
+    time: Res<Time>,
+    mut change_pilot_event: EventWriter<ChangePilotEvent>,
+    mut enter_build_mode_event: EventWriter<EnterBuildModeEvent>,
+) {
+    for (player_entity, player, needs_restored, mut transform) in q_players.iter_mut() {
+        let saved_parent = &needs_restored.saved_parent;
+
+        let Some((structure_entity, _)) = q_structures.iter().find(|(_, id)| **id == saved_parent.parent_id) else {
+            if time.elapsed_seconds() - needs_restored.started_at > PARENT_RESTORE_TIMEOUT_SECS {
+                warn!(
+                    "Unable to find the structure {} that {} was on - leaving them where they are.",
+                    saved_parent.parent_id,
+                    player.name()
+                );
+                commands.entity(player_entity).remove::<PlayerParentNeedsRestored>();
+            }
+            continue;
+        };
+
+        commands.entity(player_entity).remove::<PlayerParentNeedsRestored>();
+
+        info!("Putting {} back onto structure {structure_entity:?}", player.name());
+
+        match saved_parent.context {
+            // Someone else may have started piloting it while this player was offline
+            PlayerParentContext::Piloting if !q_pilot.contains(structure_entity) => {
+                change_pilot_event.send(ChangePilotEvent {
+                    structure_entity,
+                    pilot_entity: Some(player_entity),
+                });
+            }
+            PlayerParentContext::BuildMode => {
+                enter_build_mode_event.send(EnterBuildModeEvent {
+                    player_entity,
+                    structure_entity,
+                });
+            }
+            _ => {
+                transform.translation = saved_parent.relative_translation;
+                commands.entity(player_entity).set_parent(structure_entity);
+
+                server.broadcast_message(
+                    NettyChannelServer::Reliable,
+                    cosmos_encoder::serialize(&ServerReliableMessages::PlayerJoinShip {
+                        player_entity,
+                        ship_entity: structure_entity,
+                    }),
+                );
+            }
+        }
+    }
+}
+
+/// Saves every connected player every so often, so a server crash doesn't lose too much
+fn save_players_periodically(q_players: Query<Entity, (With<Player>, Without<PlayerConnecting>)>, mut commands: Commands) {
+    for entity in q_players.iter() {
+        commands.entity(entity).insert(NeedsSaved);
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(SAVING_SCHEDULE, on_save_player.in_set(SavingSystemSet::DoSaving))
+        .add_systems(LOADING_SCHEDULE, on_load_player.in_set(LoadingSystemSet::DoLoading))
+        .add_systems(
+            Update,
+            (
+                restore_player_parent.after(LoadingSystemSet::DoneLoading),
+                save_players_periodically.run_if(on_timer(Duration::from_secs(60))),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+}
@@ -1,15 +1,19 @@
 //! Handles client connecting and disconnecting
 
+use std::fs;
+
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_renet::renet::transport::NetcodeServerTransport;
 use bevy_renet::renet::{ClientId, RenetServer, ServerEvent};
+use cosmos_core::block::Block;
 use cosmos_core::economy::Credits;
 use cosmos_core::ecs::NeedsDespawned;
+use cosmos_core::entities::player::game_mode::GameMode;
 use cosmos_core::entities::player::render_distance::RenderDistance;
-use cosmos_core::inventory::itemstack::ItemStack;
 use cosmos_core::inventory::Inventory;
 use cosmos_core::item::Item;
+use cosmos_core::netty::connection::{ConnectionUserData, GameVersion, HandshakeResponse};
 use cosmos_core::netty::netty_rigidbody::NettyRigidBodyLocation;
 use cosmos_core::netty::server::ServerLobby;
 use cosmos_core::netty::server_reliable_messages::ServerReliableMessages;
@@ -19,52 +23,64 @@ use cosmos_core::netty::{cosmos_encoder, NettyChannelServer};
 use cosmos_core::persistence::LoadingDistance;
 use cosmos_core::physics::location::{Location, Sector};
 use cosmos_core::physics::player_world::WorldWithin;
-use cosmos_core::registry::identifiable::Identifiable;
 use cosmos_core::registry::Registry;
 use cosmos_core::structure::chunk::CHUNK_DIMENSIONSF;
 use cosmos_core::{entities::player::Player, netty::netty_rigidbody::NettyRigidBody};
 use renet_visualizer::RenetServerVisualizer;
 
+use crate::entities::player::game_mode::starting_inventory;
 use crate::entities::player::PlayerLooking;
+use crate::netty::authentication::{PlayerIdentities, ServerAccess};
 use crate::netty::network_helpers::ClientTicks;
+use crate::persistence::loading::{LoadingSystemSet, NeedsLoaded};
+use crate::persistence::saving::NeedsSaved;
+use crate::persistence::writer::SaveFileWriter;
+use crate::persistence::SaveFileIdentifier;
 use crate::physics::assign_player_world;
+use crate::settings::ServerSettings;
 use crate::state::GameState;
 
-fn generate_player_inventory(items: &Registry<Item>) -> Inventory {
-    let mut inventory = Inventory::new("Inventory", 9 * 10, Some(0..9));
-
-    // inventory.insert_item_at(0, items.from_id("cosmos:ship_hull").expect("Ship hull item to exist"), 999);
-
-    // inventory.insert_item_at(1, items.from_id("cosmos:glass").expect("Glass item to exist"), 999);
-
-    // inventory.insert_item_at(2, items.from_id("cosmos:build_block").expect("Build block item to exist"), 999);
-
-    // inventory.insert_item_at(3, items.from_id("cosmos:thruster").expect("Thruster item to exist"), 999);
+#[derive(Event, Debug)]
+/// Sent whenever a player just connected
+pub struct PlayerConnectedEvent {
+    /// The player's entity
+    pub player_entity: Entity,
+    /// Player's client id
+    pub client_id: ClientId,
+}
 
-    // inventory.insert_item_at(4, items.from_id("cosmos:laser_cannon").expect("Laser cannon item to exist"), 999);
+#[derive(Component, Debug)]
+/// A player that has connected, but is still being loaded & has not been sent to any clients yet.
+pub struct PlayerConnecting;
 
-    // inventory.insert_item_at(5, items.from_id("cosmos:reactor").expect("Reactor item to exist"), 999);
+/// How long (in seconds) refused clients stay connected, so they receive the reason they were refused
+const REFUSED_DISCONNECT_DELAY: f32 = 1.0;
 
-    // inventory.insert_item_at(6, items.from_id("cosmos:energy_cell").expect("Energy cell item to exist"), 999);
+#[derive(Resource, Default)]
+/// Clients that were refused, and will be disconnected once they've had time to receive why
+struct RefusedClients(Vec<(ClientId, f32)>);
 
-    // inventory.insert_item_at(7, items.from_id("cosmos:light").expect("Light item to exist"), 999);
+fn refuse_client(client_id: ClientId, reason: String, server: &mut RenetServer, refused_clients: &mut RefusedClients) {
+    server.send_message(
+        client_id,
+        NettyChannelServer::Handshake,
+        cosmos_encoder::serialize(&HandshakeResponse::Rejected(reason)),
+    );
 
-    // inventory.insert_item_at(8, items.from_id("cosmos:redwood_log").expect("Redwood log item to exist"), 999);
+    refused_clients.0.push((client_id, REFUSED_DISCONNECT_DELAY));
+}
 
-    for item in items.iter().rev().filter(|item| item.unlocalized_name() != "cosmos:air") {
-        inventory.insert_itemstack(&ItemStack::with_quantity(item, 999));
-    }
+fn disconnect_refused_clients(mut server: ResMut<RenetServer>, mut refused_clients: ResMut<RefusedClients>, time: Res<Time>) {
+    refused_clients.0.retain_mut(|(client_id, time_left)| {
+        *time_left -= time.delta_seconds();
 
-    inventory
-}
+        if *time_left > 0.0 {
+            return true;
+        }
 
-#[derive(Event, Debug)]
-/// Sent whenever a player just connected
-pub struct PlayerConnectedEvent {
-    /// The player's entity
-    pub player_entity: Entity,
-    /// Player's client id
-    pub client_id: ClientId,
+        server.disconnect(*client_id);
+        false
+    });
 }
 
 fn handle_server_events(
@@ -84,13 +100,18 @@ fn handle_server_events(
         &RenderDistance,
         &Credits,
     )>,
-    player_worlds: Query<(&Location, &WorldWithin, &PhysicsWorld), (With<Player>, Without<Parent>)>,
-    items: Res<Registry<Item>>,
+    q_connecting: Query<(), With<PlayerConnecting>>,
+    q_online: Query<&Player>,
     mut visualizer: ResMut<RenetServerVisualizer<200>>,
-    mut rapier_context: ResMut<RapierContext>,
     mut requested_entity: EventWriter<RequestedEntityEvent>,
-    mut player_join_ev_writer: EventWriter<PlayerConnectedEvent>,
+    (access, mut identities): (Res<ServerAccess>, ResMut<PlayerIdentities>),
+    mut refused_clients: ResMut<RefusedClients>,
+    (blocks, items): (Res<Registry<Block>>, Res<Registry<Item>>),
+    writer: Res<SaveFileWriter>,
 ) {
+    // Players that joined this frame haven't been spawned yet, so they won't be in `q_online`
+    let mut joined_names: Vec<String> = vec![];
+
     for event in server_events.read() {
         match event {
             ServerEvent::ClientConnected { client_id } => {
@@ -98,6 +119,50 @@ fn handle_server_events(
                 info!("Client {client_id} connected");
                 visualizer.add_client(client_id);
 
+                let Some(user_data) = transport.user_data(client_id) else {
+                    warn!("Unable to get user data!");
+                    server.disconnect(client_id);
+                    continue;
+                };
+                let Some(user_data) = ConnectionUserData::from_user_data(&user_data) else {
+                    warn!("Unable to deserialize user data!");
+                    refuse_client(
+                        client_id,
+                        "Unable to read your connection info. You are likely running a different version of Cosmos than the server.".into(),
+                        &mut server,
+                        &mut refused_clients,
+                    );
+                    continue;
+                };
+
+                let check = user_data
+                    .version
+                    .check_compatible(&GameVersion::current(&blocks, &items))
+                    .and_then(|_| access.check_connection(&user_data))
+                    .and_then(|_| {
+                        if q_online.iter().any(|x| x.name() == &user_data.name) || joined_names.contains(&user_data.name) {
+                            Err(format!("{} is already playing on this server.", user_data.name))
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .and_then(|_| identities.verify(&user_data.name, &user_data.identity_key));
+
+                if let Err(reason) = check {
+                    info!("Refused connection from {} ({client_id}) - {reason}", user_data.name);
+                    refuse_client(client_id, reason, &mut server, &mut refused_clients);
+                    continue;
+                }
+
+                server.send_message(
+                    client_id,
+                    NettyChannelServer::Handshake,
+                    cosmos_encoder::serialize(&HandshakeResponse::Accepted),
+                );
+
+                let name = user_data.name;
+                joined_names.push(name.clone());
+
                 for (entity, player, transform, location, velocity, inventory, render_distance, credits) in q_players.iter() {
                     let body = NettyRigidBody::new(Some(*velocity), transform.rotation, NettyRigidBodyLocation::Absolute(*location));
 
@@ -115,70 +180,19 @@ fn handle_server_events(
                     requested_entity.send(RequestedEntityEvent { client_id, entity });
                 }
 
-                let Some(user_data) = transport.user_data(client_id) else {
-                    warn!("Unable to get user data!");
-                    continue;
-                };
-                let Ok(name) = bincode::deserialize::<String>(user_data.as_slice()) else {
-                    warn!("Unable to deserialize name!");
-                    continue;
-                };
-
                 let player = Player::new(name.clone(), client_id);
-                let starting_pos = Vec3::new(0.0, CHUNK_DIMENSIONSF * 70.0 / 2.0, 0.0);
-                let location = Location::new(starting_pos, Sector::new(25, 25, 25));
-                let velocity = Velocity::default();
-                let inventory = generate_player_inventory(&items);
-
-                let netty_body = NettyRigidBody::new(Some(velocity), Quat::IDENTITY, NettyRigidBodyLocation::Absolute(location));
-
-                let inventory_serialized = cosmos_encoder::serialize(&inventory);
-
-                let credits = Credits::new(1_000_000);
-
-                let player_commands = commands.spawn((
-                    location,
-                    LockedAxes::ROTATION_LOCKED,
-                    RigidBody::Dynamic,
-                    velocity,
-                    Collider::capsule_y(0.65, 0.25),
-                    player,
-                    ReadMassProperties::default(),
-                    inventory,
-                    PlayerLooking { rotation: Quat::IDENTITY },
-                    LoadingDistance::new(2, 9999),
-                    ActiveEvents::COLLISION_EVENTS,
-                    Name::new(format!("Player ({name})")),
-                    credits,
-                ));
-
-                let player_entity = player_commands.id();
-
-                lobby.add_player(client_id, player_entity);
-
-                assign_player_world(&player_worlds, player_entity, &location, &mut commands, &mut rapier_context);
-
-                let msg = cosmos_encoder::serialize(&ServerReliableMessages::PlayerCreate {
-                    entity: player_entity,
-                    id: client_id,
-                    name,
-                    body: netty_body,
-                    inventory_serialized,
-                    render_distance: None,
-                    credits,
-                });
 
-                server.send_message(
-                    client_id,
-                    NettyChannelServer::Reliable,
-                    cosmos_encoder::serialize(&ServerReliableMessages::MOTD {
-                        motd: "Welcome to the server!".into(),
-                    }),
-                );
+                let mut player_commands = commands.spawn((player, PlayerConnecting, Name::new(format!("Player ({name})"))));
 
-                server.broadcast_message(NettyChannelServer::Reliable, msg);
+                let save_file = SaveFileIdentifier::player(&name);
+                let save_path = save_file.get_save_file_path();
+                // If they just left, their save file may not have been written yet
+                if fs::try_exists(&save_path).unwrap_or(false) || writer.is_pending(&save_path) {
+                    info!("Loading saved data for {name}");
+                    player_commands.insert((save_file, NeedsLoaded));
+                }
 
-                player_join_ev_writer.send(PlayerConnectedEvent { player_entity, client_id });
+                lobby.add_player(client_id, player_commands.id());
             }
             ServerEvent::ClientDisconnected { client_id, reason } => {
                 info!("Client {client_id} disconnected: {reason}");
@@ -186,7 +200,13 @@ fn handle_server_events(
                 client_ticks.ticks.remove(client_id);
 
                 if let Some(player_entity) = lobby.remove_player(*client_id) {
-                    commands.entity(player_entity).insert(NeedsDespawned);
+                    if q_connecting.contains(player_entity) {
+                        // Their data isn't fully loaded yet, so saving them now would overwrite their save with incomplete data.
+                        // The save file identifier is removed so their save file isn't deleted when they're despawned.
+                        commands.entity(player_entity).remove::<SaveFileIdentifier>().insert(NeedsDespawned);
+                    } else {
+                        commands.entity(player_entity).insert((NeedsSaved, NeedsDespawned));
+                    }
                 }
 
                 let message = cosmos_encoder::serialize(&ServerReliableMessages::PlayerRemove { id: *client_id });
@@ -197,12 +217,104 @@ fn handle_server_events(
     }
 }
 
+/// Once a connecting player is done loading (or has no save data), this gives them everything they need
+/// & sends them to all the clients.
+fn finish_connecting_players(
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+    q_connecting: Query<
+        (
+            Entity,
+            &Player,
+            Option<&Location>,
+            Option<&Velocity>,
+            Option<&Inventory>,
+            Option<&Credits>,
+            Option<&RenderDistance>,
+            Option<&GameMode>,
+        ),
+        (With<PlayerConnecting>, Without<NeedsLoaded>),
+    >,
+    player_worlds: Query<(&Location, &WorldWithin, &PhysicsWorld), (With<Player>, Without<Parent>)>,
+    items: Res<Registry<Item>>,
+    mut rapier_context: ResMut<RapierContext>,
+    mut player_join_ev_writer: EventWriter<PlayerConnectedEvent>,
+    server_settings: Res<ServerSettings>,
+) {
+    for (player_entity, player, location, velocity, inventory, credits, render_distance, game_mode) in q_connecting.iter() {
+        let client_id = player.id();
+        let name = player.name().clone();
+
+        let location = location.copied().unwrap_or_else(|| {
+            let starting_pos = Vec3::new(0.0, CHUNK_DIMENSIONSF * 70.0 / 2.0, 0.0);
+            Location::new(starting_pos, Sector::new(25, 25, 25))
+        });
+        let velocity = velocity.copied().unwrap_or_default();
+        let game_mode = game_mode.copied().unwrap_or(server_settings.default_game_mode);
+        let inventory = inventory
+            .cloned()
+            .unwrap_or_else(|| starting_inventory(game_mode, &server_settings, &items));
+        let credits = credits.copied().unwrap_or(Credits::new(server_settings.starting_credits));
+
+        let netty_body = NettyRigidBody::new(Some(velocity), Quat::IDENTITY, NettyRigidBodyLocation::Absolute(location));
+
+        let inventory_serialized = cosmos_encoder::serialize(&inventory);
+
+        commands.entity(player_entity).remove::<PlayerConnecting>().insert((
+            location,
+            LockedAxes::ROTATION_LOCKED,
+            RigidBody::Dynamic,
+            velocity,
+            Collider::capsule_y(0.65, 0.25),
+            ReadMassProperties::default(),
+            inventory,
+            PlayerLooking { rotation: Quat::IDENTITY },
+            LoadingDistance::new(2, 9999),
+            ActiveEvents::COLLISION_EVENTS,
+            credits,
+            game_mode,
+        ));
+
+        assign_player_world(&player_worlds, player_entity, &location, &mut commands, &mut rapier_context);
+
+        let msg = cosmos_encoder::serialize(&ServerReliableMessages::PlayerCreate {
+            entity: player_entity,
+            id: client_id,
+            name,
+            body: netty_body,
+            inventory_serialized,
+            render_distance: render_distance.copied(),
+            credits,
+        });
+
+        if !server_settings.motd.is_empty() {
+            server.send_message(
+                client_id,
+                NettyChannelServer::Reliable,
+                cosmos_encoder::serialize(&ServerReliableMessages::MOTD {
+                    motd: server_settings.motd.clone(),
+                }),
+            );
+        }
+
+        server.broadcast_message(NettyChannelServer::Reliable, msg);
+
+        player_join_ev_writer.send(PlayerConnectedEvent { player_entity, client_id });
+    }
+}
+
 pub(super) fn register(app: &mut App) {
     app.add_systems(
         Update,
-        handle_server_events
-            .run_if(in_state(GameState::Playing))
-            .in_set(NetworkingSystemsSet::ReceiveMessages),
+        (
+            handle_server_events.in_set(NetworkingSystemsSet::ReceiveMessages),
+            disconnect_refused_clients,
+            finish_connecting_players
+                .after(LoadingSystemSet::DoneLoading)
+                .after(NetworkingSystemsSet::ReceiveMessages),
+        )
+            .run_if(in_state(GameState::Playing)),
     )
+    .init_resource::<RefusedClients>()
     .add_event::<PlayerConnectedEvent>();
 }
@@ -0,0 +1,54 @@
+//! Saves & loads the world's factions.
+//!
+//! Factions are saved with the world, so they persist between restarts.
+
+use std::fs;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        schedule::{common_conditions::resource_changed, IntoSystemConfigs},
+        system::Res,
+    },
+    log::error,
+};
+use cosmos_core::faction::Factions;
+
+use crate::persistence::{world::world_directory, write_atomically};
+
+pub mod relations;
+
+fn factions_file() -> String {
+    format!("{}/factions.json", world_directory())
+}
+
+fn load_factions() -> Factions {
+    let path = factions_file();
+
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Factions::default();
+    };
+
+    serde_json::from_str(&contents).unwrap_or_else(|e| panic!("Invalid factions file ({path}) - {e}"))
+}
+
+fn save_factions(factions: Res<Factions>) {
+    // Nothing has changed yet, just been loaded
+    if factions.is_added() {
+        return;
+    }
+
+    _ = fs::create_dir_all(world_directory());
+
+    let json = serde_json::to_string_pretty(factions.as_ref()).expect("Unable to serialize factions");
+
+    if let Err(e) = write_atomically(factions_file(), json) {
+        error!("Unable to save factions - {e}");
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.insert_resource(load_factions())
+        .add_systems(Update, save_factions.run_if(resource_changed::<Factions>));
+}
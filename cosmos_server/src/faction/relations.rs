@@ -0,0 +1,168 @@
+//! Applies faction relations & the server's pvp mode to things in the world
+
+use bevy::ecs::{
+    entity::Entity,
+    query::{Has, With, Without},
+    system::{Query, Res, SystemParam},
+};
+use cosmos_core::{
+    entities::player::Player,
+    faction::{
+        relations::{Allegiance, FactionRelation},
+        Factions,
+    },
+    structure::{ownership::StructureOwner, ship::pilot::Pilot},
+};
+
+use crate::{settings::ServerSettings, universe::spawners::pirate::Pirate};
+
+/// Gets who an entity belongs to.
+///
+/// Players belong to themselves, pirate ships belong to the pirates & other structures belong to their owner (if they have one).
+pub fn allegiance(player: Option<&Player>, owner: Option<&StructureOwner>, is_pirate: bool, factions: &Factions) -> Allegiance {
+    if let Some(player) = player {
+        factions.player_allegiance(player.name())
+    } else if is_pirate {
+        Allegiance::Pirates
+    } else if let Some(owner) = owner {
+        factions.player_allegiance(owner.player_name())
+    } else {
+        Allegiance::Nobody
+    }
+}
+
+#[derive(SystemParam)]
+/// Decides if one entity is allowed to damage another, based on who they belong to & the server's pvp mode
+pub struct DamageRules<'w, 's> {
+    q_allegiance: Query<'w, 's, (Option<&'static Player>, Option<&'static StructureOwner>, Has<Pirate>)>,
+    q_pilot: Query<'w, 's, &'static Pilot, Without<Player>>,
+    q_players: Query<'w, 's, (), With<Player>>,
+    factions: Res<'w, Factions>,
+    settings: Res<'w, ServerSettings>,
+}
+
+impl<'w, 's> DamageRules<'w, 's> {
+    /// Gets who this entity belongs to. Entities that don't exist belong to nobody.
+    pub fn allegiance(&self, entity: Entity) -> Allegiance {
+        self.q_allegiance
+            .get(entity)
+            .map(|(player, owner, is_pirate)| allegiance(player, owner, is_pirate, &self.factions))
+            .unwrap_or(Allegiance::Nobody)
+    }
+
+    /// Gets who is controlling this entity. A ship that is being piloted by a player acts for that player, no matter who owns it.
+    ///
+    /// Ships flown by anything else (such as the pirates' AI pilots) act for whoever the ship itself belongs to.
+    pub fn controller_allegiance(&self, entity: Entity) -> Allegiance {
+        match self.q_pilot.get(entity) {
+            Ok(pilot) if self.q_players.contains(pilot.entity) => self.allegiance(pilot.entity),
+            _ => self.allegiance(entity),
+        }
+    }
+
+    /// The relation between whoever is controlling these two entities
+    pub fn relation(&self, a: Entity, b: Entity) -> FactionRelation {
+        self.factions
+            .allegiance_relation(&self.controller_allegiance(a), &self.controller_allegiance(b))
+    }
+
+    /// Returns true if the `attacker` is allowed to damage the `victim`.
+    ///
+    /// Damage is caused by whoever is controlling the `attacker`, and is done to whoever the `victim` belongs to.
+    /// Damage that wasn't caused by anything (`attacker` is `None`) is always allowed.
+    pub fn can_damage(&self, attacker: Option<Entity>, victim: Entity) -> bool {
+        let Some(attacker) = attacker else {
+            return true;
+        };
+
+        self.factions
+            .can_damage(&self.controller_allegiance(attacker), &self.allegiance(victim), self.settings.pvp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::{system::SystemState, world::World};
+    use bevy_renet::renet::ClientId;
+    use cosmos_core::{
+        entities::player::{game_mode::GameMode, Player},
+        faction::{
+            relations::{FactionRelation, PvpMode},
+            Factions,
+        },
+        structure::{ownership::StructureOwner, ship::pilot::Pilot},
+    };
+
+    use crate::{settings::ServerSettings, universe::spawners::pirate::Pirate};
+
+    use super::DamageRules;
+
+    fn settings(pvp: PvpMode) -> ServerSettings {
+        ServerSettings {
+            ip: None,
+            bind: "0.0.0.0".into(),
+            port: 0,
+            max_clients: 1,
+            password: None,
+            whitelist: None,
+            secure_authentication: false,
+            token_port: 0,
+            motd: String::new(),
+            default_game_mode: GameMode::Survival,
+            starting_credits: 0,
+            starter_kit: vec![],
+            pvp,
+            autosave_interval: 0,
+            world: String::new(),
+            peaceful: false,
+            spawn_asteroids: false,
+            spawn_planets: false,
+            cpu_terrain_generation: false,
+            capture_terrain_golden: None,
+        }
+    }
+
+    #[test]
+    fn pirate_piloted_ships_attack_players() {
+        let mut world = World::new();
+        world.insert_resource(Factions::default());
+        world.insert_resource(settings(PvpMode::Off));
+
+        world.spawn(Player::new("player".into(), ClientId::from_raw(0)));
+        let station = world.spawn(StructureOwner::new("player")).id();
+
+        // The same setup as `add_pirate_ai`
+        let pirate_ship = world.spawn(Pirate).id();
+        let pirate_pilot = world.spawn(Pilot { entity: pirate_ship }).id();
+        world.entity_mut(pirate_ship).insert(Pilot { entity: pirate_pilot });
+
+        let mut state = SystemState::<DamageRules>::new(&mut world);
+        let rules = state.get(&world);
+
+        assert_eq!(rules.relation(pirate_ship, station), FactionRelation::Hostile);
+        assert_eq!(rules.relation(station, pirate_ship), FactionRelation::Hostile);
+        assert!(rules.can_damage(Some(pirate_ship), station));
+    }
+
+    #[test]
+    fn player_piloted_ships_act_for_the_player() {
+        let mut world = World::new();
+        world.insert_resource(Factions::default());
+        world.insert_resource(settings(PvpMode::Off));
+
+        let ship = world.spawn(StructureOwner::new("owner")).id();
+        let player = world
+            .spawn((Player::new("pilot".into(), ClientId::from_raw(0)), Pilot { entity: ship }))
+            .id();
+        world.entity_mut(ship).insert(Pilot { entity: player });
+
+        let own_station = world.spawn(StructureOwner::new("pilot")).id();
+        let owners_station = world.spawn(StructureOwner::new("owner")).id();
+
+        let mut state = SystemState::<DamageRules>::new(&mut world);
+        let rules = state.get(&world);
+
+        assert!(rules.can_damage(Some(ship), own_station));
+        assert!(!rules.can_damage(Some(ship), owners_station));
+    }
+}
@@ -9,37 +9,68 @@ use std::{
 
 use bevy::prelude::*;
 use bevy_renet::renet::{
-    transport::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
+    transport::{generate_random_bytes, NetcodeServerTransport, ServerAuthentication, ServerConfig},
     RenetServer,
 };
 use cosmos_core::netty::{connection_config, get_local_ipaddress, server::ServerLobby, PROTOCOL_ID};
 
-use crate::netty::network_helpers::{ClientTicks, NetworkTick};
+use crate::{
+    netty::{
+        authentication::{start_token_endpoint, PlayerIdentities, ServerAccess},
+        network_helpers::{ClientTicks, NetworkTick},
+    },
+    settings::ServerSettings,
+};
 
 /// Sets up the server & makes it ready to be connected to
-pub fn init(app: &mut App, address: Option<String>) {
-    let port: u16 = 1337;
+///
+/// The [`ServerSettings`] resource must be inserted before this is called.
+pub fn init(app: &mut App) {
+    let settings = app.world.resource::<ServerSettings>();
+
+    let port = settings.port;
 
-    let local_addr = address.unwrap_or(get_local_ipaddress());
+    let local_addr = settings.ip.clone().unwrap_or_else(get_local_ipaddress);
 
-    let public_addr: SocketAddr = format!("{local_addr}:{port}").parse().unwrap();
-    let socket = UdpSocket::bind(format!("0.0.0.0:{port}")).unwrap();
+    let public_addr: SocketAddr = format!("{local_addr}:{port}")
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid server ip {local_addr}:{port} - {e}"));
+    let bind_address = format!("{}:{port}", settings.bind);
+    let socket = UdpSocket::bind(&bind_address).unwrap_or_else(|e| panic!("Unable to bind to {bind_address} - {e}"));
     socket.set_nonblocking(true).expect("Cannot set non-blocking mode!");
 
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
 
+    let access = ServerAccess::new(settings);
+
+    let authentication = if settings.secure_authentication {
+        let private_key = generate_random_bytes();
+
+        let token_address: SocketAddr = format!("{}:{}", settings.bind, settings.token_port)
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid token endpoint address - {e}"));
+
+        start_token_endpoint(token_address, access.clone(), private_key, vec![public_addr]);
+
+        ServerAuthentication::Secure { private_key }
+    } else {
+        ServerAuthentication::Unsecure
+    };
+
     let server_config = ServerConfig {
-        max_clients: 20,
+        max_clients: settings.max_clients,
         protocol_id: PROTOCOL_ID,
         public_addresses: vec![public_addr],
         current_time,
-        authentication: ServerAuthentication::Unsecure,
+        authentication,
     };
 
     let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
     let server = RenetServer::new(connection_config());
 
     app.insert_resource(ServerLobby::default())
+        .insert_resource(access)
+        .insert_resource(PlayerIdentities::load())
         .insert_resource(NetworkTick(0))
         .insert_resource(ClientTicks::default())
         .insert_resource(server)
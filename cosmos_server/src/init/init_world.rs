@@ -10,6 +10,8 @@ use bevy::prelude::*;
 use cosmos_core::netty::cosmos_encoder;
 use serde::{Deserialize, Serialize};
 
+use crate::persistence::{world::world_directory, write_atomically};
+
 #[derive(Debug, Resource, Deref, Serialize, Deserialize, Clone, Copy)]
 /// This sets the seed the server uses to generate the universe
 pub struct ServerSeed(u64);
@@ -66,13 +68,16 @@ impl ReadOnlyNoise {
 }
 
 pub(super) fn register(app: &mut App) {
-    let server_seed = if let Ok(seed) = fs::read("./world/seed.dat") {
-        cosmos_encoder::deserialize::<ServerSeed>(&seed).expect("Unable to understand './world/seed.dat' seed file. Is it corrupted?")
+    let seed_path = format!("{}/seed.dat", world_directory());
+
+    let server_seed = if let Ok(seed) = fs::read(&seed_path) {
+        cosmos_encoder::deserialize::<ServerSeed>(&seed)
+            .unwrap_or_else(|_| panic!("Unable to understand '{seed_path}' seed file. Is it corrupted?"))
     } else {
         let seed = ServerSeed(rand::random());
 
-        fs::create_dir("./world/").expect("Error creating world directory!");
-        fs::write("./world/seed.dat", cosmos_encoder::serialize(&seed)).expect("Error writing file './world/seed.dat'");
+        fs::create_dir_all(world_directory()).expect("Error creating world directory!");
+        write_atomically(&seed_path, cosmos_encoder::serialize(&seed)).unwrap_or_else(|e| panic!("Error writing file '{seed_path}' - {e}"));
 
         seed
     };
@@ -9,6 +9,7 @@ use bevy_renet::renet::RenetServer;
 use cosmos_core::{
     block::data::BlockData,
     entities::player::Player,
+    faction::Factions,
     inventory::{
         netty::{ClientInventoryMessages, InventoryIdentifier, ServerInventoryMessages},
         HeldItemStack, Inventory,
@@ -16,10 +17,13 @@ use cosmos_core::{
     item::Item,
     netty::{cosmos_encoder, server::ServerLobby, NettyChannelClient, NettyChannelServer, NoSendEntity},
     registry::Registry,
-    structure::Structure,
+    structure::{
+        ownership::{StructureOwner, StructurePermission},
+        Structure,
+    },
 };
 
-use crate::state::GameState;
+use crate::{commands::operators::Operators, state::GameState, structure::ownership::check_permission};
 
 fn sync_inventories(
     query: Query<(Entity, &Inventory, Option<&BlockData>), (Changed<Inventory>, Without<NoSendEntity>)>,
@@ -122,6 +126,30 @@ fn get_many_inventories_mut<'a, const N: usize>(
     q_inventory.get_many_mut(ents).ok()
 }
 
+/// Every inventory this message would change
+fn inventories_used(msg: &ClientInventoryMessages) -> Vec<InventoryIdentifier> {
+    match *msg {
+        ClientInventoryMessages::SwapSlots {
+            inventory_a, inventory_b, ..
+        } => vec![inventory_a, inventory_b],
+        ClientInventoryMessages::AutoMove {
+            from_inventory,
+            to_inventory,
+            ..
+        }
+        | ClientInventoryMessages::MoveItemstack {
+            from_inventory,
+            to_inventory,
+            ..
+        } => vec![from_inventory, to_inventory],
+        ClientInventoryMessages::PickupItemstack { inventory_holder, .. }
+        | ClientInventoryMessages::DepositHeldItemstack { inventory_holder, .. }
+        | ClientInventoryMessages::DepositAndSwapHeldItemstack { inventory_holder, .. }
+        | ClientInventoryMessages::InsertHeldItem { inventory_holder, .. } => vec![inventory_holder],
+        ClientInventoryMessages::ThrowHeldItemstack { .. } => vec![],
+    }
+}
+
 fn listen(
     mut commands: Commands,
     mut q_inventory: Query<&mut Inventory>,
@@ -130,6 +158,7 @@ fn listen(
     mut server: ResMut<RenetServer>,
     lobby: Res<ServerLobby>,
     items: Res<Registry<Item>>,
+    (q_player, q_owner, factions, operators): (Query<&Player>, Query<&StructureOwner>, Res<Factions>, Res<Operators>),
 ) {
     for client_id in server.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, NettyChannelClient::Inventory) {
@@ -137,9 +166,31 @@ fn listen(
                 continue;
             };
 
+            let Ok(player) = q_player.get(client_entity) else {
+                continue;
+            };
+
             let msg: ClientInventoryMessages =
                 cosmos_encoder::deserialize(&message).expect("Failed to deserialize server inventory message!");
 
+            // Players can only use their own inventory, and storage blocks on structures they have access to
+            let has_access = inventories_used(&msg).into_iter().all(|inventory| match inventory {
+                InventoryIdentifier::Entity(entity) => entity == client_entity,
+                InventoryIdentifier::BlockData(block_data) => check_permission(
+                    q_owner.get(block_data.structure_entity).ok(),
+                    player.name(),
+                    client_id,
+                    StructurePermission::AccessStorage,
+                    &factions,
+                    &operators,
+                    &mut server,
+                ),
+            });
+
+            if !has_access {
+                continue;
+            }
+
             match msg {
                 ClientInventoryMessages::SwapSlots {
                     slot_a,
@@ -234,8 +285,6 @@ fn listen(
                         continue;
                     }
 
-                    // TODO: Check if has access to inventory
-
                     if let Some(mut inventory) = get_inventory_mut(inventory_holder, &mut q_inventory, &q_structure) {
                         if let Some(is) = inventory.mut_itemstack_at(slot) {
                             let quantity = quantity.min(is.quantity());
@@ -271,8 +320,6 @@ fn listen(
                         continue;
                     };
 
-                    // TODO: Check if has access to inventory
-
                     if let Some(mut inventory) = get_inventory_mut(inventory_holder, &mut q_inventory, &q_structure) {
                         let quantity = quantity.min(held_is.quantity()); // make sure we don't deposit more than we have
                         let unused_quantity = held_is.quantity() - quantity;
@@ -299,8 +346,6 @@ fn listen(
                         continue;
                     };
 
-                    // TODO: Check if has access to inventory
-
                     if let Some(mut inventory) = get_inventory_mut(inventory_holder, &mut q_inventory, &q_structure) {
                         let itemstack_here = inventory.remove_itemstack_at(slot);
 
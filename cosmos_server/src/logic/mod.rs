@@ -0,0 +1,148 @@
+//! Runs the logic circuits on every structure.
+//!
+//! See [`cosmos_core::logic`] for how signals move through a circuit.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        change_detection::DetectChangesMut,
+        entity::Entity,
+        event::EventReader,
+        schedule::{common_conditions::in_state, IntoSystemConfigs, OnEnter},
+        system::{Commands, Query, Res, ResMut},
+    },
+    time::common_conditions::on_timer,
+};
+use cosmos_core::{
+    block::{block_events::BlockInteractEvent, Block},
+    events::block_events::BlockChangedEvent,
+    logic::{LogicBlock, LogicBlockKind, LogicCircuit, LogicSignals},
+    registry::Registry,
+    structure::{events::StructureLoadedEvent, loading::StructureLoadingSet, Structure},
+};
+
+use crate::state::GameState;
+
+/// How often every logic circuit moves its signals forward one step
+const LOGIC_TICK_RATE: Duration = Duration::from_millis(100);
+
+fn register_logic_blocks(blocks: Res<Registry<Block>>, mut logic_blocks: ResMut<Registry<LogicBlock>>) {
+    for (unlocalized_name, kind) in [
+        ("cosmos:logic_wire", LogicBlockKind::Wire),
+        ("cosmos:logic_on", LogicBlockKind::On),
+        ("cosmos:logic_button", LogicBlockKind::Button),
+        ("cosmos:logic_and", LogicBlockKind::And),
+        ("cosmos:logic_or", LogicBlockKind::Or),
+        ("cosmos:logic_not", LogicBlockKind::Not),
+        ("cosmos:logic_delay", LogicBlockKind::Delay),
+    ] {
+        if let Some(block) = blocks.from_id(unlocalized_name) {
+            logic_blocks.insert(block, kind);
+        }
+    }
+
+    let Some(logic_wire) = blocks.from_id("cosmos:logic_wire") else {
+        return;
+    };
+
+    // Everything else wires connect to (the "cosmos:uses_logic" group) reacts to the signals going into it
+    for block in blocks.iter() {
+        if logic_wire.should_connect_with(block) && logic_blocks.from_block(block).is_none() {
+            logic_blocks.insert(block, LogicBlockKind::Receiver);
+        }
+    }
+}
+
+fn logic_structure_loaded_event_processor(
+    mut event_reader: EventReader<StructureLoadedEvent>,
+    q_structure: Query<&Structure>,
+    blocks: Res<Registry<Block>>,
+    logic_blocks: Res<Registry<LogicBlock>>,
+    mut commands: Commands,
+) {
+    for ev in event_reader.read() {
+        let Ok(structure) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        let mut circuit = LogicCircuit::default();
+
+        for structure_block in structure.all_blocks_iter(false) {
+            if let Some(logic_block) = logic_blocks.from_block(structure_block.block(structure, &blocks)) {
+                let front = structure.block_rotation(structure_block.coords()).local_front();
+
+                circuit.add_block(structure_block.coords(), logic_block.kind, front);
+            }
+        }
+
+        commands.entity(ev.structure_entity).insert(circuit);
+    }
+}
+
+fn logic_block_update_system(
+    mut event_reader: EventReader<BlockChangedEvent>,
+    mut q_circuit: Query<&mut LogicCircuit>,
+    blocks: Res<Registry<Block>>,
+    logic_blocks: Res<Registry<LogicBlock>>,
+) {
+    for ev in event_reader.read() {
+        let Ok(mut circuit) = q_circuit.get_mut(ev.structure_entity) else {
+            continue;
+        };
+
+        circuit.remove_block(ev.block.coords());
+
+        if let Some(logic_block) = logic_blocks.from_block(blocks.from_numeric_id(ev.new_block)) {
+            circuit.add_block(ev.block.coords(), logic_block.kind, ev.new_block_rotation.local_front());
+        }
+    }
+}
+
+fn press_buttons(mut interact_events: EventReader<BlockInteractEvent>, mut q_circuit: Query<&mut LogicCircuit>) {
+    for ev in interact_events.read() {
+        if let Ok(mut circuit) = q_circuit.get_mut(ev.structure_entity) {
+            circuit.press_button(ev.structure_block.coords());
+        }
+    }
+}
+
+fn tick_logic(mut commands: Commands, mut q_circuit: Query<(Entity, &mut LogicCircuit, Option<&mut LogicSignals>)>) {
+    for (entity, mut circuit, signals) in q_circuit.iter_mut() {
+        if circuit.is_empty() && signals.is_none() {
+            continue;
+        }
+
+        let new_signals = circuit.tick();
+
+        match signals {
+            Some(_) if new_signals.is_empty() => {
+                commands.entity(entity).remove::<LogicSignals>();
+            }
+            Some(mut signals) => {
+                signals.set_if_neq(new_signals);
+            }
+            None if !new_signals.is_empty() => {
+                commands.entity(entity).insert(new_signals);
+            }
+            None => {}
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(OnEnter(GameState::PostLoading), register_logic_blocks).add_systems(
+        Update,
+        (
+            logic_structure_loaded_event_processor.in_set(StructureLoadingSet::StructureLoaded),
+            (
+                logic_block_update_system,
+                press_buttons,
+                tick_logic.run_if(on_timer(LOGIC_TICK_RATE)),
+            )
+                .chain(),
+        )
+            .run_if(in_state(GameState::Playing)),
+    );
+}
@@ -7,7 +7,11 @@
 #![feature(iterator_try_collect)]
 #![warn(missing_docs)]
 
-use bevy::{core::TaskPoolThreadAssignmentPolicy, prelude::*};
+use bevy::{
+    core::TaskPoolThreadAssignmentPolicy,
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+};
 use bevy_mod_debugdump::schedule_graph;
 use bevy_rapier3d::prelude::{RapierConfiguration, TimestepMode};
 use bevy_renet::{transport::NetcodeServerPlugin, RenetServerPlugin};
@@ -23,11 +27,15 @@ use bevy::log::LogPlugin;
 
 pub mod ai;
 pub mod blocks;
+pub mod chat;
 pub mod commands;
+pub mod crafting;
 pub mod entities;
 pub mod events;
+pub mod faction;
 pub mod init;
 pub mod inventory;
+pub mod logic;
 pub mod netty;
 pub mod persistence;
 pub mod physics;
@@ -37,6 +45,7 @@ pub mod registry;
 pub mod rng;
 pub mod settings;
 pub mod shop;
+pub mod shutdown;
 pub mod state;
 pub mod structure;
 pub mod universe;
@@ -52,7 +61,9 @@ fn main() {
 
     let server_settings = read_server_settings();
 
-    let ip = server_settings.ip.clone();
+    if let Err(e) = persistence::world::open_world(&server_settings.world) {
+        panic!("Unable to open world '{}' - {e}", server_settings.world);
+    }
 
     let mut app = App::new();
 
@@ -67,7 +78,20 @@ fn main() {
                 ..Default::default()
             },
         })
-        .set(ImagePlugin::default_nearest());
+        .set(ImagePlugin::default_nearest())
+        .set(if server_settings.cpu_terrain_generation {
+            // The GPU is only used for terrain generation, so don't require one if that's being done on the CPU
+            RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            }
+        } else {
+            RenderPlugin::default()
+        });
 
     #[cfg(feature = "print-schedule")]
     let default_plugins = default_plugins.disable::<LogPlugin>();
@@ -85,6 +109,8 @@ fn main() {
             ..default()
         })
         .add_plugins(default_plugins)
+        // Inserted before the server plugin, since it is set up based on these settings
+        .insert_resource(server_settings)
         .add_plugins(CosmosCorePluginGroup::new(
             GameState::PreLoading,
             GameState::Loading,
@@ -92,8 +118,7 @@ fn main() {
             GameState::Playing,
             GameState::Playing,
         ))
-        .add_plugins((RenetServerPlugin, NetcodeServerPlugin, ServerPlugin { ip }))
-        .insert_resource(server_settings);
+        .add_plugins((RenetServerPlugin, NetcodeServerPlugin, ServerPlugin));
 
     if cfg!(feature = "print-schedule") {
         println!(
@@ -0,0 +1,350 @@
+//! Decides who is allowed to join the server.
+//!
+//! Every connecting player is checked against the server's password & whitelist. If secure authentication is enabled,
+//! players must first get a connect token from the token endpoint, which does these checks before giving one out.
+//! The token endpoint proves who it is with a key saved in `settings/token_endpoint.key`, which clients remember.
+//!
+//! Players prove they own their name with their [`IdentityKey`]. The first player to join with a name claims it, and the
+//! claims are saved with the world so nobody else can join as them (and use their operator status or structures) later.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bevy::{
+    ecs::system::Resource,
+    log::{error, info, warn},
+};
+use bevy_renet::renet::transport::{ConnectToken, NETCODE_KEY_BYTES};
+use cosmos_core::netty::{
+    connection::{
+        read_token_message, write_token_message, ConnectionUserData, IdentityKey, TokenChannel, TokenEndpointSecret, TokenRequest,
+        TokenResponse,
+    },
+    PROTOCOL_ID,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persistence::{world::world_directory, write_atomically},
+    settings::ServerSettings,
+};
+
+/// How long a client has to use their connect token before it expires
+const TOKEN_EXPIRE_SECONDS: u64 = 300;
+/// How long a connection can go without hearing from the other side before it times out
+const CONNECTION_TIMEOUT_SECONDS: i32 = 15;
+/// Token requests that take longer than this to send/receive are dropped
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many token requests can be handled at once
+const TOKEN_REQUEST_THREADS: usize = 4;
+/// How many token requests can be waiting to be handled. Any more than this are dropped.
+const MAX_QUEUED_TOKEN_REQUESTS: usize = 32;
+/// Where the token endpoint's secret key is saved
+const TOKEN_ENDPOINT_KEY_FILE: &str = "settings/token_endpoint.key";
+
+#[derive(Resource, Debug, Clone)]
+/// The rules for who is allowed to join the server
+pub struct ServerAccess {
+    password: Option<String>,
+    whitelist: Option<Vec<String>>,
+    secure_authentication: bool,
+}
+
+impl ServerAccess {
+    /// Creates the access rules from the server's settings
+    pub fn new(settings: &ServerSettings) -> Self {
+        Self {
+            password: settings.password.clone(),
+            whitelist: settings.whitelist.clone(),
+            secure_authentication: settings.secure_authentication,
+        }
+    }
+
+    /// Returns true if players must get a connect token from the token endpoint before joining
+    pub fn secure_authentication(&self) -> bool {
+        self.secure_authentication
+    }
+
+    fn check(&self, name: &str, password: Option<&str>) -> Result<(), String> {
+        if let Some(whitelist) = &self.whitelist {
+            if !whitelist.iter().any(|x| x == name) {
+                return Err(format!("{name} is not whitelisted on this server."));
+            }
+        }
+
+        if let Some(server_password) = &self.password {
+            if password != Some(server_password.as_str()) {
+                return Err("Incorrect password.".into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a client that just connected is allowed to join.
+    ///
+    /// When using secure authentication, the password was already checked before their connect token was given out.
+    pub fn check_connection(&self, user_data: &ConnectionUserData) -> Result<(), String> {
+        if self.secure_authentication {
+            self.check(&user_data.name, self.password.as_deref())
+        } else {
+            self.check(&user_data.name, user_data.password.as_deref())
+        }
+    }
+}
+
+fn identities_file() -> String {
+    format!("{}/identities.json", world_directory())
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+/// The [`IdentityKey`] of every player that has joined this world, by name
+pub struct PlayerIdentities(HashMap<String, IdentityKey>);
+
+impl PlayerIdentities {
+    /// Checks that this key belongs to the player with this name.
+    ///
+    /// If nobody has joined with this name yet, they now own it & the identities are saved.
+    pub fn verify(&mut self, name: &str, identity_key: &IdentityKey) -> Result<(), String> {
+        match self.0.get(name) {
+            Some(key) => {
+                // Compare every byte, so how long this takes doesn't reveal how much of the key was right
+                let difference = key.iter().zip(identity_key).fold(0, |acc, (a, b)| acc | (a ^ b));
+
+                if difference == 0 {
+                    Ok(())
+                } else {
+                    Err(format!("The name {name} belongs to another player on this server."))
+                }
+            }
+            None => {
+                self.0.insert(name.into(), *identity_key);
+                self.save();
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Loads the identities saved with the world
+    pub fn load() -> Self {
+        let path = identities_file();
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|e| panic!("Invalid identities file ({path}) - {e}"))
+    }
+
+    fn save(&self) {
+        _ = fs::create_dir_all(world_directory());
+
+        let json = serde_json::to_string_pretty(self).expect("Unable to serialize player identities");
+
+        if let Err(e) = write_atomically(identities_file(), json) {
+            error!("Unable to save player identities - {e}");
+        }
+    }
+}
+
+/// Everything needed to hand out connect tokens
+struct TokenIssuer {
+    access: ServerAccess,
+    private_key: [u8; NETCODE_KEY_BYTES],
+    public_addresses: Vec<SocketAddr>,
+    endpoint_secret: TokenEndpointSecret,
+}
+
+/// A token request's stream, which fails once the whole request has taken too long rather than only when a single read does.
+///
+/// Otherwise a client could hold onto one of the token endpoint's threads forever by sending one byte at a time.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    fn time_left(&self) -> io::Result<Duration> {
+        self.deadline
+            .checked_duration_since(Instant::now())
+            .filter(|x| !x.is_zero())
+            .ok_or_else(|| io::ErrorKind::TimedOut.into())
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.time_left()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.time_left()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl TokenIssuer {
+    fn issue_token(&self, request: TokenRequest) -> TokenResponse {
+        if let Err(reason) = self.access.check(&request.name, request.password.as_deref()) {
+            info!("Denied connect token to {} - {reason}", request.name);
+            return TokenResponse::Denied(reason);
+        }
+
+        // The token has already checked the password, so there's no need to send it again
+        // The version & identity are checked once they connect, so they can be checked the same way as unsecure connections
+        let Some(user_data) = (ConnectionUserData {
+            name: request.name.clone(),
+            password: None,
+            identity_key: request.identity_key,
+            version: request.version,
+        })
+        .to_user_data() else {
+            return TokenResponse::Denied("Name is too long.".into());
+        };
+
+        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+
+        let token = match ConnectToken::generate(
+            current_time,
+            PROTOCOL_ID,
+            TOKEN_EXPIRE_SECONDS,
+            rand::random::<u64>(),
+            CONNECTION_TIMEOUT_SECONDS,
+            self.public_addresses.clone(),
+            Some(&user_data),
+            &self.private_key,
+        ) {
+            Ok(token) => token,
+            Err(e) => {
+                warn!("Unable to generate connect token - {e:?}");
+                return TokenResponse::Denied("Unable to generate connect token.".into());
+            }
+        };
+
+        let mut token_bytes = vec![];
+        if let Err(e) = token.write(&mut token_bytes) {
+            warn!("Unable to write connect token - {e}");
+            return TokenResponse::Denied("Unable to generate connect token.".into());
+        }
+
+        info!("Gave connect token to {}", request.name);
+
+        TokenResponse::Token(token_bytes)
+    }
+
+    fn handle_request(&self, stream: TcpStream) -> bincode::Result<()> {
+        let mut stream = DeadlineStream {
+            stream,
+            deadline: Instant::now() + TOKEN_REQUEST_TIMEOUT,
+        };
+
+        write_token_message(&mut stream, &self.endpoint_secret.public_key())?;
+        let client_key = read_token_message::<[u8; 32]>(&mut stream)?;
+
+        let channel = TokenChannel::server(&self.endpoint_secret, &client_key);
+
+        let request = channel.read::<TokenRequest>(&mut stream)?;
+        let response = self.issue_token(request);
+
+        channel.write(&mut stream, &response)
+    }
+}
+
+/// Loads the token endpoint's secret key, or creates one if this server doesn't have one yet.
+///
+/// This must stay the same between restarts, otherwise clients that joined before will refuse to connect.
+fn load_endpoint_secret() -> TokenEndpointSecret {
+    if let Ok(bytes) = fs::read(TOKEN_ENDPOINT_KEY_FILE) {
+        let bytes = <[u8; 32]>::try_from(bytes).unwrap_or_else(|_| panic!("Invalid token endpoint key file ({TOKEN_ENDPOINT_KEY_FILE})"));
+
+        return TokenEndpointSecret::from_bytes(bytes);
+    }
+
+    let secret = TokenEndpointSecret::generate();
+
+    _ = fs::create_dir_all("settings");
+    if let Err(e) = write_atomically(TOKEN_ENDPOINT_KEY_FILE, secret.to_bytes()) {
+        panic!("Unable to save token endpoint key ({TOKEN_ENDPOINT_KEY_FILE}) - {e}");
+    }
+
+    secret
+}
+
+/// Starts the TCP endpoint that gives connect tokens to players allowed to join.
+///
+/// This runs on its own threads for as long as the server is running. Only a few requests are handled at once,
+/// so a flood of connections can't use up the server's threads.
+pub(crate) fn start_token_endpoint(
+    bind_address: SocketAddr,
+    access: ServerAccess,
+    private_key: [u8; NETCODE_KEY_BYTES],
+    public_addresses: Vec<SocketAddr>,
+) {
+    let listener = TcpListener::bind(bind_address).unwrap_or_else(|e| panic!("Unable to start token endpoint on {bind_address} - {e}"));
+
+    let issuer = Arc::new(TokenIssuer {
+        access,
+        private_key,
+        public_addresses,
+        endpoint_secret: load_endpoint_secret(),
+    });
+
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(MAX_QUEUED_TOKEN_REQUESTS);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for i in 0..TOKEN_REQUEST_THREADS {
+        let issuer = issuer.clone();
+        let receiver = receiver.clone();
+
+        thread::Builder::new()
+            .name(format!("Token endpoint worker {i}"))
+            .spawn(move || loop {
+                let Ok(stream) = receiver.lock().expect("Token request queue poisoned").recv() else {
+                    return;
+                };
+
+                if let Err(e) = issuer.handle_request(stream) {
+                    warn!("Invalid connect token request - {e}");
+                }
+            })
+            .expect("Unable to spawn token endpoint worker thread");
+    }
+
+    thread::Builder::new()
+        .name("Token endpoint".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+
+                match sender.try_send(stream) {
+                    Ok(()) => {}
+                    // Dropping the stream closes it, and the client can try again later
+                    Err(TrySendError::Full(_)) => warn!("Too many connect token requests - dropping one."),
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+        })
+        .expect("Unable to spawn token endpoint thread");
+
+    info!("Token endpoint running on {bind_address}");
+}
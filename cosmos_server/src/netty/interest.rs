@@ -0,0 +1,59 @@
+//! Figures out which players have which structures & chunks loaded, so updates are only sent to those that care about them.
+//!
+//! Players that come into range later don't need to be caught up here - they will request the structure's
+//! chunks once they load it, and those are always sent in their current state.
+
+use bevy::prelude::Vec3;
+use cosmos_core::{
+    persistence::LoadingDistance,
+    physics::location::{Location, SectorUnit},
+    structure::{
+        coordinates::{BlockCoordinate, ChunkCoordinate, UnboundChunkCoordinate, UnboundCoordinateType},
+        planet::MAX_CHUNK_RENDER_DISTANCE,
+        Structure,
+    },
+};
+
+/// Returns true if a player at this location would have this structure loaded.
+///
+/// Clients only unload structures once they are past the structure's unload distance, so this uses that instead of
+/// the load distance.
+pub fn has_structure_loaded(player_location: &Location, structure_location: &Location, loading_distance: Option<&LoadingDistance>) -> bool {
+    let unload_distance = loading_distance.copied().unwrap_or_default().unload_distance() as SectorUnit;
+
+    (structure_location.sector() - player_location.sector()).abs().max_element() <= unload_distance
+}
+
+/// Returns true if a player at this location would have the chunk containing this block loaded.
+///
+/// Full structures always have every chunk loaded, but clients only load the chunks of dynamic structures (planets)
+/// that are near them.
+pub fn has_block_loaded(
+    player_location: &Location,
+    structure: &Structure,
+    structure_location: &Location,
+    loading_distance: Option<&LoadingDistance>,
+    block: BlockCoordinate,
+) -> bool {
+    if !has_structure_loaded(player_location, structure_location, loading_distance) {
+        return false;
+    }
+
+    let Structure::Dynamic(_) = structure else {
+        return true;
+    };
+
+    let relative_position: Vec3 = (*player_location - *structure_location).into();
+    let player_coords = structure.relative_coords_to_local_coords(relative_position.x, relative_position.y, relative_position.z);
+    let player_chunk = UnboundChunkCoordinate::for_unbound_block_coordinate(player_coords);
+
+    let chunk = ChunkCoordinate::for_block_coordinate(block);
+
+    // Clients unload chunks one past their render distance. Sending updates for a chunk a client doesn't have is
+    // harmless, but not sending them for one it does have leaves it out of date.
+    let rd = MAX_CHUNK_RENDER_DISTANCE + 1;
+
+    (chunk.x as UnboundCoordinateType - player_chunk.x).abs() <= rd
+        && (chunk.y as UnboundCoordinateType - player_chunk.y).abs() <= rd
+        && (chunk.z as UnboundCoordinateType - player_chunk.z).abs() <= rd
+}
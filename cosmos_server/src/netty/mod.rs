@@ -5,6 +5,8 @@ use cosmos_core::netty::sync::SyncedComponentId;
 
 use crate::registry::sync_registry;
 
+pub mod authentication;
+pub mod interest;
 pub mod network_helpers;
 pub mod server_listener;
 pub mod sync;
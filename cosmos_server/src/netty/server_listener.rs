@@ -7,6 +7,8 @@ use bevy::utils::HashMap;
 use bevy_rapier3d::prelude::Velocity;
 use bevy_renet::renet::{ClientId, RenetServer};
 use cosmos_core::block::block_events::{BlockBreakEvent, BlockInteractEvent, BlockPlaceEvent};
+use cosmos_core::block::Block;
+use cosmos_core::faction::Factions;
 use cosmos_core::inventory::Inventory;
 use cosmos_core::item::Item;
 use cosmos_core::netty::netty_rigidbody::NettyRigidBodyLocation;
@@ -15,8 +17,10 @@ use cosmos_core::netty::sync::server_entity_syncing::RequestedEntityEvent;
 use cosmos_core::netty::system_sets::NetworkingSystemsSet;
 use cosmos_core::netty::{cosmos_encoder, NettyChannelClient, NettyChannelServer};
 use cosmos_core::physics::location::Location;
+use cosmos_core::registry::identifiable::Identifiable;
 use cosmos_core::registry::Registry;
 use cosmos_core::structure::loading::ChunksNeedLoaded;
+use cosmos_core::structure::ownership::{StructureOwner, StructurePermission};
 use cosmos_core::structure::shared::build_mode::{BuildMode, ExitBuildModeEvent};
 use cosmos_core::structure::systems::StructureSystems;
 use cosmos_core::{
@@ -29,7 +33,9 @@ use cosmos_core::{
     structure::{ship::pilot::Pilot, Structure},
 };
 
+use crate::commands::operators::Operators;
 use crate::entities::player::PlayerLooking;
+use crate::structure::ownership::check_permission;
 use crate::structure::planet::chunk::ChunkNeedsSent;
 use crate::structure::planet::generation::planet_generator::RequestChunkEvent;
 use crate::structure::ship::events::{CreateShipEvent, ShipSetMovementEvent};
@@ -76,6 +82,13 @@ fn server_listen_messages(
     mut build_mode: Query<&mut BuildMode>,
 
     mut send_all_chunks: ResMut<SendAllChunks>,
+    (q_player, q_owner, blocks, factions, operators): (
+        Query<&Player>,
+        Query<&StructureOwner>,
+        Res<Registry<Block>>,
+        Res<Factions>,
+        Res<Operators>,
+    ),
 ) {
     for client_id in server.clients_id().into_iter() {
         while let Some(message) = server.receive_message(client_id, NettyChannelClient::Unreliable) {
@@ -130,6 +143,24 @@ fn server_listen_messages(
                 break;
             };
 
+            let player_name = lobby
+                .player_from_id(client_id)
+                .and_then(|x| q_player.get(x).ok())
+                .map(|x| x.name().as_str())
+                .unwrap_or_default();
+
+            let has_permission = |structure_entity: Entity, permission: StructurePermission, server: &mut RenetServer| {
+                check_permission(
+                    q_owner.get(structure_entity).ok(),
+                    player_name,
+                    client_id,
+                    permission,
+                    &factions,
+                    &operators,
+                    server,
+                )
+            };
+
             match command {
                 ClientReliableMessages::SendAllChunks { server_entity } => {
                     let Ok(structure) = structure_query.get(server_entity) else {
@@ -154,6 +185,10 @@ fn server_listen_messages(
                     });
                 }
                 ClientReliableMessages::BreakBlock { structure_entity, block } => {
+                    if !has_permission(structure_entity, StructurePermission::Build, &mut server) {
+                        continue;
+                    }
+
                     if let Some(player_entity) = lobby.player_from_id(client_id) {
                         break_block_event.send(BlockBreakEvent {
                             structure_entity,
@@ -169,6 +204,10 @@ fn server_listen_messages(
                     block_rotation: block_up,
                     inventory_slot,
                 } => {
+                    if !has_permission(structure_entity, StructurePermission::Build, &mut server) {
+                        continue;
+                    }
+
                     if let Some(player_entity) = lobby.player_from_id(client_id) {
                         place_block_event.send(BlockPlaceEvent {
                             structure_entity,
@@ -181,6 +220,22 @@ fn server_listen_messages(
                     }
                 }
                 ClientReliableMessages::InteractWithBlock { structure_entity, block } => {
+                    let Ok(structure) = structure_query.get(structure_entity) else {
+                        continue;
+                    };
+
+                    let permission = match block.block(structure, &blocks).unlocalized_name() {
+                        "cosmos:ship_core" => Some(StructurePermission::Pilot),
+                        "cosmos:build_block" | "cosmos:turret" => Some(StructurePermission::Build),
+                        "cosmos:storage" => Some(StructurePermission::AccessStorage),
+                        "cosmos:fabricator" => Some(StructurePermission::Craft),
+                        _ => None,
+                    };
+
+                    if permission.is_some_and(|permission| !has_permission(structure_entity, permission, &mut server)) {
+                        continue;
+                    }
+
                     block_interact_event.send(BlockInteractEvent {
                         structure_entity,
                         structure_block: block,
@@ -213,6 +268,7 @@ fn server_listen_messages(
                         create_ship_event_writer.send(CreateShipEvent {
                             ship_location,
                             rotation: looking.rotation,
+                            owner: StructureOwner::new(player_name),
                         });
                     }
                 }
@@ -225,6 +281,7 @@ fn server_listen_messages(
                             create_station_event_writer.send(CreateStationEvent {
                                 station_location,
                                 rotation: looking.rotation,
+                                owner: StructureOwner::new(player_name),
                             });
                         }
                     }
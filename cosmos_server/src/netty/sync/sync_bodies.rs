@@ -1,14 +1,17 @@
 //! Handles the syncing of entity's rigidbodies + velocities
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_rapier3d::prelude::Velocity;
-use bevy_renet::renet::RenetServer;
+use bevy_renet::renet::{ClientId, RenetServer};
 use cosmos_core::{
     ecs::{despawn_needed, NeedsDespawned},
     entities::player::{render_distance::RenderDistance, Player},
     netty::{
         cosmos_encoder,
-        netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation},
+        netty_rigidbody::{NettyRigidBody, NettyRigidBodyLocation, QuantizedRigidBody},
         server_reliable_messages::ServerReliableMessages,
         server_unreliable_messages::ServerUnreliableMessages,
         sync::server_entity_syncing::RequestedEntityEvent,
@@ -27,41 +30,138 @@ use crate::netty::network_helpers::NetworkTick;
 /// This only works if the entity is despawned via the `NeedsDespawned` component.
 pub struct DontNotifyClientOfDespawn;
 
+/// The most bytes of rigidbody data that will be sent to a single client each tick
+const BYTES_PER_CLIENT_PER_TICK: u64 = 4_000;
+/// The packet size can only be so big, so bodies are split into packets of about this many bytes
+const MAX_PACKET_BYTES: u64 = 1_000;
+/// Bodies are sent unreliably, so even unchanged bodies are resent this often (in seconds) in case the client missed them
+const REFRESH_INTERVAL: f32 = 1.0;
+/// Bodies this far away (in blocks) are half as important as bodies right next to the player
+const PRIORITY_DISTANCE: f32 = 500.0;
+/// Bodies moving this fast (in blocks per second) are twice as important as still bodies
+const PRIORITY_SPEED: f32 = 20.0;
+
+#[derive(Default)]
+/// What a client was last sent about a body
+struct SentBody {
+    /// `None` if the client hasn't been sent this body since it came into range
+    body: Option<QuantizedRigidBody>,
+    seconds_since_sent: f32,
+    /// Grows every tick the body needs sent but isn't, so nothing is starved of updates
+    priority: f32,
+}
+
+#[derive(Resource, Default)]
+/// Keeps track of what bodies every client has been sent, so only changed bodies need to be sent again
+struct ClientBodies(HashMap<ClientId, HashMap<Entity, SentBody>>);
+
+/// A body that may need to be sent to players
+struct SyncedBody {
+    entity: Entity,
+    body: QuantizedRigidBody,
+    location: Location,
+    loading_distance: LoadingDistance,
+    speed: f32,
+}
+
 /// Sends bodies to players only if it's within their render distance.
+///
+/// Bodies that haven't changed since the player was last sent them are skipped, and the rest are sent in order of
+/// importance (near & fast bodies first) until the player's byte budget is used up for this tick.
 fn send_bodies(
-    players: &Query<(&Player, &RenderDistance, &Location)>,
-    bodies: &[(Entity, NettyRigidBody, Location, LoadingDistance)],
+    player: &Player,
+    player_location: &Location,
+    sent_bodies: &mut HashMap<Entity, SentBody>,
+    bodies: &[SyncedBody],
+    delta_seconds: f32,
     server: &mut RenetServer,
     tick: &NetworkTick,
 ) {
-    for (player, _, loc) in players.iter() {
-        let players_bodies: Vec<(Entity, NettyRigidBody)> = bodies
-            .iter()
-            .filter(|(_, _, location, loading_distance)| {
-                location.relative_coords_to(loc).abs().max_element() < loading_distance.load_block_distance()
-            })
-            .map(|(ent, net_rb, _, _)| (*ent, *net_rb))
-            .collect();
-
-        if !players_bodies.is_empty() {
-            let sync_message = ServerUnreliableMessages::BulkBodies {
-                time_stamp: tick.0,
-                bodies: players_bodies,
-            };
-
-            let message = cosmos_encoder::serialize(&sync_message);
-
-            server.send_message(player.id(), NettyChannelServer::Unreliable, message.clone());
+    let mut in_range = HashSet::new();
+    let mut pending = vec![];
+
+    for (idx, synced) in bodies.iter().enumerate() {
+        let relative = synced.location.relative_coords_to(player_location);
+
+        if relative.abs().max_element() >= synced.loading_distance.load_block_distance() {
+            continue;
+        }
+
+        in_range.insert(synced.entity);
+
+        let sent = sent_bodies.entry(synced.entity).or_default();
+        sent.seconds_since_sent += delta_seconds;
+
+        let needs_sent = match &sent.body {
+            Some(last) => synced.body.differs_from(last) || sent.seconds_since_sent >= REFRESH_INTERVAL,
+            None => true,
+        };
+
+        if needs_sent {
+            sent.priority += (1.0 + synced.speed / PRIORITY_SPEED) / (1.0 + relative.length() / PRIORITY_DISTANCE);
+            pending.push((idx, sent.priority));
+        }
+    }
+
+    // Bodies that left the player's range are sent in full once they come back
+    sent_bodies.retain(|entity, _| in_range.contains(entity));
+
+    pending.sort_unstable_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let mut bytes_used = 0;
+    let mut packet_bytes = 0;
+    let mut packet = vec![];
+
+    for (idx, _) in pending {
+        let synced = &bodies[idx];
+        let entry = (synced.entity, synced.body);
+
+        let size = bincode::serialized_size(&entry).expect("Rigidbodies are always serializable");
+        if bytes_used + size > BYTES_PER_CLIENT_PER_TICK {
+            break;
+        }
+
+        if packet_bytes + size > MAX_PACKET_BYTES && !packet.is_empty() {
+            send_packet(player, std::mem::take(&mut packet), server, tick);
+            packet_bytes = 0;
         }
+
+        bytes_used += size;
+        packet_bytes += size;
+        packet.push(entry);
+
+        let sent = sent_bodies.get_mut(&synced.entity).expect("Inserted above");
+        sent.body = Some(synced.body);
+        sent.seconds_since_sent = 0.0;
+        sent.priority = 0.0;
+    }
+
+    if !packet.is_empty() {
+        send_packet(player, packet, server, tick);
     }
 }
 
+fn send_packet(player: &Player, bodies: Vec<(Entity, QuantizedRigidBody)>, server: &mut RenetServer, tick: &NetworkTick) {
+    let sync_message = ServerUnreliableMessages::BulkBodies {
+        time_stamp: tick.0,
+        bodies,
+    };
+
+    server.send_message(
+        player.id(),
+        NettyChannelServer::Unreliable,
+        cosmos_encoder::serialize(&sync_message),
+    );
+}
+
 fn server_sync_bodies(
     mut server: ResMut<RenetServer>,
     mut tick: ResMut<NetworkTick>,
+    time: Res<Time>,
+    mut client_bodies: ResMut<ClientBodies>,
     location_query: Query<&Location>,
     entities: Query<(Entity, &Transform, &Location, Option<&Velocity>, &LoadingDistance, Option<&Parent>), Without<NoSendEntity>>,
-    players: Query<(&Player, &RenderDistance, &Location)>,
+    players: Query<(&Player, &Location), With<RenderDistance>>,
     // Often children will not have locations or loading distances, but still need to by synced
     // q_children_need_synced: Query<
     //     (Entity, Option<&Velocity>, &Transform, &Parent),
@@ -72,12 +172,11 @@ fn server_sync_bodies(
 ) {
     tick.0 += 1;
 
-    let mut bodies = Vec::with_capacity(20);
-
-    for (entity, transform, location, velocity, unload_distance, parent) in entities.iter() {
-        bodies.push((
+    let bodies = entities
+        .iter()
+        .map(|(entity, transform, location, velocity, loading_distance, parent)| SyncedBody {
             entity,
-            NettyRigidBody::new(
+            body: QuantizedRigidBody::quantize(&NettyRigidBody::new(
                 velocity.copied(),
                 transform.rotation,
                 match parent.map(|p| p.get()) {
@@ -87,17 +186,16 @@ fn server_sync_bodies(
                     ),
                     None => NettyRigidBodyLocation::Absolute(*location),
                 },
-            ),
-            *location,
-            *unload_distance,
-        ));
-
-        // The packet size can only be so big, so limit syncing to 20 per packet
-        if bodies.len() >= 20 {
-            send_bodies(&players, &bodies, &mut server, &tick);
-            bodies.clear();
-        }
-    }
+            )),
+            location: *location,
+            loading_distance: *loading_distance,
+            speed: velocity.map(|x| x.linvel.length()).unwrap_or(0.0),
+        })
+        .collect::<Vec<SyncedBody>>();
+
+    client_bodies
+        .0
+        .retain(|client_id, _| players.iter().any(|(player, _)| player.id() == *client_id));
 
     // for (ent, velocity, transform, parent) in q_children_need_synced.iter() {
     //     let mut info = None;
@@ -121,8 +219,10 @@ fn server_sync_bodies(
 
     // }
 
-    if !bodies.is_empty() {
-        send_bodies(&players, &bodies, &mut server, &tick);
+    for (player, location) in players.iter() {
+        let sent_bodies = client_bodies.0.entry(player.id()).or_default();
+
+        send_bodies(player, location, sent_bodies, &bodies, time.delta_seconds(), &mut server, &tick);
     }
 }
 
@@ -151,16 +251,17 @@ fn notify_despawned_entities(
 }
 
 pub(super) fn register(app: &mut App) {
-    app.add_systems(
-        Update,
-        // This really needs to run immediately after `add_previous_location` to make sure nothing causes any desync
-        // in location + transform, but for now it's fine.
-        (
-            server_sync_bodies
-                .after(add_previous_location)
-                .before(NetworkingSystemsSet::ReceiveMessages),
-            pinger,
-        ),
-    )
-    .add_systems(First, notify_despawned_entities.before(despawn_needed));
+    app.init_resource::<ClientBodies>()
+        .add_systems(
+            Update,
+            // This really needs to run immediately after `add_previous_location` to make sure nothing causes any desync
+            // in location + transform, but for now it's fine.
+            (
+                server_sync_bodies
+                    .after(add_previous_location)
+                    .before(NetworkingSystemsSet::ReceiveMessages),
+                pinger,
+            ),
+        )
+        .add_systems(First, notify_despawned_entities.before(despawn_needed));
 }
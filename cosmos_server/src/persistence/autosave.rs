@@ -0,0 +1,85 @@
+//! Periodically saves every loaded entity, so a crash only loses what happened since the last autosave.
+//!
+//! Loaded planet chunks that changed are saved too, see [`crate::structure::planet::chunk`].
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Or, With, Without},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    log::info,
+    time::{Time, Timer, TimerMode},
+};
+use cosmos_core::{ecs::NeedsDespawned, entities::player::Player, persistence::LoadingDistance, physics::location::Location};
+
+use crate::{settings::ServerSettings, state::GameState};
+
+use super::{
+    loading::NeedsLoaded,
+    saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
+};
+
+#[derive(Event, Debug, Default)]
+/// Send this to save every loaded entity without unloading any of them.
+///
+/// The entities are saved at the start of the next frame.
+pub struct SaveEverythingEvent;
+
+#[derive(Resource, Debug)]
+struct AutosaveTimer(Timer);
+
+fn autosave(time: Res<Time>, mut timer: ResMut<AutosaveTimer>, mut ev_writer: EventWriter<SaveEverythingEvent>) {
+    if timer.0.tick(time.delta()).just_finished() {
+        info!("Autosaving the world...");
+        ev_writer.send_default();
+    }
+}
+
+fn save_everything(
+    mut ev_reader: EventReader<SaveEverythingEvent>,
+    q_saveable: Query<
+        Entity,
+        (
+            With<Location>,
+            Or<(With<Player>, With<LoadingDistance>)>,
+            Without<NeedsDespawned>,
+            Without<NeedsLoaded>,
+        ),
+    >,
+    mut commands: Commands,
+) {
+    if ev_reader.is_empty() {
+        return;
+    }
+
+    ev_reader.clear();
+
+    for entity in q_saveable.iter() {
+        commands.entity(entity).insert(NeedsSaved);
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_event::<SaveEverythingEvent>()
+        .add_systems(SAVING_SCHEDULE, save_everything.before(SavingSystemSet::BeginSaving));
+
+    let autosave_interval = app
+        .world
+        .get_resource::<ServerSettings>()
+        .map(|settings| settings.autosave_interval)
+        .unwrap_or(0);
+
+    if autosave_interval != 0 {
+        app.insert_resource(AutosaveTimer(Timer::new(
+            Duration::from_secs(autosave_interval),
+            TimerMode::Repeating,
+        )))
+        .add_systems(Update, autosave.run_if(in_state(GameState::Playing)));
+    }
+}
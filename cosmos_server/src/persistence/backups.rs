@@ -0,0 +1,322 @@
+//! Backs up the world being played into compressed archives, and restores it from them.
+//!
+//! Backups are taken while the server is running. Everything loaded is saved (and written to disk) first, and then every file in the world is
+//! hard linked into a staging directory before the next save pass starts. Saves replace files instead of writing over them
+//! (see [`write_atomically`](super::write_atomically)), so the staged files never change & are compressed in the background.
+//!
+//! Restoring a backup replaces the whole world, so the server is stopped first. Once everything is saved, the world is
+//! backed up one last time (just in case) and then replaced by the backup's contents.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use bevy::{
+    app::{App, AppExit, Last, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Commands, Query, ResMut, Resource},
+    },
+    log::{error, info},
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use bevy_renet::renet::RenetServer;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_lite::future;
+use walkdir::WalkDir;
+
+use crate::{commands::CommandSender, shutdown::RequestShutdownEvent, state::GameState};
+
+use super::{
+    autosave::SaveEverythingEvent,
+    world::{is_valid_world_name, unix_time, world_directory, world_name},
+    writer::SaveFileWriter,
+};
+
+/// The directory every world's backups are in
+pub const BACKUPS_DIRECTORY: &str = "backups";
+/// The file extension of every backup
+pub const BACKUP_EXTENSION: &str = "tar.gz";
+
+/// The directory the backups of the world being played are in
+pub fn backups_directory() -> String {
+    format!("{BACKUPS_DIRECTORY}/{}", world_name())
+}
+
+/// The path to the backup of the world being played with this name
+pub fn backup_path(backup_name: &str) -> String {
+    format!("{}/{backup_name}.{BACKUP_EXTENSION}", backups_directory())
+}
+
+/// The names of every backup of the world being played, oldest first
+pub fn list_backups() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(backups_directory()) else {
+        return vec![];
+    };
+
+    let mut backups = entries
+        .flatten()
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|x| x.modified()).ok()?;
+            let file_name = entry.file_name().into_string().ok()?;
+            let backup_name = file_name.strip_suffix(&format!(".{BACKUP_EXTENSION}"))?.to_owned();
+
+            Some((modified, backup_name))
+        })
+        .collect::<Vec<_>>();
+
+    backups.sort();
+
+    backups.into_iter().map(|(_, name)| name).collect()
+}
+
+#[derive(Event, Debug)]
+/// Send this to back up the world being played
+pub struct BackupWorldEvent {
+    /// What to call the backup. If this is `None`, it will be named after the current time.
+    pub backup_name: Option<String>,
+    /// Who asked for the backup, and will be told once it's done
+    pub requester: CommandSender,
+}
+
+#[derive(Event, Debug)]
+/// Send this to stop the server & replace the world being played with one of its backups
+pub struct RestoreWorldEvent {
+    /// The backup to restore
+    pub backup_name: String,
+    /// Who asked for the restore
+    pub requester: CommandSender,
+}
+
+#[derive(Resource, Debug, Default)]
+/// Backups waiting for everything to be saved before they are staged
+struct PendingBackups(Vec<(String, CommandSender)>);
+
+#[derive(Resource, Debug, Default)]
+/// The backup that will be restored once the server stops
+struct PendingRestore(Option<String>);
+
+#[derive(Component)]
+struct BackupTask {
+    backup_name: String,
+    requester: CommandSender,
+    task: Task<io::Result<()>>,
+}
+
+/// Writes the contents of this directory to a compressed archive at `archive_path`
+fn write_archive(directory: &str, archive_path: &str) -> io::Result<()> {
+    if let Some(parent) = Path::new(archive_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = format!("{archive_path}.tmp");
+
+    let mut builder = tar::Builder::new(GzEncoder::new(File::create(&temp_path)?, Compression::default()));
+    builder.append_dir_all(".", directory)?;
+    builder.into_inner()?.finish()?.sync_all()?;
+
+    fs::rename(temp_path, archive_path)
+}
+
+/// Links every file in the world into the staging directory, so they won't change as the world keeps being saved
+fn stage_world(staging_directory: &str) -> io::Result<()> {
+    let world_directory = world_directory();
+
+    for entry in WalkDir::new(world_directory)
+        .into_iter()
+        .flatten()
+        .filter(|x| x.file_type().is_file())
+    {
+        let path = entry.path();
+
+        // These are saves that are still being written
+        if path.extension().is_some_and(|x| x == "tmp") {
+            continue;
+        }
+
+        let relative_path = path
+            .strip_prefix(world_directory)
+            .expect("Every file is within the world's directory");
+        let staged_path = Path::new(staging_directory).join(relative_path);
+
+        if let Some(parent) = staged_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Hard links don't work across file systems, so fall back to copying
+        if fs::hard_link(path, &staged_path).is_err() {
+            fs::copy(path, &staged_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn on_backup_requested(
+    mut ev_reader: EventReader<BackupWorldEvent>,
+    mut pending_backups: ResMut<PendingBackups>,
+    q_backup_tasks: Query<&BackupTask>,
+    mut save_everything: EventWriter<SaveEverythingEvent>,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in ev_reader.read() {
+        let backup_name = ev.backup_name.clone().unwrap_or_else(|| format!("backup_{}", unix_time()));
+
+        if !is_valid_world_name(&backup_name) {
+            ev.requester
+                .write("Backup names can only contain letters, numbers, '-' and '_'.", &mut server);
+            continue;
+        }
+
+        if fs::try_exists(backup_path(&backup_name)).unwrap_or(false)
+            || pending_backups.0.iter().any(|(name, _)| *name == backup_name)
+            || q_backup_tasks.iter().any(|x| x.backup_name == backup_name)
+        {
+            ev.requester
+                .write(format!("There is already a backup named {backup_name}."), &mut server);
+            continue;
+        }
+
+        ev.requester
+            .write(format!("Saving the world & backing it up to {backup_name}..."), &mut server);
+
+        pending_backups.0.push((backup_name, ev.requester.clone()));
+        save_everything.send_default();
+    }
+}
+
+/// Runs before any new backups are requested this frame, so everything for the pending backups has been saved since
+fn stage_backups(
+    mut pending_backups: ResMut<PendingBackups>,
+    mut writer: ResMut<SaveFileWriter>,
+    mut server: ResMut<RenetServer>,
+    mut commands: Commands,
+) {
+    if pending_backups.0.is_empty() {
+        return;
+    }
+
+    // Those saves are written in the background, so make sure they're all on disk before they're staged
+    writer.flush();
+
+    for (backup_name, requester) in std::mem::take(&mut pending_backups.0) {
+        let staging_directory = format!("{}/.{backup_name}", backups_directory());
+
+        if let Err(e) = stage_world(&staging_directory) {
+            _ = fs::remove_dir_all(&staging_directory);
+            requester.write(format!("Unable to back up the world - {e}"), &mut server);
+            continue;
+        }
+
+        let archive_path = backup_path(&backup_name);
+
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let result = write_archive(&staging_directory, &archive_path);
+            _ = fs::remove_dir_all(&staging_directory);
+            result
+        });
+
+        commands.spawn(BackupTask {
+            backup_name,
+            requester,
+            task,
+        });
+    }
+}
+
+fn monitor_backup_tasks(mut commands: Commands, mut q_backup_tasks: Query<(Entity, &mut BackupTask)>, mut server: ResMut<RenetServer>) {
+    for (entity, mut backup_task) in q_backup_tasks.iter_mut() {
+        let Some(result) = future::block_on(future::poll_once(&mut backup_task.task)) else {
+            continue;
+        };
+
+        commands.entity(entity).despawn();
+
+        let message = match result {
+            Ok(()) => format!("Backed up the world to {}.", backup_path(&backup_task.backup_name)),
+            Err(e) => format!("Unable to back up the world to {} - {e}", backup_task.backup_name),
+        };
+
+        info!("{message}");
+        backup_task.requester.write(message, &mut server);
+    }
+}
+
+fn on_restore_requested(
+    mut ev_reader: EventReader<RestoreWorldEvent>,
+    mut pending_restore: ResMut<PendingRestore>,
+    mut shutdown: EventWriter<RequestShutdownEvent>,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in ev_reader.read() {
+        if !is_valid_world_name(&ev.backup_name) || !fs::try_exists(backup_path(&ev.backup_name)).unwrap_or(false) {
+            ev.requester
+                .write(format!("There is no backup named {}.", ev.backup_name), &mut server);
+            continue;
+        }
+
+        ev.requester.write(
+            format!(
+                "The server will stop & restore {}. Start it again once it has stopped.",
+                ev.backup_name
+            ),
+            &mut server,
+        );
+
+        pending_restore.0 = Some(ev.backup_name.clone());
+        shutdown.send_default();
+    }
+}
+
+/// Replaces the world being played with the contents of this backup
+fn restore_backup(backup_name: &str) -> io::Result<()> {
+    let world_directory = world_directory();
+    let restoring_directory = format!("{world_directory}.restoring");
+
+    write_archive(world_directory, &backup_path(&format!("before_restore_{}", unix_time())))?;
+
+    _ = fs::remove_dir_all(&restoring_directory);
+    tar::Archive::new(GzDecoder::new(File::open(backup_path(backup_name))?)).unpack(&restoring_directory)?;
+
+    fs::remove_dir_all(world_directory)?;
+    fs::rename(restoring_directory, world_directory)
+}
+
+/// Nothing is saved after the server exits, so this is the only time the world's files can be safely replaced
+fn restore_on_exit(mut ev_reader: EventReader<AppExit>, mut pending_restore: ResMut<PendingRestore>) {
+    if ev_reader.is_empty() {
+        return;
+    }
+
+    ev_reader.clear();
+
+    let Some(backup_name) = pending_restore.0.take() else {
+        return;
+    };
+
+    info!("Restoring backup {backup_name}...");
+
+    match restore_backup(&backup_name) {
+        Ok(()) => info!("Restored backup {backup_name}."),
+        Err(e) => error!("Unable to restore backup {backup_name} - {e}"),
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.init_resource::<PendingBackups>()
+        .init_resource::<PendingRestore>()
+        .add_event::<BackupWorldEvent>()
+        .add_event::<RestoreWorldEvent>()
+        .add_systems(
+            Update,
+            (stage_backups, on_backup_requested, monitor_backup_tasks, on_restore_requested)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Last, restore_on_exit);
+}
@@ -0,0 +1,116 @@
+//! Block & item ids are saved as their numeric ids, which depend on the order everything was registered in.
+//!
+//! To keep saves valid when blocks or items are added/removed, every save file also stores which unlocalized name
+//! each numeric id referred to. When loaded, any ids that have changed are remapped to their current values, and anything
+//! that no longer exists becomes `cosmos:unknown`.
+
+use bevy::{
+    app::App,
+    ecs::{
+        component::Component,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res},
+    },
+};
+use cosmos_core::{
+    block::Block,
+    inventory::Inventory,
+    item::Item,
+    registry::{id_palette::IdPalette, identifiable::Identifiable, Registry},
+    structure::{
+        chunk::{netty::SerializedChunkBlockData, Chunk},
+        Structure,
+    },
+};
+
+use crate::{entities::player::game_mode::SurvivalInventory, structure::persistence::chunk::AllBlockData};
+
+use super::{
+    saving::{BlueprintingSystemSet, NeedsBlueprinted, NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
+    SerializedData,
+};
+
+/// Takes the place of any block or item that was saved but no longer exists
+const UNKNOWN_ID: &str = "cosmos:unknown";
+
+fn write_id_palettes<T: Component>(
+    mut q_serialized_data: Query<&mut SerializedData, With<T>>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+) {
+    if q_serialized_data.is_empty() {
+        return;
+    }
+
+    let block_palette = IdPalette::from_registry(&blocks);
+    let item_palette = IdPalette::from_registry(&items);
+
+    for mut s_data in q_serialized_data.iter_mut() {
+        s_data.serialize_data("cosmos:block_palette", &block_palette);
+        s_data.serialize_data("cosmos:item_palette", &item_palette);
+    }
+}
+
+/// Changes every block & item id in this freshly read save data to the ids they currently have.
+///
+/// Call this on any [`SerializedData`] read from the disk before it is used. Saves without palettes
+/// were made before palettes existed, and are assumed to already have the correct ids.
+pub(crate) fn remap_ids(s_data: &mut SerializedData, blocks: &Registry<Block>, items: &Registry<Item>) {
+    let (Some(block_palette), Some(item_palette)) = (
+        s_data.deserialize_data::<IdPalette>("cosmos:block_palette"),
+        s_data.deserialize_data::<IdPalette>("cosmos:item_palette"),
+    ) else {
+        return;
+    };
+
+    let block_mapping = block_palette.create_mapping(blocks, blocks.from_id(UNKNOWN_ID).expect("Missing cosmos:unknown block").id());
+    let item_mapping = item_palette.create_mapping(items, items.from_id(UNKNOWN_ID).expect("Missing cosmos:unknown item").id());
+
+    if !block_mapping.is_identity() {
+        if let Some(mut structure) = s_data.deserialize_data::<Structure>("cosmos:structure") {
+            structure.remap_block_ids(&block_mapping);
+            s_data.serialize_data("cosmos:structure", &structure);
+        }
+
+        if let Some(mut chunk) = s_data.deserialize_data::<Chunk>("cosmos:chunk") {
+            chunk.remap_block_ids(&block_mapping);
+            s_data.serialize_data("cosmos:chunk", &chunk);
+        }
+    }
+
+    if !item_mapping.is_identity() {
+        if let Some(mut inventory) = s_data.deserialize_data::<Inventory>("cosmos:inventory") {
+            inventory.remap_item_ids(&item_mapping, items);
+            s_data.serialize_data("cosmos:inventory", &inventory);
+        }
+
+        if let Some(mut survival_inventory) = s_data.deserialize_data::<SurvivalInventory>("cosmos:survival_inventory") {
+            survival_inventory.0.remap_item_ids(&item_mapping, items);
+            s_data.serialize_data("cosmos:survival_inventory", &survival_inventory);
+        }
+
+        // Chunks of dynamic structures store their own block data, while fixed structures store all of it on the structure.
+        if s_data.read_data("cosmos:chunk").is_some() {
+            if let Some(mut block_data) = s_data.deserialize_data::<SerializedChunkBlockData>("cosmos:block_data") {
+                block_data.remap_item_ids(&item_mapping, items);
+                s_data.serialize_data("cosmos:block_data", &block_data);
+            }
+        } else if let Some(mut all_block_data) = s_data.deserialize_data::<AllBlockData>("cosmos:block_data") {
+            for block_data in all_block_data.values_mut() {
+                block_data.remap_item_ids(&item_mapping, items);
+            }
+            s_data.serialize_data("cosmos:block_data", &all_block_data);
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        SAVING_SCHEDULE,
+        (
+            write_id_palettes::<NeedsSaved>.in_set(SavingSystemSet::DoSaving),
+            write_id_palettes::<NeedsBlueprinted>.in_set(BlueprintingSystemSet::DoBlueprinting),
+        ),
+    );
+}
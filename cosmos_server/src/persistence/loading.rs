@@ -13,17 +13,26 @@ use bevy::{
     ecs::schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
     hierarchy::BuildChildren,
     log::{error, warn},
-    prelude::{App, Commands, Component, Entity, Quat, Query, Update, With, Without},
+    prelude::{App, Commands, Component, Entity, Quat, Query, Res, ResMut, Resource, Update, With, Without},
     reflect::Reflect,
+    tasks::{IoTaskPool, Task},
 };
 use bevy_rapier3d::prelude::Velocity;
 
 use cosmos_core::{
-    ecs::NeedsDespawned, netty::cosmos_encoder, persistence::LoadingDistance, physics::location::Location,
-    structure::loading::StructureLoadingSet,
+    block::Block,
+    ecs::NeedsDespawned,
+    item::Item,
+    netty::cosmos_encoder,
+    persistence::LoadingDistance,
+    physics::location::Location,
+    registry::Registry,
+    structure::{blueprint::Blueprint, loading::StructureLoadingSet},
 };
 
-use super::{EntityId, SaveFileIdentifier, SaveFileIdentifierType, SerializedData};
+use futures_lite::future;
+
+use super::{id_palettes::remap_ids, writer::SaveFileWriter, EntityId, SaveFileIdentifier, SaveFileIdentifierType, SerializedData};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 /// Put anything related to loading entities in from serialized data into this set
@@ -62,20 +71,105 @@ pub struct NeedsBlueprintLoaded {
     pub path: String,
 }
 
+#[derive(Debug)]
+/// Why a save file couldn't be read
+pub(crate) enum ReadError {
+    /// The file couldn't be read, which usually means it doesn't exist
+    Missing,
+    /// The file was read, but doesn't contain valid save data
+    Corrupted,
+}
+
+#[derive(Resource, Default)]
+/// Save files being read & decoded in the background.
+///
+/// Everything read at the same time is loaded in on the same frame, so entities that belong to each other
+/// (such as a structure & its sub-entities) are always loaded together.
+struct SaveFileReads {
+    reading: Vec<(Entity, String, Task<Result<SerializedData, ReadError>>)>,
+    done: Vec<(Entity, String, Result<SerializedData, ReadError>)>,
+}
+
+/// Reads & decodes a save file. This blocks, so call it from a task.
+pub(crate) fn read_save_file(path: &str) -> Result<SerializedData, ReadError> {
+    let data = fs::read(path).map_err(|_| ReadError::Missing)?;
+
+    cosmos_encoder::deserialize::<SerializedData>(&data).map_err(|_| ReadError::Corrupted)
+}
+
+fn begin_reading_save_files(
+    q_needs_read: Query<(Entity, &SaveFileIdentifier), (Without<SerializedData>, With<NeedsLoaded>)>,
+    writer: Res<SaveFileWriter>,
+    mut reads: ResMut<SaveFileReads>,
+) {
+    // Wait for the current batch to be loaded in before starting the next one
+    if !reads.reading.is_empty() || !reads.done.is_empty() {
+        return;
+    }
+
+    let thread_pool = IoTaskPool::get();
+
+    for (ent, sfi) in q_needs_read.iter() {
+        let path = sfi.get_save_file_path();
+
+        // Reading this now could give outdated data (or nothing at all), so wait until it's written
+        if writer.is_pending(&path) {
+            continue;
+        }
+
+        let task_path = path.clone();
+        let task = thread_pool.spawn(async move { read_save_file(&task_path) });
+
+        reads.reading.push((ent, path, task));
+    }
+}
+
 fn check_needs_loaded(
     q_entity_ids: Query<(Entity, &EntityId)>,
-    q_sfis: Query<(Entity, &SaveFileIdentifier), (Without<SerializedData>, With<NeedsLoaded>)>,
+    q_sfis: Query<(Entity, &SaveFileIdentifier), With<NeedsLoaded>>,
+    mut reads: ResMut<SaveFileReads>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
     mut commands: Commands,
 ) {
-    for (ent, nl) in q_sfis.iter() {
-        let path = nl.get_save_file_path();
-        let Ok(data) = fs::read(&path) else {
-            warn!("Error reading file at '{path}'. Is it there?");
-            commands.entity(ent).insert(NeedsDespawned);
+    let reads = reads.as_mut();
+
+    let mut i = 0;
+    while i < reads.reading.len() {
+        if let Some(result) = future::block_on(future::poll_once(&mut reads.reading[i].2)) {
+            let (ent, path, _) = reads.reading.swap_remove(i);
+            reads.done.push((ent, path, result));
+        } else {
+            i += 1;
+        }
+    }
+
+    if !reads.reading.is_empty() {
+        return;
+    }
+
+    for (ent, path, result) in std::mem::take(&mut reads.done) {
+        let Ok((_, nl)) = q_sfis.get(ent) else {
+            // This entity was despawned while its save file was being read
             continue;
         };
 
-        let serialized_data: SerializedData = cosmos_encoder::deserialize(&data).expect("Error deserializing data for {path}");
+        let mut serialized_data = match result {
+            Ok(serialized_data) => serialized_data,
+            Err(ReadError::Missing) => {
+                warn!("Error reading file at '{path}'. Is it there?");
+                commands.entity(ent).insert(NeedsDespawned);
+                continue;
+            }
+            Err(ReadError::Corrupted) => {
+                error!("Error deserializing data for {path} - is the file corrupted?");
+                // The save file identifier is removed so the corrupted file isn't deleted when this is despawned
+                commands.entity(ent).remove::<SaveFileIdentifier>().insert(NeedsDespawned);
+                continue;
+            }
+        };
+
+        remap_ids(&mut serialized_data, &blocks, &items);
 
         match &nl.identifier_type {
             SaveFileIdentifierType::Base(entity_id, _, _) => {
@@ -85,7 +179,7 @@ fn check_needs_loaded(
                 if let Some(looking_for_entity) = match &base.identifier_type {
                     SaveFileIdentifierType::Base(entity_id, _, _) => Some(entity_id),
                     SaveFileIdentifierType::SubEntity(_, entity_id) => Some(entity_id),
-                    SaveFileIdentifierType::BelongsTo(_, _) => None,
+                    SaveFileIdentifierType::BelongsTo(_, _) | SaveFileIdentifierType::Player(_) => None,
                 } {
                     let mut parent = None;
                     // Most often the parent will also be being loaded, so we have to search through the currently being loaded.
@@ -104,7 +198,7 @@ fn check_needs_loaded(
                                 }
                             }
                             // Not managed by this system, managed by whoever this belongs to
-                            SaveFileIdentifierType::BelongsTo(_, _) => {}
+                            SaveFileIdentifierType::BelongsTo(_, _) | SaveFileIdentifierType::Player(_) => {}
                         }
                     }
 
@@ -125,13 +219,20 @@ fn check_needs_loaded(
             }
             // Not managed by this system, managed by whoever this belongs to
             SaveFileIdentifierType::BelongsTo(_, _) => {}
+            // Players are identified by their name, not an entity id
+            SaveFileIdentifierType::Player(_) => {}
         }
 
         commands.entity(ent).insert(serialized_data);
     }
 }
 
-fn check_blueprint_needs_loaded(query: Query<(Entity, &NeedsBlueprintLoaded), Without<SerializedData>>, mut commands: Commands) {
+fn check_blueprint_needs_loaded(
+    query: Query<(Entity, &NeedsBlueprintLoaded), Without<SerializedData>>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+    mut commands: Commands,
+) {
     for (ent, blueprint_needs_loaded) in query.iter() {
         let path = &blueprint_needs_loaded.path;
         let Ok(data) = fs::read(path) else {
@@ -140,11 +241,26 @@ fn check_blueprint_needs_loaded(query: Query<(Entity, &NeedsBlueprintLoaded), Wi
             continue;
         };
 
-        let Ok(serialized_data) = cosmos_encoder::deserialize::<SerializedData>(&data) else {
-            error!("Error deserializing data for {path}");
-            continue;
+        let blueprint = match Blueprint::decode(&data) {
+            Ok(blueprint) => blueprint,
+            Err(e) => {
+                error!("Unable to load blueprint {path} - {e}");
+                commands.entity(ent).insert(NeedsDespawned);
+                continue;
+            }
         };
 
+        // Spawning this anyway would fill the structure with `cosmos:unknown` blocks wherever the missing ones were
+        if let Err(e) = blueprint.validate(&blocks) {
+            error!("Refusing to load blueprint {path} - {e}");
+            commands.entity(ent).insert(NeedsDespawned);
+            continue;
+        }
+
+        let mut serialized_data = blueprint.data;
+
+        remap_ids(&mut serialized_data, &blocks, &items);
+
         commands.entity(ent).insert(serialized_data);
     }
 }
@@ -157,7 +273,7 @@ fn done_loading_blueprint(query: Query<Entity, With<NeedsBlueprintLoaded>>, mut
 }
 
 /// To add your own loading event, add a system after `begin_loading` and before `done_loading`.
-fn done_loading(query: Query<Entity, With<NeedsLoaded>>, mut commands: Commands) {
+fn done_loading(query: Query<Entity, (With<NeedsLoaded>, With<SerializedData>)>, mut commands: Commands) {
     for ent in query.iter() {
         commands.entity(ent).remove::<NeedsLoaded>().remove::<SerializedData>();
     }
@@ -195,7 +311,9 @@ pub(super) fn register(app: &mut App) {
     .add_systems(
         LOADING_SCHEDULE,
         (
-            check_needs_loaded.in_set(LoadingSystemSet::BeginLoading),
+            (begin_reading_save_files, check_needs_loaded)
+                .chain()
+                .in_set(LoadingSystemSet::BeginLoading),
             default_load.in_set(LoadingSystemSet::DoLoading),
             done_loading.in_set(LoadingSystemSet::DoneLoading),
         ),
@@ -219,4 +337,6 @@ pub(super) fn register(app: &mut App) {
             done_loading_blueprint.in_set(LoadingBlueprintSystemSet::DoneLoadingBlueprints),
         ),
     );
+
+    app.init_resource::<SaveFileReads>();
 }
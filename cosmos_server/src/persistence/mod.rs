@@ -2,7 +2,9 @@
 
 use std::{
     fmt::Display,
-    fs,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
     sync::{Arc, Mutex},
 };
 
@@ -12,16 +14,20 @@ use bevy::{
     utils::{HashMap, HashSet},
 };
 use rand::{distributions::Alphanumeric, Rng};
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 
-use cosmos_core::{
-    physics::location::{Location, Sector},
-    structure::chunk::netty::SaveData,
-};
+use cosmos_core::physics::location::Sector;
+
+pub use cosmos_core::persistence::SerializedData;
 
+pub mod autosave;
+pub mod backups;
+pub mod id_palettes;
 pub mod loading;
 pub mod player_loading;
 pub mod saving;
+pub mod world;
+pub mod writer;
 
 #[derive(Component, Debug, Reflect, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
 /// NOT ALL ENTITIES WILL HAVE THIS ON THEM!
@@ -110,8 +116,15 @@ pub(crate) enum SaveFileIdentifierType {
     /// ## Note:
     /// While saving is handled for you, it is up to you to load this yourself.
     ///
-    /// This will be saved to `world/x_y_z/belongsToEntityId/thisEntityId.cent`
+    /// This will be saved to `worlds/world_name/x_y_z/belongsToEntityId/thisEntityId.cent`
     BelongsTo(Box<SaveFileIdentifier>, String),
+    /// A player's save file, which is identified by their name.
+    ///
+    /// Players are not saved in any sector, so they will never be loaded by
+    /// the load/unload near players logic.
+    ///
+    /// This will be saved to `worlds/world_name/players/playerName.cent`
+    Player(String),
 }
 
 #[derive(Debug, Component, Clone)]
@@ -135,6 +148,13 @@ impl SaveFileIdentifier {
         }
     }
 
+    /// Creates a new SaveFileIdentifier for the player with this name
+    pub fn player(player_name: impl Into<String>) -> Self {
+        Self {
+            identifier_type: SaveFileIdentifierType::Player(player_name.into()),
+        }
+    }
+
     /// If this SaveFileIdentifier is a base identifier (not child),
     /// this will return its EntityId. Otherwise, returns None.
     pub fn entity_id(&self) -> Option<&EntityId> {
@@ -165,6 +185,7 @@ impl SaveFileIdentifier {
                 .unwrap_or(entity.as_str().to_owned()),
             SaveFileIdentifierType::SubEntity(_, entity_id) => entity_id.as_str().to_owned(),
             SaveFileIdentifierType::BelongsTo(_, name) => name.to_owned(),
+            SaveFileIdentifierType::Player(name) => Self::get_player_file_name(name),
         }
     }
 
@@ -174,6 +195,7 @@ impl SaveFileIdentifier {
             SaveFileIdentifierType::Base(entity, _, _) => entity.as_str().to_owned(),
             SaveFileIdentifierType::SubEntity(_, entity_id) => entity_id.as_str().to_owned(),
             SaveFileIdentifierType::BelongsTo(_, name) => name.to_owned(),
+            SaveFileIdentifierType::Player(name) => Self::get_player_file_name(name),
         }
     }
 
@@ -181,7 +203,9 @@ impl SaveFileIdentifier {
     fn get_save_file_directory(&self, base_get_save_file_name: impl Fn(&Self) -> String) -> String {
         match &self.identifier_type {
             SaveFileIdentifierType::Base(_, sector, _) => {
-                let directory = sector.map(Self::get_sector_path).unwrap_or("world/nowhere".into());
+                let directory = sector
+                    .map(Self::get_sector_path)
+                    .unwrap_or_else(|| format!("{}/nowhere", world::world_directory()));
 
                 format!("{directory}/{}", base_get_save_file_name(self))
             }
@@ -199,6 +223,7 @@ impl SaveFileIdentifier {
                     base_get_save_file_name(self)
                 )
             }
+            SaveFileIdentifierType::Player(_) => format!("{}/players/{}", world::world_directory(), base_get_save_file_name(self)),
         }
     }
 
@@ -211,96 +236,56 @@ impl SaveFileIdentifier {
     fn get_sector_path(sector: Sector) -> String {
         let (x, y, z) = (sector.x(), sector.y(), sector.z());
 
-        format!("world/{x}_{y}_{z}")
+        format!("{}/{x}_{y}_{z}", world::world_directory())
     }
-}
-
-#[derive(Component, Debug, Reflect, Serialize, Deserialize)]
-/// Stores the serialized data for an entity.
-///
-/// This is either read from or written to a save file depending on if an entity is being loaded or saved.
-pub struct SerializedData {
-    save_data: SaveData,
-
-    /// Used to identify the location this should be saved under
-    location: Option<Location>,
-    should_save: bool,
-}
 
-impl SerializedData {
-    /// Use this to set location. This will make sure the folder name
-    /// reflects the actual location.
-    pub fn set_location(&mut self, loc: &Location) {
-        self.serialize_data("cosmos:location", loc);
-        self.location = Some(*loc);
+    /// Player names are sent by the client, so this makes sure they can never escape the players directory.
+    ///
+    /// Any character that isn't alphanumeric or an underscore is replaced by its hex code.
+    fn get_player_file_name(player_name: &str) -> String {
+        player_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c.to_string()
+                } else {
+                    format!("-{:x}", c as u32)
+                }
+            })
+            .collect()
     }
 }
 
-impl Default for SerializedData {
-    fn default() -> Self {
-        Self {
-            save_data: SaveData::default(),
-            location: None,
-            should_save: true,
-        }
-    }
+/// Returns true if a sector has at some point been generated at this location
+pub fn is_sector_generated(sector: Sector) -> bool {
+    fs::try_exists(SaveFileIdentifier::get_sector_path(sector)).unwrap_or(false)
 }
 
-impl SerializedData {
-    /// Saves the data to that data id. Will overwrite any existing data at that id.
-    ///
-    /// Will only save if `should_save()` returns true.
-    pub fn save(&mut self, data_id: impl Into<String>, data: Vec<u8>) {
-        if self.should_save() {
-            self.save_data.save(data_id, data);
-        }
-    }
-
-    /// Calls `cosmos_encoder::serialize` on the passed in data.
-    /// Then sends that data into the `save` method, with the given data id.
-    ///
-    /// Will only serialize & save if `should_save()` returns true.
-
-    pub fn serialize_data(&mut self, data_id: impl Into<String>, data: &impl Serialize) {
-        if self.should_save() {
-            self.save_data.serialize_data(data_id, data);
-        }
-    }
-
-    /// Reads the data as raw bytes at the given data id. Use `deserialize_data` for a streamlined way to read the data.
-    pub fn read_data(&self, data_id: &str) -> Option<&Vec<u8>> {
-        self.save_data.read_data(data_id)
-    }
+/// Writes the contents to this file without ever leaving it half-written.
+///
+/// The contents are written to a temporary file next to it first, which is then renamed over the real file.
+/// If the server crashes midway through, the old file is left untouched.
+pub fn write_atomically(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
 
-    /// Deserializes the data as the given type (via `cosmos_encoder::deserialize`) at the given id. Will panic if the
-    /// data is not properly serialized.
-    pub fn deserialize_data<T: DeserializeOwned>(&self, data_id: &str) -> Option<T> {
-        self.save_data.deserialize_data(data_id)
-    }
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
 
-    /// Sets whether this should actually be saved - if false, when save and serialize_data is called,
-    /// nothing will happen.
-    pub fn set_should_save(&mut self, should_save: bool) {
-        self.should_save = should_save;
-    }
-
-    /// If this is false, no data will be saved/serialized when `save` and `serialize_data` is called.
-    ///
-    /// No data will be written to the disk either if this is false.
-    pub fn should_save(&self) -> bool {
-        self.should_save
-    }
-}
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
 
-/// Returns true if a sector has at some point been generated at this location
-pub fn is_sector_generated(sector: Sector) -> bool {
-    fs::try_exists(SaveFileIdentifier::get_sector_path(sector)).unwrap_or(false)
+    fs::rename(&temp_path, path)
 }
 
 pub(super) fn register(app: &mut App) {
     saving::register(app);
+    autosave::register(app);
+    backups::register(app);
+    id_palettes::register(app);
     loading::register(app);
     player_loading::register(app);
+    writer::register(app);
 
     app.register_type::<EntityId>().register_type::<SerializedData>();
 }
@@ -8,7 +8,7 @@ use std::{
 
 use bevy::{
     log::warn,
-    prelude::{App, Commands, Component, DespawnRecursiveExt, Entity, IntoSystemConfigs, Name, Query, ResMut, Update, With, Without},
+    prelude::{App, Commands, Component, DespawnRecursiveExt, Entity, IntoSystemConfigs, Name, Query, Res, ResMut, Update, With, Without},
     tasks::{AsyncComputeTaskPool, Task},
     time::common_conditions::on_timer,
 };
@@ -21,7 +21,9 @@ use cosmos_core::{
 use futures_lite::future;
 use walkdir::{DirEntry, WalkDir};
 
-use super::{loading::NeedsLoaded, saving::NeedsSaved, EntityId, SaveFileIdentifier, SectorsCache};
+use super::{
+    loading::NeedsLoaded, saving::NeedsSaved, writer::SaveFileWriter, EntityId, SaveFileIdentifier, SaveFileIdentifierType, SectorsCache,
+};
 
 fn unload_far(
     query: Query<&Location, With<Player>>,
@@ -47,11 +49,20 @@ const DEFAULT_LOAD_DISTANCE: u32 = (LOAD_DISTANCE / SECTOR_DIMENSIONS) as u32;
 #[derive(Component, Debug)]
 struct LoadingTask(Task<Vec<SaveFileIdentifier>>);
 
+/// Gets the identifier of the entity this ultimately belongs to
+fn base_identifier(sfi: &SaveFileIdentifier) -> &SaveFileIdentifier {
+    match &sfi.identifier_type {
+        SaveFileIdentifierType::SubEntity(parent, _) | SaveFileIdentifierType::BelongsTo(parent, _) => base_identifier(parent),
+        SaveFileIdentifierType::Base(..) | SaveFileIdentifierType::Player(_) => sfi,
+    }
+}
+
 fn monitor_loading_task(
     // Because entities can be added while the scan task is in progress,
     // we need to re-check all the loaded entities before actually spawning them.
     loaded_entities: Query<&EntityId>,
     mut query: Query<(Entity, &mut LoadingTask)>,
+    writer: Res<SaveFileWriter>,
     mut commands: Commands,
 ) {
     let Ok((entity, mut task)) = query.get_single_mut() else {
@@ -61,7 +72,19 @@ fn monitor_loading_task(
     if let Some(save_file_ids) = future::block_on(future::poll_once(&mut task.0)) {
         commands.entity(entity).despawn_recursive();
 
+        // Some of these were just unloaded & haven't been written yet, so the scan may have missed some of their files.
+        // Those entities (and everything that belongs to them) are skipped, and will be found again by the next scan.
+        let pending_bases = save_file_ids
+            .iter()
+            .filter(|sfi| writer.is_pending(&sfi.get_save_file_path()))
+            .filter_map(|sfi| base_identifier(sfi).entity_id().cloned())
+            .collect::<Vec<EntityId>>();
+
         for sfi in save_file_ids {
+            if base_identifier(&sfi).entity_id().is_some_and(|x| pending_bases.contains(x)) {
+                continue;
+            }
+
             if !loaded_entities.iter().any(|x| {
                 x == sfi
                     .entity_id()
@@ -122,7 +145,7 @@ fn load_near(
                                 }
                             }
                         } else {
-                            let dir = format!("world/{}_{}_{}", sector.x(), sector.y(), sector.z());
+                            let dir = SaveFileIdentifier::get_sector_path(sector);
 
                             if fs::try_exists(&dir).unwrap_or(false) {
                                 for file in WalkDir::new(&dir)
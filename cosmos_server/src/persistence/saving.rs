@@ -12,22 +12,33 @@ use bevy::{
     ecs::schedule::{IntoSystemSetConfigs, SystemSet},
     hierarchy::Parent,
     log::{error, warn},
-    prelude::{App, Commands, Component, Entity, First, IntoSystemConfigs, Query, ResMut, With, Without},
+    prelude::{App, Commands, Component, Entity, First, IntoSystemConfigs, ParamSet, Query, Res, ResMut, With, Without},
     reflect::Reflect,
 };
 use bevy_rapier3d::prelude::Velocity;
 use cosmos_core::{
+    block::Block,
+    blockitems::BlockItems,
     ecs::{despawn_needed, NeedsDespawned},
-    netty::cosmos_encoder,
+    entities::player::Player,
+    item::Item,
     persistence::LoadingDistance,
     physics::location::Location,
+    registry::{identifiable::Identifiable, Registry},
+    shop::ShopEntry,
+    structure::{
+        blueprint::{Blueprint, BlueprintMetadata},
+        Structure,
+    },
 };
-use std::{
-    fs,
-    io::{self, ErrorKind},
-};
+use std::{fs, io::ErrorKind};
+
+use crate::shop::prices::DefaultShopEntries;
 
-use super::{EntityId, SaveFileIdentifier, SaveFileIdentifierType, SectorsCache, SerializedData};
+use super::{
+    world::unix_time, write_atomically, writer::SaveFileWriter, EntityId, SaveFileIdentifier, SaveFileIdentifierType, SectorsCache,
+    SerializedData,
+};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 /// This system set is for when entities are being saved normally - NOT FOR A BLUEPRINT (use [`BlueprintingSystemSet`] for that.)
@@ -64,6 +75,8 @@ pub struct NeedsBlueprinted {
     pub blueprint_name: String,
     /// The subdirectory the blueprint resides in (same as the blueprint type)
     pub subdir_name: String,
+    /// The name of the player that made this blueprint, if a player made it
+    pub author: Option<String>,
 }
 
 fn check_needs_saved(query: Query<Entity, (With<NeedsSaved>, Without<SerializedData>)>, mut commands: Commands) {
@@ -82,7 +95,7 @@ fn check_needs_blueprinted(query: Query<Entity, (With<NeedsBlueprinted>, Without
 ///
 /// This is NOT how the structures are saved in the world, but rather used to get structure
 /// files that can be loaded through commands.
-fn save_blueprint(data: &SerializedData, needs_blueprinted: &NeedsBlueprinted) -> std::io::Result<()> {
+fn save_blueprint(data: &SerializedData, metadata: &BlueprintMetadata, needs_blueprinted: &NeedsBlueprinted) -> std::io::Result<()> {
     if let Err(e) = fs::create_dir("saves") {
         match e.kind() {
             ErrorKind::AlreadyExists => {}
@@ -97,22 +110,57 @@ fn save_blueprint(data: &SerializedData, needs_blueprinted: &NeedsBlueprinted) -
         }
     }
 
-    fs::write(
+    write_atomically(
         format!(
             "blueprints/{}/{}.bp",
             needs_blueprinted.subdir_name, needs_blueprinted.blueprint_name
         ),
-        cosmos_encoder::serialize(&data),
-    )?;
-
-    Ok(())
+        Blueprint::encode(metadata, data),
+    )
 }
 
 /// Put all systems that add data to blueprinted entities before this and after `begin_blueprinting`
-fn done_blueprinting(mut query: Query<(Entity, &mut SerializedData, &NeedsBlueprinted, Option<&NeedsSaved>)>, mut commands: Commands) {
-    for (entity, mut serialized_data, needs_blueprinted, needs_saved) in query.iter_mut() {
-        save_blueprint(&serialized_data, needs_blueprinted)
-            .unwrap_or_else(|e| warn!("Failed to save blueprint for {entity:?} \n\n{e}\n\n"));
+fn done_blueprinting(
+    mut query: Query<(
+        Entity,
+        &mut SerializedData,
+        &NeedsBlueprinted,
+        Option<&NeedsSaved>,
+        Option<&Structure>,
+    )>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+    block_items: Res<BlockItems>,
+    default_shop_entries: Option<Res<DefaultShopEntries>>,
+    mut commands: Commands,
+) {
+    // Blueprints are priced by what it would cost to buy every item needed to build them from a shop
+    let price_of = |item: &Item| {
+        default_shop_entries.as_ref()?.0.iter().find_map(|entry| match *entry {
+            ShopEntry::Selling { item_id, price_per, .. } if item_id == item.id() => Some(price_per as u64),
+            _ => None,
+        })
+    };
+
+    for (entity, mut serialized_data, needs_blueprinted, needs_saved, structure) in query.iter_mut() {
+        if let Some(structure) = structure {
+            let metadata = BlueprintMetadata::new(
+                needs_blueprinted.blueprint_name.clone(),
+                needs_blueprinted.subdir_name.clone(),
+                needs_blueprinted.author.clone(),
+                unix_time(),
+                structure,
+                &blocks,
+                &items,
+                &block_items,
+                &price_of,
+            );
+
+            save_blueprint(&serialized_data, &metadata, needs_blueprinted)
+                .unwrap_or_else(|e| warn!("Failed to save blueprint for {entity:?} \n\n{e}\n\n"));
+        } else {
+            warn!("Failed to save blueprint for {entity:?} - only structures can be blueprinted.");
+        }
 
         commands.entity(entity).remove::<NeedsBlueprinted>();
 
@@ -136,44 +184,90 @@ fn create_entity_ids(mut commands: Commands, q_without_id: Query<(Entity, &Seria
 }
 
 /// Make sure any systems that serialize data for saving are run before this
+///
+/// The data is encoded & written to disk in the background by the [`SaveFileWriter`].
 fn done_saving(
-    query: Query<
-        (
-            Entity,
-            Option<&Name>,
-            &SerializedData,
-            &EntityId,
-            Option<&LoadingDistance>,
-            Option<&SaveFileIdentifier>,
-        ),
-        With<NeedsSaved>,
-    >,
+    mut queries: ParamSet<(
+        Query<
+            (
+                Option<&Name>,
+                &mut SerializedData,
+                &EntityId,
+                Option<&LoadingDistance>,
+                Option<&SaveFileIdentifier>,
+            ),
+            With<NeedsSaved>,
+        >,
+        Query<(&SerializedData, &EntityId, Option<&LoadingDistance>)>,
+    )>,
+    q_needs_saved: Query<Entity, With<NeedsSaved>>,
     q_parent: Query<&Parent>,
     q_entity_id: Query<&EntityId>,
-    q_serialized_data: Query<(&SerializedData, &EntityId, Option<&LoadingDistance>)>,
+    q_player: Query<&Player>,
+    q_save_file_identifier: Query<&SaveFileIdentifier>,
     dead_saves_query: Query<&SaveFileIdentifier, (With<NeedsDespawned>, Without<NeedsSaved>)>,
     mut sectors_cache: ResMut<SectorsCache>,
+    mut writer: ResMut<SaveFileWriter>,
     mut commands: Commands,
 ) {
     for dead_save in dead_saves_query.iter() {
-        let path = dead_save.get_save_file_path();
-        if fs::try_exists(&path).unwrap_or(false) {
-            fs::remove_file(path).expect("Error deleting old save file!");
+        writer.delete(dead_save.get_save_file_path());
 
-            if let SaveFileIdentifierType::Base(entity_id, Some(sector), load_distance) = &dead_save.identifier_type {
-                sectors_cache.remove(entity_id, *sector, *load_distance);
-            }
+        if let SaveFileIdentifierType::Base(entity_id, Some(sector), load_distance) = &dead_save.identifier_type {
+            sectors_cache.remove(entity_id, *sector, *load_distance);
         }
     }
 
-    for (entity, name, sd, entity_id, loading_distance, save_file_identifier) in query.iter() {
+    // Children need their parent's data to know where they're saved, so this is all worked out before any data is taken
+    let save_identifiers = {
+        let q_serialized_data = queries.p1();
+
+        q_needs_saved
+            .iter()
+            .map(|entity| {
+                let should_save = q_serialized_data.get(entity).is_ok_and(|(sd, _, _)| sd.should_save());
+
+                let belongs_to = q_save_file_identifier
+                    .get(entity)
+                    .ok()
+                    .filter(|sfi| matches!(sfi.identifier_type, SaveFileIdentifierType::BelongsTo(_, _)));
+
+                let save_identifier = if !should_save {
+                    None
+                } else if let Some(belongs_to) = belongs_to {
+                    // These are saved wherever whatever they belong to decided, such as planet chunks in their planet's folder
+                    Some(belongs_to.clone())
+                } else {
+                    calculate_sfi(entity, &q_parent, &q_entity_id, &q_player, &q_serialized_data)
+                };
+
+                (entity, save_identifier)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut query = queries.p0();
+
+    for (entity, save_identifier) in save_identifiers {
         commands.entity(entity).remove::<NeedsSaved>().remove::<SerializedData>();
 
+        let Ok((name, mut sd, entity_id, loading_distance, save_file_identifier)) = query.get_mut(entity) else {
+            continue;
+        };
+
         if !sd.should_save() {
             continue;
         }
 
-        if loading_distance.is_none() {
+        let Some(save_identifier) = save_identifier else {
+            error!("Could not calculate save file identifier for {entity:?}");
+            continue;
+        };
+
+        // Whatever these belong to decides when they're loaded
+        let belongs_to_another = matches!(&save_identifier.identifier_type, SaveFileIdentifierType::BelongsTo(_, _));
+
+        if loading_distance.is_none() && !belongs_to_another {
             if let Some(name) = name {
                 warn!("Missing load distance for {name} {entity:?}");
             } else {
@@ -181,33 +275,23 @@ fn done_saving(
             }
         }
 
-        if let Some(save_file_identifier) = save_file_identifier {
-            let path = save_file_identifier.get_save_file_path();
-            if fs::try_exists(&path).unwrap_or(false) {
-                if fs::remove_file(&path).is_err() {
-                    warn!("Error deleting old save file at {path}!");
-                }
-
-                if let SaveFileIdentifierType::Base(entity_id, Some(sector), load_distance) = &save_file_identifier.identifier_type {
-                    sectors_cache.remove(entity_id, *sector, *load_distance);
-                }
-            }
-        }
-
-        let serialized: Vec<u8> = cosmos_encoder::serialize(&sd);
+        let location = sd.location();
+        let data = std::mem::take(&mut *sd);
+        let path = save_identifier.get_save_file_path();
 
-        let Some(save_identifier) = calculate_sfi(entity, &q_parent, &q_entity_id, &q_serialized_data) else {
-            error!("Could not calculate save file identifier for {entity:?}");
-            continue;
-        };
+        if let Some(save_file_identifier) = save_file_identifier {
+            // The new file is written before the old one is removed, so a crash in between never loses this entity.
+            writer.write_replacing(path, data, save_file_identifier.get_save_file_path());
 
-        if let Err(e) = write_file(&save_identifier, &serialized) {
-            warn!("{e}");
-            continue;
+            if let SaveFileIdentifierType::Base(entity_id, Some(sector), load_distance) = &save_file_identifier.identifier_type {
+                sectors_cache.remove(entity_id, *sector, *load_distance);
+            }
+        } else {
+            writer.write(path, data);
         }
 
         if matches!(&save_identifier.identifier_type, SaveFileIdentifierType::Base(_, _, _)) {
-            if let Some(loc) = sd.location {
+            if let Some(loc) = location {
                 sectors_cache.insert(loc.sector(), entity_id.clone(), loading_distance.map(|ld| ld.load_distance()));
             }
         }
@@ -220,8 +304,14 @@ fn calculate_sfi(
     entity: Entity,
     q_parent: &Query<&Parent>,
     q_entity_id: &Query<&EntityId>,
+    q_player: &Query<&Player>,
     q_serialized_data: &Query<(&SerializedData, &EntityId, Option<&LoadingDistance>)>,
 ) -> Option<SaveFileIdentifier> {
+    // Players are always saved by name, even if they are currently a child of a structure
+    if let Ok(player) = q_player.get(entity) {
+        return Some(SaveFileIdentifier::player(player.name()));
+    }
+
     let Ok(parent) = q_parent.get(entity) else {
         let Ok((sd, entity_id, loading_distance)) = q_serialized_data.get(entity) else {
             error!("Entity {entity:?} missing entity serialized data. Cannot save {entity:?}.");
@@ -229,7 +319,7 @@ fn calculate_sfi(
         };
 
         return Some(SaveFileIdentifier::new(
-            sd.location.map(|l| l.sector()),
+            sd.location().map(|l| l.sector()),
             entity_id.clone(),
             loading_distance.map(|ld| ld.load_distance()),
         ));
@@ -240,7 +330,7 @@ fn calculate_sfi(
         return None;
     };
 
-    let Some(parent_sfi) = calculate_sfi(parent.get(), q_parent, q_entity_id, q_serialized_data) else {
+    let Some(parent_sfi) = calculate_sfi(parent.get(), q_parent, q_entity_id, q_player, q_serialized_data) else {
         error!("Could not calculate parent save file identifier - not saving {entity:?}");
         return None;
     };
@@ -248,18 +338,6 @@ fn calculate_sfi(
     Some(SaveFileIdentifier::sub_entity(parent_sfi, entity_id.clone()))
 }
 
-fn write_file(save_identifier: &SaveFileIdentifier, serialized: &[u8]) -> io::Result<()> {
-    let path = save_identifier.get_save_file_path();
-
-    let directory = &path[0..path.rfind('/').expect("No / found in file path!")];
-
-    fs::create_dir_all(directory)?;
-
-    fs::write(&path, serialized)?;
-
-    Ok(())
-}
-
 fn default_save(mut query: Query<(&mut SerializedData, Option<&Location>, Option<&Velocity>, Option<&LoadingDistance>), With<NeedsSaved>>) {
     for (mut data, loc, vel, loading_distance) in query.iter_mut() {
         if let Some(loc) = loc {
@@ -0,0 +1,153 @@
+//! Each world is saved to its own directory within `worlds/`, along with its seed & some information about it.
+//!
+//! The server plays one world at a time, chosen with the `--world` argument.
+
+use std::{
+    fs,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::log::{info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::write_atomically;
+
+/// The directory every world's directory is in
+pub const WORLDS_DIRECTORY: &str = "worlds";
+/// The name of the world that is played if none is given
+pub const DEFAULT_WORLD_NAME: &str = "world";
+/// Worlds used to always be saved here, before multiple worlds were supported
+const LEGACY_WORLD_DIRECTORY: &str = "world";
+/// The file in each world's directory that stores its [`WorldMetadata`]
+pub const METADATA_FILE: &str = "world.json";
+
+/// The world being played
+struct OpenWorld {
+    name: String,
+    directory: String,
+}
+
+static OPEN_WORLD: OnceLock<OpenWorld> = OnceLock::new();
+
+#[derive(Debug, Error, PartialEq, Eq)]
+/// Reasons a world can't be opened
+pub enum WorldError {
+    #[error("World names can only contain letters, numbers, '-' and '_'.")]
+    /// The world's name can't be used as a directory name
+    InvalidName,
+    #[error("Unable to create the world's directory - {0}")]
+    /// The world's directory couldn't be created
+    Io(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Information about a world, which is stored in its directory
+pub struct WorldMetadata {
+    /// The world's name, which is also the name of its directory
+    pub name: String,
+    /// When this world was first played (in seconds since the unix epoch)
+    pub created_at: u64,
+    /// When this world was last played (in seconds since the unix epoch)
+    pub last_played_at: u64,
+    /// The version of the server that last played this world
+    pub game_version: String,
+}
+
+/// The current time in seconds since the unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0)
+}
+
+/// Returns true if this can be used as a world's name
+pub fn is_valid_world_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The directory a world with this name is saved to
+pub fn world_directory_for(name: &str) -> String {
+    format!("{WORLDS_DIRECTORY}/{name}")
+}
+
+/// The directory everything in the world being played is saved to.
+///
+/// This is set by [`open_world`], and will panic if called before it.
+pub fn world_directory() -> &'static str {
+    &open_world_info().directory
+}
+
+/// The name of the world being played.
+///
+/// This is set by [`open_world`], and will panic if called before it.
+pub fn world_name() -> &'static str {
+    &open_world_info().name
+}
+
+fn open_world_info() -> &'static OpenWorld {
+    OPEN_WORLD
+        .get()
+        .expect("The world must be opened before anything is saved or loaded")
+}
+
+/// Reads the metadata of the world saved in this directory
+pub fn read_metadata(world_directory: &str) -> Option<WorldMetadata> {
+    let contents = fs::read_to_string(format!("{world_directory}/{METADATA_FILE}")).ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+/// Sets the world that will be played, creating it if it doesn't exist yet.
+///
+/// This must be called before anything in the world is saved or loaded, and can only be called once.
+pub fn open_world(name: &str) -> Result<(), WorldError> {
+    if !is_valid_world_name(name) {
+        return Err(WorldError::InvalidName);
+    }
+
+    let directory = world_directory_for(name);
+
+    if name == DEFAULT_WORLD_NAME && fs::try_exists(LEGACY_WORLD_DIRECTORY).unwrap_or(false) && !fs::try_exists(&directory).unwrap_or(false)
+    {
+        info!("Moving the world in '{LEGACY_WORLD_DIRECTORY}/' to '{directory}/'.");
+
+        fs::create_dir_all(WORLDS_DIRECTORY).map_err(|e| WorldError::Io(e.to_string()))?;
+        fs::rename(LEGACY_WORLD_DIRECTORY, &directory).map_err(|e| WorldError::Io(e.to_string()))?;
+    }
+
+    fs::create_dir_all(&directory).map_err(|e| WorldError::Io(e.to_string()))?;
+
+    let now = unix_time();
+    let metadata = match read_metadata(&directory) {
+        Some(metadata) => WorldMetadata {
+            last_played_at: now,
+            game_version: env!("CARGO_PKG_VERSION").into(),
+            ..metadata
+        },
+        None => WorldMetadata {
+            name: name.into(),
+            created_at: now,
+            last_played_at: now,
+            game_version: env!("CARGO_PKG_VERSION").into(),
+        },
+    };
+
+    let json = serde_json::to_string_pretty(&metadata).expect("Unable to serialize world metadata");
+    if let Err(e) = write_atomically(format!("{directory}/{METADATA_FILE}"), json) {
+        warn!("Unable to save world metadata - {e}");
+    }
+
+    info!("Playing world '{name}'.");
+
+    if OPEN_WORLD
+        .set(OpenWorld {
+            name: name.into(),
+            directory,
+        })
+        .is_err()
+    {
+        panic!("A world has already been opened");
+    }
+
+    Ok(())
+}
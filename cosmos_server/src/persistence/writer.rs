@@ -0,0 +1,241 @@
+//! Encodes & writes save files in the background, so saving never stalls the game.
+//!
+//! Operations on the same file always happen in the order they were queued. If a file is written to again before its
+//! last write is done, only the newest data is written once it is. Anything that reads save files should wait until
+//! [`SaveFileWriter::is_pending`] is false for that file, otherwise it may read outdated data (or nothing at all).
+//!
+//! If too many operations are waiting to finish, queueing another one blocks until some of them are done.
+
+use std::{fs, io::ErrorKind};
+
+use bevy::{
+    app::App,
+    ecs::{
+        schedule::IntoSystemConfigs,
+        system::{ResMut, Resource},
+    },
+    log::warn,
+    tasks::{IoTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+use cosmos_core::netty::cosmos_encoder;
+use futures_lite::future;
+
+use super::{
+    saving::{SavingSystemSet, SAVING_SCHEDULE},
+    write_atomically, SerializedData,
+};
+
+/// Once this many operations are waiting to finish, queueing more will block until some are done
+const MAX_PENDING_OPERATIONS: usize = 512;
+
+enum FileOperation {
+    /// Encodes the data & writes it to the file, then deletes the file it replaces (if any)
+    Write { data: SerializedData, replaces: Option<String> },
+    /// Deletes the file if it exists
+    Delete,
+}
+
+impl FileOperation {
+    fn run(self, path: &str) {
+        match self {
+            Self::Write { data, .. } => {
+                let serialized = cosmos_encoder::serialize(&data);
+
+                if let Err(e) = write_file(path, &serialized) {
+                    warn!("Unable to save {path} - {e}");
+                }
+            }
+            Self::Delete => {
+                if let Err(e) = fs::remove_file(path) {
+                    if e.kind() != ErrorKind::NotFound {
+                        warn!("Error deleting old save file at {path} - {e}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn write_file(path: &str, serialized: &[u8]) -> std::io::Result<()> {
+    if let Some(index) = path.rfind('/') {
+        fs::create_dir_all(&path[0..index])?;
+    }
+
+    write_atomically(path, serialized)
+}
+
+struct InProgress {
+    task: Task<()>,
+    /// The file that will be deleted once this is done
+    replaces: Option<String>,
+}
+
+#[derive(Resource, Default)]
+/// Writes save files in the background, in the order they were queued for each file.
+pub struct SaveFileWriter {
+    in_progress: HashMap<String, InProgress>,
+    /// The next operation for each file, which starts once the one in progress for that file is done
+    queued: HashMap<String, FileOperation>,
+    /// Files that will be deleted once the file replacing them is written
+    awaiting_replacement: HashSet<String>,
+}
+
+impl SaveFileWriter {
+    /// Encodes & writes this data to the file at `path` in the background
+    pub fn write(&mut self, path: String, data: SerializedData) {
+        self.make_room();
+        self.enqueue(path, FileOperation::Write { data, replaces: None });
+    }
+
+    /// Encodes & writes this data to the file at `path` in the background, then deletes the file at `replaces`.
+    ///
+    /// If the server stops in between, both files will exist instead of neither.
+    pub fn write_replacing(&mut self, path: String, data: SerializedData, replaces: String) {
+        if path == replaces {
+            self.write(path, data);
+            return;
+        }
+
+        self.make_room();
+
+        // Whatever was going to happen to the replaced file doesn't matter anymore
+        if let Some(older) = self.discard_queued(&replaces) {
+            self.enqueue(older, FileOperation::Delete);
+        }
+        self.awaiting_replacement.insert(replaces.clone());
+
+        self.enqueue(
+            path,
+            FileOperation::Write {
+                data,
+                replaces: Some(replaces),
+            },
+        );
+    }
+
+    /// Deletes the file at `path` in the background, if it exists
+    pub fn delete(&mut self, path: String) {
+        self.make_room();
+        self.enqueue(path, FileOperation::Delete);
+    }
+
+    /// Returns true if this file will be changed by something that was queued. Don't read it until this is false.
+    pub fn is_pending(&self, path: &str) -> bool {
+        self.in_progress.contains_key(path) || self.queued.contains_key(path) || self.awaiting_replacement.contains(path)
+    }
+
+    /// Returns true if there is nothing left to write
+    pub fn is_idle(&self) -> bool {
+        self.in_progress.is_empty() && self.queued.is_empty() && self.awaiting_replacement.is_empty()
+    }
+
+    /// Blocks until everything that has been queued is done
+    pub fn flush(&mut self) {
+        while let Some(path) = self.in_progress.keys().next().cloned() {
+            self.wait_for(path);
+        }
+    }
+
+    fn enqueue(&mut self, path: String, mut operation: FileOperation) {
+        // Something newer is happening to this file, so it shouldn't be deleted anymore
+        self.awaiting_replacement.remove(&path);
+
+        if !self.in_progress.contains_key(&path) {
+            self.start(path, operation);
+            return;
+        }
+
+        // A write that replaces another file still has to delete that file, even if its data is outdated
+        if let Some(old) = self.discard_queued(&path) {
+            match &mut operation {
+                FileOperation::Write {
+                    replaces: replaces @ None, ..
+                } => *replaces = Some(old),
+                _ => self.enqueue(old, FileOperation::Delete),
+            }
+        }
+
+        self.queued.insert(path, operation);
+    }
+
+    /// Removes the operation queued for this file.
+    ///
+    /// If it was a write replacing a file that should still be deleted, that file is returned.
+    fn discard_queued(&mut self, path: &str) -> Option<String> {
+        match self.queued.remove(path) {
+            Some(FileOperation::Write { replaces: Some(old), .. }) if self.awaiting_replacement.contains(&old) => Some(old),
+            _ => None,
+        }
+    }
+
+    fn start(&mut self, path: String, mut operation: FileOperation) {
+        let replaces = match &mut operation {
+            FileOperation::Write { replaces, .. } => replaces.take(),
+            FileOperation::Delete => None,
+        };
+
+        let task_path = path.clone();
+        let task = IoTaskPool::get().spawn(async move { operation.run(&task_path) });
+
+        self.in_progress.insert(path, InProgress { task, replaces });
+    }
+
+    fn finish(&mut self, path: String, replaces: Option<String>) {
+        if let Some(replaces) = replaces {
+            // Only delete it if nothing newer has happened to it since
+            if self.awaiting_replacement.contains(&replaces) {
+                self.enqueue(replaces, FileOperation::Delete);
+            }
+        }
+
+        if let Some(operation) = self.queued.remove(&path) {
+            self.start(path, operation);
+        }
+    }
+
+    fn wait_for(&mut self, path: String) {
+        let Some(in_progress) = self.in_progress.remove(&path) else {
+            return;
+        };
+
+        future::block_on(in_progress.task);
+
+        self.finish(path, in_progress.replaces);
+    }
+
+    /// Starts the next operation for every file whose current operation is done
+    fn update(&mut self) {
+        let finished = self
+            .in_progress
+            .iter_mut()
+            .filter_map(|(path, in_progress)| future::block_on(future::poll_once(&mut in_progress.task)).map(|_| path.clone()))
+            .collect::<Vec<_>>();
+
+        for path in finished {
+            let in_progress = self.in_progress.remove(&path).expect("This was just found");
+            self.finish(path, in_progress.replaces);
+        }
+    }
+
+    fn make_room(&mut self) {
+        self.update();
+
+        while self.in_progress.len() + self.queued.len() >= MAX_PENDING_OPERATIONS {
+            let Some(path) = self.in_progress.keys().next().cloned() else {
+                break;
+            };
+
+            self.wait_for(path);
+        }
+    }
+}
+
+fn update_save_file_writer(mut writer: ResMut<SaveFileWriter>) {
+    writer.update();
+}
+
+pub(super) fn register(app: &mut App) {
+    app.init_resource::<SaveFileWriter>()
+        .add_systems(SAVING_SCHEDULE, update_save_file_writer.after(SavingSystemSet::DoneSaving));
+}
@@ -3,39 +3,42 @@
 use bevy::{log::info, prelude::Plugin};
 
 use crate::{
-    ai, blocks, commands, events,
+    ai, blocks, chat, commands, crafting, entities, events, faction,
     init::{self, init_server},
-    inventory, netty, persistence, physics, projectiles, registry, shop, structure, universe, utility_runs,
+    inventory, logic, netty, persistence, physics, projectiles, registry, shop, shutdown, structure, universe, utility_runs,
 };
 
 /// The server's plugin
 ///
 /// Contains all the systems + resources needed for a server
-pub struct ServerPlugin {
-    /// The server's IP because renet needs this for some dumb and annoying reason
-    pub ip: Option<String>,
-}
+pub struct ServerPlugin;
 
 impl Plugin for ServerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         info!("Setting up server");
-        init_server::init(app, self.ip.clone());
+        init_server::init(app);
         commands::register(app);
+        faction::register(app);
+        chat::register(app);
         init::register(app);
         registry::register(app);
         netty::register(app);
         events::register(app);
+        entities::register(app);
         physics::register(app);
         blocks::register(app);
         structure::register(app);
+        logic::register(app);
         inventory::register(app);
         super::register(app);
         projectiles::register(app);
         persistence::register(app);
         universe::register(app);
         shop::register(app);
+        crafting::register(app);
         ai::register(app);
         utility_runs::register(app);
+        shutdown::register(app);
 
         info!("Done setting up server!");
     }
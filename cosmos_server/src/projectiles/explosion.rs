@@ -22,7 +22,10 @@ use cosmos_core::{
         player_world::{PlayerWorld, WorldWithin},
         structure_physics::ChunkPhysicsPart,
     },
-    projectiles::missile::{Explosion, ExplosionSystemSet},
+    projectiles::{
+        missile::{Explosion, ExplosionSystemSet},
+        Causer,
+    },
     registry::Registry,
     structure::{
         coordinates::{BlockCoordinate, UnboundBlockCoordinate, UnboundCoordinateType},
@@ -31,7 +34,7 @@ use cosmos_core::{
     },
 };
 
-use crate::netty::sync::sync_bodies::DontNotifyClientOfDespawn;
+use crate::{faction::relations::DamageRules, netty::sync::sync_bodies::DontNotifyClientOfDespawn};
 
 /// 1 unit of explosion power = this amount of health. Bigger this number is, the more damage explosives will do.
 const HEALTH_PER_EXPLOSION_POWER: f32 = 8.0;
@@ -51,7 +54,7 @@ pub struct ExplosionHitEvent {
 
 fn respond_to_explosion(
     mut commands: Commands,
-    q_explosions: Query<(Entity, &Location, &WorldWithin, Option<&PhysicsWorld>, &Explosion), Added<Explosion>>,
+    q_explosions: Query<(Entity, &Location, &WorldWithin, Option<&PhysicsWorld>, &Explosion, Option<&Causer>), Added<Explosion>>,
     q_player_world: Query<&Location, With<PlayerWorld>>,
     q_excluded: Query<(), Or<(With<Explosion>, Without<Collider>)>>,
 
@@ -65,8 +68,9 @@ fn respond_to_explosion(
     mut ev_writer_explosion_hit: EventWriter<ExplosionHitEvent>,
 
     q_shield: Query<&Shield>,
+    damage_rules: DamageRules,
 ) {
-    for (ent, &explosion_loc, world_within, physics_world, &explosion) in q_explosions.iter() {
+    for (ent, &explosion_loc, world_within, physics_world, &explosion, causer) in q_explosions.iter() {
         commands.entity(ent).insert((NeedsDespawned, DontNotifyClientOfDespawn));
 
         let Ok(player_world_loc) = q_player_world.get(world_within.0) else {
@@ -111,6 +115,10 @@ fn respond_to_explosion(
         let max_radius_sqrd = max_radius * max_radius;
 
         for &hit in ents.iter() {
+            if !damage_rules.can_damage(causer.map(|x| x.0), hit) {
+                continue;
+            }
+
             let Ok((structure_g_trans, structure_loc, mut structure)) = q_structure.get_mut(hit) else {
                 ev_writer_explosion_hit.send(ExplosionHitEvent {
                     explosion,
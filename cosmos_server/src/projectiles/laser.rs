@@ -10,6 +10,7 @@ use cosmos_core::{
 };
 
 use crate::{
+    faction::relations::DamageRules,
     persistence::{
         saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
         SerializedData,
@@ -46,10 +47,15 @@ fn respond_laser_hit_event(
     blocks: Res<Registry<Block>>,
     mut block_take_damage_event_writer: EventWriter<BlockTakeDamageEvent>,
     mut block_destroy_event_writer: EventWriter<BlockDestroyedEvent>,
+    damage_rules: DamageRules,
 ) {
     for ev in reader.read() {
         let entity_hit = ev.entity_hit();
         if let Ok(parent) = parent_query.get(entity_hit) {
+            if !damage_rules.can_damage(ev.causer(), parent.get()) {
+                continue;
+            }
+
             if let Ok(mut structure) = structure_query.get_mut(parent.get()) {
                 let local_position_hit = ev.local_position_hit();
 
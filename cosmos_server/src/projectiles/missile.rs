@@ -23,7 +23,10 @@ use cosmos_core::{
         collision_handling::CollisionBlacklist,
         location::{CosmosBundleSet, Location},
     },
-    projectiles::missile::{Explosion, ExplosionSystemSet, Missile},
+    projectiles::{
+        missile::{Explosion, ExplosionSystemSet, Missile},
+        Causer,
+    },
 };
 
 #[derive(Component)]
@@ -71,9 +74,27 @@ fn apply_missile_thrust(mut commands: Commands, time: Res<Time>, q_missiles: Que
     }
 }
 
+/// Spawns the explosion for this missile. The explosion's damage is blamed on whatever fired the missile.
+fn spawn_explosion(commands: &mut Commands, location: &Location, velocity: &Velocity, missile: &Missile, causer: Option<&Causer>) {
+    let mut ecmds = commands.spawn((
+        *location,
+        *velocity,
+        RigidBody::Dynamic,
+        LoadingDistance::new(1, 2),
+        Explosion {
+            power: missile.strength,
+            color: missile.color,
+        },
+    ));
+
+    if let Some(&causer) = causer {
+        ecmds.insert(causer);
+    }
+}
+
 fn respond_to_collisions(
     mut ev_reader: EventReader<CollisionEvent>,
-    q_missile: Query<(&Location, &Velocity, &Missile, &CollisionBlacklist)>,
+    q_missile: Query<(&Location, &Velocity, &Missile, &CollisionBlacklist, Option<&Causer>)>,
     q_parent: Query<&Parent>,
     mut commands: Commands,
 ) {
@@ -90,7 +111,7 @@ fn respond_to_collisions(
             None
         };
 
-        let Some(((location, velocity, missile, collision_blacklist), missile_entity, hit_entity)) = entities else {
+        let Some(((location, velocity, missile, collision_blacklist, causer), missile_entity, hit_entity)) = entities else {
             continue;
         };
 
@@ -100,21 +121,16 @@ fn respond_to_collisions(
 
         commands.entity(missile_entity).insert(NeedsDespawned);
 
-        commands.spawn((
-            *location,
-            *velocity,
-            RigidBody::Dynamic,
-            LoadingDistance::new(1, 2),
-            Explosion {
-                power: missile.strength,
-                color: missile.color,
-            },
-        ));
+        spawn_explosion(&mut commands, location, velocity, missile, causer);
     }
 }
 
-fn despawn_missiles(mut commands: Commands, mut query: Query<(Entity, &Velocity, &Location, &mut Missile)>, time: Res<Time>) {
-    for (ent, velocity, location, mut missile) in query.iter_mut() {
+fn despawn_missiles(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Velocity, &Location, &mut Missile, Option<&Causer>)>,
+    time: Res<Time>,
+) {
+    for (ent, velocity, location, mut missile, causer) in query.iter_mut() {
         missile.lifetime = missile
             .lifetime
             .checked_sub(Duration::from_secs_f32(time.delta_seconds()))
@@ -123,16 +139,7 @@ fn despawn_missiles(mut commands: Commands, mut query: Query<(Entity, &Velocity,
         if missile.lifetime == Duration::ZERO {
             commands.entity(ent).insert(NeedsDespawned);
 
-            commands.spawn((
-                *location,
-                *velocity,
-                RigidBody::Dynamic,
-                LoadingDistance::new(1, 2),
-                Explosion {
-                    power: missile.strength,
-                    color: missile.color,
-                },
-            ));
+            spawn_explosion(&mut commands, location, velocity, &missile, causer);
         }
     }
 }
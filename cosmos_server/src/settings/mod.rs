@@ -1,16 +1,59 @@
 //! Settings for the server
 
-use bevy::ecs::system::Resource;
+use std::fs;
+
+use bevy::{ecs::system::Resource, log::warn};
 use clap::{arg, Parser};
+use cosmos_core::{entities::player::game_mode::GameMode, faction::relations::PvpMode, netty::connection::DEFAULT_PORT};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::{world::DEFAULT_WORLD_NAME, write_atomically};
+
+/// Where the server's config file is stored
+const CONFIG_FILE_PATH: &str = "settings/server.toml";
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 /// Command line arguments for the server
+///
+/// Any networking options passed in here override the ones in the server's config file.
 pub struct Args {
     /// Ip of the server
     #[arg(long)]
     ip: Option<String>,
 
+    /// The address the server should listen on
+    #[arg(long)]
+    bind: Option<String>,
+
+    /// The port the server should run on
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// The maximum number of players that can be connected at once
+    #[arg(long)]
+    max_clients: Option<usize>,
+
+    /// The password players must provide to join
+    #[arg(long)]
+    password: Option<String>,
+
+    /// A comma-separated list of the only player names allowed to join
+    #[arg(long, value_delimiter = ',')]
+    whitelist: Option<Vec<String>>,
+
+    /// If this is true, players must get a connect token from the server's token endpoint before joining
+    #[arg(long, default_value_t = false)]
+    secure_authentication: bool,
+
+    /// The TCP port the token endpoint runs on. Defaults to the server's port.
+    #[arg(long)]
+    token_port: Option<u16>,
+
+    /// The name of the world to play. A new world is created if none exists with this name.
+    #[arg(long, default_value_t = DEFAULT_WORLD_NAME.to_owned())]
+    world: String,
+
     /// If this is true, no enemies will spawn
     #[arg(long, default_value_t = false)]
     peaceful: bool,
@@ -22,29 +65,182 @@ pub struct Args {
     /// If this is true, no planets will spawn
     #[arg(long, default_value_t = false)]
     no_planets: bool,
+
+    /// If this is true, planet terrain will be generated on the CPU instead of the GPU.
+    ///
+    /// Use this if the server has no GPU.
+    #[arg(long, default_value_t = false)]
+    cpu_terrain_generation: bool,
+
+    /// Generates the terrain the CPU terrain generation is tested against on the GPU, saves it to this file, then stops the server.
+    ///
+    /// Do this whenever the terrain generation shaders change.
+    #[arg(long)]
+    capture_terrain_golden: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// An item every new survival player starts with
+pub struct StarterKitItem {
+    /// The item's unlocalized name
+    pub item: String,
+    /// How many of this item they start with
+    pub quantity: u16,
+}
+
+impl StarterKitItem {
+    fn new(item: &str, quantity: u16) -> Self {
+        Self {
+            item: item.into(),
+            quantity,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+/// The settings stored in the server's config file
+struct ServerConfigFile {
+    ip: Option<String>,
+    bind: String,
+    port: u16,
+    max_clients: usize,
+    password: Option<String>,
+    whitelist: Option<Vec<String>>,
+    secure_authentication: bool,
+    token_port: Option<u16>,
+    motd: String,
+    default_game_mode: GameMode,
+    starting_credits: u64,
+    pvp: PvpMode,
+    autosave_interval: u64,
+    // Arrays of tables must come after every plain value in toml
+    starter_kit: Vec<StarterKitItem>,
+}
+
+impl Default for ServerConfigFile {
+    fn default() -> Self {
+        Self {
+            ip: None,
+            bind: "0.0.0.0".into(),
+            port: DEFAULT_PORT,
+            max_clients: 20,
+            password: None,
+            whitelist: None,
+            secure_authentication: false,
+            token_port: None,
+            motd: "Welcome to the server!".into(),
+            default_game_mode: GameMode::Survival,
+            starting_credits: 5_000,
+            pvp: PvpMode::On,
+            autosave_interval: 300,
+            starter_kit: vec![
+                StarterKitItem::new("cosmos:ship_core", 1),
+                StarterKitItem::new("cosmos:fabricator", 1),
+                StarterKitItem::new("cosmos:ship_hull_grey", 64),
+                StarterKitItem::new("cosmos:thruster", 4),
+                StarterKitItem::new("cosmos:energy_cell", 2),
+                StarterKitItem::new("cosmos:power_cable", 16),
+                StarterKitItem::new("cosmos:laser_cannon", 2),
+            ],
+        }
+    }
+}
+
+/// Reads the server's config file, creating it with the default values if it doesn't exist yet.
+fn read_config_file() -> ServerConfigFile {
+    let Ok(contents) = fs::read_to_string(CONFIG_FILE_PATH) else {
+        let config = ServerConfigFile::default();
+
+        _ = fs::create_dir_all("settings");
+
+        if let Err(e) = write_atomically(
+            CONFIG_FILE_PATH,
+            toml::to_string(&config).expect("Error parsing server config into toml."),
+        ) {
+            warn!("Unable to create server config file - {e}");
+        }
+
+        return config;
+    };
+
+    toml::from_str::<ServerConfigFile>(&contents).unwrap_or_else(|e| {
+        panic!("Invalid server config file ({CONFIG_FILE_PATH}) - {e}");
+    })
 }
 
 #[derive(Resource)]
-/// Settings for the server from the command line
+/// Settings for the server from the command line & config file
 pub struct ServerSettings {
     /// The IP the server should run on
     pub ip: Option<String>,
+    /// The address the server listens on
+    pub bind: String,
+    /// The port the server runs on
+    pub port: u16,
+    /// The maximum number of players that can be connected at once
+    pub max_clients: usize,
+    /// The password players must provide to join, if any
+    pub password: Option<String>,
+    /// If this is set, only players with these names can join
+    pub whitelist: Option<Vec<String>>,
+    /// If players must get a connect token from the server's token endpoint before joining
+    pub secure_authentication: bool,
+    /// The TCP port the token endpoint runs on
+    pub token_port: u16,
+    /// The message of the day sent to players when they join. Nothing is sent if this is empty.
+    pub motd: String,
+    /// The game mode new players start in
+    pub default_game_mode: GameMode,
+    /// How many credits new players start with
+    pub starting_credits: u64,
+    /// The items new survival players start with
+    pub starter_kit: Vec<StarterKitItem>,
+    /// Which players can damage each other's structures
+    pub pvp: PvpMode,
+    /// How many seconds there are between autosaves. If this is 0, the server never autosaves.
+    pub autosave_interval: u64,
+    /// The name of the world being played
+    pub world: String,
     /// If enemies shouldn't spawn
     pub peaceful: bool,
     /// If asteroids should spawn
     pub spawn_asteroids: bool,
     /// If planets should spawn
     pub spawn_planets: bool,
+    /// If planet terrain should be generated on the CPU instead of the GPU
+    pub cpu_terrain_generation: bool,
+    /// If set, the server saves the terrain the CPU terrain generation is tested against to this file, then stops
+    pub capture_terrain_golden: Option<String>,
 }
 
-/// Reads the server settings passed in from the command line
+/// Reads the server settings passed in from the command line & the server's config file
 pub(super) fn read_server_settings() -> ServerSettings {
     let args = Args::parse();
+    let config = read_config_file();
+
+    let port = args.port.unwrap_or(config.port);
 
     ServerSettings {
-        ip: args.ip,
+        ip: args.ip.or(config.ip),
+        bind: args.bind.unwrap_or(config.bind),
+        port,
+        max_clients: args.max_clients.unwrap_or(config.max_clients),
+        password: args.password.or(config.password),
+        whitelist: args.whitelist.or(config.whitelist),
+        secure_authentication: args.secure_authentication || config.secure_authentication,
+        token_port: args.token_port.or(config.token_port).unwrap_or(port),
+        motd: config.motd,
+        default_game_mode: config.default_game_mode,
+        starting_credits: config.starting_credits,
+        starter_kit: config.starter_kit,
+        pvp: config.pvp,
+        autosave_interval: config.autosave_interval,
+        world: args.world,
         peaceful: args.peaceful,
         spawn_planets: !args.no_planets,
         spawn_asteroids: !args.no_asteroids,
+        cpu_terrain_generation: args.cpu_terrain_generation,
+        capture_terrain_golden: args.capture_terrain_golden,
     }
 }
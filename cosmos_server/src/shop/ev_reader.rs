@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy_renet::renet::{ClientId, RenetServer};
 use cosmos_core::{
-    block::{block_events::BlockInteractEvent, Block},
+    block::{block_events::BlockInteractEvent, data::BlockData, Block, ALL_BLOCK_FACES},
     economy::Credits,
     entities::player::Player,
     inventory::Inventory,
@@ -9,30 +9,80 @@ use cosmos_core::{
     netty::{cosmos_encoder, server::ServerLobby, system_sets::NetworkingSystemsSet, NettyChannelClient, NettyChannelServer},
     registry::{identifiable::Identifiable, Registry},
     shop::{
-        netty::{ClientShopMessages, ServerShopMessages, ShopPurchaseError, ShopSellError},
-        Shop,
+        market::ShopMarket,
+        netty::{ClientShopMessages, ServerShopMessages, ShopEditError, ShopPurchaseError, ShopSellError},
+        Shop, ShopEntry,
     },
     structure::{coordinates::BlockCoordinate, Structure},
 };
 
-use super::prices::DefaultShopEntries;
-
 use crate::GameState;
 
-fn generate_fake_shop(default: &DefaultShopEntries) -> Shop {
-    Shop {
-        name: "Cool Shop".into(),
-        contents: default.0.clone(),
+/// Finds the storage block next to this shop that player-owned shops stock their items from
+fn adjacent_storage(structure: &Structure, shop_block: BlockCoordinate, blocks: &Registry<Block>) -> Option<Entity> {
+    let storage_block = blocks.from_id("cosmos:storage")?;
+
+    ALL_BLOCK_FACES
+        .iter()
+        .filter_map(|face| BlockCoordinate::try_from(face.direction_coordinates() + shop_block).ok())
+        .filter(|&coords| structure.is_within_blocks(coords) && structure.block_id_at(coords) == storage_block.id())
+        .find_map(|coords| structure.block_data(coords))
+}
+
+#[derive(Component, Debug, PartialEq, Eq)]
+/// The shop a player has open, who is sent the shop's contents whenever someone else changes it
+struct ViewingShop {
+    structure_entity: Entity,
+    shop_block: BlockCoordinate,
+}
+
+/// Sends the shop's new contents to everyone looking at it, other than the player who changed it (they're sent the result)
+fn send_to_viewers(
+    server: &mut RenetServer,
+    q_viewers: &Query<(&Player, &ViewingShop)>,
+    changed_by: ClientId,
+    structure_entity: Entity,
+    shop_block: BlockCoordinate,
+    shop: &Shop,
+) {
+    let viewing = ViewingShop {
+        structure_entity,
+        shop_block,
+    };
+
+    let message = cosmos_encoder::serialize(&ServerShopMessages::ShopContents {
+        shop_block,
+        structure_entity,
+        shop_data: shop.clone(),
+    });
+
+    for (player, _) in q_viewers.iter().filter(|(player, x)| **x == viewing && player.id() != changed_by) {
+        server.send_message(player.id(), NettyChannelServer::Shop, message.clone());
+    }
+}
+
+/// Player-owned shops can only sell what is in their storage
+fn update_stock(shop: &mut Shop, storage: Option<&Inventory>, items: &Registry<Item>) {
+    if shop.owner.is_none() {
+        return;
     }
+
+    shop.update_stock(|item_id| match (storage, items.try_from_numeric_id(item_id)) {
+        (Some(storage), Some(item)) => storage.quantity_of(item) as u32,
+        _ => 0,
+    });
 }
 
 fn on_interact_with_shop(
+    mut commands: Commands,
     mut server: ResMut<RenetServer>,
     q_structure: Query<&Structure>,
     q_player: Query<&Player>,
     blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
     mut ev_reader: EventReader<BlockInteractEvent>,
-    default_shop_entries: Res<DefaultShopEntries>,
+    mut q_shop: Query<&mut Shop>,
+    q_storage: Query<&Inventory, With<BlockData>>,
 ) {
     for ev in ev_reader.read() {
         let Ok(player) = q_player.get(ev.interactor) else {
@@ -45,19 +95,34 @@ fn on_interact_with_shop(
 
         let block = ev.structure_block.block(structure, &blocks);
 
-        if block.unlocalized_name() == "cosmos:shop" {
-            let fake_shop_data = generate_fake_shop(&default_shop_entries);
-
-            server.send_message(
-                player.id(),
-                NettyChannelServer::Shop,
-                cosmos_encoder::serialize(&ServerShopMessages::OpenShop {
-                    shop_block: ev.structure_block.coords(),
-                    structure_entity: ev.structure_entity,
-                    shop_data: fake_shop_data,
-                }),
-            );
+        if block.unlocalized_name() != "cosmos:shop" {
+            continue;
         }
+
+        let Some(mut shop) = structure
+            .block_data(ev.structure_block.coords())
+            .and_then(|x| q_shop.get_mut(x).ok())
+        else {
+            continue;
+        };
+
+        let storage = adjacent_storage(structure, ev.structure_block.coords(), &blocks).and_then(|x| q_storage.get(x).ok());
+        update_stock(&mut shop, storage, &items);
+
+        commands.entity(ev.interactor).insert(ViewingShop {
+            structure_entity: ev.structure_entity,
+            shop_block: ev.structure_block.coords(),
+        });
+
+        server.send_message(
+            player.id(),
+            NettyChannelServer::Shop,
+            cosmos_encoder::serialize(&ServerShopMessages::OpenShop {
+                shop_block: ev.structure_block.coords(),
+                structure_entity: ev.structure_entity,
+                shop_data: shop.clone(),
+            }),
+        );
     }
 }
 
@@ -79,31 +144,46 @@ struct SellEvent {
     quantity: u32,
 }
 
-fn get_shop(
-    _structure_entity: Entity,
-    _shop_block: BlockCoordinate,
-    default_shop_entries: &DefaultShopEntries,
-    _q_structure: &Query<&Structure>,
-    _q_shop_data: &mut Query<&mut Shop>,
-) -> Option<Shop> {
-    // let structure = q_structure.get(structure_entity).ok()?;
+enum ShopEdit {
+    SetEntry(ShopEntry),
+    RemoveEntry(ShopEntry),
+    WithdrawCredits,
+    DepositCredits(u64),
+}
 
-    // let block_data = structure.block_data(shop_block)?;
+#[derive(Event)]
+struct EditShopEvent {
+    client_id: ClientId,
+    shop_block: BlockCoordinate,
+    structure_entity: Entity,
+    edit: ShopEdit,
+}
 
-    // let mut shop = q_shop_data.get_mut(block_data).ok()?;
+/// Returns the shop's entity & the entity of the storage it stocks from, if it has one
+fn get_shop(
+    structure_entity: Entity,
+    shop_block: BlockCoordinate,
+    q_structure: &Query<&Structure>,
+    blocks: &Registry<Block>,
+) -> Option<(Entity, Option<Entity>)> {
+    let structure = q_structure.get(structure_entity).ok()?;
+
+    let shop_ent = structure.block_data(shop_block)?;
 
-    Some(generate_fake_shop(default_shop_entries))
+    Some((shop_ent, adjacent_storage(structure, shop_block, blocks)))
 }
 
 fn listen_sell_events(
     mut server: ResMut<RenetServer>,
     mut ev_reader: EventReader<SellEvent>,
     q_structure: Query<&Structure>,
-    mut q_shop_data: Query<&mut Shop>,
+    mut q_shop_data: Query<(&mut Shop, Option<&mut ShopMarket>)>,
     lobby: Res<ServerLobby>,
-    mut q_player: Query<(&mut Inventory, &mut Credits)>,
+    mut q_player: Query<(&mut Inventory, &mut Credits), Without<BlockData>>,
+    mut q_storage: Query<&mut Inventory, With<BlockData>>,
+    q_viewers: Query<(&Player, &ViewingShop)>,
     items: Res<Registry<Item>>,
-    default_shop_entries: Res<DefaultShopEntries>,
+    blocks: Res<Registry<Block>>,
 ) {
     for &SellEvent {
         client_id,
@@ -141,23 +221,65 @@ fn listen_sell_events(
             continue;
         }
 
-        let Some(mut shop) = get_shop(structure_entity, shop_block, &default_shop_entries, &q_structure, &mut q_shop_data) else {
+        let Some((shop_ent, storage_ent)) = get_shop(structure_entity, shop_block, &q_structure, &blocks) else {
+            continue;
+        };
+
+        let Ok((mut shop, mut market)) = q_shop_data.get_mut(shop_ent) else {
             continue;
         };
 
+        // Inventories can't take more than this at once, so no shop is willing to buy more than this at once
+        let Ok(stack_quantity) = u16::try_from(quantity) else {
+            server.send_message(
+                client_id,
+                NettyChannelServer::Shop,
+                cosmos_encoder::serialize(&ServerShopMessages::SellResult {
+                    shop_block,
+                    structure_entity,
+                    details: Err(ShopSellError::NotWillingToBuyThatMany(shop.clone())),
+                }),
+            );
+            continue;
+        };
+
+        let mut storage = storage_ent.and_then(|x| q_storage.get_mut(x).ok());
+
+        let details = if shop.owner.is_some() && !storage.as_ref().map(|x| x.can_insert(item, stack_quantity)).unwrap_or(false) {
+            Err(ShopSellError::NotEnoughInventorySpace)
+        } else if let Err(error) = shop.sell(item_id, quantity, &mut credits, market.as_ref().and_then(|x| x.item(item_id))) {
+            Err(error)
+        } else {
+            inventory.take_item(item, quantity as usize);
+
+            if shop.owner.is_some() {
+                if let Some(storage) = storage.as_mut() {
+                    storage.insert(item, stack_quantity);
+                }
+            }
+
+            update_stock(&mut shop, storage.as_deref(), &items);
+
+            if let Some(market) = market.as_mut() {
+                if let Some(market_item) = market.item_mut(item_id) {
+                    market_item.sold(quantity);
+                }
+
+                market.update_shop(&mut shop);
+            }
+
+            send_to_viewers(&mut server, &q_viewers, client_id, structure_entity, shop_block, &shop);
+
+            Ok(shop.clone())
+        };
+
         server.send_message(
             client_id,
             NettyChannelServer::Shop,
             cosmos_encoder::serialize(&ServerShopMessages::SellResult {
                 shop_block,
                 structure_entity,
-                details: if let Err(error) = shop.sell(item_id, quantity, &mut credits) {
-                    Err(error)
-                } else {
-                    inventory.take_item(item, quantity as usize);
-
-                    Ok(shop.clone())
-                },
+                details,
             }),
         );
     }
@@ -167,11 +289,13 @@ fn listen_buy_events(
     mut server: ResMut<RenetServer>,
     mut ev_reader: EventReader<BuyEvent>,
     q_structure: Query<&Structure>,
-    mut q_shop_data: Query<&mut Shop>,
+    mut q_shop_data: Query<(&mut Shop, Option<&mut ShopMarket>)>,
     lobby: Res<ServerLobby>,
-    mut q_player: Query<(&mut Inventory, &mut Credits)>,
+    mut q_player: Query<(&mut Inventory, &mut Credits), Without<BlockData>>,
+    mut q_storage: Query<&mut Inventory, With<BlockData>>,
+    q_viewers: Query<(&Player, &ViewingShop)>,
     items: Res<Registry<Item>>,
-    default_shop_entries: Res<DefaultShopEntries>,
+    blocks: Res<Registry<Block>>,
 ) {
     for &BuyEvent {
         client_id,
@@ -196,7 +320,8 @@ fn listen_buy_events(
             continue;
         };
 
-        if !inventory.can_insert(item, quantity as u16) {
+        // Inventories can't take more than a u16's worth at once
+        let Some(stack_quantity) = u16::try_from(quantity).ok().filter(|&x| inventory.can_insert(item, x)) else {
             server.send_message(
                 client_id,
                 NettyChannelServer::Shop,
@@ -207,45 +332,143 @@ fn listen_buy_events(
                 }),
             );
             continue;
-        }
+        };
+
+        let Some((shop_ent, storage_ent)) = get_shop(structure_entity, shop_block, &q_structure, &blocks) else {
+            continue;
+        };
 
-        let Some(mut shop) = get_shop(structure_entity, shop_block, &default_shop_entries, &q_structure, &mut q_shop_data) else {
+        let Ok((mut shop, mut market)) = q_shop_data.get_mut(shop_ent) else {
             continue;
         };
 
-        match shop.buy(item_id, quantity, &mut credits) {
+        let mut storage = storage_ent.and_then(|x| q_storage.get_mut(x).ok());
+
+        update_stock(&mut shop, storage.as_deref(), &items);
+
+        let details = match shop.buy(item_id, quantity, &mut credits, market.as_ref().and_then(|x| x.item(item_id))) {
             Ok(_) => {
-                server.send_message(
-                    client_id,
-                    NettyChannelServer::Shop,
-                    cosmos_encoder::serialize(&ServerShopMessages::PurchaseResult {
-                        shop_block,
-                        structure_entity,
-                        details: Ok(shop.clone()),
-                    }),
-                );
+                if shop.owner.is_some() {
+                    if let Some(storage) = storage.as_mut() {
+                        storage.take_item(item, quantity as usize);
+                    }
+                }
+
+                inventory.insert(item, stack_quantity);
+
+                if let Some(market) = market.as_mut() {
+                    if let Some(market_item) = market.item_mut(item_id) {
+                        market_item.bought(quantity);
+                    }
 
-                inventory.insert(item, quantity as u16);
+                    market.update_shop(&mut shop);
+                }
+
+                send_to_viewers(&mut server, &q_viewers, client_id, structure_entity, shop_block, &shop);
+
+                Ok(shop.clone())
             }
-            Err(msg) => {
-                server.send_message(
-                    client_id,
-                    NettyChannelServer::Shop,
-                    cosmos_encoder::serialize(&ServerShopMessages::PurchaseResult {
-                        shop_block,
-                        structure_entity,
-                        details: Err(msg),
-                    }),
-                );
+            Err(msg) => Err(msg),
+        };
+
+        server.send_message(
+            client_id,
+            NettyChannelServer::Shop,
+            cosmos_encoder::serialize(&ServerShopMessages::PurchaseResult {
+                shop_block,
+                structure_entity,
+                details,
+            }),
+        );
+    }
+}
+
+fn listen_edit_events(
+    mut server: ResMut<RenetServer>,
+    mut ev_reader: EventReader<EditShopEvent>,
+    q_structure: Query<&Structure>,
+    mut q_shop_data: Query<&mut Shop>,
+    lobby: Res<ServerLobby>,
+    mut q_player: Query<(&Player, &mut Credits)>,
+    q_viewers: Query<(&Player, &ViewingShop)>,
+    q_storage: Query<&Inventory, With<BlockData>>,
+    items: Res<Registry<Item>>,
+    blocks: Res<Registry<Block>>,
+) {
+    for ev in ev_reader.read() {
+        let Some(player_ent) = lobby.player_from_id(ev.client_id) else {
+            error!("Bad player id: {}", ev.client_id);
+            continue;
+        };
+
+        let Ok((player, mut credits)) = q_player.get_mut(player_ent) else {
+            error!("No credits on player entity: {player_ent:?}");
+            continue;
+        };
+
+        let Some((shop_ent, storage_ent)) = get_shop(ev.structure_entity, ev.shop_block, &q_structure, &blocks) else {
+            continue;
+        };
+
+        let Ok(mut shop) = q_shop_data.get_mut(shop_ent) else {
+            continue;
+        };
+
+        let details = if !shop.is_owned_by(player.name()) {
+            Err(ShopEditError::NotOwner)
+        } else {
+            match ev.edit {
+                ShopEdit::SetEntry(entry) => {
+                    shop.set_entry(entry);
+                    Ok(())
+                }
+                ShopEdit::RemoveEntry(entry) => {
+                    shop.remove_entry(&entry);
+                    Ok(())
+                }
+                ShopEdit::WithdrawCredits => {
+                    credits.increase(shop.credits.amount());
+                    shop.credits = Credits::new(0);
+                    Ok(())
+                }
+                ShopEdit::DepositCredits(amount) => {
+                    if credits.decrease(amount) {
+                        shop.credits.increase(amount);
+                        Ok(())
+                    } else {
+                        Err(ShopEditError::InsufficientFunds)
+                    }
+                }
             }
-        }
+            .map(|_| {
+                update_stock(&mut shop, storage_ent.and_then(|x| q_storage.get(x).ok()), &items);
+
+                send_to_viewers(&mut server, &q_viewers, ev.client_id, ev.structure_entity, ev.shop_block, &shop);
+
+                shop.clone()
+            })
+        };
+
+        server.send_message(
+            ev.client_id,
+            NettyChannelServer::Shop,
+            cosmos_encoder::serialize(&ServerShopMessages::EditResult {
+                shop_block: ev.shop_block,
+                structure_entity: ev.structure_entity,
+                details,
+            }),
+        );
     }
 }
 
 fn listen_client_shop_messages(
+    mut commands: Commands,
     mut ev_writer_buy: EventWriter<BuyEvent>,
     mut ev_writer_sell: EventWriter<SellEvent>,
+    mut ev_writer_edit: EventWriter<EditShopEvent>,
     mut server: ResMut<RenetServer>,
+    lobby: Res<ServerLobby>,
+    q_viewing: Query<&ViewingShop>,
 ) {
     for client_id in server.clients_id() {
         while let Some(message) = server.receive_message(client_id, NettyChannelClient::Shop) {
@@ -283,6 +506,71 @@ fn listen_client_shop_messages(
                         structure_entity,
                     });
                 }
+                ClientShopMessages::SetEntry {
+                    shop_block,
+                    structure_entity,
+                    entry,
+                } => {
+                    ev_writer_edit.send(EditShopEvent {
+                        client_id,
+                        shop_block,
+                        structure_entity,
+                        edit: ShopEdit::SetEntry(entry),
+                    });
+                }
+                ClientShopMessages::RemoveEntry {
+                    shop_block,
+                    structure_entity,
+                    entry,
+                } => {
+                    ev_writer_edit.send(EditShopEvent {
+                        client_id,
+                        shop_block,
+                        structure_entity,
+                        edit: ShopEdit::RemoveEntry(entry),
+                    });
+                }
+                ClientShopMessages::WithdrawCredits {
+                    shop_block,
+                    structure_entity,
+                } => {
+                    ev_writer_edit.send(EditShopEvent {
+                        client_id,
+                        shop_block,
+                        structure_entity,
+                        edit: ShopEdit::WithdrawCredits,
+                    });
+                }
+                ClientShopMessages::DepositCredits {
+                    shop_block,
+                    structure_entity,
+                    amount,
+                } => {
+                    ev_writer_edit.send(EditShopEvent {
+                        client_id,
+                        shop_block,
+                        structure_entity,
+                        edit: ShopEdit::DepositCredits(amount),
+                    });
+                }
+                ClientShopMessages::CloseShop {
+                    shop_block,
+                    structure_entity,
+                } => {
+                    let Some(player_ent) = lobby.player_from_id(client_id) else {
+                        continue;
+                    };
+
+                    // They may have already opened another shop
+                    let closed = ViewingShop {
+                        structure_entity,
+                        shop_block,
+                    };
+
+                    if q_viewing.get(player_ent).map(|x| *x == closed).unwrap_or(false) {
+                        commands.entity(player_ent).remove::<ViewingShop>();
+                    }
+                }
             }
         }
     }
@@ -296,11 +584,13 @@ pub(super) fn register(app: &mut App) {
             listen_client_shop_messages,
             listen_buy_events,
             listen_sell_events,
+            listen_edit_events,
         )
             .chain()
             .run_if(in_state(GameState::Playing))
             .after(NetworkingSystemsSet::ProcessReceivedMessages),
     )
     .add_event::<BuyEvent>()
-    .add_event::<SellEvent>();
+    .add_event::<SellEvent>()
+    .add_event::<EditShopEvent>();
 }
@@ -0,0 +1,78 @@
+//! Runs the supply & demand simulation for shops that aren't owned by players
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::Query,
+    },
+    time::common_conditions::on_timer,
+};
+use cosmos_core::{
+    physics::location::Sector,
+    shop::{
+        market::{MarketItem, ShopMarket},
+        Shop, ShopEntry,
+    },
+};
+use rand::Rng;
+
+use crate::{init::init_world::ServerSeed, rng::get_rng_for_sector, state::GameState};
+
+/// How often every shop's market is simulated
+const SIMULATION_INTERVAL: Duration = Duration::from_secs(10);
+/// How much stock a shop aims to have of items it only buys
+const DEFAULT_TARGET_STOCK: u32 = 1_000;
+
+/// Creates the market for a shop in this sector.
+///
+/// Every item's scarcity is seeded from the sector, so nearby shops in the same sector agree on what's rare, while
+/// shops in other sectors will have different prices worth trading between.
+pub fn generate_market(entries: &[ShopEntry], sector: Sector, server_seed: &ServerSeed) -> ShopMarket {
+    let mut rng = get_rng_for_sector(server_seed, &sector);
+    let mut market = ShopMarket::default();
+
+    for entry in entries {
+        let scarcity = rng.gen_range(0.6..1.6);
+
+        if market.item_mut(entry.item_id()).is_some() {
+            continue;
+        }
+
+        let (base_price, target_stock) = match *entry {
+            ShopEntry::Selling {
+                max_quantity_selling,
+                price_per,
+                ..
+            } => (price_per, max_quantity_selling),
+            ShopEntry::Buying { price_per, .. } => (price_per, DEFAULT_TARGET_STOCK),
+        };
+
+        // Rare items are harder to keep in stock
+        let target_stock = (target_stock as f32 / scarcity) as u32;
+
+        market
+            .items
+            .push(MarketItem::new(entry.item_id(), base_price, scarcity, target_stock));
+    }
+
+    market
+}
+
+fn simulate_markets(mut q_shops: Query<(&mut Shop, &mut ShopMarket)>) {
+    for (mut shop, mut market) in q_shops.iter_mut() {
+        market.simulate(SIMULATION_INTERVAL.as_secs_f32());
+        market.update_shop(&mut shop);
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(
+        Update,
+        simulate_markets
+            .run_if(on_timer(SIMULATION_INTERVAL))
+            .run_if(in_state(GameState::Playing)),
+    );
+}
@@ -4,10 +4,14 @@ use bevy::app::App;
 
 mod ev_reader;
 mod generate_shop;
+pub mod market;
+mod payouts;
 pub mod prices;
 
 pub(super) fn register(app: &mut App) {
     ev_reader::register(app);
     generate_shop::register(app);
     prices::register(app);
+    market::register(app);
+    payouts::register(app);
 }
@@ -0,0 +1,97 @@
+//! Pays the owners of player-owned shops the credits their shop had when it was removed.
+//!
+//! Owners that aren't online are paid the next time they join. These payouts are saved with the world.
+
+use std::{collections::HashMap, fs};
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        event::EventReader,
+        query::Without,
+        system::{Query, ResMut, Resource},
+    },
+    log::{error, info},
+};
+use cosmos_core::{economy::Credits, entities::player::Player, shop::ShopRemovedEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::netty::netty_events::PlayerConnecting,
+    persistence::{world::world_directory, write_atomically},
+};
+
+fn payouts_file() -> String {
+    format!("{}/shop_payouts.json", world_directory())
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+/// The credits owed to each player that wasn't online when their shop was removed
+struct PendingPayouts(HashMap<String, u64>);
+
+impl PendingPayouts {
+    fn load() -> Self {
+        let path = payouts_file();
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|e| panic!("Invalid shop payouts file ({path}) - {e}"))
+    }
+
+    fn save(&self) {
+        _ = fs::create_dir_all(world_directory());
+
+        let json = serde_json::to_string_pretty(self).expect("Unable to serialize shop payouts");
+
+        if let Err(e) = write_atomically(payouts_file(), json) {
+            error!("Unable to save shop payouts - {e}");
+        }
+    }
+}
+
+fn pay_removed_shop_credits(
+    mut ev_reader: EventReader<ShopRemovedEvent>,
+    mut q_players: Query<(&Player, &mut Credits), Without<PlayerConnecting>>,
+    mut payouts: ResMut<PendingPayouts>,
+) {
+    for ev in ev_reader.read() {
+        let Some(owner) = &ev.shop.owner else {
+            continue;
+        };
+
+        let amount = ev.shop.credits.amount();
+        if amount == 0 {
+            continue;
+        }
+
+        if let Some((_, mut credits)) = q_players.iter_mut().find(|(player, _)| player.name() == owner) {
+            credits.increase(amount);
+        } else {
+            *payouts.0.entry(owner.clone()).or_default() += amount;
+            payouts.save();
+
+            info!("{owner}'s shop was removed while they were offline - they will be paid {amount} credits when they join.");
+        }
+    }
+}
+
+/// Pays players that are owed credits once they've finished joining
+fn pay_joined_players(mut q_players: Query<(&Player, &mut Credits), Without<PlayerConnecting>>, mut payouts: ResMut<PendingPayouts>) {
+    if payouts.0.is_empty() {
+        return;
+    }
+
+    for (player, mut credits) in q_players.iter_mut() {
+        if let Some(amount) = payouts.0.remove(player.name()) {
+            credits.increase(amount);
+            payouts.save();
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.insert_resource(PendingPayouts::load())
+        .add_systems(Update, (pay_removed_shop_credits, pay_joined_players));
+}
@@ -1,4 +1,4 @@
-//! Temporary: generates default shop prices
+//! Generates the base prices shops use before supply & demand are factored in
 
 use std::fs;
 
@@ -438,7 +438,9 @@ fn get_entries(entries: Vec<PrettyShopEntry>, items: &Registry<Item>) -> Vec<Sho
 }
 
 #[derive(Resource)]
-/// Contains the default entries for a shop
+/// Contains the entries every generated shop starts with.
+///
+/// Their prices & quantities are only a base - each shop's market adjusts them (see [`super::market`]).
 pub struct DefaultShopEntries(pub Vec<ShopEntry>);
 
 pub(super) fn register(app: &mut App) {
@@ -0,0 +1,180 @@
+//! Shuts the server down without losing anything.
+//!
+//! A shutdown is started by the `stop` command, or when the server receives SIGINT/SIGTERM (ctrl+c). Every player is
+//! told the server is stopping and then disconnected, which saves them. With no players left, every other entity
+//! unloads the same way it would if all players had flown away, which saves them too. Once nothing is left to save,
+//! the server exits.
+//!
+//! Receiving a second signal while shutting down exits immediately without saving anything else.
+
+use std::{
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{
+    app::{App, AppExit, Update},
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        query::{With, Without},
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Query, Res, ResMut, Resource},
+    },
+    log::{error, info, warn},
+    time::Time,
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    chat::ServerChatMessages,
+    ecs::NeedsDespawned,
+    entities::player::Player,
+    netty::{cosmos_encoder, NettyChannelServer},
+    persistence::LoadingDistance,
+};
+
+use crate::{
+    persistence::{saving::NeedsSaved, writer::SaveFileWriter},
+    state::GameState,
+};
+
+/// How long (in seconds) players are warned before they are disconnected
+const SHUTDOWN_WARNING_TIME: f32 = 1.0;
+/// How long (in seconds) the server waits for everything to save before giving up and exiting anyway
+const MAX_UNLOADING_TIME: f32 = 30.0;
+
+#[derive(Event, Debug, Default)]
+/// Send this to save everything & stop the server
+pub struct RequestShutdownEvent;
+
+#[derive(Resource, Debug)]
+/// Set by the signal handler, which runs on its own thread
+struct ShutdownSignal(Arc<AtomicBool>);
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+enum ShutdownState {
+    #[default]
+    Running,
+    /// Players have been told the server is stopping, and will be disconnected once this runs out
+    Warning { time_left: f32 },
+    /// Waiting for every entity to unload, giving up once this runs out
+    Unloading { time_left: f32 },
+}
+
+fn listen_for_signals(signal: Res<ShutdownSignal>, mut ev_writer: EventWriter<RequestShutdownEvent>) {
+    if signal.0.swap(false, Ordering::Relaxed) {
+        info!("Received a shutdown signal.");
+        ev_writer.send_default();
+    }
+}
+
+fn begin_shutdown(mut ev_reader: EventReader<RequestShutdownEvent>, mut state: ResMut<ShutdownState>, mut server: ResMut<RenetServer>) {
+    if ev_reader.is_empty() {
+        return;
+    }
+
+    ev_reader.clear();
+
+    if *state != ShutdownState::Running {
+        return;
+    }
+
+    info!("Stopping the server...");
+
+    server.broadcast_message(
+        NettyChannelServer::Chat,
+        cosmos_encoder::serialize(&ServerChatMessages::Message {
+            sender: None,
+            message: "The server is shutting down.".into(),
+        }),
+    );
+
+    *state = ShutdownState::Warning {
+        time_left: SHUTDOWN_WARNING_TIME,
+    };
+}
+
+fn finish_shutdown(
+    mut state: ResMut<ShutdownState>,
+    mut server: ResMut<RenetServer>,
+    q_players: Query<(), With<Player>>,
+    q_needs_saved: Query<(), With<NeedsSaved>>,
+    q_loaded: Query<(), (With<LoadingDistance>, Without<NeedsDespawned>)>,
+    mut writer: ResMut<SaveFileWriter>,
+    time: Res<Time>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    match *state {
+        ShutdownState::Running => {}
+        ShutdownState::Warning { time_left } => {
+            let time_left = time_left - time.delta_seconds();
+
+            if time_left > 0.0 {
+                *state = ShutdownState::Warning { time_left };
+            } else {
+                info!("Disconnecting all players & saving the world...");
+
+                server.disconnect_all();
+
+                *state = ShutdownState::Unloading {
+                    time_left: MAX_UNLOADING_TIME,
+                };
+            }
+        }
+        ShutdownState::Unloading { time_left } => {
+            // Nobody should be able to join while the server is shutting down
+            server.disconnect_all();
+
+            if q_players.is_empty() && q_needs_saved.is_empty() && q_loaded.is_empty() && writer.is_idle() {
+                info!("Everything is saved - goodbye!");
+                app_exit.send(AppExit);
+                return;
+            }
+
+            let time_left = time_left - time.delta_seconds();
+
+            if time_left > 0.0 {
+                *state = ShutdownState::Unloading { time_left };
+            } else {
+                warn!("Gave up waiting for everything to save - some entities may not have been saved.");
+                // Whatever has already been saved should at least make it to disk
+                writer.flush();
+                app_exit.send(AppExit);
+            }
+        }
+    }
+}
+
+fn set_signal_handler() -> ShutdownSignal {
+    let signal = Arc::new(AtomicBool::new(false));
+    let handler_signal = signal.clone();
+    let received_signal = AtomicBool::new(false);
+
+    if let Err(e) = ctrlc::set_handler(move || {
+        if received_signal.swap(true, Ordering::Relaxed) {
+            warn!("Received a second shutdown signal - exiting without saving.");
+            process::exit(1);
+        }
+
+        info!("Shutting down - send the signal again to exit without saving.");
+        handler_signal.store(true, Ordering::Relaxed);
+    }) {
+        error!("Unable to listen for shutdown signals - {e}");
+    }
+
+    ShutdownSignal(signal)
+}
+
+pub(super) fn register(app: &mut App) {
+    app.insert_resource(set_signal_handler())
+        .init_resource::<ShutdownState>()
+        .add_event::<RequestShutdownEvent>()
+        .add_systems(
+            Update,
+            (listen_for_signals, begin_shutdown, finish_shutdown)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
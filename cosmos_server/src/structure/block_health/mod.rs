@@ -1,15 +1,21 @@
 //! This handles what to do when a block is destroyed
 
-use bevy::prelude::{in_state, App, EventReader, EventWriter, IntoSystemConfigs, Query, Res, ResMut, Update};
+use bevy::{
+    prelude::{in_state, App, EventReader, EventWriter, IntoSystemConfigs, Query, Res, ResMut, Update},
+    utils::HashMap,
+};
 use bevy_renet::renet::RenetServer;
 use cosmos_core::{
     block::Block,
+    entities::player::Player,
     events::block_events::BlockChangedEvent,
     netty::{
         cosmos_encoder,
         server_reliable_messages::{BlockHealthUpdate, ServerReliableMessages},
         NettyChannelServer,
     },
+    persistence::LoadingDistance,
+    physics::location::Location,
     registry::Registry,
     structure::{
         block_health::events::{BlockDestroyedEvent, BlockTakeDamageEvent},
@@ -17,7 +23,7 @@ use cosmos_core::{
     },
 };
 
-use crate::state::GameState;
+use crate::{netty::interest::has_block_loaded, state::GameState};
 
 fn monitor_block_destroyed(
     mut event_reader: EventReader<BlockDestroyedEvent>,
@@ -32,18 +38,36 @@ fn monitor_block_destroyed(
     }
 }
 
-fn monitor_block_health_changed(mut server: ResMut<RenetServer>, mut event_reader: EventReader<BlockTakeDamageEvent>) {
-    let changes = event_reader
-        .read()
-        .map(|ev| BlockHealthUpdate {
-            block: ev.block,
-            new_health: ev.new_health,
-            structure_entity: ev.structure_entity,
-        })
-        .collect::<Vec<BlockHealthUpdate>>();
-
-    if !changes.is_empty() {
-        server.broadcast_message(
+/// Health changes are only sent to players that have the damaged block's chunk loaded
+fn monitor_block_health_changed(
+    mut server: ResMut<RenetServer>,
+    q_players: Query<(&Player, &Location)>,
+    q_structure: Query<(&Structure, &Location, Option<&LoadingDistance>)>,
+    mut event_reader: EventReader<BlockTakeDamageEvent>,
+) {
+    let mut changes = HashMap::new();
+
+    for ev in event_reader.read() {
+        let Ok((structure, structure_location, loading_distance)) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        for (player, player_location) in q_players.iter() {
+            if !has_block_loaded(player_location, structure, structure_location, loading_distance, ev.block.coords()) {
+                continue;
+            }
+
+            changes.entry(player.id()).or_insert_with(Vec::new).push(BlockHealthUpdate {
+                block: ev.block,
+                new_health: ev.new_health,
+                structure_entity: ev.structure_entity,
+            });
+        }
+    }
+
+    for (client_id, changes) in changes {
+        server.send_message(
+            client_id,
             NettyChannelServer::Reliable,
             cosmos_encoder::serialize(&ServerReliableMessages::BlockHealthChange { changes }),
         );
@@ -4,6 +4,7 @@ use bevy::prelude::App;
 
 pub mod asteroid;
 pub mod block_health;
+pub mod ownership;
 pub mod persistence;
 pub mod planet;
 pub mod server_structure_builder;
@@ -18,6 +19,7 @@ pub(super) fn register(app: &mut App) {
     planet::register(app);
     block_health::register(app);
     asteroid::register(app);
+    ownership::register(app);
 
     persistence::register(app);
     shared::register(app);
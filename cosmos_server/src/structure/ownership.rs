@@ -0,0 +1,79 @@
+//! Saves who owns each structure, and checks if players are allowed to use them
+
+use bevy::{
+    app::App,
+    ecs::{
+        entity::Entity,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query},
+    },
+};
+use bevy_renet::renet::{ClientId, RenetServer};
+use cosmos_core::{
+    chat::ServerChatMessages,
+    faction::Factions,
+    netty::{cosmos_encoder, NettyChannelServer},
+    structure::ownership::{has_permission, StructureOwner, StructurePermission},
+};
+
+use crate::{
+    commands::operators::Operators,
+    persistence::{
+        loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
+        saving::{SavingSystemSet, SAVING_SCHEDULE},
+        SerializedData,
+    },
+};
+
+/// Returns true if this player can do this to a structure with this owner.
+///
+/// Operators can do anything to any structure. If they can't, the player is told why.
+pub fn check_permission(
+    owner: Option<&StructureOwner>,
+    player_name: &str,
+    client_id: ClientId,
+    permission: StructurePermission,
+    factions: &Factions,
+    operators: &Operators,
+    server: &mut RenetServer,
+) -> bool {
+    if operators.is_operator(player_name) || has_permission(owner, player_name, permission, factions) {
+        return true;
+    }
+
+    let owner_name = owner.map(|x| x.player_name()).unwrap_or_default();
+
+    server.send_message(
+        client_id,
+        NettyChannelServer::Chat,
+        cosmos_encoder::serialize(&ServerChatMessages::Message {
+            sender: None,
+            message: format!(
+                "You cannot {} this structure - it belongs to {owner_name}.",
+                permission.description()
+            ),
+        }),
+    );
+
+    false
+}
+
+fn on_save_owner(mut q_owner: Query<(&mut SerializedData, &StructureOwner)>) {
+    for (mut serialized_data, owner) in q_owner.iter_mut() {
+        serialized_data.serialize_data("cosmos:structure_owner", owner);
+    }
+}
+
+fn on_load_owner(mut commands: Commands, query: Query<(Entity, &SerializedData), With<NeedsLoaded>>) {
+    for (entity, serialized_data) in query.iter() {
+        if let Some(owner) = serialized_data.deserialize_data::<StructureOwner>("cosmos:structure_owner") {
+            commands.entity(entity).insert(owner);
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(LOADING_SCHEDULE, on_load_owner.in_set(LoadingSystemSet::DoLoading))
+        .add_systems(SAVING_SCHEDULE, on_save_owner.in_set(SavingSystemSet::DoSaving));
+}
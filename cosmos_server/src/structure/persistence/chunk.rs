@@ -53,11 +53,13 @@ fn save_dynamic_structure_block_data(
 
 /// Fixed structures have their block data stored on the structure itself.
 ///
+/// Planet chunks being saved have a [`SaveChunk`], and save their own block data.
+///
 /// Perhaps reevaluate this in the future?
 fn save_fixed_structure_block_data(
     q_structure: Query<&Structure, Without<NeedsDespawned>>,
     mut q_serialized_data: Query<&mut SerializedData>,
-    mut q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), With<NeedsSaved>>,
+    mut q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), (With<NeedsSaved>, Without<SaveChunk>)>,
     mut commands: Commands,
 ) {
     let mut all_block_data = HashMap::<Entity, AllBlockData>::default();
@@ -94,7 +96,7 @@ fn save_fixed_structure_block_data(
 fn done_blueprinting_block_data_fixed_structure(
     q_structure: Query<&Structure, Without<NeedsDespawned>>,
     q_serialized_data: Query<&mut SerializedData>,
-    q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), With<NeedsSaved>>,
+    q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), (With<NeedsSaved>, Without<SaveChunk>)>,
     commands: Commands,
 ) {
     save_fixed_structure_block_data(q_structure, q_serialized_data, q_chunks, commands);
@@ -103,7 +105,7 @@ fn done_blueprinting_block_data_fixed_structure(
 fn done_saving_block_data_fixed_structure(
     q_structure: Query<&Structure, Without<NeedsDespawned>>,
     q_serialized_data: Query<&mut SerializedData>,
-    q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), With<NeedsSaved>>,
+    q_chunks: Query<(Entity, &ChunkEntity, &mut SerializedBlockData), (With<NeedsSaved>, Without<SaveChunk>)>,
     commands: Commands,
 ) {
     save_fixed_structure_block_data(q_structure, q_serialized_data, q_chunks, commands);
@@ -1,7 +1,15 @@
 //! Responsible for the default generation of biospheres.
 
-use crate::{init::init_world::ServerSeed, state::GameState, structure::planet::biosphere::biome::GenerateChunkFeaturesEvent};
-use bevy::{prelude::*, utils::hashbrown::HashSet};
+use crate::{
+    init::init_world::ServerSeed, persistence::write_atomically, settings::ServerSettings, state::GameState,
+    structure::planet::biosphere::biome::GenerateChunkFeaturesEvent,
+};
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::hashbrown::HashSet,
+};
 use bevy_app_compute::prelude::*;
 use cosmos_core::{
     block::{Block, BlockFace},
@@ -15,10 +23,12 @@ use cosmos_core::{
         planet::{
             generation::{
                 biome::{Biome, BiomeParameters, BiosphereBiomesRegistry},
+                cpu_terrain_generation::CpuTerrainGenerator,
                 terrain_generation::{
                     add_terrain_compute_worker, BiosphereShaderWorker, ChunkData, ChunkDataSlice, GenerationParams, GpuPermutationTable,
                     TerrainData, U32Vec4, N_CHUNKS,
                 },
+                terrain_golden::{golden_params, serialize_golden, GOLDEN_SEED},
             },
             Planet,
         },
@@ -26,6 +36,8 @@ use cosmos_core::{
     },
     utils::array_utils::{flatten, flatten_4d},
 };
+use futures_lite::future;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use super::{Biosphere, BiosphereMarkerComponent, TGenerateChunkEvent};
 
@@ -47,6 +59,10 @@ pub(crate) struct GeneratingChunks(Vec<NeedGeneratedChunk>);
 #[derive(Resource, Default)]
 pub(crate) struct SentToGpuTime(f32);
 
+#[derive(Resource, Default)]
+/// The chunks currently being generated on the CPU, if the server is generating terrain without a GPU
+struct CpuGenerationTask(Option<Task<Vec<TerrainData>>>);
+
 #[derive(Event)]
 pub(crate) struct DoneGeneratingChunkEvent {
     needs_generated_chunk: Option<NeedGeneratedChunk>,
@@ -74,6 +90,14 @@ fn read_gpu_data(
     let v: Vec<TerrainData> = worker.try_read_vec("values").expect("Failed to read chunk generation values!");
     *chunk_data = ChunkData::new(v);
 
+    send_done_generating_events(&mut currently_generating_chunks, &mut ev_writer);
+}
+
+/// Sends a [`DoneGeneratingChunkEvent`] for every chunk that was being generated, once [`ChunkData`] has their values.
+fn send_done_generating_events(
+    currently_generating_chunks: &mut GeneratingChunks,
+    ev_writer: &mut EventWriter<MutEvent<DoneGeneratingChunkEvent>>,
+) {
     for (w, needs_generated_chunk) in std::mem::take(&mut currently_generating_chunks.0).into_iter().enumerate() {
         let chunk_data_slice = ChunkDataSlice {
             start: flatten_4d(0, 0, 0, w, CHUNK_DIMENSIONS_USIZE, CHUNK_DIMENSIONS_USIZE, CHUNK_DIMENSIONS_USIZE),
@@ -269,6 +293,63 @@ fn send_chunks_to_gpu(
     }
 }
 
+/// Same as [`send_chunks_to_gpu`], but for servers generating terrain on the CPU
+fn send_chunks_to_cpu(
+    mut currently_generating_chunks: ResMut<GeneratingChunks>,
+    mut needs_generated_chunks: ResMut<NeedGeneratedChunks>,
+    perm_table: Res<GpuPermutationTable>,
+    mut cpu_task: ResMut<CpuGenerationTask>,
+) {
+    if !currently_generating_chunks.0.is_empty() || needs_generated_chunks.0.is_empty() {
+        return;
+    }
+
+    for _ in 0..N_CHUNKS {
+        let Some(doing) = needs_generated_chunks.0.pop() else {
+            break;
+        };
+
+        currently_generating_chunks.0.push(doing);
+    }
+
+    let todo = currently_generating_chunks
+        .0
+        .iter()
+        .map(|x| x.generation_params)
+        .collect::<Vec<GenerationParams>>();
+
+    let generator = CpuTerrainGenerator::new(&perm_table);
+
+    let thread_pool = AsyncComputeTaskPool::get();
+
+    cpu_task.0 = Some(thread_pool.spawn(async move {
+        // Laid out the same way the GPU would return it - one chunk after the other
+        todo.par_iter().flat_map_iter(|params| generator.generate_chunk(params)).collect()
+    }));
+}
+
+/// Same as [`read_gpu_data`], but for servers generating terrain on the CPU
+fn read_cpu_data(
+    mut cpu_task: ResMut<CpuGenerationTask>,
+    mut ev_writer: EventWriter<MutEvent<DoneGeneratingChunkEvent>>,
+    mut currently_generating_chunks: ResMut<GeneratingChunks>,
+    mut chunk_data: ResMut<ChunkData>,
+) {
+    let Some(task) = &mut cpu_task.0 else {
+        return;
+    };
+
+    let Some(v) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+
+    cpu_task.0 = None;
+
+    *chunk_data = ChunkData::new(v);
+
+    send_done_generating_events(&mut currently_generating_chunks, &mut ev_writer);
+}
+
 /// Calls generate_face_chunk, generate_edge_chunk, and generate_corner_chunk to generate the chunks of a planet.
 pub(crate) fn generate_planet<T: BiosphereMarkerComponent, E: TGenerateChunkEvent>(
     mut query: Query<(&mut Structure, &Location)>,
@@ -343,33 +424,55 @@ fn set_permutation_table(perm_table: Res<GpuPermutationTable>, mut worker: ResMu
     worker.write_slice("permutation_table", &perm_table.0);
 }
 
-/// https://github.com/Mapet13/opensimplex_noise_rust/blob/master/src/lib.rs#L54
-fn generate_perm_table(seed: u64) -> GpuPermutationTable {
-    let mut perm = [0; GpuPermutationTable::TALBE_SIZE];
+/// Generates the golden terrain on the GPU & saves it to the file passed in with `--capture-terrain-golden`, then stops the server.
+///
+/// See [`cosmos_core::structure::planet::generation::terrain_golden`]
+fn capture_terrain_golden(
+    mut worker: ResMut<AppComputeWorker<BiosphereShaderWorker>>,
+    mut sent_to_gpu: Local<bool>,
+    biospheres: Res<Registry<Biosphere>>,
+    server_settings: Res<ServerSettings>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(path) = &server_settings.capture_terrain_golden else {
+        return;
+    };
 
-    let mut source: Vec<i64> = (0..GpuPermutationTable::TALBE_SIZE).map(|x| x as i64).collect();
+    let grass = biospheres.from_id("cosmos:grass").expect("Missing cosmos:grass biosphere");
+    let golden_params = golden_params(grass.id() as u32);
 
-    let seed: i128 = (seed as i128 * 6_364_136_223_846_793_005) + 1_442_695_040_888_963_407;
-    for i in (0..GpuPermutationTable::TALBE_SIZE).rev() {
-        let mut r = ((seed + 31) % (i as i128 + 1)) as i64;
-        if r < 0 {
-            r += (i + 1) as i64;
-        }
-        perm[i] = source[r as usize];
-        source[r as usize] = source[i];
+    if !*sent_to_gpu {
+        let mut todo = [GenerationParams::default(); N_CHUNKS as usize];
+        todo[..golden_params.len()].copy_from_slice(&golden_params);
+
+        worker.write_slice("permutation_table", &GpuPermutationTable::from_seed(GOLDEN_SEED).0);
+        worker.write("params", &todo);
+        worker.write("chunk_count", &(golden_params.len() as u32));
+
+        worker.execute();
+
+        *sent_to_gpu = true;
+        return;
     }
 
-    GpuPermutationTable(
-        perm.into_iter()
-            .array_chunks::<4>()
-            // Unfortunately must truncate the i64 to u32 to play nice with the gpu
-            .map(|[x, y, z, w]| U32Vec4::new(x as u32, y as u32, z as u32, w as u32))
-            .collect(),
-    )
+    if !worker.ready() {
+        return;
+    }
+
+    let values: Vec<TerrainData> = worker.try_read_vec("values").expect("Failed to read chunk generation values!");
+    let n_values = golden_params.len() * CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE * CHUNK_DIMENSIONS_USIZE;
+
+    match write_atomically(path, serialize_golden(&values[..n_values])) {
+        Ok(()) => info!("Saved the golden terrain to {path}."),
+        Err(e) => error!("Unable to save the golden terrain to {path} - {e}"),
+    }
+
+    app_exit.send(AppExit);
 }
 
+/// https://github.com/Mapet13/opensimplex_noise_rust/blob/master/src/lib.rs#L54
 fn setup_permutation_table(seed: Res<ServerSeed>, mut commands: Commands) {
-    let permutation_table = generate_perm_table(seed.as_u64());
+    let permutation_table = GpuPermutationTable::from_seed(seed.as_u64());
 
     commands.insert_resource(permutation_table);
 }
@@ -381,6 +484,8 @@ pub enum BiosphereGenerationSet {
     FlagChunksNeedGenerated,
     /// Chunk generation requests are sent to the GPU when it is available for new generations. This is handled for all biospheres
     /// automatically that put their chunk requests in [`NeedGeneratedChunks`]
+    ///
+    /// If the server is generating terrain on the CPU, the requests are instead generated on the CPU in this set.
     GpuInteraction,
     /// Chunks that are ready to be populated with blocks are now sent and can be read via the EventReader for [`DoneGeneratingChunkEvent`].
     GenerateChunks,
@@ -389,6 +494,17 @@ pub enum BiosphereGenerationSet {
 }
 
 pub(super) fn register(app: &mut App) {
+    let cpu_terrain_generation = app
+        .world
+        .get_resource::<ServerSettings>()
+        .map(|settings| settings.cpu_terrain_generation)
+        .unwrap_or(false);
+    let capture_terrain_golden = app
+        .world
+        .get_resource::<ServerSettings>()
+        .map(|settings| settings.capture_terrain_golden.is_some())
+        .unwrap_or(false);
+
     app.configure_sets(
         Update,
         (
@@ -400,20 +516,39 @@ pub(super) fn register(app: &mut App) {
             .run_if(in_state(GameState::Playing))
             .chain(),
     )
-    .add_plugins(AppComputeWorkerPlugin::<BiosphereShaderWorker>::default())
     .add_systems(OnEnter(GameState::PreLoading), setup_permutation_table)
-    .add_systems(OnExit(GameState::PostLoading), add_terrain_compute_worker)
-    .add_systems(OnEnter(GameState::Playing), set_permutation_table)
-    .add_systems(
-        Update,
-        (send_chunks_to_gpu, read_gpu_data)
-            .in_set(BiosphereGenerationSet::GpuInteraction)
-            .chain(),
-    )
     .add_systems(Update, send_chunk_init_event.in_set(BiosphereGenerationSet::GenerateChunkFeatures))
     .init_resource::<NeedGeneratedChunks>()
     .init_resource::<GeneratingChunks>()
     .init_resource::<ChunkData>()
-    .init_resource::<SentToGpuTime>()
     .add_mut_event::<DoneGeneratingChunkEvent>();
+
+    if cpu_terrain_generation {
+        info!("Generating planet terrain on the CPU.");
+
+        app.add_systems(
+            Update,
+            (send_chunks_to_cpu, read_cpu_data)
+                .in_set(BiosphereGenerationSet::GpuInteraction)
+                .chain(),
+        )
+        .init_resource::<CpuGenerationTask>();
+    } else if capture_terrain_golden {
+        info!("Capturing the golden terrain - the server will stop once it's saved.");
+
+        app.add_plugins(AppComputeWorkerPlugin::<BiosphereShaderWorker>::default())
+            .add_systems(OnExit(GameState::PostLoading), add_terrain_compute_worker)
+            .add_systems(Update, capture_terrain_golden.in_set(BiosphereGenerationSet::GpuInteraction));
+    } else {
+        app.add_plugins(AppComputeWorkerPlugin::<BiosphereShaderWorker>::default())
+            .add_systems(OnExit(GameState::PostLoading), add_terrain_compute_worker)
+            .add_systems(OnEnter(GameState::Playing), set_permutation_table)
+            .add_systems(
+                Update,
+                (send_chunks_to_gpu, read_gpu_data)
+                    .in_set(BiosphereGenerationSet::GpuInteraction)
+                    .chain(),
+            )
+            .init_resource::<SentToGpuTime>();
+    }
 }
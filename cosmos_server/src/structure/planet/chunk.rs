@@ -4,30 +4,41 @@ use bevy::{
     app::Update,
     ecs::{
         entity::Entity,
+        event::EventReader,
+        query::Without,
         schedule::{IntoSystemSetConfigs, SystemSet},
-        system::{Commands, ResMut},
+        system::{Commands, ResMut, Resource},
     },
     log::warn,
     prelude::{App, Component, IntoSystemConfigs, Query, With},
+    utils::{HashMap, HashSet},
 };
 use bevy_renet::renet::{ClientId, RenetServer};
 use cosmos_core::{
+    ecs::NeedsDespawned,
+    events::block_events::BlockChangedEvent,
     netty::{cosmos_encoder, server_reliable_messages::ServerReliableMessages, system_sets::NetworkingSystemsSet, NettyChannelServer},
+    physics::location::Location,
     structure::{
         chunk::{netty::SerializedBlockData, Chunk, ChunkEntity},
+        coordinates::ChunkCoordinate,
+        planet::Planet,
         Structure,
     },
 };
 
 use crate::{
     persistence::{
+        autosave::SaveEverythingEvent,
         loading::LoadingSystemSet,
         saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
-        SerializedData,
+        EntityId, SaveFileIdentifier, SerializedData,
     },
     structure::persistence::BlockDataNeedsSaved,
 };
 
+use super::persistence::chunk_save_file_identifier;
+
 #[derive(Component, Debug)]
 /// A component used to indicate that a chunk needs saving
 pub struct SaveChunk(pub Chunk);
@@ -38,6 +49,108 @@ fn save_chunks(mut query: Query<(&mut SerializedData, &SaveChunk), With<NeedsSav
     }
 }
 
+#[derive(Resource, Debug, Default)]
+/// The chunks of each planet that had blocks changed since they were last saved
+struct ChangedPlanetChunks(HashMap<Entity, HashSet<ChunkCoordinate>>);
+
+#[derive(Component, Debug)]
+/// A loaded chunk being saved by [`save_changed_planet_chunks`]. Everything added to save it is removed once it's saved.
+struct SavingLoadedChunk;
+
+fn track_changed_planet_chunks(
+    mut ev_reader: EventReader<BlockChangedEvent>,
+    q_planets: Query<(), With<Planet>>,
+    mut changed_chunks: ResMut<ChangedPlanetChunks>,
+) {
+    for ev in ev_reader.read() {
+        if q_planets.contains(ev.structure_entity) {
+            changed_chunks
+                .0
+                .entry(ev.structure_entity)
+                .or_default()
+                .insert(ChunkCoordinate::for_block_coordinate(ev.block.coords()));
+        }
+    }
+}
+
+/// Planet chunks are normally only saved when they're unloaded, so this saves the loaded ones that changed.
+///
+/// Chunks with block data are always saved, since changes to their block data (such as a chest's items) aren't tracked.
+fn save_changed_planet_chunks(
+    mut ev_reader: EventReader<SaveEverythingEvent>,
+    q_planets: Query<(Entity, &Structure, &EntityId, Option<&SaveFileIdentifier>, &Location), (With<Planet>, Without<NeedsDespawned>)>,
+    mut changed_chunks: ResMut<ChangedPlanetChunks>,
+    mut commands: Commands,
+) {
+    if ev_reader.is_empty() {
+        return;
+    }
+
+    ev_reader.clear();
+
+    // Planets that have never been saved don't have an entity id yet, so their chunks are saved once they're unloaded
+    for (planet_entity, structure, entity_id, planet_sfi, location) in q_planets.iter() {
+        let changed = changed_chunks.0.remove(&planet_entity).unwrap_or_default();
+
+        for chunk in structure.chunks().values() {
+            let coords = chunk.chunk_coordinates();
+
+            if !changed.contains(&coords) && chunk.all_block_data_entities().is_empty() {
+                continue;
+            }
+
+            let Some(chunk_entity) = structure.chunk_entity(coords) else {
+                continue;
+            };
+
+            let mut ecmds = commands.entity(chunk_entity);
+
+            ecmds.insert((
+                chunk_save_file_identifier(coords, entity_id, planet_sfi, location),
+                SaveChunk(chunk.clone()),
+                SavingLoadedChunk,
+                NeedsSaved,
+            ));
+
+            if !chunk.all_block_data_entities().is_empty() {
+                ecmds.insert(SerializedBlockData::new(coords));
+            }
+
+            for (_, &entity) in chunk.all_block_data_entities() {
+                commands.entity(entity).insert(BlockDataNeedsSaved);
+            }
+        }
+    }
+
+    // Planets that are gone saved their chunks as they were unloaded
+    changed_chunks.0.retain(|planet_entity, _| q_planets.contains(*planet_entity));
+}
+
+fn done_saving_loaded_chunks(
+    q_saved_chunks: Query<(Entity, &ChunkEntity), With<SavingLoadedChunk>>,
+    q_structure: Query<&Structure>,
+    mut commands: Commands,
+) {
+    for (entity, chunk_ent) in q_saved_chunks.iter() {
+        // The chunk is still loaded, so it shouldn't be treated as a saved entity (which would delete its file when it's despawned)
+        commands
+            .entity(entity)
+            .remove::<(SaveChunk, SavingLoadedChunk, SaveFileIdentifier, EntityId, SerializedBlockData)>();
+
+        let Some(chunk) = q_structure
+            .get(chunk_ent.structure_entity)
+            .ok()
+            .and_then(|structure| structure.chunk_from_chunk_coordinates(chunk_ent.chunk_location))
+        else {
+            continue;
+        };
+
+        for (_, &block_data_ent) in chunk.all_block_data_entities() {
+            commands.entity(block_data_ent).remove::<BlockDataNeedsSaved>();
+        }
+    }
+}
+
 #[derive(Debug, Component)]
 /// A component used to indicate that a chunk needs to be sent to the listed clients
 pub struct ChunkNeedsSent {
@@ -125,7 +238,16 @@ fn send_chunks(
 }
 
 pub(super) fn register(app: &mut App) {
-    app.add_systems(SAVING_SCHEDULE, save_chunks.in_set(SavingSystemSet::DoSaving));
+    app.add_systems(SAVING_SCHEDULE, save_chunks.in_set(SavingSystemSet::DoSaving))
+        .add_systems(
+            SAVING_SCHEDULE,
+            (
+                save_changed_planet_chunks.before(SavingSystemSet::BeginSaving),
+                done_saving_loaded_chunks.after(SavingSystemSet::DoneSaving),
+            ),
+        )
+        .add_systems(Update, track_changed_planet_chunks)
+        .init_resource::<ChangedPlanetChunks>();
 
     app.configure_sets(
         Update,
@@ -1,13 +1,16 @@
 //! Structure serialization/deserialization
 
-use std::fs;
-
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
 use bevy_rapier3d::prelude::PhysicsWorld;
 use cosmos_core::{
-    block::data::persistence::ChunkLoadBlockDataEvent,
+    block::{data::persistence::ChunkLoadBlockDataEvent, Block},
+    item::Item,
     netty::{cosmos_encoder, NoSendEntity},
     physics::location::Location,
+    registry::Registry,
     structure::{
         chunk::{netty::SerializedChunkBlockData, Chunk, ChunkEntity},
         coordinates::{ChunkCoordinate, CoordinateType},
@@ -16,11 +19,14 @@ use cosmos_core::{
         ChunkInitEvent, Structure,
     },
 };
+use futures_lite::future;
 use serde::{Deserialize, Serialize};
 
 use crate::persistence::{
-    loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
+    id_palettes::remap_ids,
+    loading::{read_save_file, LoadingSystemSet, NeedsLoaded, ReadError, LOADING_SCHEDULE},
     saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
+    writer::SaveFileWriter,
     EntityId, SaveFileIdentifier, SerializedData,
 };
 
@@ -88,67 +94,123 @@ fn structure_created(created: Query<Entity, (Added<Structure>, Without<EntityId>
     }
 }
 
+/// The save file identifier of the planet chunk at these coordinates, which is saved in its planet's folder
+pub(crate) fn chunk_save_file_identifier(
+    coords: ChunkCoordinate,
+    planet_id: &EntityId,
+    planet_sfi: Option<&SaveFileIdentifier>,
+    planet_location: &Location,
+) -> SaveFileIdentifier {
+    let (cx, cy, cz): (CoordinateType, CoordinateType, CoordinateType) = coords.into();
+
+    let planet_sfi = planet_sfi
+        .cloned()
+        .unwrap_or_else(|| SaveFileIdentifier::new(Some(planet_location.sector()), planet_id.clone(), None));
+
+    SaveFileIdentifier::as_child(format!("{cx}_{cy}_{cz}"), planet_sfi)
+}
+
+#[derive(Component)]
+/// A chunk's save file being read in the background
+struct ChunkReadTask {
+    chunk_coords: ChunkCoordinate,
+    structure_entity: Entity,
+    path: String,
+    task: Task<Result<SerializedData, ReadError>>,
+}
+
 fn populate_chunks(
     query: Query<(Entity, &ChunkNeedsPopulated)>,
-    structure_query: Query<(&EntityId, Option<&SaveFileIdentifier>, &Location, &PhysicsWorld)>,
+    structure_query: Query<(&EntityId, Option<&SaveFileIdentifier>, &Location)>,
+    writer: Res<SaveFileWriter>,
     mut commands: Commands,
 ) {
+    let thread_pool = IoTaskPool::get();
+
     for (entity, needs) in query.iter() {
-        let Ok((entity_id, structure_svi, loc, physics_world)) = structure_query.get(needs.structure_entity) else {
+        let Ok((entity_id, structure_svi, loc)) = structure_query.get(needs.structure_entity) else {
             commands.entity(entity).remove::<ChunkNeedsPopulated>();
 
             continue;
         };
 
-        let (cx, cy, cz): (CoordinateType, CoordinateType, CoordinateType) = needs.chunk_coords.into();
+        let path = chunk_save_file_identifier(needs.chunk_coords, entity_id, structure_svi, loc).get_save_file_path();
+
+        if writer.is_pending(&path) {
+            // This chunk is still being saved, just try again next frame or whenever it's available
+            continue;
+        }
+
+        let task_path = path.clone();
+        let task = thread_pool.spawn(async move { read_save_file(&task_path) });
+
+        commands
+            .entity(entity)
+            .insert(ChunkReadTask {
+                chunk_coords: needs.chunk_coords,
+                structure_entity: needs.structure_entity,
+                path,
+                task,
+            })
+            .remove::<ChunkNeedsPopulated>();
+    }
+}
+
+/// Loads the chunks that have been read, and generates the ones that have never been saved
+fn finish_reading_chunks(
+    mut query: Query<(Entity, &mut ChunkReadTask)>,
+    structure_query: Query<&PhysicsWorld>,
+    blocks: Res<Registry<Block>>,
+    items: Res<Registry<Item>>,
+    mut commands: Commands,
+) {
+    for (entity, mut read_task) in query.iter_mut() {
+        let Some(result) = future::block_on(future::poll_once(&mut read_task.task)) else {
+            continue;
+        };
+
+        commands.entity(entity).remove::<ChunkReadTask>();
 
-        let svi = if let Some(structure_svi) = structure_svi {
-            SaveFileIdentifier::as_child(format!("{cx}_{cy}_{cz}"), structure_svi.clone())
-        } else {
-            SaveFileIdentifier::as_child(
-                format!("{cx}_{cy}_{cz}"),
-                SaveFileIdentifier::new(Some(loc.sector()), entity_id.clone(), None),
-            )
+        let Ok(physics_world) = structure_query.get(read_task.structure_entity) else {
+            continue;
         };
 
-        if let Ok(chunk) = fs::read(svi.get_save_file_path()) {
-            if chunk.is_empty() {
-                // This can happen if the file is currently being saved, just try again next frame or whenever it's available
-                continue;
-            }
+        let needs_generated = ChunkNeedsGenerated {
+            coords: read_task.chunk_coords,
+            structure_entity: read_task.structure_entity,
+        };
 
-            let serialized_data = cosmos_encoder::deserialize::<SerializedData>(&chunk).unwrap_or_else(|_| {
-                panic!(
-                    "Error parsing chunk @ {cx} {cy} {cz} - is the file corrupted? File len: {}",
-                    chunk.len()
-                )
-            });
+        match result {
+            Ok(mut serialized_data) => {
+                remap_ids(&mut serialized_data, &blocks, &items);
 
-            commands
-                .entity(entity)
-                .insert((
+                commands.entity(entity).insert((
                     serialized_data,
                     NeedsLoaded,
                     Name::new("Needs Loaded Chunk"),
                     NoSendEntity,
                     ChunkEntity {
-                        structure_entity: needs.structure_entity,
-                        chunk_location: needs.chunk_coords,
+                        structure_entity: read_task.structure_entity,
+                        chunk_location: read_task.chunk_coords,
                     },
                     *physics_world,
-                ))
-                .remove::<ChunkNeedsPopulated>();
-        } else {
-            commands
-                .entity(entity)
-                .insert((
-                    ChunkNeedsGenerated {
-                        coords: needs.chunk_coords,
-                        structure_entity: needs.structure_entity,
-                    },
-                    Name::new("Needs Generated Chunk"),
-                ))
-                .remove::<ChunkNeedsPopulated>();
+                ));
+            }
+            Err(ReadError::Missing) => {
+                commands
+                    .entity(entity)
+                    .insert((needs_generated, Name::new("Needs Generated Chunk")));
+            }
+            Err(ReadError::Corrupted) => {
+                error!(
+                    "Error deserializing chunk {} - is the file corrupted? It will be generated again.",
+                    read_task.path
+                );
+
+                commands
+                    .entity(entity)
+                    .insert((needs_generated, Name::new("Needs Generated Chunk")));
+            }
         }
     }
 }
@@ -196,7 +258,7 @@ fn load_chunk(
 }
 
 pub(super) fn register(app: &mut App) {
-    app.add_systems(Update, (structure_created, populate_chunks).chain())
+    app.add_systems(Update, (structure_created, populate_chunks, finish_reading_chunks).chain())
         .add_systems(SAVING_SCHEDULE, on_save_structure.in_set(SavingSystemSet::DoSaving))
         .add_systems(LOADING_SCHEDULE, on_load_structure.in_set(LoadingSystemSet::DoLoading))
         .add_systems(LOADING_SCHEDULE, load_chunk.in_set(LoadingSystemSet::DoLoading));
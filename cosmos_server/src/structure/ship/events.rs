@@ -19,6 +19,7 @@ use cosmos_core::{
         coordinates::ChunkCoordinate,
         full_structure::FullStructure,
         loading::StructureLoadingSet,
+        ownership::StructureOwner,
         ship::{ship_builder::TShipBuilder, ship_movement::ShipMovement},
         Structure,
     },
@@ -76,6 +77,8 @@ pub struct CreateShipEvent {
     pub ship_location: Location,
     /// The rotation of the ship
     pub rotation: Quat,
+    /// The player that created this ship
+    pub owner: StructureOwner,
 }
 
 pub(crate) fn create_ship_event_reader(mut event_reader: EventReader<CreateShipEvent>, mut commands: Commands) {
@@ -90,7 +93,7 @@ pub(crate) fn create_ship_event_reader(mut event_reader: EventReader<CreateShipE
 
         builder.insert_ship(&mut entity, ev.ship_location, Velocity::zero(), &mut structure);
 
-        entity.insert(structure).insert(ShipNeedsCreated);
+        entity.insert((structure, ShipNeedsCreated, ev.owner.clone()));
     }
 }
 
@@ -4,7 +4,7 @@ use bevy::prelude::*;
 use cosmos_core::{
     physics::location::Location,
     structure::{
-        coordinates::ChunkCoordinate, full_structure::FullStructure, loading::StructureLoadingSet,
+        coordinates::ChunkCoordinate, full_structure::FullStructure, loading::StructureLoadingSet, ownership::StructureOwner,
         station::station_builder::TStationBuilder, Structure,
     },
 };
@@ -20,6 +20,8 @@ pub struct CreateStationEvent {
     pub station_location: Location,
     /// The rotation of the station
     pub rotation: Quat,
+    /// The player that created this station
+    pub owner: StructureOwner,
 }
 
 pub(crate) fn create_station_event_reader(mut event_reader: EventReader<CreateStationEvent>, mut commands: Commands) {
@@ -32,7 +34,7 @@ pub(crate) fn create_station_event_reader(mut event_reader: EventReader<CreateSt
 
         builder.insert_station(&mut entity, ev.station_location, &mut structure);
 
-        entity.insert(structure).insert(StationNeedsCreated);
+        entity.insert((structure, StationNeedsCreated, ev.owner.clone()));
     }
 }
 
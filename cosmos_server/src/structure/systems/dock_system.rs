@@ -24,14 +24,18 @@ use bevy_rapier3d::{
 };
 use cosmos_core::{
     block::Block,
+    entities::player::Player,
     events::block_events::BlockChangedEvent,
+    faction::Factions,
     physics::structure_physics::ChunkPhysicsPart,
     registry::{identifiable::Identifiable, Registry},
     structure::{
         events::StructureLoadedEvent,
         full_structure::FullStructure,
         loading::StructureLoadingSet,
+        ownership::{has_permission, StructureOwner, StructurePermission},
         shields::SHIELD_COLLISION_GROUP,
+        ship::pilot::Pilot,
         systems::{
             dock_system::{DockSystem, Docked},
             StructureSystem, StructureSystemType, StructureSystems, SystemActive,
@@ -111,6 +115,7 @@ fn on_active(
     q_velocity: Query<&Velocity>,
     q_docked_list: Query<&DockedEntities>,
     mut commands: Commands,
+    (q_owner, q_pilot, q_player, factions): (Query<&StructureOwner>, Query<&Pilot>, Query<&Player>, Res<Factions>),
 ) {
     for e in q_inactive.iter() {
         commands.entity(e).remove::<JustUndocked>();
@@ -195,6 +200,24 @@ fn on_active(
                 continue;
             };
 
+            // Whoever is flying the ship docks it, or its owner if nobody is
+            let docker_name = q_pilot
+                .get(ss.structure_entity())
+                .ok()
+                .and_then(|pilot| q_player.get(pilot.entity).ok())
+                .map(|player| player.name().as_str())
+                .or_else(|| q_owner.get(ss.structure_entity()).ok().map(|owner| owner.player_name()))
+                .unwrap_or_default();
+
+            if !has_permission(
+                q_owner.get(structure_entity).ok(),
+                docker_name,
+                StructurePermission::Dock,
+                &factions,
+            ) {
+                continue;
+            }
+
             let hit_block_face = hit_structure.block_rotation(hit_coords).local_front();
             let hit_rotation = Quat::from_affine3(&hit_g_trans.affine());
             let front_direction = hit_rotation.mul_vec3(hit_block_face.direction_vec3());
@@ -56,14 +56,15 @@ fn block_update_system(
 
 fn update_energy(
     sys_query: Query<&StructureSystems>,
-    e_gen_query: Query<(&EnergyGenerationSystem, &StructureSystem)>,
+    e_gen_query: Query<&StructureSystem, With<EnergyGenerationSystem>>,
     mut e_storage_query: Query<&mut EnergyStorageSystem>,
     time: Res<Time>,
 ) {
-    for (gen, system) in e_gen_query.iter() {
+    for system in e_gen_query.iter() {
         if let Ok(systems) = sys_query.get(system.structure_entity()) {
             if let Ok(mut storage) = systems.query_mut(&mut e_storage_query) {
-                storage.increase_energy(gen.energy_generation_rate() * time.delta_seconds());
+                // Each power network generates its own energy, so only the storage system knows how it is split up
+                storage.generate(time.delta_seconds());
             }
         }
     }
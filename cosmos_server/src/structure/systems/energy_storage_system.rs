@@ -1,23 +1,36 @@
 //! Represents all the energy stored on a structure
 
-use bevy::prelude::{in_state, App, Commands, EventReader, IntoSystemConfigs, OnEnter, Query, Res, ResMut, Update};
+use bevy::{
+    ecs::{component::Component, entity::Entity, query::With},
+    prelude::{in_state, App, Commands, EventReader, IntoSystemConfigs, OnEnter, Query, Res, ResMut, Update},
+    utils::HashSet,
+};
 
 use cosmos_core::{
     block::Block,
     events::block_events::BlockChangedEvent,
-    registry::Registry,
+    registry::{identifiable::Identifiable, Registry},
     structure::{
+        coordinates::BlockCoordinate,
         events::StructureLoadedEvent,
         loading::StructureLoadingSet,
         systems::{
-            energy_storage_system::{EnergyStorageBlocks, EnergyStorageProperty, EnergyStorageSystem},
+            energy_generation_system::EnergyGenerationBlocks,
+            energy_storage_system::{power_grid::PowerBlock, EnergyStorageBlocks, EnergyStorageProperty, EnergyStorageSystem},
             StructureSystemType, StructureSystems,
         },
         Structure,
     },
 };
 
-use crate::state::GameState;
+use crate::{
+    persistence::{
+        loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
+        saving::{NeedsSaved, SavingSystemSet, SAVING_SCHEDULE},
+        SerializedData,
+    },
+    state::GameState,
+};
 
 use super::sync::register_structure_system;
 
@@ -31,46 +44,128 @@ fn register_energy_blocks(blocks: Res<Registry<Block>>, mut storage: ResMut<Ener
     }
 }
 
+/// Every block power cables connect to is part of the power grid, and can store & generate power if it is
+/// registered as an energy storage or generation block.
+fn power_block(
+    block: &Block,
+    power_cable: &Block,
+    energy_storage_blocks: &EnergyStorageBlocks,
+    energy_generation_blocks: &EnergyGenerationBlocks,
+) -> Option<PowerBlock> {
+    if block.id() != power_cable.id() && !power_cable.should_connect_with(block) {
+        return None;
+    }
+
+    Some(PowerBlock {
+        block_id: block.id(),
+        capacity: energy_storage_blocks.get(block).map(|x| x.capacity).unwrap_or(0.0),
+        generation_rate: energy_generation_blocks.get(block).map(|x| x.generation_rate).unwrap_or(0.0),
+    })
+}
+
 fn block_update_system(
     mut event: EventReader<BlockChangedEvent>,
     energy_storage_blocks: Res<EnergyStorageBlocks>,
+    energy_generation_blocks: Res<EnergyGenerationBlocks>,
     blocks: Res<Registry<Block>>,
     mut system_query: Query<&mut EnergyStorageSystem>,
     systems_query: Query<&StructureSystems>,
 ) {
+    let Some(power_cable) = blocks.from_id("cosmos:power_cable") else {
+        return;
+    };
+
+    let mut changed_structures = HashSet::new();
+
     for ev in event.read() {
         if let Ok(systems) = systems_query.get(ev.structure_entity) {
             if let Ok(mut system) = systems.query_mut(&mut system_query) {
-                if let Some(prop) = energy_storage_blocks.get(blocks.from_numeric_id(ev.old_block)) {
-                    system.block_removed(prop);
-                }
+                system.block_removed(ev.block.coords());
 
-                if let Some(prop) = energy_storage_blocks.get(blocks.from_numeric_id(ev.new_block)) {
-                    system.block_added(prop);
+                if let Some(power_block) = power_block(
+                    blocks.from_numeric_id(ev.new_block),
+                    power_cable,
+                    &energy_storage_blocks,
+                    &energy_generation_blocks,
+                ) {
+                    system.block_added(ev.block.coords(), power_block);
                 }
+
+                changed_structures.insert(ev.structure_entity);
+            }
+        }
+    }
+
+    // Networks are only recalculated once per structure, no matter how many blocks changed
+    for structure_entity in changed_structures {
+        if let Ok(systems) = systems_query.get(structure_entity) {
+            if let Ok(mut system) = systems.query_mut(&mut system_query) {
+                system.recalculate_networks();
             }
         }
     }
 }
 
+#[derive(Component, Debug)]
+/// The energy a structure's storage blocks held when it was saved.
+///
+/// This is given back to them once the structure is done loading & its power grid is rebuilt.
+struct SavedStoredEnergy(Vec<(BlockCoordinate, f32)>);
+
+fn on_save_stored_energy(
+    mut q_needs_saved: Query<(&StructureSystems, &mut SerializedData), With<NeedsSaved>>,
+    q_energy_storage: Query<&EnergyStorageSystem>,
+) {
+    for (systems, mut sd) in q_needs_saved.iter_mut() {
+        if let Ok(energy_storage) = systems.query(&q_energy_storage) {
+            sd.serialize_data("cosmos:stored_energy", &energy_storage.stored_energy());
+        }
+    }
+}
+
+fn on_load_stored_energy(mut commands: Commands, q_needs_loaded: Query<(Entity, &SerializedData), With<NeedsLoaded>>) {
+    for (entity, sd) in q_needs_loaded.iter() {
+        if let Some(stored_energy) = sd.deserialize_data::<Vec<(BlockCoordinate, f32)>>("cosmos:stored_energy") {
+            commands.entity(entity).insert(SavedStoredEnergy(stored_energy));
+        }
+    }
+}
+
 fn structure_loaded_event(
     mut event_reader: EventReader<StructureLoadedEvent>,
-    mut structure_query: Query<(&Structure, &mut StructureSystems)>,
+    mut structure_query: Query<(&Structure, &mut StructureSystems, Option<&SavedStoredEnergy>)>,
     blocks: Res<Registry<Block>>,
     mut commands: Commands,
     energy_storage_blocks: Res<EnergyStorageBlocks>,
+    energy_generation_blocks: Res<EnergyGenerationBlocks>,
     registry: Res<Registry<StructureSystemType>>,
 ) {
+    let Some(power_cable) = blocks.from_id("cosmos:power_cable") else {
+        return;
+    };
+
     for ev in event_reader.read() {
-        if let Ok((structure, mut systems)) = structure_query.get_mut(ev.structure_entity) {
+        if let Ok((structure, mut systems, saved_stored_energy)) = structure_query.get_mut(ev.structure_entity) {
             let mut system = EnergyStorageSystem::default();
 
             for block in structure.all_blocks_iter(false) {
-                if let Some(prop) = energy_storage_blocks.get(block.block(structure, &blocks)) {
-                    system.block_added(prop);
+                if let Some(power_block) = power_block(
+                    block.block(structure, &blocks),
+                    power_cable,
+                    &energy_storage_blocks,
+                    &energy_generation_blocks,
+                ) {
+                    system.block_added(block.coords(), power_block);
                 }
             }
 
+            system.recalculate_networks();
+
+            if let Some(saved_stored_energy) = saved_stored_energy {
+                system.restore_stored_energy(&saved_stored_energy.0);
+                commands.entity(ev.structure_entity).remove::<SavedStoredEnergy>();
+            }
+
             systems.add_system(&mut commands, system, &registry);
         }
     }
@@ -87,6 +182,8 @@ pub(super) fn register(app: &mut App) {
             )
                 .run_if(in_state(GameState::Playing)),
         )
+        .add_systems(SAVING_SCHEDULE, on_save_stored_energy.in_set(SavingSystemSet::DoSaving))
+        .add_systems(LOADING_SCHEDULE, on_load_stored_energy.in_set(LoadingSystemSet::DoLoading))
         .register_type::<EnergyStorageSystem>();
 
     register_structure_system::<EnergyStorageSystem>(app, false, "cosmos:energy_cell");
@@ -2,16 +2,18 @@
 
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{ecs::query::Has, prelude::*};
 use bevy_rapier3d::prelude::{PhysicsWorld, Velocity, DEFAULT_WORLD_ID};
 use bevy_renet::renet::RenetServer;
 use cosmos_core::{
     block::Block,
+    logic::LogicSignals,
     netty::{cosmos_encoder, server_laser_cannon_system_messages::ServerStructureSystemMessages, NettyChannelServer},
     physics::location::Location,
     projectiles::laser::Laser,
     registry::Registry,
     structure::{
+        structure_block::StructureBlock,
         systems::{
             energy_storage_system::EnergyStorageSystem,
             laser_cannon_system::{LaserCannonCalculator, LaserCannonProperty, LaserCannonSystem, SystemCooldown},
@@ -45,7 +47,7 @@ fn register_laser_blocks(blocks: Res<Registry<Block>>, mut cannon: ResMut<LineBl
 pub const LASER_BASE_VELOCITY: f32 = 200.0;
 
 fn update_system(
-    mut query: Query<(&LaserCannonSystem, &StructureSystem, &mut SystemCooldown), With<SystemActive>>,
+    mut query: Query<(&LaserCannonSystem, &StructureSystem, &mut SystemCooldown, Has<SystemActive>)>,
     mut es_query: Query<&mut EnergyStorageSystem>,
     systems: Query<(
         Entity,
@@ -55,15 +57,31 @@ fn update_system(
         &GlobalTransform,
         &Velocity,
         Option<&PhysicsWorld>,
+        Option<&LogicSignals>,
     )>,
     time: Res<Time>,
     mut commands: Commands,
     mut server: ResMut<RenetServer>,
 ) {
-    for (cannon_system, system, mut cooldown) in query.iter_mut() {
-        if let Ok((ship_entity, systems, structure, location, global_transform, ship_velocity, physics_world)) =
+    for (cannon_system, system, mut cooldown, system_active) in query.iter_mut() {
+        if let Ok((ship_entity, systems, structure, location, global_transform, ship_velocity, physics_world, logic_signals)) =
             systems.get(system.structure_entity())
         {
+            // Lines fire when the pilot activates this system, or when any of their cannons are receiving a logic signal
+            let firing_lines = cannon_system
+                .lines
+                .iter()
+                .filter(|line| {
+                    system_active
+                        || logic_signals
+                            .is_some_and(|signals| signals.iter().any(|(coords, on)| on && line.within(&StructureBlock::new(coords))))
+                })
+                .collect::<Vec<_>>();
+
+            if firing_lines.is_empty() {
+                continue;
+            }
+
             if let Ok(mut energy_storage_system) = systems.query_mut(&mut es_query) {
                 let sec = time.elapsed_seconds();
 
@@ -74,10 +92,10 @@ fn update_system(
 
                     let mut any_fired = false;
 
-                    for line in cannon_system.lines.iter() {
-                        if energy_storage_system.get_energy() >= line.property.energy_per_shot {
+                    for line in firing_lines {
+                        if energy_storage_system.energy_at(line.start.coords()) >= line.property.energy_per_shot {
                             any_fired = true;
-                            energy_storage_system.decrease_energy(line.property.energy_per_shot);
+                            energy_storage_system.decrease_energy_at(line.start.coords(), line.property.energy_per_shot);
 
                             let location = structure.block_world_location(line.start.coords(), global_transform, location);
 
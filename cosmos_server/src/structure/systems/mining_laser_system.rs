@@ -153,7 +153,7 @@ fn update_mining_beams(
             continue;
         };
 
-        if energy_storage_system.decrease_energy(beam.property.energy_per_second * delta_time) != 0.0 {
+        if energy_storage_system.decrease_energy_at(beam.power_coords, beam.property.energy_per_second * delta_time) != 0.0 {
             commands.entity(entity).insert(NeedsDespawned);
             continue;
         }
@@ -273,6 +273,8 @@ struct MiningBeam {
     property: MiningLaserProperty,
     system_entity: Entity,
     structure_entity: Entity,
+    /// The block this beam draws its power through
+    power_coords: BlockCoordinate,
 }
 
 fn on_activate_system(
@@ -292,7 +294,7 @@ fn on_activate_system(
                 for line in mining_system.lines.iter() {
                     let energy = line.property.energy_per_second * sec;
 
-                    if energy_storage_system.decrease_energy(energy) == 0.0 {
+                    if energy_storage_system.decrease_energy_at(line.start.coords(), energy) == 0.0 {
                         let beam_direction = line.direction.direction_vec3();
 
                         let beam_begin = line.end();
@@ -305,6 +307,7 @@ fn on_activate_system(
                                     property: line.property,
                                     structure_entity: ship_entity,
                                     system_entity,
+                                    power_coords: line.start.coords(),
                                 },
                                 DespawnWithStructure,
                                 TransformBundle::from_transform(Transform::from_translation(rel_pos).looking_to(beam_direction, Vec3::Y)),
@@ -21,7 +21,7 @@ use cosmos_core::{
         collision_handling::{CollisionBlacklist, CollisionBlacklistedEntity},
         location::{CosmosBundleSet, Location},
     },
-    projectiles::missile::Missile,
+    projectiles::{missile::Missile, Causer},
     registry::Registry,
     structure::{
         systems::{
@@ -203,9 +203,9 @@ fn update_missile_system(
         let mut any_fired = false;
 
         for line in cannon_system.lines.iter() {
-            if energy_storage_system.get_energy() >= line.property.energy_per_shot {
+            if energy_storage_system.energy_at(line.start.coords()) >= line.property.energy_per_shot {
                 any_fired = true;
-                energy_storage_system.decrease_energy(line.property.energy_per_shot);
+                energy_storage_system.decrease_energy_at(line.start.coords(), line.property.energy_per_shot);
 
                 let location = structure.block_world_location(line.start.coords(), global_transform, location);
 
@@ -246,6 +246,7 @@ fn update_missile_system(
                         entity: system.structure_entity(),
                         search_parents: true,
                     }),
+                    Causer(system.structure_entity()),
                 ));
 
                 if let Some(targetting) = focus.locked_on_to() {
@@ -14,6 +14,7 @@ pub mod missile_launcher_system;
 mod shield_system;
 pub(crate) mod sync;
 mod thruster_system;
+pub mod turret_system;
 
 /// A system that is created by the addition and removal of blocks
 pub trait BlockStructureSystem<T> {
@@ -35,4 +36,5 @@ pub(super) fn register(app: &mut App) {
     mining_laser_system::register(app);
     energy_storage_system::register(app);
     missile_launcher_system::register(app);
+    turret_system::register(app);
 }
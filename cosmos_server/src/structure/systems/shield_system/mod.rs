@@ -295,7 +295,7 @@ fn power_shields(
                 continue;
             };
 
-            let not_used = ecs.decrease_energy(power_usage);
+            let not_used = ecs.decrease_energy_at(shield.block_coord, power_usage);
 
             let old_strength = shield.strength;
             shield.strength += (power_usage - not_used) * shield.power_efficiency;
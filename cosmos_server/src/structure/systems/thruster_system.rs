@@ -1,7 +1,5 @@
-use std::ops::Mul;
-
 use bevy::{
-    prelude::{in_state, App, Commands, EventReader, IntoSystemConfigs, OnEnter, Quat, Query, Res, ResMut, Transform, Update, Vec3, With},
+    prelude::{in_state, App, Commands, EventReader, IntoSystemConfigs, OnEnter, Query, Ref, Res, ResMut, Transform, Update, Vec3, With},
     time::Time,
 };
 use bevy_rapier3d::prelude::{ExternalImpulse, ReadMassProperties, Velocity};
@@ -12,11 +10,14 @@ use cosmos_core::{
     structure::{
         events::StructureLoadedEvent,
         loading::StructureLoadingSet,
-        ship::{pilot::Pilot, ship_movement::ShipMovement},
+        ship::{
+            pilot::Pilot,
+            ship_movement::{limit_speed, ShipMovement},
+        },
         systems::{
             dock_system::Docked,
             energy_storage_system::EnergyStorageSystem,
-            thruster_system::{ThrusterBlocks, ThrusterProperty, ThrusterSystem},
+            thruster_system::{ThrusterBlocks, ThrusterOutput, ThrusterProperty, ThrusterSystem},
             StructureSystem, StructureSystemType, StructureSystems,
         },
         Structure,
@@ -27,9 +28,6 @@ use crate::state::GameState;
 
 use super::sync::register_structure_system;
 
-const MAX_SHIP_SPEED: f32 = 200.0;
-const MAX_BRAKE_DELTA_PER_THRUST: f32 = 300.0;
-
 fn register_thruster_blocks(blocks: Res<Registry<Block>>, mut storage: ResMut<ThrusterBlocks>) {
     if let Some(block) = blocks.from_id("cosmos:thruster") {
         storage.insert(
@@ -37,6 +35,7 @@ fn register_thruster_blocks(blocks: Res<Registry<Block>>, mut storage: ResMut<Th
             ThrusterProperty {
                 strength: 10.0,
                 energy_consupmtion: 100.0,
+                omnidirectional: false,
             },
         );
     }
@@ -47,6 +46,7 @@ fn register_thruster_blocks(blocks: Res<Registry<Block>>, mut storage: ResMut<Th
             ThrusterProperty {
                 strength: 1.0,
                 energy_consupmtion: 100.0,
+                omnidirectional: true,
             },
         )
     }
@@ -57,23 +57,48 @@ fn block_update_system(
     energy_storage_blocks: Res<ThrusterBlocks>,
     blocks: Res<Registry<Block>>,
     mut system_query: Query<&mut ThrusterSystem>,
-    systems_query: Query<&StructureSystems>,
+    systems_query: Query<(&Structure, &StructureSystems)>,
 ) {
     for ev in event.read() {
-        if let Ok(systems) = systems_query.get(ev.structure_entity) {
+        if let Ok((structure, systems)) = systems_query.get(ev.structure_entity) {
             if let Ok(mut system) = systems.query_mut(&mut system_query) {
+                let coords = ev.block.coords();
+
                 if let Some(prop) = energy_storage_blocks.get(blocks.from_numeric_id(ev.old_block)) {
-                    system.block_removed(prop);
+                    system.block_removed(prop, coords);
                 }
 
                 if let Some(prop) = energy_storage_blocks.get(blocks.from_numeric_id(ev.new_block)) {
-                    system.block_added(prop);
+                    system.block_added(prop, coords, structure.block_relative_position(coords), ev.new_block_rotation);
                 }
             }
         }
     }
 }
 
+/// Thrusters' torque depends on where they are compared to the center of mass, which changes as blocks are placed & broken
+fn update_thruster_torque(mut q_thrusters: Query<(&mut ThrusterSystem, &StructureSystem)>, q_mass: Query<Ref<ReadMassProperties>>) {
+    for (mut thruster_system, system) in q_thrusters.iter_mut() {
+        let Ok(mass) = q_mass.get(system.structure_entity()) else {
+            continue;
+        };
+
+        if !mass.is_changed() && !thruster_system.is_changed() {
+            continue;
+        }
+
+        let torque = thruster_system.calculate_torque(mass.0.local_center_of_mass);
+
+        // Only mark this as changed (and sync it) if the torque actually changed
+        if thruster_system.torque() != torque {
+            thruster_system.set_torque(torque);
+        }
+    }
+}
+
+/// Ships spinning slower than this (in radians per second) aren't worth spending energy to stop
+const MIN_STABILIZED_ANGULAR_SPEED: f32 = 0.01;
+
 fn update_movement(
     thrusters_query: Query<(&ThrusterSystem, &StructureSystem)>,
     mut query: Query<
@@ -89,71 +114,52 @@ fn update_movement(
         With<Pilot>,
     >,
     mut energy_query: Query<&mut EnergyStorageSystem>,
+    thruster_blocks: Res<ThrusterBlocks>,
     time: Res<Time>,
 ) {
     for (thruster_system, system) in thrusters_query.iter() {
         if let Ok((movement, systems, transform, mut velocity, mut external_impulse, readmass, docked)) =
             query.get_mut(system.structure_entity())
         {
-            // Rotation
-            if docked.is_none() {
-                let torque = Quat::from_affine3(&transform.compute_affine()).mul(movement.torque * 5.0);
-
-                const MAX_ANGLE_PER_SECOND: f32 = 100.0;
-
-                let max = MAX_ANGLE_PER_SECOND * time.delta_seconds();
-
-                velocity.angvel = torque.clamp_length(0.0, max);
-
-                velocity.linvel = velocity.linvel.clamp_length(0.0, MAX_SHIP_SPEED);
-            }
-
-            // Position
-            let normal = movement.into_normal_vector();
-
-            let mut movement_vector = if normal.x == 0.0 && normal.y == 0.0 && normal.z == 0.0 {
-                Vec3::ZERO
+            let rotating = docked.is_none() && movement.torque != Vec3::ZERO;
+            // Thrusters counter any spin the pilot doesn't want, which uses energy the same as turning does
+            let stabilizing = docked.is_none() && !rotating && velocity.angvel.length() > MIN_STABILIZED_ANGULAR_SPEED;
+
+            let output = if movement.into_normal_vector() == Vec3::ZERO && !rotating && !stabilizing {
+                ThrusterOutput::default()
+            } else if let Ok(mut energy_system) = systems.query_mut(&mut energy_query) {
+                let energy_used = thruster_system.energy_consumption() * time.delta_seconds();
+
+                // Thrusters only push as hard as the power their own network could give them
+                let powered =
+                    energy_system.decrease_energy_spread_per_network(|block_id| thruster_blocks.is_thruster(block_id), energy_used);
+
+                thruster_system.output(readmass.0.local_center_of_mass, |coords| {
+                    energy_system
+                        .network_at(coords)
+                        .and_then(|network| powered.get(&network))
+                        .copied()
+                        .unwrap_or(0.0)
+                })
             } else {
-                let mut movement_vector = transform.forward() * normal.z;
-                movement_vector += transform.right() * normal.x;
-                movement_vector += transform.up() * normal.y;
-
-                movement_vector = movement_vector.normalize();
-
-                let delta = time.delta_seconds();
-
-                let mut energy_used = thruster_system.energy_consumption() * delta;
-
-                let ratio;
-
-                if let Ok(mut energy_system) = systems.query_mut(&mut energy_query) {
-                    if energy_used > energy_system.get_energy() {
-                        ratio = energy_system.get_energy() / energy_used;
-                        energy_used = energy_system.get_energy();
-                    } else {
-                        ratio = 1.0;
-                    }
-
-                    energy_system.decrease_energy(energy_used);
-
-                    movement_vector * (thruster_system.thrust_total() * ratio)
-                } else {
-                    Vec3::ZERO
-                }
+                ThrusterOutput::default()
             };
 
-            if movement.braking {
-                let mut brake_vec = -velocity.linvel * readmass.0.mass;
-                let delta = time.delta_seconds() * MAX_BRAKE_DELTA_PER_THRUST * thruster_system.thrust_total();
-
-                if brake_vec.length_squared() >= delta * delta {
-                    brake_vec = brake_vec.normalize() * delta;
-                }
+            if docked.is_none() {
+                external_impulse.torque_impulse +=
+                    movement.torque_impulse(transform, velocity.angvel, &readmass.0, &output, time.delta_seconds());
 
-                movement_vector += brake_vec;
+                limit_speed(&mut velocity);
             }
 
-            external_impulse.impulse += movement_vector;
+            external_impulse.impulse += movement.thrust_impulse(
+                transform,
+                velocity.linvel,
+                readmass.0.mass,
+                thruster_system,
+                &output,
+                time.delta_seconds(),
+            );
         }
     }
 }
@@ -172,7 +178,14 @@ fn structure_loaded_event(
 
             for block in structure.all_blocks_iter(false) {
                 if let Some(prop) = thruster_blocks.get(block.block(structure, &blocks)) {
-                    system.block_added(prop);
+                    let coords = block.coords();
+
+                    system.block_added(
+                        prop,
+                        coords,
+                        structure.block_relative_position(coords),
+                        structure.block_rotation(coords),
+                    );
                 }
             }
 
@@ -189,7 +202,7 @@ pub(super) fn register(app: &mut App) {
             (
                 structure_loaded_event.in_set(StructureLoadingSet::StructureLoaded),
                 block_update_system,
-                update_movement,
+                (update_thruster_torque, update_movement).chain(),
             )
                 .run_if(in_state(GameState::Playing)),
         )
@@ -0,0 +1,170 @@
+//! Keeps track of the turrets on each structure & lets players turn them on or off
+
+use bevy::{
+    ecs::query::Has,
+    prelude::{in_state, App, Commands, Component, Entity, EventReader, IntoSystemConfigs, OnEnter, Query, Res, ResMut, Update, With},
+};
+use bevy_renet::renet::RenetServer;
+use cosmos_core::{
+    block::{block_events::BlockInteractEvent, Block},
+    chat::ServerChatMessages,
+    entities::player::Player,
+    events::block_events::BlockChangedEvent,
+    netty::{cosmos_encoder, NettyChannelServer},
+    registry::Registry,
+    structure::{
+        events::StructureLoadedEvent,
+        loading::StructureLoadingSet,
+        systems::{
+            turret_system::{TurretBlocks, TurretProperty, TurretSystem},
+            StructureSystemType, StructureSystems,
+        },
+        Structure,
+    },
+};
+
+use crate::{
+    persistence::{
+        loading::{LoadingSystemSet, NeedsLoaded, LOADING_SCHEDULE},
+        saving::{SavingSystemSet, SAVING_SCHEDULE},
+        SerializedData,
+    },
+    state::GameState,
+};
+
+use super::sync::register_structure_system;
+
+#[derive(Component, Debug)]
+/// The turrets on this structure won't fire. Interacting with any turret toggles this.
+pub struct TurretsDisabled;
+
+fn register_turret_blocks(blocks: Res<Registry<Block>>, mut turret_blocks: ResMut<TurretBlocks>) {
+    if let Some(block) = blocks.from_id("cosmos:turret") {
+        turret_blocks.insert(
+            block,
+            TurretProperty {
+                energy_per_shot: 100.0,
+                strength: 8.0,
+                range: 600.0,
+            },
+        );
+    }
+}
+
+fn turret_block_update_system(
+    mut event: EventReader<BlockChangedEvent>,
+    turret_blocks: Res<TurretBlocks>,
+    blocks: Res<Registry<Block>>,
+    mut system_query: Query<&mut TurretSystem>,
+    q_systems: Query<&StructureSystems>,
+) {
+    for ev in event.read() {
+        let Ok(systems) = q_systems.get(ev.structure_entity) else {
+            continue;
+        };
+
+        let Ok(mut system) = systems.query_mut(&mut system_query) else {
+            continue;
+        };
+
+        if turret_blocks.get(blocks.from_numeric_id(ev.old_block)).is_some() {
+            system.block_removed(ev.block.coords());
+        }
+
+        if let Some(&property) = turret_blocks.get(blocks.from_numeric_id(ev.new_block)) {
+            system.block_added(property, ev.block.coords());
+        }
+    }
+}
+
+fn turret_structure_loaded_event_processor(
+    mut event_reader: EventReader<StructureLoadedEvent>,
+    mut structure_query: Query<(&Structure, &mut StructureSystems)>,
+    blocks: Res<Registry<Block>>,
+    mut commands: Commands,
+    turret_blocks: Res<TurretBlocks>,
+    registry: Res<Registry<StructureSystemType>>,
+) {
+    for ev in event_reader.read() {
+        if let Ok((structure, mut systems)) = structure_query.get_mut(ev.structure_entity) {
+            let mut system = TurretSystem::default();
+
+            for block in structure.all_blocks_iter(false) {
+                if let Some(&property) = turret_blocks.get(block.block(structure, &blocks)) {
+                    system.block_added(property, block.coords());
+                }
+            }
+
+            systems.add_system(&mut commands, system, &registry);
+        }
+    }
+}
+
+fn toggle_turrets(
+    mut interact_events: EventReader<BlockInteractEvent>,
+    q_structure: Query<(&Structure, Has<TurretsDisabled>)>,
+    blocks: Res<Registry<Block>>,
+    q_player: Query<&Player>,
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+) {
+    for ev in interact_events.read() {
+        let Ok((structure, disabled)) = q_structure.get(ev.structure_entity) else {
+            continue;
+        };
+
+        if structure.block_at(ev.structure_block.coords(), &blocks).unlocalized_name() != "cosmos:turret" {
+            continue;
+        }
+
+        let message = if disabled {
+            commands.entity(ev.structure_entity).remove::<TurretsDisabled>();
+            "Turrets enabled - they will fire at anything hostile to this structure's owner."
+        } else {
+            commands.entity(ev.structure_entity).insert(TurretsDisabled);
+            "Turrets disabled."
+        };
+
+        if let Ok(player) = q_player.get(ev.interactor) {
+            server.send_message(
+                player.id(),
+                NettyChannelServer::Chat,
+                cosmos_encoder::serialize(&ServerChatMessages::Message {
+                    sender: None,
+                    message: message.into(),
+                }),
+            );
+        }
+    }
+}
+
+fn on_save_turrets_disabled(mut q_disabled: Query<&mut SerializedData, With<TurretsDisabled>>) {
+    for mut serialized_data in q_disabled.iter_mut() {
+        serialized_data.serialize_data("cosmos:turrets_disabled", &true);
+    }
+}
+
+fn on_load_turrets_disabled(mut commands: Commands, query: Query<(Entity, &SerializedData), With<NeedsLoaded>>) {
+    for (entity, serialized_data) in query.iter() {
+        if serialized_data.deserialize_data::<bool>("cosmos:turrets_disabled").unwrap_or(false) {
+            commands.entity(entity).insert(TurretsDisabled);
+        }
+    }
+}
+
+pub(super) fn register(app: &mut App) {
+    app.add_systems(OnEnter(GameState::PostLoading), register_turret_blocks)
+        .add_systems(
+            Update,
+            (
+                turret_structure_loaded_event_processor.in_set(StructureLoadingSet::StructureLoaded),
+                turret_block_update_system,
+                toggle_turrets,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(LOADING_SCHEDULE, on_load_turrets_disabled.in_set(LoadingSystemSet::DoLoading))
+        .add_systems(SAVING_SCHEDULE, on_save_turrets_disabled.in_set(SavingSystemSet::DoSaving));
+
+    register_structure_system::<TurretSystem>(app, false, "cosmos:turret");
+}
@@ -0,0 +1,89 @@
+//! Inspects & repairs Cosmos worlds without starting the game.
+//!
+//! Run this from the server's directory (the one its `worlds` directory is in), and never while the server is
+//! running - the server doesn't expect its files to change underneath it.
+
+#![warn(missing_docs)]
+
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use world::World;
+
+mod save_file;
+mod world;
+
+/// The directory every world is saved in - this must match the server's
+const WORLDS_DIRECTORY: &str = "worlds";
+/// The world the server plays if it isn't given one
+const DEFAULT_WORLD_NAME: &str = "world";
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+/// Inspects & repairs Cosmos worlds without starting the game
+struct Args {
+    /// The name of the world to open
+    #[arg(long, default_value_t = DEFAULT_WORLD_NAME.to_owned())]
+    world: String,
+
+    /// The directory of the world to open. Use this instead of `--world` for worlds outside of the `worlds` directory.
+    #[arg(long)]
+    directory: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lists every sector with entities saved in it, and every player
+    List,
+    /// Prints the contents of a save file (.cent) or blueprint (.bp) as JSON
+    Dump {
+        /// The file to print
+        file: PathBuf,
+    },
+    /// Replaces every block of one type with another in the world
+    RewriteBlock {
+        /// The unlocalized name of the block to replace, such as `cosmos:stone`
+        from: String,
+        /// The unlocalized name of the block to replace it with
+        to: String,
+        /// Also replace the block in every blueprint in this directory
+        #[arg(long)]
+        blueprints: Option<PathBuf>,
+    },
+    /// Deletes an entity & everything saved with it
+    Delete {
+        /// The id of the entity to delete
+        entity_id: String,
+    },
+    /// Checks that every save file can be read & everything saved with an entity still has that entity
+    Validate {
+        /// Delete anything saved with an entity that no longer exists, and any half-written saves
+        #[arg(long, default_value_t = false)]
+        fix: bool,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    // Dumping a file doesn't need a world, since blueprints aren't saved in one
+    if let Command::Dump { file } = &args.command {
+        return save_file::dump(file);
+    }
+
+    let directory = args.directory.unwrap_or_else(|| Path::new(WORLDS_DIRECTORY).join(&args.world));
+    let world = World::open(directory)?;
+
+    match args.command {
+        Command::List => world.list(),
+        Command::Dump { .. } => unreachable!("Handled above"),
+        Command::RewriteBlock { from, to, blueprints } => world.rewrite_block(&from, &to, blueprints.as_deref())?,
+        Command::Delete { entity_id } => world.delete(&entity_id)?,
+        Command::Validate { fix } => world.validate(fix)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Reading, writing & describing individual save files (`.cent`) and blueprints (`.bp`)
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use cosmos_core::{
+    economy::Credits,
+    entities::player::{game_mode::GameMode, render_distance::RenderDistance},
+    netty::cosmos_encoder,
+    persistence::{LoadingDistance, SerializedData},
+    physics::location::Location,
+    registry::id_palette::IdPalette,
+    structure::{blueprint::Blueprint, ownership::StructureOwner},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Reads & decodes the save file at this path. Use [`read_blueprint`] for blueprints.
+pub fn read(path: &Path) -> anyhow::Result<SerializedData> {
+    let data = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+
+    cosmos_encoder::deserialize(&data).map_err(|e| anyhow!("{} isn't a valid save file - {e}", path.display()))
+}
+
+/// Encodes & writes this data to the file at `path`.
+pub fn write(path: &Path, serialized_data: &SerializedData) -> io::Result<()> {
+    write_bytes(path, &cosmos_encoder::serialize(serialized_data))
+}
+
+/// Returns true if the file at this path is a blueprint
+pub fn is_blueprint(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("bp"))
+}
+
+/// Reads & decodes the blueprint at this path, including its header if it has one
+pub fn read_blueprint(path: &Path) -> anyhow::Result<Blueprint> {
+    let data = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+
+    Blueprint::decode(&data).map_err(|e| anyhow!("{} isn't a valid blueprint - {e}", path.display()))
+}
+
+/// Encodes & writes this blueprint to the file at `path`.
+///
+/// Blueprints without a header are written the same way they were read, so older versions of the game can still load them.
+pub fn write_blueprint(path: &Path, blueprint: &Blueprint) -> io::Result<()> {
+    match &blueprint.header.metadata {
+        Some(metadata) => write_bytes(path, &Blueprint::encode(metadata, &blueprint.data)),
+        None => write(path, &blueprint.data),
+    }
+}
+
+/// Like the server, this writes to a temporary file first, so the original is never left half-written.
+fn write_bytes(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+
+    fs::rename(&temp_path, path)
+}
+
+/// Unlike [`SerializedData::deserialize_data`], this won't panic if the data is invalid
+pub fn decode<T: DeserializeOwned>(serialized_data: &SerializedData, data_id: &str) -> Option<T> {
+    serialized_data
+        .read_data(data_id)
+        .and_then(|data| cosmos_encoder::deserialize(data).ok())
+}
+
+/// What kind of entity this data was saved from
+pub fn entity_type(serialized_data: &SerializedData) -> &'static str {
+    let is = |data_id: &str| decode::<bool>(serialized_data, data_id).unwrap_or(false);
+
+    if is("cosmos:is_planet") {
+        "planet"
+    } else if is("cosmos:is_ship") {
+        "ship"
+    } else if is("cosmos:is_station") {
+        "station"
+    } else if serialized_data.read_data("cosmos:asteroid").is_some() {
+        "asteroid"
+    } else if serialized_data.read_data("cosmos:player_name").is_some() {
+        "player"
+    } else if serialized_data.read_data("cosmos:chunk").is_some() {
+        "chunk"
+    } else {
+        "unknown"
+    }
+}
+
+/// The name of the player this data was saved from, or the name of whoever owns it
+pub fn entity_name(serialized_data: &SerializedData) -> Option<String> {
+    decode::<String>(serialized_data, "cosmos:player_name").or_else(|| {
+        decode::<StructureOwner>(serialized_data, "cosmos:structure_owner").map(|owner| format!("owned by {}", owner.player_name()))
+    })
+}
+
+fn decode_json<T: DeserializeOwned + Serialize>(data: &[u8]) -> Option<Value> {
+    cosmos_encoder::deserialize::<T>(data)
+        .ok()
+        .and_then(|x| serde_json::to_value(x).ok())
+}
+
+/// Decodes the data saved under this id, if it's something whose type is known.
+///
+/// Anything else (such as structures & inventories) is too big to be worth reading as JSON.
+fn decode_known(data_id: &str, data: &[u8]) -> Option<Value> {
+    match data_id {
+        "cosmos:location" => decode_json::<Location>(data),
+        "cosmos:loading_distance" => decode_json::<LoadingDistance>(data),
+        "cosmos:block_palette" | "cosmos:item_palette" => decode_json::<IdPalette>(data),
+        "cosmos:player_name" => decode_json::<String>(data),
+        "cosmos:credits" => decode_json::<Credits>(data),
+        "cosmos:game_mode" => decode_json::<GameMode>(data),
+        "cosmos:render_distance" => decode_json::<RenderDistance>(data),
+        "cosmos:structure_owner" => decode_json::<StructureOwner>(data),
+        "cosmos:asteroid" => decode_json::<f32>(data),
+        "cosmos:is_planet"
+        | "cosmos:is_ship"
+        | "cosmos:is_station"
+        | "cosmos:ai_controlled"
+        | "cosmos:pirate"
+        | "cosmos:turrets_disabled" => decode_json::<bool>(data),
+        _ => None,
+    }
+}
+
+/// Describes this data as JSON. Data ids whose contents can't be decoded only list their size.
+pub fn to_json(serialized_data: &SerializedData) -> Value {
+    let mut data_ids = serialized_data.data_ids().collect::<Vec<_>>();
+    data_ids.sort();
+
+    let data = data_ids
+        .into_iter()
+        .map(|data_id| {
+            let raw = serialized_data.read_data(data_id).expect("This data id was just listed");
+
+            let value = decode_known(data_id, raw).unwrap_or_else(|| json!({ "bytes": raw.len() }));
+
+            (data_id.to_owned(), value)
+        })
+        .collect::<Map<_, _>>();
+
+    json!({
+        "type": entity_type(serialized_data),
+        "location": serialized_data.location(),
+        "data": data,
+    })
+}
+
+/// Prints the save file or blueprint at this path as JSON. A blueprint's header is printed along with its data.
+pub fn dump(path: &Path) -> anyhow::Result<()> {
+    let json = if is_blueprint(path) {
+        let blueprint = read_blueprint(path)?;
+
+        let mut json = to_json(&blueprint.data);
+        json["format_version"] = json!(blueprint.header.format_version);
+        json["metadata"] = serde_json::to_value(&blueprint.header.metadata)?;
+
+        json
+    } else {
+        to_json(&read(path)?)
+    };
+
+    println!("{}", serde_json::to_string_pretty(&json)?);
+
+    Ok(())
+}
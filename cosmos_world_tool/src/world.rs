@@ -0,0 +1,369 @@
+//! Finds every save file in a world & works out what each one belongs to from where it's saved.
+//!
+//! This mirrors how the server lays out its world directory:
+//!
+//! - `players/{name}.cent` - a player
+//! - `{x}_{y}_{z}/{entity_id}.cent` or `{x}_{y}_{z}/{load_distance}_{entity_id}.cent` - an entity in that sector
+//!   (or `nowhere/...` for entities without a sector)
+//! - `{...}/{entity_id}/{child}.cent` - a sub-entity or chunk saved with the entity `{entity_id}`
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use cosmos_core::{persistence::SerializedData, registry::id_palette::IdPalette};
+use walkdir::WalkDir;
+
+use crate::save_file;
+
+/// The directory players are saved in, within a world
+const PLAYERS_DIRECTORY: &str = "players";
+/// The directory entities without a sector are saved in, within a world
+const NOWHERE_DIRECTORY: &str = "nowhere";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a save file was saved for, based on where it is
+pub enum SaveFileKind {
+    /// A player's save file
+    Player,
+    /// An entity saved directly in a sector's directory
+    Entity {
+        /// The name of the sector's directory, such as `0_0_0`
+        sector: String,
+    },
+    /// A sub-entity or chunk, saved in the directory of whatever it belongs to
+    Child,
+}
+
+#[derive(Debug, Clone)]
+/// A `.cent` file within a world
+pub struct SaveFile {
+    /// The path to the file
+    pub path: PathBuf,
+    /// The entity id (or name, for players & chunks) this file is saved under
+    pub id: String,
+    /// What this was saved for
+    pub kind: SaveFileKind,
+}
+
+impl SaveFile {
+    /// The directory everything saved with this is in. This may not exist.
+    pub fn children_directory(&self) -> PathBuf {
+        self.path.with_file_name(&self.id)
+    }
+}
+
+/// Gets the entity id from a base entity's file name, which may start with its load distance
+fn parse_entity_file_name(file_stem: &str) -> String {
+    match file_stem.split_once('_') {
+        Some((load_distance, entity_id)) if load_distance.parse::<u32>().is_ok() => entity_id.to_owned(),
+        _ => file_stem.to_owned(),
+    }
+}
+
+/// Returns true if this is the name of a sector's directory, such as `-1_0_3`
+fn is_sector_directory(name: &str) -> bool {
+    name == NOWHERE_DIRECTORY || (name.split('_').count() == 3 && name.split('_').all(|x| x.parse::<i64>().is_ok()))
+}
+
+/// A world's directory, opened without the server running
+pub struct World {
+    directory: PathBuf,
+}
+
+impl World {
+    /// Opens the world saved in this directory
+    pub fn open(directory: PathBuf) -> anyhow::Result<Self> {
+        if !directory.is_dir() {
+            bail!("There is no world at {}", directory.display());
+        }
+
+        Ok(Self { directory })
+    }
+
+    /// Every `.cent` file in this world
+    pub fn save_files(&self) -> Vec<SaveFile> {
+        WalkDir::new(&self.directory)
+            .sort_by_file_name()
+            .into_iter()
+            .flatten()
+            .filter(|x| x.file_type().is_file() && x.path().extension() == Some(OsStr::new("cent")))
+            .filter_map(|entry| self.identify(entry.path()))
+            .collect()
+    }
+
+    fn identify(&self, path: &Path) -> Option<SaveFile> {
+        let relative_path = path.strip_prefix(&self.directory).ok()?;
+        let first_directory = relative_path.components().next()?.as_os_str().to_str()?;
+        let file_stem = path.file_stem()?.to_str()?.to_owned();
+
+        let depth = relative_path.components().count();
+
+        let (id, kind) = if first_directory == PLAYERS_DIRECTORY {
+            (file_stem, SaveFileKind::Player)
+        } else if !is_sector_directory(first_directory) {
+            return None;
+        } else if depth == 2 {
+            let entity_id = parse_entity_file_name(&file_stem);
+
+            (
+                entity_id,
+                SaveFileKind::Entity {
+                    sector: first_directory.to_owned(),
+                },
+            )
+        } else {
+            (file_stem, SaveFileKind::Child)
+        };
+
+        Some(SaveFile {
+            path: path.to_owned(),
+            id,
+            kind,
+        })
+    }
+
+    /// Finds the save file of whatever this child was saved with. Returns `None` if it's missing.
+    fn parent_of(&self, child: &SaveFile) -> Option<PathBuf> {
+        let directory = child.path.parent()?;
+        let parent_id = directory.file_name()?.to_str()?;
+        let containing_directory = directory.parent()?;
+
+        fs::read_dir(containing_directory)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.extension() == Some(OsStr::new("cent"))
+                    && path
+                        .file_stem()
+                        .and_then(|x| x.to_str())
+                        .is_some_and(|stem| parse_entity_file_name(stem) == parent_id || stem == parent_id)
+            })
+    }
+
+    /// Prints every sector with entities saved in it, and every player
+    pub fn list(&self) {
+        let mut sectors = BTreeMap::<String, Vec<SaveFile>>::new();
+        let mut players = vec![];
+
+        for save_file in self.save_files() {
+            match &save_file.kind {
+                SaveFileKind::Entity { sector, .. } => sectors.entry(sector.clone()).or_default().push(save_file),
+                SaveFileKind::Player => players.push(save_file),
+                SaveFileKind::Child => {}
+            }
+        }
+
+        for (sector, entities) in sectors {
+            println!("Sector {sector}");
+
+            for entity in entities {
+                let children = WalkDir::new(entity.children_directory())
+                    .into_iter()
+                    .flatten()
+                    .filter(|x| x.file_type().is_file())
+                    .count();
+
+                match save_file::read(&entity.path) {
+                    Ok(serialized_data) => {
+                        let name = save_file::entity_name(&serialized_data)
+                            .map(|x| format!(" ({x})"))
+                            .unwrap_or_default();
+
+                        println!(
+                            "  {} - {}{name}, {children} file(s) saved with it",
+                            entity.id,
+                            save_file::entity_type(&serialized_data)
+                        );
+                    }
+                    Err(e) => println!("  {} - {e}", entity.id),
+                }
+            }
+        }
+
+        if !players.is_empty() {
+            println!("Players");
+
+            for player in players {
+                let name = save_file::read(&player.path)
+                    .ok()
+                    .and_then(|x| save_file::entity_name(&x))
+                    .unwrap_or(player.id);
+
+                println!("  {name}");
+            }
+        }
+    }
+
+    /// Deletes the entity with this id & everything saved with it.
+    ///
+    /// Returns an error if nothing has that id.
+    pub fn delete(&self, entity_id: &str) -> anyhow::Result<()> {
+        let matching = self
+            .save_files()
+            .into_iter()
+            .filter(|x| x.kind != SaveFileKind::Player && x.id == entity_id)
+            .collect::<Vec<_>>();
+
+        if matching.is_empty() {
+            bail!("There is no entity with the id {entity_id}");
+        }
+
+        for save_file in matching {
+            remove_with_children(&save_file)?;
+            println!("Deleted {}", save_file.path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Renames `from` to `to` in the block palette of every save file, and every blueprint in `blueprints_directory` if
+    /// there is one. Once loaded, every `from` block in them will be a `to` block instead.
+    pub fn rewrite_block(&self, from: &str, to: &str, blueprints_directory: Option<&Path>) -> anyhow::Result<()> {
+        let mut rewritten = 0;
+
+        for save_file in self.save_files() {
+            let mut serialized_data = match save_file::read(&save_file.path) {
+                Ok(serialized_data) => serialized_data,
+                Err(e) => {
+                    println!("Skipping {e}");
+                    continue;
+                }
+            };
+
+            if rename_block(&save_file.path, &mut serialized_data, from, to) {
+                save_file::write(&save_file.path, &serialized_data)?;
+                rewritten += 1;
+            }
+        }
+
+        if let Some(blueprints_directory) = blueprints_directory {
+            for path in WalkDir::new(blueprints_directory)
+                .into_iter()
+                .flatten()
+                .filter(|x| x.file_type().is_file() && save_file::is_blueprint(x.path()))
+                .map(|x| x.into_path())
+            {
+                let mut blueprint = match save_file::read_blueprint(&path) {
+                    Ok(blueprint) => blueprint,
+                    Err(e) => {
+                        println!("Skipping {e}");
+                        continue;
+                    }
+                };
+
+                if !rename_block(&path, &mut blueprint.data, from, to) {
+                    continue;
+                }
+
+                // Keep the header's block counts in line with what's actually in the blueprint
+                if let Some(metadata) = &mut blueprint.header.metadata {
+                    if let Some(count) = metadata.block_counts.remove(from) {
+                        *metadata.block_counts.entry(to.to_owned()).or_default() += count;
+                    }
+                }
+
+                save_file::write_blueprint(&path, &blueprint)?;
+                rewritten += 1;
+            }
+        }
+
+        println!("Changed {from} to {to} in {rewritten} file(s).");
+
+        Ok(())
+    }
+
+    /// Checks that every save file can be read, that everything saved with an entity still has that entity,
+    /// and that no saves were left half-written.
+    ///
+    /// If `fix` is true, anything without a parent and any half-written saves are deleted. Files that can't be read
+    /// are only reported, since they may still be recoverable.
+    pub fn validate(&self, fix: bool) -> anyhow::Result<()> {
+        let mut problems = 0;
+
+        for save_file in self.save_files() {
+            // This was saved with something that was just deleted
+            if !save_file.path.exists() {
+                continue;
+            }
+
+            if let Err(e) = save_file::read(&save_file.path) {
+                problems += 1;
+                println!("Unreadable: {e}");
+            }
+
+            if save_file.kind == SaveFileKind::Child && self.parent_of(&save_file).is_none() {
+                problems += 1;
+                println!("No parent: {}", save_file.path.display());
+
+                if fix {
+                    remove_with_children(&save_file)?;
+                    println!("  Deleted it & everything saved with it");
+                }
+            }
+        }
+
+        for temp_file in WalkDir::new(&self.directory)
+            .into_iter()
+            .flatten()
+            .filter(|x| x.file_type().is_file() && x.path().extension() == Some(OsStr::new("tmp")))
+        {
+            problems += 1;
+            println!("Half-written save: {}", temp_file.path().display());
+
+            if fix {
+                fs::remove_file(temp_file.path())?;
+                println!("  Deleted it");
+            }
+        }
+
+        if problems == 0 {
+            println!("No problems found.");
+        } else {
+            println!("Found {problems} problem(s).");
+        }
+
+        Ok(())
+    }
+}
+
+/// Renames `from` to `to` in this data's block palette. Returns false if nothing was changed.
+fn rename_block(path: &Path, serialized_data: &mut SerializedData, from: &str, to: &str) -> bool {
+    let Some(mut palette) = save_file::decode::<IdPalette>(serialized_data, "cosmos:block_palette") else {
+        if serialized_data.read_data("cosmos:structure").is_some() || serialized_data.read_data("cosmos:chunk").is_some() {
+            println!(
+                "Skipping {} - it was saved before block palettes existed. Load & save it once first.",
+                path.display()
+            );
+        }
+        return false;
+    };
+
+    if !palette.rename(from, to) {
+        return false;
+    }
+
+    serialized_data.serialize_data("cosmos:block_palette", &palette);
+
+    true
+}
+
+/// Removes this save file & the directory of everything saved with it
+fn remove_with_children(save_file: &SaveFile) -> anyhow::Result<()> {
+    // A parent may have been removed along with its children already
+    if save_file.path.exists() {
+        fs::remove_file(&save_file.path)?;
+    }
+
+    let children_directory = save_file.children_directory();
+    if children_directory.is_dir() {
+        fs::remove_dir_all(children_directory)?;
+    }
+
+    Ok(())
+}